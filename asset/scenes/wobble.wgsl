@@ -0,0 +1,18 @@
+// demo.scene 里 jelly 材质用的 shader
+// object.params: x 频率，y 振幅，z 颜色随时间变化的强度
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var moved = in;
+    let phase = globals.time * object.params.x + in.position.y * 6.0;
+    moved.position += in.normal * sin(phase) * object.params.y;
+    return object_vertex(moved);
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front: bool) -> @location(0) vec4<f32> {
+    let normal = normalize(select(-in.normal, in.normal, front));
+    let pulse = 0.5 + 0.5 * sin(globals.time * 2.0 + in.world.y * 4.0);
+    let albedo = mix(object_albedo(in.uv).rgb, vec3<f32>(1.0, 0.3, 0.6), pulse * object.params.z);
+    return vec4<f32>(tonemap(shade(albedo, in.world, normal)), 1.0);
+}
@@ -1,6 +1,8 @@
 struct Uniforms { 
     @location(0) dt:f32,
     @location(1) time:f32,
+    // 选中的实例编号 + 1，0 表示没选中
+    selected: u32,
 };
 
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
@@ -13,6 +15,7 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
     @location(0) color: vec3f,
+    @location(1) @interpolate(flat) id: u32,
 };
 
 struct InstanceInput {
@@ -23,9 +26,11 @@ struct InstanceInput {
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
      var out: VertexOutput;
     out.color = model.color;
+    out.id = instance_index + 1u;
 
     // 计算圆周运动的位置
     var angle = uniforms.time + instance.position.x; // 使用时间和实例位置计算角度
@@ -47,5 +52,13 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4f {
         abs(sin( in.color.g * uniforms.time * 0.4)),
         abs(sin( in.color.b * uniforms.time * 0.3))
     );
+    if in.id == uniforms.selected {
+        return vec4f(mix(animated_color, vec3f(1.0, 0.5, 0.0), 0.6), 1.0);
+    }
     return vec4f(animated_color, 1.0);
+}
+
+@fragment
+fn fs_id(in: VertexOutput) -> @location(0) u32 {
+    return in.id;
 }
\ No newline at end of file
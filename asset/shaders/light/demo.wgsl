@@ -0,0 +1,66 @@
+// 光照模型的演示，前面会拼上 lights.wgsl 和 brdf.wgsl
+
+struct Frame {
+    view_proj: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    brdf: u32,
+    ambient: vec3<f32>,
+    _pad: f32,
+};
+
+struct Object {
+    model: mat4x4<f32>,
+    albedo: vec3<f32>,
+    roughness: f32,
+    metallic: f32,
+    shininess: f32,
+    _pad: vec2<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> frame: Frame;
+
+@group(1)
+@binding(0)
+var<storage, read> lights: LightArray;
+
+@group(2)
+@binding(0)
+var<uniform> object: Object;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>, @location(1) normal: vec3<f32>) -> VertexOutput {
+    let world = object.model * vec4<f32>(position, 1.0);
+    // 只有旋转和等比缩放，直接用 model 变换法线
+    let n = (object.model * vec4<f32>(normal, 0.0)).xyz;
+    return VertexOutput(frame.view_proj * world, world.xyz, n);
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front: bool) -> @location(0) vec4<f32> {
+    // 平面两面都要能看到
+    let normal = normalize(select(-in.normal, in.normal, front));
+    let surface = Surface(
+        object.albedo,
+        object.roughness,
+        object.metallic,
+        object.shininess,
+        normal,
+        normalize(frame.camera_position - in.world),
+    );
+    var color = frame.ambient * object.albedo;
+    for (var i = 0u; i < lights.count; i++) {
+        let sample = sample_light(lights.items[i], in.world);
+        let n_dot_l = max(dot(normal, sample.direction), 0.0);
+        color += brdf(frame.brdf, surface, sample.direction) * sample.radiance * n_dot_l;
+    }
+    // Reinhard，亮的地方别直接爆掉
+    return vec4<f32>(color / (color + vec3<f32>(1.0)), 1.0);
+}
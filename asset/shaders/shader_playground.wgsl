@@ -2,6 +2,10 @@ struct EnvUniforms {
     world: mat4x4<f32>,
     view: mat4x4<f32>,
     proj: mat4x4<f32>,
+    // rgb 是高亮颜色，a 是混合比例
+    highlight: vec4<f32>,
+    // 拾取用的编号，0 留给空白
+    id: u32,
 };
 @group(0) @binding(0) var<uniform> env_uniforms: EnvUniforms;
 
@@ -14,6 +18,7 @@ struct TimeUniforms {
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) @interpolate(flat) id: u32,
 };
 
 @vertex
@@ -22,7 +27,8 @@ fn vs_main(
     @location(1) color: vec3<f32>,
 ) -> VertexOutput {
     var result: VertexOutput;
-    result.color = vec4f(color, 1.0);
+    result.color = vec4f(mix(color, env_uniforms.highlight.rgb, env_uniforms.highlight.a), 1.0);
+    result.id = env_uniforms.id;
 
     // result.position = vec4f(position,1.0);
     result.position = env_uniforms.proj * env_uniforms.view * env_uniforms.world * vec4f(position,1.0);
@@ -33,4 +39,9 @@ fn vs_main(
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color;
-}
\ No newline at end of file
+}
+
+@fragment
+fn fs_id(in: VertexOutput) -> @location(0) u32 {
+    return in.id;
+}
@@ -0,0 +1,601 @@
+// 资源管理器，挂在 GfxContext 上，网格、贴图、shader 都从这里拿
+// 同一个路径只读一次，拿到的是 Handle<T>；Handle 全丢掉以后 GPU 资源跟着释放
+// 读文件和解码在后台线程里做，上传 GPU 要 device，留到每帧开头的 update 里
+// update 隔一会儿看一下文件的修改时间，改了就重新读；读完 generation 加一，
+// 用到它的 pipeline 拿 AssetSet 对比 generation 决定要不要重建
+// 路径和其它地方一样相对工作目录，比如 asset/scenes/wobble.wgsl；内置模型写成 builtin:cube
+// 场景自己的 shader 放在 asset/shaders 下面，编进程序里一份，用 HotShader 建 pipeline，改了文件就重建
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+
+use image::DynamicImage;
+
+use crate::{
+    gfx::GfxContext,
+    texture::{compressed, SamplerOptions, TextureBundle, TextureOptions},
+    utils::models::{obj, ModelBuffers, ModelType},
+};
+
+/// 多久看一次文件有没有改
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 能被 Assets 管的资源
+pub(crate) trait Asset: Sized + Send + Sync + 'static {
+    /// 后台线程交给主线程的东西
+    type Decoded: Send + 'static;
+    /// 统计和报错的时候显示
+    const KIND: &'static str;
+
+    /// 在后台线程里跑：读文件、解码
+    fn decode(path: &Path) -> Result<Self::Decoded, String>;
+    /// 在主线程里跑：上传到 GPU
+    fn upload(context: &GfxContext, path: &Path, decoded: Self::Decoded) -> Result<Self, String>;
+}
+
+struct SlotState<T> {
+    /// 重新加载的时候还是旧的，读完才换
+    value: Option<Arc<T>>,
+    /// 最近一次读失败的原因
+    error: Option<String>,
+    generation: u64,
+    loading: bool,
+}
+
+struct Slot<T> {
+    path: PathBuf,
+    state: Mutex<SlotState<T>>,
+}
+
+/// 指向一个资源，clone 只加引用计数
+pub(crate) struct Handle<T: Asset> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T: Asset> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T: Asset> Handle<T> {
+    pub(crate) fn path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    /// 还没读完或者第一次就读失败了返回 None
+    pub(crate) fn get(&self) -> Option<Arc<T>> {
+        self.slot.state.lock().unwrap().value.clone()
+    }
+
+    pub(crate) fn error(&self) -> Option<String> {
+        self.slot.state.lock().unwrap().error.clone()
+    }
+
+    /// 每加载成功一次加一，0 表示还没加载好
+    pub(crate) fn generation(&self) -> u64 {
+        self.slot.state.lock().unwrap().generation
+    }
+}
+
+/// 读完的结果，从后台线程发回来
+struct Finished {
+    slot: Weak<dyn AnySlot>,
+    decoded: Result<Box<dyn Any + Send>, String>,
+}
+
+/// 擦掉类型的 Slot，update 和 AssetSet 用
+trait AnySlot: Send + Sync {
+    fn path(&self) -> &Path;
+    fn kind(&self) -> &'static str;
+    fn generation(&self) -> u64;
+    fn error(&self) -> Option<String>;
+    fn loading(&self) -> bool;
+    /// 开个后台线程去读；已经在读了就不管，返回 false
+    fn start(self: Arc<Self>, sender: mpsc::Sender<Finished>) -> bool;
+    fn finish(&self, context: &GfxContext, decoded: Result<Box<dyn Any + Send>, String>);
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Asset> AnySlot for Slot<T> {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn kind(&self) -> &'static str {
+        T::KIND
+    }
+
+    fn generation(&self) -> u64 {
+        self.state.lock().unwrap().generation
+    }
+
+    fn error(&self) -> Option<String> {
+        self.state.lock().unwrap().error.clone()
+    }
+
+    fn loading(&self) -> bool {
+        self.state.lock().unwrap().loading
+    }
+
+    fn start(self: Arc<Self>, sender: mpsc::Sender<Finished>) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.loading {
+                return false;
+            }
+            state.loading = true;
+        }
+        let path = self.path.clone();
+        let slot: Arc<dyn AnySlot> = self;
+        let slot = Arc::downgrade(&slot);
+        std::thread::spawn(move || {
+            // decode 里 panic 了也要发个结果回去，不然 loading 一直是 true，wait 就卡死了
+            let decoded = std::panic::catch_unwind(|| T::decode(&path))
+                .unwrap_or_else(|panic| Err(panic_message(&*panic)))
+                .map(|d| Box::new(d) as Box<dyn Any + Send>);
+            // 收不到说明程序在退出，不用管
+            let _ = sender.send(Finished { slot, decoded });
+        });
+        true
+    }
+
+    fn finish(&self, context: &GfxContext, decoded: Result<Box<dyn Any + Send>, String>) {
+        let uploaded = decoded
+            .and_then(|d| T::upload(context, &self.path, *d.downcast::<T::Decoded>().unwrap()));
+        let mut state = self.state.lock().unwrap();
+        state.loading = false;
+        match uploaded {
+            Ok(value) => {
+                state.value = Some(Arc::new(value));
+                state.error = None;
+                state.generation += 1;
+            }
+            Err(e) => state.error = Some(format!("{} {}: {}", T::KIND, self.path.display(), e)),
+        }
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    format!("panicked while decoding: {}", message)
+}
+
+struct Tracked {
+    slot: Weak<dyn AnySlot>,
+    modified: Option<SystemTime>,
+}
+
+struct Inner {
+    /// 去重用，Handle 都丢了以后 Weak 就升不上去了
+    slots: HashMap<(TypeId, PathBuf), Tracked>,
+    last_scan: Instant,
+}
+
+pub(crate) struct Assets {
+    inner: Mutex<Inner>,
+    sender: mpsc::Sender<Finished>,
+    receiver: Mutex<mpsc::Receiver<Finished>>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 去掉 . 和 ..，同一个文件的不同写法算一个
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+impl Assets {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Assets {
+            inner: Mutex::new(Inner {
+                slots: HashMap::new(),
+                last_scan: Instant::now(),
+            }),
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    /// 已经有人在用就直接给同一个，不然开始在后台读
+    pub(crate) fn load<T: Asset>(&self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = normalize(path.as_ref());
+        let key = (TypeId::of::<T>(), path.clone());
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(slot) = inner.slots.get(&key).and_then(|t| t.slot.upgrade()) {
+            let slot = slot.into_any().downcast::<Slot<T>>().unwrap();
+            return Handle { slot };
+        }
+        let slot = Arc::new(Slot {
+            path: path.clone(),
+            state: Mutex::new(SlotState {
+                value: None,
+                error: None,
+                generation: 0,
+                loading: false,
+            }),
+        });
+        let any: Arc<dyn AnySlot> = slot.clone();
+        inner.slots.insert(
+            key,
+            Tracked {
+                slot: Arc::downgrade(&any),
+                modified: modified_time(&path),
+            },
+        );
+        any.start(self.sender.clone());
+        Handle { slot }
+    }
+
+    pub(crate) fn mesh(&self, path: impl AsRef<Path>) -> Handle<ModelBuffers> {
+        self.load(path)
+    }
+
+    pub(crate) fn texture(&self, path: impl AsRef<Path>) -> Handle<TextureBundle> {
+        self.load(path)
+    }
+
+    pub(crate) fn shader(&self, path: impl AsRef<Path>) -> Handle<Shader> {
+        self.load(path)
+    }
+
+    /// 内置模型：位置 + 法线 + uv，u32 索引，等到生成完再返回
+    pub(crate) fn builtin(&self, context: &GfxContext, model: ModelType) -> Handle<ModelBuffers> {
+        let handle = self.mesh(format!("builtin:{}", model.name()));
+        self.wait(context, &handle).unwrap();
+        handle
+    }
+
+    /// 每帧开头调一次：后台读完的上传到 GPU，隔一会儿看看文件有没有改
+    pub(crate) fn update(&self, context: &GfxContext) {
+        let finished: Vec<Finished> = self.receiver.lock().unwrap().try_iter().collect();
+        for f in finished {
+            if let Some(slot) = f.slot.upgrade() {
+                slot.finish(context, f.decoded);
+            }
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.last_scan.elapsed() < SCAN_INTERVAL {
+            return;
+        }
+        inner.last_scan = Instant::now();
+        // 没人用的丢掉，GPU 资源在最后一个 Handle 丢掉的时候就已经释放了
+        inner.slots.retain(|_, t| t.slot.strong_count() > 0);
+        for tracked in inner.slots.values_mut() {
+            let Some(slot) = tracked.slot.upgrade() else {
+                continue;
+            };
+            // 内置的和删掉的文件拿不到时间，不算改了
+            // 上一次还没读完就先不记时间，下次扫的时候再试，不然这次改动就丢了
+            let now = modified_time(slot.path());
+            if now.is_some() && now != tracked.modified && slot.start(self.sender.clone()) {
+                tracked.modified = now;
+            }
+        }
+    }
+
+    /// 一直等到 handle 第一次读完，场景 ready 里一定要拿到东西的时候用
+    pub(crate) fn wait<T: Asset>(
+        &self,
+        context: &GfxContext,
+        handle: &Handle<T>,
+    ) -> Result<Arc<T>, String> {
+        loop {
+            self.update(context);
+            if let Some(value) = handle.get() {
+                return Ok(value);
+            }
+            if let Some(error) = handle.error() {
+                return Err(error);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// (种类, 还活着的数量, 正在读的数量)
+    pub(crate) fn stats(&self) -> Vec<(&'static str, usize, usize)> {
+        let inner = self.inner.lock().unwrap();
+        let mut stats: Vec<(&'static str, usize, usize)> = Vec::new();
+        for slot in inner.slots.values().filter_map(|t| t.slot.upgrade()) {
+            let kind = slot.kind();
+            let i = match stats.iter().position(|s| s.0 == kind) {
+                Some(i) => i,
+                None => {
+                    stats.push((kind, 0, 0));
+                    stats.len() - 1
+                }
+            };
+            stats[i].1 += 1;
+            stats[i].2 += slot.loading() as usize;
+        }
+        stats.sort_by_key(|s| s.0);
+        stats
+    }
+}
+
+/// 一组资源，场景拿它判断能不能开始画、要不要重建 pipeline
+#[derive(Clone, Default)]
+pub(crate) struct AssetSet {
+    /// 每个资源和上次看到的 generation
+    slots: Vec<(Arc<dyn AnySlot>, u64)>,
+}
+
+pub(crate) enum AssetSetState {
+    /// (读完的, 总数)
+    Loading(usize, usize),
+    Failed(String),
+    Ready,
+}
+
+impl AssetSet {
+    pub(crate) fn add<T: Asset>(&mut self, handle: &Handle<T>) {
+        let slot: Arc<dyn AnySlot> = handle.slot.clone();
+        let generation = slot.generation();
+        self.slots.push((slot, generation));
+    }
+
+    /// 有一个第一次就读失败了就算失败
+    pub(crate) fn state(&self) -> AssetSetState {
+        let mut ready = 0;
+        for (slot, _) in &self.slots {
+            if slot.generation() > 0 {
+                ready += 1;
+            } else if let (Some(error), false) = (slot.error(), slot.loading()) {
+                return AssetSetState::Failed(error);
+            }
+        }
+        if ready == self.slots.len() {
+            AssetSetState::Ready
+        } else {
+            AssetSetState::Loading(ready, self.slots.len())
+        }
+    }
+
+    /// 有资源重新加载过了返回 true，同时记下新的 generation
+    pub(crate) fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (slot, seen) in &mut self.slots {
+            let generation = slot.generation();
+            changed |= generation != *seen;
+            *seen = generation;
+        }
+        changed
+    }
+
+    /// 重新加载失败的，旧的值还在用
+    pub(crate) fn errors(&self) -> Vec<String> {
+        self.slots
+            .iter()
+            .filter_map(|(slot, _)| slot.error())
+            .collect()
+    }
+}
+
+impl Asset for ModelBuffers {
+    /// 位置 + 法线 + uv 和 u32 索引，和 ModelType::gen_textured 一样
+    type Decoded = (Vec<[f32; 8]>, Vec<u32>);
+    const KIND: &'static str = "mesh";
+
+    fn decode(path: &Path) -> Result<Self::Decoded, String> {
+        let path = path.to_string_lossy();
+        if let Some(name) = path.strip_prefix("builtin:") {
+            let model = ModelType::from_name(name)
+                .ok_or_else(|| format!("unknown builtin mesh `{}`", name))?;
+            let (vertices, indices) = model.gen_textured();
+            return Ok((vertices, indices.into_iter().map(u32::from).collect()));
+        }
+        obj::load(path.as_ref()).map_err(|e| e.to_string())
+    }
+
+    fn upload(context: &GfxContext, path: &Path, decoded: Self::Decoded) -> Result<Self, String> {
+        let (vertices, indices) = decoded;
+        let label = path.to_string_lossy();
+        Ok(ModelBuffers::new(context, &label, &vertices, &indices))
+    }
+}
+
+/// 贴图在后台解码成图片；KTX2 / DDS 的转码要看设备支持什么格式，留到上传的时候
+pub(crate) enum TextureData {
+    Image(DynamicImage),
+    Container(Vec<u8>),
+}
+
+impl Asset for TextureBundle {
+    type Decoded = TextureData;
+    const KIND: &'static str = "texture";
+
+    fn decode(path: &Path) -> Result<Self::Decoded, String> {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        if compressed::is_container(&bytes) {
+            return Ok(TextureData::Container(bytes));
+        }
+        image::load_from_memory(&bytes)
+            .map(TextureData::Image)
+            .map_err(|e| e.to_string())
+    }
+
+    /// 都当颜色贴图：sRGB、GPU 生成 mip、重复寻址
+    fn upload(context: &GfxContext, path: &Path, decoded: Self::Decoded) -> Result<Self, String> {
+        let label = path.to_string_lossy();
+        let options = TextureOptions {
+            label: Some(&label),
+            sampler: SamplerOptions {
+                address_mode: wgpu::AddressMode::Repeat,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        match decoded {
+            TextureData::Image(image) => Ok(TextureBundle::from_image(context, image, options)),
+            TextureData::Container(bytes) => {
+                TextureBundle::from_bytes(context, &bytes, options).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// WGSL 源码，拼库和建 pipeline 由用的地方来做，要跟着文件重建就用 HotShader
+pub(crate) struct Shader {
+    pub(crate) source: String,
+}
+
+impl Asset for Shader {
+    type Decoded = String;
+    const KIND: &'static str = "shader";
+
+    fn decode(path: &Path) -> Result<Self::Decoded, String> {
+        std::fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+
+    fn upload(_context: &GfxContext, _path: &Path, source: Self::Decoded) -> Result<Self, String> {
+        Ok(Shader { source })
+    }
+}
+
+/// 从一个 shader 文件建出来的 pipeline（或者几个 pipeline），文件改了就用同一个 build 重建
+/// 新的编不过就打出错误，继续用旧的
+pub(crate) struct HotShader<T> {
+    shader: Handle<Shader>,
+    generation: u64,
+    /// 现在的 value 是拿哪份源码建的，文件读进来一样就不用重建
+    source: String,
+    value: T,
+}
+
+impl<T> HotShader<T> {
+    /// 先用编进程序里的 embedded 建好，不等文件；文件只拿来热重载，
+    /// 不在仓库根目录下跑、读不到文件也照样能用
+    pub(crate) fn new(
+        context: &GfxContext,
+        path: impl AsRef<Path>,
+        embedded: &'static str,
+        build: impl FnOnce(&str) -> T,
+    ) -> Self {
+        let shader = context.assets.shader(path);
+        HotShader {
+            generation: 0,
+            value: build(embedded),
+            source: embedded.to_owned(),
+            shader,
+        }
+    }
+
+    /// 每帧画之前调，重建过返回 true
+    pub(crate) fn update(&mut self, context: &GfxContext, build: impl FnOnce(&str) -> T) -> bool {
+        let generation = self.shader.generation();
+        if generation == self.generation {
+            return false;
+        }
+        self.generation = generation;
+        let source = self.shader.get().unwrap();
+        if source.source == self.source {
+            return false;
+        }
+        context
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+        let value = build(&source.source);
+        match pollster::block_on(context.device.pop_error_scope()) {
+            Some(e) => {
+                eprintln!("{}: {}", self.shader.path().display(), e);
+                false
+            }
+            None => {
+                self.value = value;
+                self.source.clone_from(&source.source);
+                true
+            }
+        }
+    }
+}
+
+impl<T> std::ops::Deref for HotShader<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// decode 一定会 panic
+    struct Exploding;
+
+    impl Asset for Exploding {
+        type Decoded = ();
+        const KIND: &'static str = "exploding";
+
+        fn decode(_path: &Path) -> Result<(), String> {
+            panic!("boom")
+        }
+
+        fn upload(_context: &GfxContext, _path: &Path, _decoded: ()) -> Result<Self, String> {
+            Ok(Exploding)
+        }
+    }
+
+    fn slot() -> Arc<Slot<Exploding>> {
+        Arc::new(Slot {
+            path: PathBuf::from("exploding"),
+            state: Mutex::new(SlotState {
+                value: None,
+                error: None,
+                generation: 0,
+                loading: false,
+            }),
+        })
+    }
+
+    #[test]
+    fn panicking_decode_sends_an_error() {
+        let slot = slot();
+        let (sender, receiver) = mpsc::channel();
+        assert!(slot.clone().start(sender));
+        let finished = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("decode thread never reported back");
+        let error = finished.decoded.err().unwrap();
+        assert!(error.contains("boom"), "{}", error);
+    }
+
+    #[test]
+    fn start_is_refused_while_loading() {
+        let slot = slot();
+        let (sender, _receiver) = mpsc::channel();
+        assert!(slot.clone().start(sender.clone()));
+        // 结果还没交给 finish，还算在读
+        assert!(!slot.clone().start(sender));
+        assert!(slot.loading());
+    }
+}
@@ -0,0 +1,611 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Write as _,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::{gfx, studio::Studio};
+
+// 跑分模式
+// cargo run --release -- --bench [场景名] [--warmup 秒] [--measure 秒] [--out 文件名] [--software]
+// 每个场景先预热再测量，结果写成 <out>.json 和 <out>.csv
+
+#[derive(Clone, Debug)]
+pub(crate) struct BenchOptions {
+    /// 只跑名字里包含这个字符串（不分大小写）或者序号等于它的场景
+    pub(crate) scene: Option<String>,
+    pub(crate) warmup: f32,
+    pub(crate) measure: f32,
+    /// 不带扩展名
+    pub(crate) out: PathBuf,
+    /// 用软件 adapter，方便在没有显卡的机器上对比
+    pub(crate) software: bool,
+}
+
+impl BenchOptions {
+    /// 没有 --bench 时返回 None
+    pub(crate) fn from_args(args: impl Iterator<Item = String>) -> Option<Self> {
+        let mut args = args.peekable();
+        let mut options = Self::default();
+        let mut enabled = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bench" => {
+                    enabled = true;
+                    if args.peek().is_some_and(|next| !next.starts_with("--")) {
+                        options.scene = args.next();
+                    }
+                }
+                "--warmup" => options.warmup = parse_seconds(&arg, args.next()),
+                "--measure" => options.measure = parse_seconds(&arg, args.next()),
+                "--out" => options.out = args.next().expect("--out needs a path").into(),
+                "--software" => options.software = true,
+                _ => println!("unknown argument: {}", arg),
+            }
+        }
+        enabled.then_some(options)
+    }
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            warmup: 2.0,
+            measure: 5.0,
+            out: PathBuf::from("bench"),
+            software: false,
+        }
+    }
+}
+
+fn parse_seconds(flag: &str, value: Option<String>) -> f32 {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("{} needs a number of seconds", flag))
+}
+
+pub(crate) struct SceneReport {
+    pub(crate) index: usize,
+    pub(crate) name: String,
+    pub(crate) frames: usize,
+    pub(crate) mean_ms: f32,
+    pub(crate) p50_ms: f32,
+    pub(crate) p95_ms: f32,
+    pub(crate) p99_ms: f32,
+    /// adapter 不支持 timestamp query 时为 None
+    pub(crate) gpu_mean_ms: Option<f32>,
+    /// 按 pass 的 label 分开的 GPU 平均时间，同名的 pass 一帧内加在一起
+    pub(crate) gpu_passes: Vec<(&'static str, f32)>,
+    pub(crate) counters: Vec<(&'static str, f64)>,
+}
+
+enum Phase {
+    Warmup,
+    Measure,
+}
+
+pub(crate) struct BenchRunner {
+    options: BenchOptions,
+    /// 还没跑的场景序号
+    pending: Vec<usize>,
+    current: Option<(usize, Phase, Instant)>,
+    gpu_timer: Option<GpuTimer>,
+    gpu_samples: Vec<f32>,
+    pass_samples: Vec<(&'static str, Vec<f32>)>,
+    reports: Vec<SceneReport>,
+    adapter: wgpu::AdapterInfo,
+    started: bool,
+}
+
+/// frame_times 不需要排好序
+pub(crate) fn percentile(frame_times: &[f32], p: f32) -> f32 {
+    if frame_times.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = frame_times.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = (p / 100.0 * (sorted.len() - 1) as f32).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+fn mean(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
+
+impl BenchRunner {
+    pub(crate) fn new(context: &gfx::GfxContext, options: BenchOptions) -> Self {
+        let gpu_timer = GpuTimer::new(context);
+        if gpu_timer.is_none() {
+            println!("bench: timestamp queries not supported, skipping GPU times");
+        }
+        Self {
+            options,
+            pending: Vec::new(),
+            current: None,
+            gpu_timer,
+            gpu_samples: Vec::new(),
+            pass_samples: Vec::new(),
+            reports: Vec::new(),
+            adapter: context.adapter.get_info(),
+            started: false,
+        }
+    }
+
+    pub(crate) fn started(&self) -> bool {
+        self.started
+    }
+
+    /// 选出要跑的场景，返回第一个的序号
+    pub(crate) fn start(&mut self, studio: &Studio) -> Option<usize> {
+        self.started = true;
+        let filter = self.options.scene.as_ref().map(|s| s.to_lowercase());
+        self.pending = (0..studio.names.len())
+            .filter(|&index| match &filter {
+                None => true,
+                Some(filter) => {
+                    index.to_string() == *filter
+                        || studio.names[index].to_lowercase().contains(filter.as_str())
+                }
+            })
+            .rev()
+            .collect();
+        if self.pending.is_empty() {
+            println!("bench: no scene matches {:?}", self.options.scene);
+        }
+        self.next_scene()
+    }
+
+    fn next_scene(&mut self) -> Option<usize> {
+        let index = self.pending.pop()?;
+        self.current = Some((index, Phase::Warmup, Instant::now()));
+        self.gpu_samples.clear();
+        self.pass_samples.clear();
+        Some(index)
+    }
+
+    pub(crate) fn begin_frame(&self, context: &gfx::GfxContext) {
+        if let Some(timer) = &self.gpu_timer {
+            timer.begin(context);
+        }
+    }
+
+    pub(crate) fn end_frame(&mut self, context: &gfx::GfxContext) {
+        let Some(timer) = self.gpu_timer.as_mut() else {
+            return;
+        };
+        for frame in timer.end(context) {
+            self.gpu_samples.push(frame.total);
+            let mut sums: Vec<(&'static str, f32)> = Vec::new();
+            for (label, ms) in frame.passes {
+                match sums.iter_mut().find(|(l, _)| *l == label) {
+                    Some((_, sum)) => *sum += ms,
+                    None => sums.push((label, ms)),
+                }
+            }
+            for (label, ms) in sums {
+                match self.pass_samples.iter_mut().find(|(l, _)| *l == label) {
+                    Some((_, samples)) => samples.push(ms),
+                    None => self.pass_samples.push((label, vec![ms])),
+                }
+            }
+        }
+    }
+
+    /// 每帧结束后调用
+    /// 返回 Some(Some(i)) 表示该切到场景 i，Some(None) 表示全部跑完
+    pub(crate) fn advance(
+        &mut self,
+        studio: &Studio,
+        frame_times: &mut Vec<f32>,
+    ) -> Option<Option<usize>> {
+        let (index, phase, since) = self.current.as_mut()?;
+        let elapsed = since.elapsed().as_secs_f32();
+        match phase {
+            Phase::Warmup if elapsed >= self.options.warmup => {
+                // 预热期间的数据不要
+                frame_times.clear();
+                self.gpu_samples.clear();
+                self.pass_samples.clear();
+                *phase = Phase::Measure;
+                *since = Instant::now();
+                None
+            }
+            Phase::Measure if elapsed >= self.options.measure => {
+                let index = *index;
+                let report = SceneReport {
+                    index,
+                    name: studio.names[index].clone(),
+                    frames: frame_times.len(),
+                    mean_ms: mean(frame_times),
+                    p50_ms: percentile(frame_times, 50.0),
+                    p95_ms: percentile(frame_times, 95.0),
+                    p99_ms: percentile(frame_times, 99.0),
+                    gpu_mean_ms: (!self.gpu_samples.is_empty()).then(|| mean(&self.gpu_samples)),
+                    gpu_passes: self
+                        .pass_samples
+                        .iter()
+                        .map(|(label, samples)| (*label, mean(samples)))
+                        .collect(),
+                    counters: studio.current_counters(),
+                };
+                println!(
+                    "bench: [{}] {} frames {} p50 {:.2}ms p95 {:.2}ms p99 {:.2}ms",
+                    report.index,
+                    report.name,
+                    report.frames,
+                    report.p50_ms,
+                    report.p95_ms,
+                    report.p99_ms
+                );
+                for (label, ms) in &report.gpu_passes {
+                    println!("bench:     {} {:.3}ms", label, ms);
+                }
+                self.reports.push(report);
+                frame_times.clear();
+                let next = self.next_scene();
+                if next.is_none() {
+                    self.current = None;
+                    self.write_reports();
+                }
+                Some(next)
+            }
+            _ => None,
+        }
+    }
+
+    fn write_reports(&self) {
+        let json_path = self.options.out.with_extension("json");
+        let csv_path = self.options.out.with_extension("csv");
+        std::fs::write(&json_path, self.to_json()).unwrap();
+        std::fs::write(&csv_path, self.to_csv()).unwrap();
+        println!(
+            "bench: wrote {} and {}",
+            json_path.display(),
+            csv_path.display()
+        );
+    }
+
+    fn to_json(&self) -> String {
+        let commit = git_commit()
+            .map(|c| format!("\"{}\"", c))
+            .unwrap_or_else(|| "null".to_string());
+        let mut json = String::new();
+        writeln!(json, "{{").unwrap();
+        writeln!(json, "  \"commit\": {},", commit).unwrap();
+        writeln!(json, "  \"adapter\": \"{}\",", escape(&self.adapter.name)).unwrap();
+        writeln!(json, "  \"backend\": \"{:?}\",", self.adapter.backend).unwrap();
+        writeln!(
+            json,
+            "  \"device_type\": \"{:?}\",",
+            self.adapter.device_type
+        )
+        .unwrap();
+        writeln!(json, "  \"warmup_s\": {},", self.options.warmup).unwrap();
+        writeln!(json, "  \"measure_s\": {},", self.options.measure).unwrap();
+        writeln!(json, "  \"scenes\": [").unwrap();
+        for (i, report) in self.reports.iter().enumerate() {
+            let gpu = report
+                .gpu_mean_ms
+                .map(json_number)
+                .unwrap_or_else(|| "null".to_string());
+            let counters = report
+                .counters
+                .iter()
+                .map(|(name, value)| format!("\"{}\": {}", escape(name), json_number(*value)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(json, "    {{").unwrap();
+            writeln!(json, "      \"index\": {},", report.index).unwrap();
+            writeln!(json, "      \"name\": \"{}\",", escape(&report.name)).unwrap();
+            writeln!(json, "      \"frames\": {},", report.frames).unwrap();
+            writeln!(json, "      \"mean_ms\": {},", json_number(report.mean_ms)).unwrap();
+            writeln!(json, "      \"p50_ms\": {},", json_number(report.p50_ms)).unwrap();
+            writeln!(json, "      \"p95_ms\": {},", json_number(report.p95_ms)).unwrap();
+            writeln!(json, "      \"p99_ms\": {},", json_number(report.p99_ms)).unwrap();
+            writeln!(json, "      \"gpu_mean_ms\": {},", gpu).unwrap();
+            let passes = report
+                .gpu_passes
+                .iter()
+                .map(|(label, ms)| format!("\"{}\": {}", escape(label), json_number(*ms)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(json, "      \"gpu_passes_ms\": {{{}}},", passes).unwrap();
+            writeln!(json, "      \"counters\": {{{}}}", counters).unwrap();
+            let comma = if i + 1 < self.reports.len() { "," } else { "" };
+            writeln!(json, "    }}{}", comma).unwrap();
+        }
+        writeln!(json, "  ]").unwrap();
+        writeln!(json, "}}").unwrap();
+        json
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "index,name,frames,mean_ms,p50_ms,p95_ms,p99_ms,gpu_mean_ms,gpu_passes_ms,counters\n",
+        );
+        for report in &self.reports {
+            let counters = report
+                .counters
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join(";");
+            let passes = report
+                .gpu_passes
+                .iter()
+                .map(|(label, ms)| format!("{}={}", label, ms))
+                .collect::<Vec<_>>()
+                .join(";");
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{}",
+                report.index,
+                csv_field(&report.name),
+                report.frames,
+                report.mean_ms,
+                report.p50_ms,
+                report.p95_ms,
+                report.p99_ms,
+                report
+                    .gpu_mean_ms
+                    .map(|ms| ms.to_string())
+                    .unwrap_or_default(),
+                csv_field(&passes),
+                csv_field(&counters)
+            )
+            .unwrap();
+        }
+        csv
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// JSON 里没有 NaN 和 inf，写成 null
+fn json_number<T: Copy + Into<f64> + std::fmt::Display>(value: T) -> String {
+    if value.into().is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// 文本列都加引号，里面的引号写两遍，名字里有逗号也不会错列
+fn csv_field(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn git_commit() -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 一帧最多量多少个 pass，多出来的不计时
+const MAX_TIMED_PASSES: u32 = 32;
+/// 0 和 1 是整帧的开始和结束，后面每个 pass 占两个
+const QUERY_COUNT: u32 = 2 + MAX_TIMED_PASSES * 2;
+/// 读回的缓冲轮着用，GPU 落后几帧也不用等
+const READBACK_RING: usize = 3;
+const MAP_PENDING: u8 = 0;
+const MAP_OK: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// 场景在 pass 描述里通过 GfxContext::render_timestamps 拿 timestamp_writes
+/// 只在跑分时打开，平时返回 None
+pub(crate) struct PassTimestamps {
+    query_set: wgpu::QuerySet,
+    armed: Cell<bool>,
+    /// 这一帧按顺序登记的 pass
+    labels: RefCell<Vec<&'static str>>,
+}
+
+impl PassTimestamps {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("bench timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: QUERY_COUNT,
+            }),
+            armed: Cell::new(false),
+            labels: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// 给 pass 分配一对 query，返回开始的序号
+    fn allocate(&self, label: &'static str) -> Option<u32> {
+        if !self.armed.get() {
+            return None;
+        }
+        let mut labels = self.labels.borrow_mut();
+        if labels.len() as u32 >= MAX_TIMED_PASSES {
+            return None;
+        }
+        labels.push(label);
+        Some(labels.len() as u32 * 2)
+    }
+
+    pub(crate) fn render_writes(
+        &self,
+        label: &'static str,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let index = self.allocate(label)?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    pub(crate) fn compute_writes(
+        &self,
+        label: &'static str,
+    ) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        let index = self.allocate(label)?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+}
+
+/// 一帧的 GPU 时间，毫秒
+struct GpuFrame {
+    total: f32,
+    passes: Vec<(&'static str, f32)>,
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    /// map_async 的回调里写 MAP_OK 或 MAP_FAILED
+    state: Arc<AtomicU8>,
+    /// None 表示空闲
+    labels: Option<Vec<&'static str>>,
+}
+
+/// 用两个空的 compute pass 把场景这一帧提交的所有命令夹在中间，
+/// 中间的 pass 各自写 timestamp；结果晚几帧非阻塞地读回
+struct GpuTimer {
+    resolve_buffer: wgpu::Buffer,
+    ring: Vec<Readback>,
+    period: f32,
+}
+
+impl GpuTimer {
+    fn new(context: &gfx::GfxContext) -> Option<Self> {
+        let timestamps = context.timestamps.as_ref()?;
+        timestamps.armed.set(true);
+        let size = QUERY_COUNT as wgpu::BufferAddress * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bench resolve"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let ring = (0..READBACK_RING)
+            .map(|_| Readback {
+                buffer: context.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("bench read"),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                state: Arc::new(AtomicU8::new(MAP_PENDING)),
+                labels: None,
+            })
+            .collect();
+        Some(Self {
+            resolve_buffer,
+            ring,
+            period: context.queue.get_timestamp_period(),
+        })
+    }
+
+    fn stamp(timestamps: &PassTimestamps, encoder: &mut wgpu::CommandEncoder, index: u32) {
+        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("bench timestamp"),
+            timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                query_set: &timestamps.query_set,
+                beginning_of_pass_write_index: Some(index),
+                end_of_pass_write_index: None,
+            }),
+        });
+    }
+
+    fn begin(&self, context: &gfx::GfxContext) {
+        let timestamps = context.timestamps.as_ref().unwrap();
+        timestamps.labels.borrow_mut().clear();
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        Self::stamp(timestamps, &mut encoder, 0);
+        context.queue.submit(Some(encoder.finish()));
+    }
+
+    /// 提交这一帧的读回，返回之前已经读回来的帧
+    fn end(&mut self, context: &gfx::GfxContext) -> Vec<GpuFrame> {
+        let timestamps = context.timestamps.as_ref().unwrap();
+        let labels = std::mem::take(&mut *timestamps.labels.borrow_mut());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        Self::stamp(timestamps, &mut encoder, 1);
+        // 环里都在等 GPU 时这一帧就不计了
+        if let Some(slot) = self.ring.iter_mut().find(|slot| slot.labels.is_none()) {
+            let used = 2 + labels.len() as u32 * 2;
+            let bytes = used as u64 * std::mem::size_of::<u64>() as u64;
+            encoder.resolve_query_set(&timestamps.query_set, 0..used, &self.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &slot.buffer, 0, bytes);
+            context.queue.submit(Some(encoder.finish()));
+
+            let state = slot.state.clone();
+            slot.buffer
+                .slice(..bytes)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let mapped = if result.is_ok() { MAP_OK } else { MAP_FAILED };
+                    state.store(mapped, Ordering::Release);
+                });
+            slot.labels = Some(labels);
+        } else {
+            context.queue.submit(Some(encoder.finish()));
+        }
+        context.device.poll(wgpu::Maintain::Poll);
+
+        let period = self.period;
+        let ms = |ticks: [u64; 2]| {
+            ticks[1]
+                .checked_sub(ticks[0])
+                .map(|t| t as f32 * period / 1_000_000.0)
+        };
+        let mut frames = Vec::new();
+        for slot in &mut self.ring {
+            match slot.state.swap(MAP_PENDING, Ordering::Acquire) {
+                MAP_OK => {}
+                MAP_FAILED => {
+                    slot.labels = None;
+                    continue;
+                }
+                _ => continue,
+            }
+            let labels = slot.labels.take().unwrap();
+            let bytes = (2 + labels.len() as u64 * 2) * 8;
+            let ticks: Vec<u64> = slot
+                .buffer
+                .slice(..bytes)
+                .get_mapped_range()
+                .chunks_exact(8)
+                .map(|t| u64::from_le_bytes(t.try_into().unwrap()))
+                .collect();
+            slot.buffer.unmap();
+            let Some(total) = ms([ticks[0], ticks[1]]) else {
+                continue;
+            };
+            let passes = labels
+                .iter()
+                .enumerate()
+                .filter_map(|(i, &label)| Some((label, ms([ticks[2 + i * 2], ticks[3 + i * 2]])?)))
+                .collect();
+            frames.push(GpuFrame { total, passes });
+        }
+        frames
+    }
+}
@@ -82,7 +82,7 @@ impl ScenePainter for GUISceneExample {
                     view: &view,
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("egui render pass"),
                 occlusion_query_set: None,
             });
         }
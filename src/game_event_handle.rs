@@ -31,7 +31,9 @@ impl ApplicationHandler for GameEntry {
         event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame_time));
         match self {
             GameEntry::Ready(game) => {}
-            GameEntry::Loading => {
+            GameEntry::Loading(bench) => {
+                let bench = bench.take();
+                let software = bench.as_ref().is_some_and(|b| b.software);
                 let window = Arc::new(
                     event_loop
                         .create_window(
@@ -43,9 +45,9 @@ impl ApplicationHandler for GameEntry {
                 window.set_outer_position(*OUTTER_SIZE);
                 pollster::block_on(async move {
                     println!("in async : Loading");
-                    let context = gfx::GfxContext::new(window.clone()).await;
+                    let context = gfx::GfxContext::new(window.clone(), software).await;
                     let context = Arc::new(Mutex::new(context));
-                    let game = Game::new(window, context.clone());
+                    let game = Game::new(window, context.clone(), bench);
                     *self = GameEntry::Ready(game);
                     println!("in async : Ready");
                 });
@@ -58,7 +60,9 @@ impl ApplicationHandler for GameEntry {
             let now = std::time::Instant::now();
             let delta_time = now - game.last_update;
             let time = *game.time.lock().unwrap() + delta_time.as_secs_f32();
-            if delta_time >= *FRAME_DURATION {
+            // 跑分时不限帧率
+            let bench = game.bench.is_some();
+            if bench || delta_time >= *FRAME_DURATION {
                 // 更新游戏逻辑
                 game.update_game(delta_time.as_secs_f32(), time); 
 
@@ -69,8 +73,12 @@ impl ApplicationHandler for GameEntry {
             }
 
             // 计算下一帧的时间
-            let next_frame_time = game.last_update + *FRAME_DURATION;
-            event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame_time));
+            if bench {
+                event_loop.set_control_flow(ControlFlow::Poll);
+            } else {
+                let next_frame_time = game.last_update + *FRAME_DURATION;
+                event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame_time));
+            }
         }
     }
 
@@ -89,6 +97,9 @@ impl ApplicationHandler for GameEntry {
                     .unwrap()
                     .handle_input(&game.window, &event);
             }
+            if let Some(studio) = game.studio.as_ref() {
+                studio.input_current_scene(&event);
+            }
 
             match event {
                 winit::event::WindowEvent::Resized(size) => {
@@ -97,6 +108,9 @@ impl ApplicationHandler for GameEntry {
                     // now arrivate the normal full size in window
                     game.set_gui();
                     game.list_painter();
+                    if !game.start_bench() {
+                        event_loop.exit();
+                    }
                     game.window.request_redraw();
                 }
                 winit::event::WindowEvent::Moved(_) => {
@@ -122,15 +136,29 @@ impl ApplicationHandler for GameEntry {
                             if physical_key == keyboard::PhysicalKey::Code(KeyCode::Space) {
                                 game.mount_next_scene();
                             }
+                            // 轮换调试视图，所有接入了 ViewModes 的场景共用
+                            if physical_key == keyboard::PhysicalKey::Code(KeyCode::KeyV) {
+                                let context = game.context.lock().unwrap();
+                                context.view_mode.set(context.view_mode.get().next());
+                            }
                         }
                     }
                 },
                 winit::event::WindowEvent::RedrawRequested => {
                     println!("RedrawRequested");
+                    if let Some(bench) = &game.bench {
+                        bench.begin_frame(&game.context.lock().unwrap());
+                    }
                     game.studio.as_ref().unwrap().render_current_scene(
                         *game.delta_time.clone().lock().unwrap(),
                         *game.time.clone().lock().unwrap(),
                     );
+                    if let Some(bench) = &mut game.bench {
+                        bench.end_frame(&game.context.lock().unwrap());
+                    }
+                    if !game.advance_bench() {
+                        event_loop.exit();
+                    }
                 }
                 _ => {}
             }
@@ -1,30 +1,64 @@
-use std::sync::Arc;
+use std::{cell::Cell, sync::Arc};
 use wgpu::RequestAdapterOptions;
 use winit::window::Window;
 
+use crate::{assets::Assets, bench::PassTimestamps, utils::view_modes::ViewMode};
+
 pub(crate) struct GfxContext {
     pub(crate) adapter: wgpu::Adapter,
     pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
     pub(crate) surface: wgpu::Surface<'static>,
     pub(crate) surface_config: Option<wgpu::SurfaceConfiguration>,
+    /// 各个场景共用的网格、贴图、shader
+    pub(crate) assets: Assets,
+    /// 全局的调试视图，按 V 轮换，接入了 ViewModes 的场景才有效果
+    pub(crate) view_mode: Cell<ViewMode>,
+    /// 跑分模式下量每个 pass 的 GPU 时间，设备不支持 timestamp query 时为 None
+    pub(crate) timestamps: Option<PassTimestamps>,
 }
 
 impl GfxContext {
-    pub(crate) async fn new(window: Arc<Window>) -> Self {
+    /// force_fallback_adapter 为 true 时用软件实现，跑分对比用
+    pub(crate) async fn new(window: Arc<Window>, force_fallback_adapter: bool) -> Self {
         let instance = wgpu::Instance::default();
 
         let adapter = instance
-            .request_adapter(&RequestAdapterOptions::default())
+            .request_adapter(&RequestAdapterOptions {
+                force_fallback_adapter,
+                ..Default::default()
+            })
             .await
             .unwrap();
 
+        // 压缩纹理格式能开的都开，不支持的由纹理加载器在 CPU 上解压
+        // timestamp query 给跑分模式量 GPU 时间
+        // 线框的调试视图有 POLYGON_MODE_LINE 就用，没有就用重心坐标画
+        // Rgba32Float 纹理要 FLOAT32_FILTERABLE 才能线性采样，没有就降成 Rgba16Float
+        let required_features = adapter.features()
+            & (wgpu::Features::TEXTURE_COMPRESSION_BC
+                | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                | wgpu::Features::TEXTURE_COMPRESSION_ASTC
+                | wgpu::Features::TIMESTAMP_QUERY
+                | wgpu::Features::POLYGON_MODE_LINE
+                | wgpu::Features::FLOAT32_FILTERABLE);
+
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features,
+                    ..Default::default()
+                },
+                None,
+            )
             .await
             .unwrap();
 
         let surface = unsafe { instance.create_surface(window.clone()).unwrap() };
+        let timestamps = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| PassTimestamps::new(&device));
 
         GfxContext {
             device,
@@ -32,6 +66,24 @@ impl GfxContext {
             surface,
             adapter,
             surface_config: None,
+            assets: Assets::new(),
+            view_mode: Cell::new(ViewMode::Shaded),
+            timestamps,
         }
     }
+
+    /// 填到 RenderPassDescriptor 的 timestamp_writes，不在跑分时是 None
+    pub(crate) fn render_timestamps(
+        &self,
+        label: &'static str,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.timestamps.as_ref()?.render_writes(label)
+    }
+
+    pub(crate) fn compute_timestamps(
+        &self,
+        label: &'static str,
+    ) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        self.timestamps.as_ref()?.compute_writes(label)
+    }
 }
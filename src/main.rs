@@ -21,12 +21,14 @@ mod time_world;
 
 fn main() {
     let event_loop = EventLoop::new().unwrap();
-    let mut game = GameEntry::Loading;
+    let bench = bench::BenchOptions::from_args(std::env::args().skip(1));
+    let mut game = GameEntry::Loading(bench);
     let _ = event_loop.run_app(&mut game);
 }
 
 enum GameEntry {
-    Loading,
+    /// 带着命令行里的跑分参数
+    Loading(Option<bench::BenchOptions>),
     Ready(Game),
 }
 
@@ -40,6 +42,7 @@ pub struct Game {
     pub frame_counter: time_world::FrameCounter,
     pub delta_time: Arc<Mutex<f32>>,
     pub time: Arc<Mutex<f32>>,
+    pub(crate) bench: Option<bench::BenchRunner>,
 }
 
 mod gfx;
@@ -58,13 +61,61 @@ impl Game {
     fn list_painter(&mut self) {
         let context: Arc<Mutex<gfx::GfxContext>> = self.context.clone();
         let mut studio_var = studio::Studio::new(context);
-        studio_var.add_scene::<studio::try_simple_2d_light_shadow::Simple2DLightShadow, _>(());
-        studio_var.add_scene::<studio::shader_playgroud_scene::ShaderPlaygroundScene, _>(());
-        studio_var.add_scene::<studio::depth_buffer_example::DepthBufferExample, _>(());
-        studio_var.add_scene::<studio::circle_instances::CircleInstancesScene, _>(());
+        studio_var.add_scene::<studio::try_simple_2d_light_shadow::Simple2DLightShadow, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::light::demo::LightingDemo, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::light::shadow_demo::ShadowDemo, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::light::cascade_demo::CascadeDemo, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::mesh_on_ray::MeshOnRay, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::sdf::SdfRaymarch, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::path_tracer::PathTracer, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::scene_file::SceneFileScene, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+            "asset/scenes/demo.scene",
+        ));
+        studio_var.add_scene::<studio::text_demo::TextDemo, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::shader_playgroud_scene::ShaderPlaygroundScene, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::depth_buffer_example::DepthBufferExample, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
+        studio_var.add_scene::<studio::circle_instances::CircleInstancesScene, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
         studio_var
             .add_scene::<studio::uniform_matrix_and_transform_in_shader::UniformMatrixAtGpu, _>(());
-        studio_var.add_scene::<studio::instances::InstanceScene, _>(());
+        studio_var.add_scene::<studio::instances::InstanceScene, _>((
+            self.window.clone(),
+            self.gui.as_ref().unwrap().clone(),
+        ));
         studio_var
             .add_scene::<egui::first::GUISceneExample, (Arc<Window>, Arc<Mutex<EguiRenderer>>,Rc<RefCell<f32>>)>((
                 self.window.clone(),
@@ -72,12 +123,32 @@ impl Game {
                 self.frame_counter.fps.clone(),
             ));
         studio_var.add_scene::<studio::cube::CubeScene, _>(());
-        studio_var.add_scene::<studio::bunnymark::BunnyMarkScene, _>(());
+        // 同一个 bunnymark 用不同的绘制路径注册多次，方便对比
+        for path in [
+            studio::bunnymark::BunnyMarkPath::SpriteBatch,
+            studio::bunnymark::BunnyMarkPath::Storage,
+            studio::bunnymark::BunnyMarkPath::Compute,
+            studio::bunnymark::BunnyMarkPath::PerDraw,
+        ] {
+            studio_var.add_scene::<studio::bunnymark::BunnyMarkScene, _>((
+                path,
+                self.window.clone(),
+                self.gui.as_ref().unwrap().clone(),
+                self.frame_counter.fps.clone(),
+            ));
+        }
         studio_var.add_scene::<studio::texture_example::TextureExample, _>(());
         studio_var.initialize_scene(self.scene_index);
         self.studio = Some(studio_var);
     }
-    fn new(window: Arc<Window>, context: Arc<Mutex<gfx::GfxContext>>) -> Self {
+    fn new(
+        window: Arc<Window>,
+        context: Arc<Mutex<gfx::GfxContext>>,
+        bench: Option<bench::BenchOptions>,
+    ) -> Self {
+        let bench = bench.map(|options| bench::BenchRunner::new(&context.lock().unwrap(), options));
+        let mut frame_counter = time_world::FrameCounter::new();
+        frame_counter.record_frame_times = bench.is_some();
         Self {
             window,
             context: context.clone(),
@@ -85,9 +156,44 @@ impl Game {
             studio: None,
             last_update: std::time::Instant::now(),
             gui: None,
-            frame_counter: time_world::FrameCounter::new(),
+            frame_counter,
             delta_time: Arc::new(Mutex::new(0.0)),
             time: Arc::new(Mutex::new(0.0)),
+            bench,
+        }
+    }
+    /// 场景列好之后开始跑分，返回 false 表示没有要跑的场景
+    fn start_bench(&mut self) -> bool {
+        let (Some(bench), Some(studio)) = (self.bench.as_mut(), self.studio.as_mut()) else {
+            return true;
+        };
+        if bench.started() {
+            return true;
+        }
+        match bench.start(studio) {
+            Some(index) => {
+                self.scene_index = index;
+                studio.initialize_scene(index);
+                studio.prepare_bench();
+                true
+            }
+            None => false,
+        }
+    }
+    /// 每帧画完之后调用，返回 false 表示跑分结束
+    fn advance_bench(&mut self) -> bool {
+        let (Some(bench), Some(studio)) = (self.bench.as_mut(), self.studio.as_mut()) else {
+            return true;
+        };
+        match bench.advance(studio, &mut self.frame_counter.frame_times) {
+            Some(Some(index)) => {
+                self.scene_index = index;
+                studio.initialize_scene(index);
+                studio.prepare_bench();
+                true
+            }
+            Some(None) => false,
+            None => true,
         }
     }
     fn mount_next_scene(&mut self) {
@@ -127,8 +233,18 @@ impl Game {
     }
 }
 
+mod assets;
+
+mod bench;
+
 mod game_event_handle;
 
 mod painter;
 
+mod sprite;
+
+mod text;
+
+mod texture;
+
 mod utils;
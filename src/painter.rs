@@ -18,12 +18,25 @@ use wgpu::TextureView;
 
 pub trait Painter {
     fn paint(&mut self, context: &gfx::GfxContext, dt: f32, time: f32);
+    /// 窗口事件，需要键盘鼠标的场景自己实现
+    fn input(&mut self, _event: &winit::event::WindowEvent) {}
+    /// 跑分时一起记录的场景数据，比如兔子数量
+    fn counters(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+    /// 跑分模式切到这个场景后调用一次，场景在这里把工作量固定下来
+    fn prepare_bench(&mut self) {}
 }
 pub(crate) trait Sandy {
     type Extra;
     fn ready(context: &gfx::GfxContext, extra: Self::Extra) -> Self
     where
         Self: Sized;
+    /// 跑分报告里的场景名，同一个类型用不同参数注册多次时要区分开
+    fn name(_extra: &Self::Extra) -> String {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap().to_string()
+    }
 }
 
 pub(crate) struct VertexBuff {
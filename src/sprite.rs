@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{gfx, texture::TextureBundle};
+
+pub(crate) mod atlas;
+
+// 2D 精灵批量绘制
+// 所有精灵共用一张图集，每个精灵是一条实例数据，整批只有一次 draw
+
+/// 一个精灵的实例数据，position 是中心点
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct SpriteInstance {
+    pub(crate) position: [f32; 2],
+    pub(crate) size: [f32; 2],
+    /// 图集里的 uv 范围，来自 AtlasRegion::uv
+    pub(crate) uv: [f32; 4],
+    /// 0xAABBGGRR
+    pub(crate) color: u32,
+    /// 弧度
+    pub(crate) rotation: f32,
+}
+
+impl SpriteInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x4,
+        3 => Uint32,
+        4 => Float32,
+    ];
+
+    pub(crate) fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub(crate) struct SpriteBatch {
+    pipeline: wgpu::RenderPipeline,
+    globals_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+    instances: Vec<SpriteInstance>,
+}
+
+impl SpriteBatch {
+    /// capacity 只是初始大小，放不下时 buffer 会翻倍
+    pub(crate) fn new(
+        context: &gfx::GfxContext,
+        texture: &TextureBundle,
+        format: wgpu::TextureFormat,
+        capacity: usize,
+    ) -> Self {
+        let globals_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("sprite globals"),
+                contents: bytemuck::bytes_of(&glam::Mat4::IDENTITY.to_cols_array_2d()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sprite bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                });
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sprite bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: globals_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                    },
+                ],
+            });
+
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sprite pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+        let shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("sprite shader"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("sprite/sprite.wgsl"))),
+            });
+        let pipeline = context
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("sprite pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: Default::default(),
+                    buffers: &[SpriteInstance::layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        let capacity = capacity.max(1);
+        Self {
+            pipeline,
+            globals_buffer,
+            bind_group,
+            instance_buffer: Self::create_instance_buffer(context, capacity),
+            capacity,
+            instances: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn create_instance_buffer(context: &gfx::GfxContext, capacity: usize) -> wgpu::Buffer {
+        context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sprite instances"),
+            size: (capacity * std::mem::size_of::<SpriteInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// 屏幕像素坐标，左下角是原点
+    pub(crate) fn set_screen_size(&self, context: &gfx::GfxContext, width: f32, height: f32) {
+        let projection = glam::Mat4::orthographic_rh(0.0, width, 0.0, height, -1.0, 1.0);
+        context.queue.write_buffer(
+            &self.globals_buffer,
+            0,
+            bytemuck::bytes_of(&projection.to_cols_array_2d()),
+        );
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    pub(crate) fn push(&mut self, sprite: SpriteInstance) {
+        self.instances.push(sprite);
+    }
+
+    /// 在 render pass 之前把这一帧的实例写进 buffer
+    pub(crate) fn upload(&mut self, context: &gfx::GfxContext) {
+        if self.instances.len() > self.capacity {
+            self.capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(context, self.capacity);
+        }
+        context.queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+    }
+
+    pub(crate) fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        if self.instances.is_empty() {
+            return;
+        }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        rpass.draw(0..4, 0..self.instances.len() as u32);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use image::{DynamicImage, RgbaImage};
+
+use crate::{
+    gfx,
+    texture::{MipGeneration, TextureBundle, TextureError, TextureOptions},
+};
+
+// 运行时把一堆小图拼成一张大图
+// 用的是最简单的 shelf 排布：按高度从高到低，一行一行往下摆
+
+/// 每张图四周留的空隙，避免线性过滤时采到隔壁
+const PADDING: u32 = 2;
+
+/// 图集里一张图的位置
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct AtlasRegion {
+    /// 像素坐标 x, y, w, h
+    pub(crate) rect: [u32; 4],
+    /// uv 的 min.x, min.y, max.x, max.y
+    pub(crate) uv: [f32; 4],
+}
+
+#[derive(Default)]
+pub(crate) struct AtlasBuilder {
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl AtlasBuilder {
+    pub(crate) fn add_image(&mut self, name: impl Into<String>, image: RgbaImage) -> &mut Self {
+        self.images.push((name.into(), image));
+        self
+    }
+
+    pub(crate) fn add_png_bytes(
+        &mut self,
+        name: impl Into<String>,
+        bytes: &[u8],
+    ) -> Result<&mut Self, TextureError> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        Ok(self.add_image(name, image))
+    }
+
+    /// 排好位置、拼成一张图并上传；设备上最大的贴图都放不下就返回错误
+    pub(crate) fn build(
+        self,
+        context: &gfx::GfxContext,
+        label: Option<&str>,
+    ) -> Result<Atlas, String> {
+        let max_size = context.device.limits().max_texture_dimension_2d;
+        let sizes: Vec<[u32; 2]> = self
+            .images
+            .iter()
+            .map(|(_, image)| [image.width(), image.height()])
+            .collect();
+        let (atlas_size, rects) = pack(&sizes, max_size)?;
+
+        let mut pixels = RgbaImage::new(atlas_size[0], atlas_size[1]);
+        let mut regions = Vec::with_capacity(rects.len());
+        let mut names = HashMap::new();
+        for ((name, image), rect) in self.images.into_iter().zip(rects) {
+            image::imageops::replace(&mut pixels, &image, rect[0] as i64, rect[1] as i64);
+            let uv = [
+                rect[0] as f32 / atlas_size[0] as f32,
+                rect[1] as f32 / atlas_size[1] as f32,
+                (rect[0] + rect[2]) as f32 / atlas_size[0] as f32,
+                (rect[1] + rect[3]) as f32 / atlas_size[1] as f32,
+            ];
+            names.insert(name, regions.len());
+            regions.push(AtlasRegion { rect, uv });
+        }
+        println!(
+            "packed {} sprites into a {}x{} atlas",
+            regions.len(),
+            atlas_size[0],
+            atlas_size[1]
+        );
+
+        let texture = TextureBundle::from_image(
+            context,
+            DynamicImage::ImageRgba8(pixels),
+            TextureOptions {
+                label,
+                mips: MipGeneration::None,
+                ..Default::default()
+            },
+        );
+        Ok(Atlas {
+            texture,
+            regions,
+            names,
+        })
+    }
+}
+
+/// 打包好的图集，regions 就是 uv 表
+pub(crate) struct Atlas {
+    pub(crate) texture: TextureBundle,
+    pub(crate) regions: Vec<AtlasRegion>,
+    names: HashMap<String, usize>,
+}
+
+impl Atlas {
+    pub(crate) fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    pub(crate) fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.index_of(name).map(|index| &self.regions[index])
+    }
+}
+
+/// 返回图集大小和每张图的 [x, y, w, h]，顺序和输入一致
+/// 从能装下总面积的最小 2 的幂开始试，放不下就把短边翻倍，超过 max_size 还放不下就报错
+fn pack(sizes: &[[u32; 2]], max_size: u32) -> Result<([u32; 2], Vec<[u32; 4]>), String> {
+    let area: u64 = sizes
+        .iter()
+        .map(|[w, h]| ((w + PADDING * 2) * (h + PADDING * 2)) as u64)
+        .sum();
+    let widest = sizes
+        .iter()
+        .map(|[w, _]| w + PADDING * 2)
+        .max()
+        .unwrap_or(1);
+    let side = ((area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .next_power_of_two();
+    let mut atlas_size = [side, side];
+
+    // 高的先摆，每一行的高度由第一个决定
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i][1]));
+
+    loop {
+        if atlas_size[0] > max_size || atlas_size[1] > max_size {
+            return Err(format!(
+                "{} sprites don't fit into a {}x{} atlas",
+                sizes.len(),
+                max_size,
+                max_size
+            ));
+        }
+        if let Some(rects) = shelf(sizes, &order, atlas_size) {
+            return Ok((atlas_size, rects));
+        }
+        if atlas_size[0] <= atlas_size[1] {
+            atlas_size[0] *= 2;
+        } else {
+            atlas_size[1] *= 2;
+        }
+    }
+}
+
+fn shelf(sizes: &[[u32; 2]], order: &[usize], atlas_size: [u32; 2]) -> Option<Vec<[u32; 4]>> {
+    let mut rects = vec![[0; 4]; sizes.len()];
+    let (mut x, mut y, mut row_height) = (0, 0, 0);
+    for &i in order {
+        let [w, h] = sizes[i];
+        let (padded_w, padded_h) = (w + PADDING * 2, h + PADDING * 2);
+        if x + padded_w > atlas_size[0] {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        if x + padded_w > atlas_size[0] || y + padded_h > atlas_size[1] {
+            return None;
+        }
+        rects[i] = [x + PADDING, y + PADDING, w, h];
+        x += padded_w;
+        row_height = row_height.max(padded_h);
+    }
+    Some(rects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_into_smallest_atlas() {
+        let (size, rects) = pack(&[[60, 60], [60, 60]], 4096).unwrap();
+        assert_eq!(size, [128, 128]);
+        assert_eq!(rects, [[2, 2, 60, 60], [66, 2, 60, 60]]);
+    }
+
+    #[test]
+    fn reports_sprites_that_dont_fit() {
+        assert!(pack(&[[100, 100]], 64).is_err());
+        assert!(pack(&[[60, 60]; 5], 128).is_err());
+    }
+}
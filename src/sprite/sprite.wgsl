@@ -0,0 +1,48 @@
+struct Globals {
+    mvp: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> globals: Globals;
+
+struct InstanceInput {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) uv: vec4<f32>,
+    @location(3) color: u32,
+    @location(4) rotation: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vi: u32, sprite: InstanceInput) -> VertexOutput {
+    // 4 个顶点的 triangle strip，corner 是 (0,0) (1,0) (0,1) (1,1)
+    let corner = vec2<f32>(f32(vi & 1u), 0.5 * f32(vi & 2u));
+    let local = (corner - 0.5) * sprite.size;
+    let c = cos(sprite.rotation);
+    let s = sin(sprite.rotation);
+    let rotated = vec2<f32>(local.x * c - local.y * s, local.x * s + local.y * c);
+    let pos = globals.mvp * vec4<f32>(sprite.position + rotated, 0.0, 1.0);
+    // 屏幕 y 向上，图片 v 向下
+    let tex_coords = mix(sprite.uv.xy, sprite.uv.zw, vec2<f32>(corner.x, 1.0 - corner.y));
+    let color = vec4<f32>((vec4<u32>(sprite.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+    return VertexOutput(pos, tex_coords, color);
+}
+
+@group(0)
+@binding(1)
+var tex: texture_2d<f32>;
+@group(0)
+@binding(2)
+var sam: sampler;
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return vertex.color * textureSampleLevel(tex, sam, vertex.tex_coords, 0.0);
+}
@@ -23,10 +23,16 @@ pub mod shader_playgroud_scene;
 pub mod mesh_on_ray;
 pub mod try_simple_2d_light_shadow;
 pub mod light;
+pub mod sdf;
+pub mod path_tracer;
+pub mod scene_file;
+pub mod text_demo;
 
 pub struct Studio {
     context: Arc<Mutex<gfx::GfxContext>>,
     pub(crate) ready_functions: Vec<Box<dyn Fn(&gfx::GfxContext) -> Box<dyn Painter>>>,
+    /// 和 ready_functions 一一对应，跑分报告里用
+    pub(crate) names: Vec<String>,
     current_scene: Option<Rc<RefCell<Box<dyn Painter>>>>,
 }
 impl Studio {
@@ -34,6 +40,7 @@ impl Studio {
         Studio {
             context,
             ready_functions: Vec::new(),
+            names: Vec::new(),
             current_scene: None,
         }
     }
@@ -43,6 +50,7 @@ impl Studio {
         T: Sandy<Extra = E> + Painter + 'static,
         E: 'static + Clone,
     {
+        self.names.push(T::name(&extra));
         // 处理 Copy 类型
         if std::mem::needs_drop::<E>() {
             // 非 Copy 类型
@@ -68,9 +76,29 @@ impl Studio {
         }
     }
 
+    pub(crate) fn prepare_bench(&self) {
+        if let Some(scene) = &self.current_scene {
+            scene.borrow_mut().prepare_bench();
+        }
+    }
+
+    pub fn input_current_scene(&self, event: &winit::event::WindowEvent) {
+        if let Some(scene) = &self.current_scene {
+            scene.borrow_mut().input(event);
+        }
+    }
+
+    pub fn current_counters(&self) -> Vec<(&'static str, f64)> {
+        match &self.current_scene {
+            Some(scene) => scene.borrow().counters(),
+            None => Vec::new(),
+        }
+    }
+
     pub fn render_current_scene(&self, dt: f32, time: f32) {
         if let Some(scene) = &self.current_scene {
             let context = self.context.lock().unwrap();
+            context.assets.update(&context);
             scene.borrow_mut().paint(&context, dt, time);
         }
     }
@@ -1,144 +1,428 @@
-use std::{borrow::Cow, ops::Deref, rc::Rc};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 use bytemuck::{Pod, Zeroable};
+use egui::Align2;
 use nanorand::{Rng, WyRand};
-use wgpu::{util::DeviceExt, FragmentState};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::Window,
+};
 
-use crate::painter::{Painter, Sandy};
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    sprite::{
+        atlas::{Atlas, AtlasBuilder},
+        SpriteBatch, SpriteInstance,
+    },
+};
 
 const MAX_BUNNIES: usize = 1 << 20;
 const BUNNY_SIZE: f32 = 0.15 * 256.0;
 const GRAVITY: f32 = -9.8 * 100.0;
 const MAX_VELOCITY: f32 = 750.0;
+/// 自动生成时每帧新增的数量，直到 MAX_BUNNIES
+const SPAWN_PER_FRAME: usize = 1024;
+/// 跑分时一次性生成的数量，预热和测量期间保持不变
+const BENCH_BUNNIES: usize = 100_000;
+const WORKGROUP_SIZE: u32 = 64;
+
+/// 兔子画到屏幕上的几种方式，注册成不同的场景来对比吞吐
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BunnyMarkPath {
+    /// 每只兔子一次 draw，uniform 用 dynamic offset 切换
+    PerDraw,
+    /// 精灵批，实例数据走 vertex buffer
+    SpriteBatch,
+    /// 兔子整个放进 storage buffer，vertex shader 用 instance_index 取
+    Storage,
+    /// 和 Storage 一样画，但物理在 compute shader 里算，CPU 只负责生成
+    Compute,
+}
+
+enum BunnyRenderer {
+    PerDraw {
+        pipeline: wgpu::RenderPipeline,
+        global_group: wgpu::BindGroup,
+        local_group: wgpu::BindGroup,
+        local_buffer: wgpu::Buffer,
+        uniform_alignment: wgpu::BufferAddress,
+    },
+    SpriteBatch(SpriteBatch),
+    Storage {
+        pipeline: wgpu::RenderPipeline,
+        global_group: wgpu::BindGroup,
+        storage_group: wgpu::BindGroup,
+        storage_buffer: wgpu::Buffer,
+        simulation: Option<ComputeThing>,
+    },
+}
 
 impl Sandy for BunnyMarkScene {
-    type Extra = ();
-    fn ready(context: &crate::gfx::GfxContext, _: Self::Extra) -> Self {
-        // 开始前，理清楚一下有什么内容
-        // global的一些变量和buffer资源
-        // texture的buffer写入和布局
-        // 生成一下顶点情况
-        let config = context.surface_config.as_ref().unwrap();
-        let GlobalThing {
-            global_bind_group_layout,
-            uniform_alignment,
-            config,
-            global_group,
-        } = GlobalThing::ready(context, config);
-        let LocalThing {
-            local_bind_group_layout,
-            local_buffer,
-            local_group,
-        } = LocalThing::ready(context, uniform_alignment);
-        let pipeline_layout =
-            context
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("pipeline_layout"),
-                    bind_group_layouts: &[&global_bind_group_layout, &local_bind_group_layout],
-                    push_constant_ranges: &[],
-                });
-        let shader = context
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("shader"),
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-                    "shader/bunnymark.wgsl"
-                ))),
-            });
-        let pipeline = context
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    compilation_options: Default::default(),
-                    buffers: &[],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: config.view_formats[0],
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::default(),
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleStrip,
-                    strip_index_format: Some(wgpu::IndexFormat::Uint16),
-                    ..wgpu::PrimitiveState::default()
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            });
+    type Extra = (
+        BunnyMarkPath,
+        Arc<Window>,
+        Arc<Mutex<EguiRenderer>>,
+        Rc<RefCell<f32>>,
+    );
+    fn name((path, ..): &Self::Extra) -> String {
+        format!("BunnyMarkScene/{:?}", path)
+    }
+    fn ready(context: &crate::gfx::GfxContext, (path, window, egui, fps): Self::Extra) -> Self {
+        // 图集里放两张：朝右的和水平翻转后朝左的
+        let icon = image::load_from_memory(include_bytes!("../icon512.png"))
+            .unwrap()
+            .resize_exact(64, 64, image::imageops::FilterType::Triangle)
+            .to_rgba8();
+        let flipped = image::imageops::flip_horizontal(&icon);
+        let mut builder = AtlasBuilder::default();
+        builder
+            .add_image("bunny", icon)
+            .add_image("bunny_flipped", flipped);
+        let atlas = builder.build(context, Some("bunny atlas")).unwrap();
+        let uv = [
+            atlas.region("bunny").unwrap().uv,
+            atlas.region("bunny_flipped").unwrap().uv,
+        ];
 
-        let rng = WyRand::new_seed(42);
+        let config = context.surface_config.as_ref().unwrap();
+        println!("BunnyMark path: {:?}", path);
+        let renderer = match path {
+            BunnyMarkPath::PerDraw => {
+                let GlobalThing {
+                    global_bind_group_layout,
+                    global_group,
+                    ..
+                } = GlobalThing::ready(context, (config, &atlas, uv));
+                let LocalThing {
+                    local_bind_group_layout,
+                    local_buffer,
+                    local_group,
+                    uniform_alignment,
+                } = LocalThing::ready(context, ());
+                let pipeline = create_pipeline(
+                    context,
+                    include_str!("shader/bunnymark.wgsl"),
+                    &[&global_bind_group_layout, &local_bind_group_layout],
+                    config.view_formats[0],
+                );
+                BunnyRenderer::PerDraw {
+                    pipeline,
+                    global_group,
+                    local_group,
+                    local_buffer,
+                    uniform_alignment,
+                }
+            }
+            BunnyMarkPath::SpriteBatch => {
+                let batch = SpriteBatch::new(
+                    context,
+                    &atlas.texture,
+                    config.view_formats[0],
+                    SPAWN_PER_FRAME,
+                );
+                batch.set_screen_size(context, config.width as f32, config.height as f32);
+                BunnyRenderer::SpriteBatch(batch)
+            }
+            BunnyMarkPath::Storage | BunnyMarkPath::Compute => {
+                let GlobalThing {
+                    global_bind_group_layout,
+                    global_group,
+                    ..
+                } = GlobalThing::ready(context, (config, &atlas, uv));
+                let StorageThing {
+                    storage_bind_group_layout,
+                    storage_buffer,
+                    storage_group,
+                } = StorageThing::ready(context, ());
+                let pipeline = create_pipeline(
+                    context,
+                    include_str!("shader/bunnymark_storage.wgsl"),
+                    &[&global_bind_group_layout, &storage_bind_group_layout],
+                    config.view_formats[0],
+                );
+                let simulation = (path == BunnyMarkPath::Compute)
+                    .then(|| ComputeThing::ready(context, &storage_buffer));
+                BunnyRenderer::Storage {
+                    pipeline,
+                    global_group,
+                    storage_group,
+                    storage_buffer,
+                    simulation,
+                }
+            }
+        };
 
         let mut ins = BunnyMarkScene {
-            pipeline,
-            global_group,
-            local_group,
-            bunnies: Vec::new(),
-            local_buffer,
+            path,
+            renderer,
+            uv,
+            bunnies: Vec::with_capacity(MAX_BUNNIES),
+            uploaded: 0,
             extent: [config.width, config.height],
-            rng,
+            rng: WyRand::new_seed(42),
+            spawn_batch: 1024,
+            auto_spawn: true,
+            window,
+            egui,
+            fps,
         };
+        ins.spawn(64);
+        ins
+    }
+}
 
-        let spawn_count = 64;
-        let color = ins.rng.generate::<u32>();
-        println!(
-            "Spawning {} bunnies, total at {}",
-            spawn_count,
-            ins.bunnies.len() + spawn_count
-        );
-        for _ in 0..spawn_count {
-            let speed = ins.rng.generate::<f32>() * MAX_VELOCITY - (MAX_VELOCITY * 0.5);
-            ins.bunnies.push(Bunny {
-                position: [0.0, 0.5 * (ins.extent[1] as f32)],
+fn create_pipeline(
+    context: &crate::gfx::GfxContext,
+    source: &'static str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = context
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("pipeline_layout"),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+    context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::default(),
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: Some(wgpu::IndexFormat::Uint16),
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+}
+
+pub struct BunnyMarkScene {
+    path: BunnyMarkPath,
+    renderer: BunnyRenderer,
+    /// 朝右、朝左两张图的 uv
+    uv: [[f32; 4]; 2],
+    bunnies: Vec<Bunny>,
+    /// Compute 路径下已经写进 GPU 的数量，之后只追加新生成的
+    uploaded: usize,
+    extent: [u32; 2],
+    rng: WyRand,
+    /// B 键或者按钮一次生成的数量
+    spawn_batch: usize,
+    auto_spawn: bool,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+    fps: Rc<RefCell<f32>>,
+}
+impl BunnyMarkScene {
+    fn spawn(&mut self, count: usize) {
+        let count = count.min(MAX_BUNNIES - self.bunnies.len());
+        if count == 0 {
+            return;
+        }
+        let color = self.rng.generate::<u32>();
+        let total = self.bunnies.len() + count;
+        // 每帧都在加，隔一段才打印一次
+        if total % (1 << 16) < count {
+            println!("Spawning {} bunnies, total at {}", count, total);
+        }
+        for _ in 0..count {
+            let speed = self.rng.generate::<f32>() * MAX_VELOCITY - (MAX_VELOCITY * 0.5);
+            self.bunnies.push(Bunny {
+                position: [0.0, 0.5 * (self.extent[1] as f32)],
                 velocity: [speed, 0.0],
                 color,
                 _pad: 0,
             });
         }
+    }
 
-        ins
+    fn clear(&mut self) {
+        self.bunnies.clear();
+        self.uploaded = 0;
     }
-}
 
-pub struct BunnyMarkScene {
-    global_group: wgpu::BindGroup,
-    local_group: wgpu::BindGroup,
-    pipeline: wgpu::RenderPipeline,
-    bunnies: Vec<Bunny>,
-    local_buffer: wgpu::Buffer,
-    extent: [u32; 2],
-    rng: WyRand,
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("BunnyMark")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.label(format!("path: {:?}", self.path));
+                ui.label(format!("bunnies: {}", self.bunnies.len()));
+                ui.label(format!("FPS: {:.2}", *self.fps.borrow()));
+                ui.add(
+                    egui::Slider::new(&mut self.spawn_batch, 1..=65536)
+                        .logarithmic(true)
+                        .text("batch"),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Spawn (B)").clicked() {
+                        self.spawn(self.spawn_batch);
+                    }
+                    if ui.button("Clear (C)").clicked() {
+                        self.clear();
+                    }
+                });
+                ui.checkbox(&mut self.auto_spawn, "auto spawn");
+            });
+    }
+
+    /// 把这一帧的兔子写到当前路径用的 buffer 里
+    fn upload(&mut self, context: &crate::gfx::GfxContext) {
+        match &mut self.renderer {
+            BunnyRenderer::PerDraw {
+                local_buffer,
+                uniform_alignment,
+                ..
+            } => {
+                // 每只兔子占一个 uniform 对齐的槽
+                let stride = *uniform_alignment as usize;
+                let mut bytes = vec![0u8; self.bunnies.len() * stride];
+                for (slot, bunny) in bytes.chunks_exact_mut(stride).zip(&self.bunnies) {
+                    slot[..std::mem::size_of::<Bunny>()].copy_from_slice(bytemuck::bytes_of(bunny));
+                }
+                context.queue.write_buffer(local_buffer, 0, &bytes);
+            }
+            BunnyRenderer::SpriteBatch(batch) => {
+                batch.clear();
+                for bunny in &self.bunnies {
+                    batch.push(SpriteInstance {
+                        position: bunny.position,
+                        size: [BUNNY_SIZE; 2],
+                        uv: self.uv[(bunny.velocity[0] < 0.0) as usize],
+                        color: bunny.color,
+                        rotation: 0.0,
+                    });
+                }
+                batch.upload(context);
+            }
+            BunnyRenderer::Storage {
+                storage_buffer,
+                simulation: None,
+                ..
+            } => {
+                context
+                    .queue
+                    .write_buffer(storage_buffer, 0, bytemuck::cast_slice(&self.bunnies));
+            }
+            // GPU 上的数据由 compute shader 接着算，这里只追加新生成的兔子
+            BunnyRenderer::Storage {
+                storage_buffer,
+                simulation: Some(_),
+                ..
+            } => {
+                if self.uploaded < self.bunnies.len() {
+                    context.queue.write_buffer(
+                        storage_buffer,
+                        (self.uploaded * std::mem::size_of::<Bunny>()) as wgpu::BufferAddress,
+                        bytemuck::cast_slice(&self.bunnies[self.uploaded..]),
+                    );
+                    self.uploaded = self.bunnies.len();
+                }
+            }
+        }
+    }
 }
 impl Painter for BunnyMarkScene {
-    fn paint(&mut self, context: &crate::gfx::GfxContext,dt:f32, time: f32) {
-        let delta = 0.01;
-        for bunny in self.bunnies.iter_mut() {
-            bunny.update_data(delta, &self.extent);
+    fn counters(&self) -> Vec<(&'static str, f64)> {
+        vec![("bunnies", self.bunnies.len() as f64)]
+    }
+
+    fn prepare_bench(&mut self) {
+        self.auto_spawn = false;
+        self.clear();
+        self.spawn(BENCH_BUNNIES);
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(code),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        {
+            match code {
+                KeyCode::KeyB => self.spawn(self.spawn_batch),
+                KeyCode::KeyC => self.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        if self.auto_spawn {
+            self.spawn(SPAWN_PER_FRAME);
         }
 
-        let uniform_alignment = context.device.limits().min_uniform_buffer_offset_alignment;
-        context.queue.write_buffer(&self.local_buffer, 0, unsafe {
-            std::slice::from_raw_parts(
-                self.bunnies.as_ptr() as *const u8,
-                self.bunnies.len() * uniform_alignment as usize,
-            )
-        });
+        let delta = 0.01;
+        if self.path != BunnyMarkPath::Compute {
+            for bunny in self.bunnies.iter_mut() {
+                bunny.update_data(delta, &self.extent);
+            }
+        }
+        self.upload(context);
 
         let mut encoder = context
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
 
+        if let BunnyRenderer::Storage {
+            simulation: Some(simulation),
+            ..
+        } = &self.renderer
+        {
+            simulation.dispatch(
+                context,
+                &mut encoder,
+                SimParams {
+                    extent: [self.extent[0] as f32, self.extent[1] as f32],
+                    delta,
+                    gravity: GRAVITY,
+                    bunny_size: BUNNY_SIZE,
+                    count: self.bunnies.len() as u32,
+                    _pad: [0; 2],
+                },
+            );
+        }
+
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -161,18 +445,59 @@ impl Painter for BunnyMarkScene {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("bunnymark"),
                 occlusion_query_set: None,
             });
-            rpass.set_pipeline(&self.pipeline);
-            rpass.set_bind_group(0, &self.global_group, &[]);
-            for i in 0..self.bunnies.len() {
-                let offset =
-                    (i as wgpu::DynamicOffset) * (uniform_alignment as wgpu::DynamicOffset);
-                rpass.set_bind_group(1, &self.local_group, &[offset]);
-                rpass.draw(0..4, 0..1);
+            match &self.renderer {
+                BunnyRenderer::PerDraw {
+                    pipeline,
+                    global_group,
+                    local_group,
+                    uniform_alignment,
+                    ..
+                } => {
+                    rpass.set_pipeline(pipeline);
+                    rpass.set_bind_group(0, global_group, &[]);
+                    for i in 0..self.bunnies.len() {
+                        let offset = (i as wgpu::DynamicOffset)
+                            * (*uniform_alignment as wgpu::DynamicOffset);
+                        rpass.set_bind_group(1, local_group, &[offset]);
+                        rpass.draw(0..4, 0..1);
+                    }
+                }
+                // 整批兔子一次 draw
+                BunnyRenderer::SpriteBatch(batch) => batch.draw(&mut rpass),
+                BunnyRenderer::Storage {
+                    pipeline,
+                    global_group,
+                    storage_group,
+                    ..
+                } => {
+                    rpass.set_pipeline(pipeline);
+                    rpass.set_bind_group(0, global_group, &[]);
+                    rpass.set_bind_group(1, storage_group, &[]);
+                    rpass.draw(0..4, 0..self.bunnies.len() as u32);
+                }
             }
         }
+
+        let config = context.surface_config.as_ref().unwrap();
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
         context.queue.submit(Some(encoder.finish()));
         frame.present();
     }
@@ -183,9 +508,23 @@ struct Globals {
     mvp: [[f32; 4]; 4],
     size: [f32; 2],
     pad: [f32; 2],
+    /// 朝右、朝左两张图在图集里的 uv
+    uv: [[f32; 4]; 2],
 }
-#[repr(C, align(256))]
-#[derive(Clone, Copy, Zeroable)]
+/// 和 bunnymark_compute.wgsl 里的 SimParams 对应
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SimParams {
+    extent: [f32; 2],
+    delta: f32,
+    gravity: f32,
+    bunny_size: f32,
+    count: u32,
+    _pad: [u32; 2],
+}
+/// 和 shader 里的 Bunny / Locals 对应
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 struct Bunny {
     position: [f32; 2],
     velocity: [f32; 2],
@@ -193,6 +532,7 @@ struct Bunny {
     _pad: u32,
 }
 impl Bunny {
+    /// 改这里的话 bunnymark_compute.wgsl 也要一起改
     fn update_data(&mut self, delta: f32, extent: &[u32; 2]) {
         self.position[0] += self.velocity[0] * delta;
         self.position[1] += self.velocity[1] * delta;
@@ -210,13 +550,12 @@ impl Bunny {
 /// 主要
 struct GlobalThing<'a> {
     global_bind_group_layout: wgpu::BindGroupLayout,
-    uniform_alignment: wgpu::BufferAddress,
     config: &'a wgpu::SurfaceConfiguration,
     global_group: wgpu::BindGroup,
 }
 impl<'a> Sandy for GlobalThing<'a> {
-    type Extra = &'a wgpu::SurfaceConfiguration;
-    fn ready(context: &crate::gfx::GfxContext, config: Self::Extra) -> Self
+    type Extra = (&'a wgpu::SurfaceConfiguration, &'a Atlas, [[f32; 4]; 2]);
+    fn ready(context: &crate::gfx::GfxContext, (config, atlas, uv): Self::Extra) -> Self
     where
         Self: Sized,
     {
@@ -233,6 +572,7 @@ impl<'a> Sandy for GlobalThing<'a> {
             .to_cols_array_2d(),
             size: [BUNNY_SIZE; 2],
             pad: [0.0; 2],
+            uv,
         };
 
         let global_buffer = context
@@ -279,54 +619,8 @@ impl<'a> Sandy for GlobalThing<'a> {
                         },
                     ],
                 });
-        let texture = {
-            let img_data = include_bytes!("../icon512.png");
-            let decoder = png::Decoder::new(std::io::Cursor::new(img_data));
-            let mut reader = decoder.read_info().unwrap();
-            let mut buf = vec![0; reader.output_buffer_size()];
-            let info = reader.next_frame(&mut buf).unwrap();
-
-            let size = wgpu::Extent3d {
-                width: info.width,
-                height: info.height,
-                depth_or_array_layers: 1,
-            };
-            let texture = context.device.create_texture(&wgpu::TextureDescriptor {
-                label: None,
-                size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-            context.queue.write_texture(
-                texture.as_image_copy(),
-                &buf,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(info.width * 4),
-                    rows_per_image: None,
-                },
-                size,
-            );
-            texture
-        };
-
-        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: None,
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
 
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        // 全局到shader的bind_group
+        // 全局到shader的bind_group，纹理用的是兔子图集
         let global_group = context
             .device
             .create_bind_group(&wgpu::BindGroupDescriptor {
@@ -338,25 +632,18 @@ impl<'a> Sandy for GlobalThing<'a> {
                     },
                     wgpu::BindGroupEntry {
                         binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&view),
+                        resource: wgpu::BindingResource::TextureView(&atlas.texture.view),
                     },
                     wgpu::BindGroupEntry {
                         binding: 2,
-                        resource: wgpu::BindingResource::Sampler(&sampler),
+                        resource: wgpu::BindingResource::Sampler(&atlas.texture.sampler),
                     },
                 ],
                 label: None,
             });
-        // 世界buffer的声明，这里使用的是create_buffer_init
-        // init是包括了多一个初始化的参数步骤
-        // 可以减少后续数据的上传步骤
-        // 世界uniform到shader
-        let uniform_alignment =
-            context.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
 
         Self {
             global_bind_group_layout,
-            uniform_alignment,
             config,
             global_group,
         }
@@ -367,14 +654,17 @@ struct LocalThing {
     local_bind_group_layout: wgpu::BindGroupLayout,
     local_buffer: wgpu::Buffer,
     local_group: wgpu::BindGroup,
+    uniform_alignment: wgpu::BufferAddress,
 }
 
 impl Sandy for LocalThing {
-    type Extra = (wgpu::BufferAddress);
-    fn ready(context: &crate::gfx::GfxContext, (uniform_alignment): Self::Extra) -> Self
+    type Extra = ();
+    fn ready(context: &crate::gfx::GfxContext, _: Self::Extra) -> Self
     where
         Self: Sized,
     {
+        let uniform_alignment =
+            context.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
         let local_bind_group_layout =
             context
                 .device
@@ -421,6 +711,139 @@ impl Sandy for LocalThing {
             local_bind_group_layout,
             local_buffer,
             local_group,
+            uniform_alignment,
         }
     }
 }
+
+/// 所有兔子一整块 storage buffer
+struct StorageThing {
+    storage_bind_group_layout: wgpu::BindGroupLayout,
+    storage_buffer: wgpu::Buffer,
+    storage_group: wgpu::BindGroup,
+}
+
+impl Sandy for StorageThing {
+    type Extra = ();
+    fn ready(context: &crate::gfx::GfxContext, _: Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let storage_bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("storage_bind_group_layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        // Compute 路径下 compute shader 也直接读写这块
+        let storage_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("bunnies"),
+            size: (MAX_BUNNIES * std::mem::size_of::<Bunny>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let storage_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &storage_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: storage_buffer.as_entire_binding(),
+                }],
+                label: None,
+            });
+        Self {
+            storage_bind_group_layout,
+            storage_buffer,
+            storage_group,
+        }
+    }
+}
+
+/// 在 GPU 上跑 Bunny::update_data
+struct ComputeThing {
+    pipeline: wgpu::ComputePipeline,
+    params_buffer: wgpu::Buffer,
+    group: wgpu::BindGroup,
+}
+
+impl ComputeThing {
+    fn ready(context: &crate::gfx::GfxContext, storage_buffer: &wgpu::Buffer) -> Self {
+        let shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("bunnymark compute"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                    "shader/bunnymark_compute.wgsl"
+                ))),
+            });
+        let pipeline = context
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("bunnymark compute pipeline"),
+                layout: None,
+                module: &shader,
+                entry_point: "cs_main",
+                compilation_options: Default::default(),
+                cache: None,
+            });
+        let params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sim params"),
+            size: std::mem::size_of::<SimParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: storage_buffer.as_entire_binding(),
+                    },
+                ],
+                label: None,
+            });
+        Self {
+            pipeline,
+            params_buffer,
+            group,
+        }
+    }
+
+    fn dispatch(
+        &self,
+        context: &crate::gfx::GfxContext,
+        encoder: &mut wgpu::CommandEncoder,
+        params: SimParams,
+    ) {
+        if params.count == 0 {
+            return;
+        }
+        context
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("bunnymark simulation"),
+            timestamp_writes: context.compute_timestamps("bunnymark simulation"),
+        });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.group, &[]);
+        cpass.dispatch_workgroups(params.count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
@@ -1,6 +1,8 @@
 struct Uniforms { 
     @location(0) delta_time:f32,
     @location(1) time:f32,
+    // 选中的实例编号 + 1，0 表示没选中
+    selected: u32,
 };
 
 @group(0) @binding(0) var<uniform> uniforms: Uniforms;
@@ -12,6 +14,7 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
     @location(0) color: vec3f,
+    @location(1) @interpolate(flat) id: u32,
 };
 
 struct InstanceInput {
@@ -23,8 +26,10 @@ struct InstanceInput {
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
     var out: VertexOutput;
+    out.id = instance_index + 1u;
     let speed = 9.8 / instance.radius;
     var world_pos = (model.position * instance.radius) + instance.position;
  
@@ -59,5 +64,13 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    if in.id == uniforms.selected {
+        return vec4f(mix(in.color, vec3f(1.0, 0.5, 0.0), 0.6), 1.0);
+    }
     return vec4f(in.color, 1.0);
+}
+
+@fragment
+fn fs_id(in: VertexOutput) -> @location(0) u32 {
+    return in.id;
 }
\ No newline at end of file
@@ -1,9 +1,17 @@
+// 点一下圆可以选中它，左上角的窗口里切换拾取方式、改它的半径和位置
+use std::sync::{Arc, Mutex};
+
 use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::Vec2;
 use wgpu::{core::device::queue, util::DeviceExt, FragmentState, VertexState};
+use winit::{event::WindowEvent, window::Window};
 
 use crate::{
+    egui::EguiRenderer,
     gfx,
     painter::{Painter, Sandy},
+    utils::picking::{pixel_to_ndc, IdBuffer, Picker},
 };
 mod sources;
 pub struct CircleInstancesScene {
@@ -15,6 +23,15 @@ pub struct CircleInstancesScene {
     pipeline: wgpu::RenderPipeline,
     vertexes_data_size: usize,
     instance_count: usize,
+    instances: Vec<Instance>,
+    id_pipeline: wgpu::RenderPipeline,
+    /// 上一帧的时间，CPU 拾取时按它算圆的位置
+    time: f32,
+    picker: Picker,
+    /// CPU 模式下点击的像素，paint 里拿到窗口大小再测
+    cpu_click: Option<Vec2>,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
 }
 
 const CIRCLE_SEGMENTS: u32 = 360 / 2;
@@ -68,9 +85,20 @@ struct Instance {
     position: [f32; 2],
 }
 
+/// circle_instances.wgsl 里 vs_main 对 world_pos 做的变换，对每个实例是个仿射变换
+/// 返回 (2x2 矩阵的两列, 平移)
+fn instance_transform(instance: &Instance, time: f32) -> (glam::Mat2, Vec2) {
+    let speed = 9.8 / instance.radius;
+    let angle = (Vec2::from(instance.position) + time) * speed * 0.01;
+    let (sin_x, cos_x) = angle.x.sin_cos();
+    let (sin_y, cos_y) = angle.y.sin_cos();
+    let matrix = glam::Mat2::from_cols(Vec2::new(cos_x, 0.0), Vec2::new(-sin_x, sin_y + cos_y));
+    (matrix, Vec2::new(sin_x, cos_y) * 0.1)
+}
+
 impl Sandy for CircleInstancesScene {
-    type Extra = ();
-    fn ready(context: &gfx::GfxContext, _extra: Self::Extra) -> Self {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+    fn ready(context: &gfx::GfxContext, (window, egui): Self::Extra) -> Self {
         let (vertex_data, indexes_data) = gen_vertexes();
         let vertexes_data_size = vertex_data.len() * std::mem::size_of::<f32>();
         let instances_data = gen_instance();
@@ -112,6 +140,34 @@ impl Sandy for CircleInstancesScene {
                     bind_group_layouts: &[&uniform_bind_group_layout],
                     push_constant_ranges: &[],
                 });
+        let vertex_buffers = [
+            wgpu::VertexBufferLayout {
+                // 每个顶点的大小
+                array_stride: 2 * 4 as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &[wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                }],
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: 3 * 4 as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &[
+                    wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Float32,
+                        offset: 0,
+                        shader_location: 1,
+                    },
+                    wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Float32x2,
+                        offset: 4,
+                        shader_location: 2,
+                    },
+                ],
+            },
+        ];
         let pipeline = context
             .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -120,34 +176,7 @@ impl Sandy for CircleInstancesScene {
                 vertex: VertexState {
                     module: &shader,
                     entry_point: "vs_main",
-                    buffers: &[
-                        wgpu::VertexBufferLayout {
-                            // 每个顶点的大小
-                            array_stride: 2 * 4 as wgpu::BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &[wgpu::VertexAttribute {
-                                format: wgpu::VertexFormat::Float32x2,
-                                offset: 0,
-                                shader_location: 0,
-                            }],
-                        },
-                        wgpu::VertexBufferLayout {
-                            array_stride: 3 * 4 as wgpu::BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Instance,
-                            attributes: &[
-                                wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Float32,
-                                    offset: 0,
-                                    shader_location: 1,
-                                },
-                                wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Float32x2,
-                                    offset: 4,
-                                    shader_location: 2,
-                                },
-                            ],
-                        },
-                    ],
+                    buffers: &vertex_buffers,
                     compilation_options: Default::default(),
                 },
                 primitive: wgpu::PrimitiveState::default(),
@@ -166,7 +195,38 @@ impl Sandy for CircleInstancesScene {
                 multiview: None,
                 cache: None,
             });
+        // 和上面一样的顶点，片元输出实例编号
+        let id_pipeline = context
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Id Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &vertex_buffers,
+                    compilation_options: Default::default(),
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(IdBuffer::depth_stencil()),
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: "fs_id",
+                    compilation_options: Default::default(),
+                    targets: &[Some(IdBuffer::target())],
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
         CircleInstancesScene {
+            instances: instances_data,
+            id_pipeline,
+            time: 0.0,
+            picker: Picker::ready(context, ()),
+            cpu_click: None,
+            window,
+            egui,
             vertex_buffer,
             indexes_buffer,
             instance_buffer,
@@ -179,6 +239,67 @@ impl Sandy for CircleInstancesScene {
     }
 }
 
+impl CircleInstancesScene {
+    /// 把点击的位置按每个圆的变换逆回去，看落没落在圆里，后画的在上面
+    fn pick_cpu(&self, pixel: Vec2, size: Vec2) -> Option<u32> {
+        let ndc = pixel_to_ndc(pixel, size);
+        self.instances
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, instance)| {
+                let (matrix, offset) = instance_transform(instance, self.time);
+                // 被压扁成一条线的时候看不见，也点不到
+                if matrix.determinant().abs() < 1e-5 {
+                    return false;
+                }
+                let world = matrix.inverse() * (ndc - offset);
+                world.distance(Vec2::from(instance.position)) <= instance.radius
+            })
+            .map(|(i, _)| i as u32)
+    }
+
+    fn inspector(&mut self, context: &gfx::GfxContext, ui: &egui::Context) {
+        egui::Window::new("Picking")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                self.picker.ui(ui, |id| format!("circle {}", id));
+                let Some(id) = self.picker.selected else {
+                    return;
+                };
+                let instance = &mut self.instances[id as usize];
+                let mut changed = false;
+                changed |= ui
+                    .add(egui::Slider::new(&mut instance.radius, 0.01..=0.3).text("radius"))
+                    .changed();
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut instance.position[0])
+                                .speed(0.01)
+                                .prefix("x "),
+                        )
+                        .changed();
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut instance.position[1])
+                                .speed(0.01)
+                                .prefix("y "),
+                        )
+                        .changed();
+                });
+                if changed {
+                    context.queue.write_buffer(
+                        &self.instance_buffer,
+                        id as u64 * std::mem::size_of::<Instance>() as u64,
+                        bytemuck::bytes_of(instance),
+                    );
+                }
+            });
+    }
+}
+
 impl Painter for CircleInstancesScene {
     fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
         let frame = context.surface.get_current_texture().unwrap();
@@ -191,12 +312,20 @@ impl Painter for CircleInstancesScene {
                 label: Some("Render Encoder"),
             });
 
+        let config = context.surface_config.as_ref().unwrap();
+        let size = Vec2::new(config.width as f32, config.height as f32);
+        if let Some(pixel) = self.cpu_click.take() {
+            self.picker.selected = self.pick_cpu(pixel, size);
+        }
+        self.time = time;
         context.queue.write_buffer(
             &self.uniform_buffer,
             0,
             bytemuck::cast_slice(&[TimeUniforms {
                 delta_time: dt,
                 time,
+                selected: self.picker.selected.map_or(0, |id| id + 1),
+                _pad: 0,
             }]),
         );
         {
@@ -216,7 +345,7 @@ impl Painter for CircleInstancesScene {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("circle instances"),
                 occlusion_query_set: None,
             });
             render_pass.set_pipeline(&self.pipeline);
@@ -230,8 +359,44 @@ impl Painter for CircleInstancesScene {
                 0..self.instance_count as u32,
             );
         }
+        if self.picker.ids.requested() {
+            let mut id_pass = self.picker.ids.begin(context, &mut encoder);
+            id_pass.set_pipeline(&self.id_pipeline);
+            id_pass.set_bind_group(0, &self.bind_group, &[]);
+            id_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            id_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            id_pass.set_index_buffer(self.indexes_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            id_pass.draw_indexed(0..CIRCLE_SEGMENTS * 3, 0, 0..self.instance_count as u32);
+            drop(id_pass);
+            self.picker.ids.copy(&mut encoder);
+        }
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.inspector(context, ui),
+        );
+
         context.queue.submit(std::iter::once(encoder.finish()));
         frame.present();
+        self.picker.finish(context);
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        let over_ui = self.egui.lock().unwrap().context.is_pointer_over_area();
+        if let Some(pixel) = self.picker.input(event, over_ui) {
+            self.cpu_click = Some(pixel);
+        }
     }
 }
 
@@ -240,6 +405,9 @@ impl Painter for CircleInstancesScene {
 struct TimeUniforms {
     delta_time: f32,
     time: f32,
+    /// 选中的实例编号 + 1，0 表示没选中
+    selected: u32,
+    _pad: u32,
 }
 impl TimeUniforms {
     fn ready(
@@ -252,6 +420,8 @@ impl TimeUniforms {
                 contents: bytemuck::cast_slice(&[TimeUniforms {
                     delta_time: 0.0,
                     time: 0.0,
+                    selected: 0,
+                    _pad: 0,
                 }]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
@@ -3,21 +3,28 @@ use std::borrow::Cow;
 use wgpu::{util::DeviceExt, Buffer, PipelineLayout};
 
 use crate::{
+    assets::HotShader,
     gfx::{self, GfxContext},
     model::{create_texels, create_vertices, generate_matrix},
     painter::{Sandy, TextureBuff, VertexBuff},
-    utils::{self, Vertex},
+    utils::{
+        self,
+        view_modes::{MeshLayout, ViewCamera, ViewMesh, ViewModes},
+        Vertex,
+    },
 };
 
 use super::Painter;
 
 pub(crate) struct CubeScene {
     pub(crate) bind_group: wgpu::BindGroup,
-    pub(crate) pipeline: wgpu::RenderPipeline,
+    pub(crate) pipeline: HotShader<wgpu::RenderPipeline>,
     pub(crate) pipeline_layout: PipelineLayout,
     pub(crate) texture_source: TextureBuff,
     pub(crate) uniform_buf: Buffer,
     pub(crate) vertex_source: VertexBuff,
+    /// 按 V 切线框、法线这些调试视图
+    pub(crate) view_modes: ViewModes,
 }
 
 impl Sandy for CubeScene {
@@ -25,23 +32,6 @@ impl Sandy for CubeScene {
     fn ready(context: &gfx::GfxContext, _: Self::Extra) -> Self {
         // vertex_buf, index_buf, vertex_size
         let vertex_source = VertexBuff::ready(context, ());
-        let vertex_buffers_layout = [wgpu::VertexBufferLayout {
-            array_stride: vertex_source.vertex_size as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: 4 * 4,
-                    shader_location: 1,
-                },
-            ],
-        }];
-
         // texture, texels, size
         let texture_source = TextureBuff::ready(context, ());
 
@@ -111,42 +101,28 @@ impl Sandy for CubeScene {
                 ],
                 label: None,
             });
-        // pipeline
-        let shader = context
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/cube.wgsl"))),
-            });
+        // pipeline，改了 asset/shaders/cube.wgsl 就重建
+        let pipeline = HotShader::new(
+            context,
+            "asset/shaders/cube.wgsl",
+            include_str!("../../asset/shaders/cube.wgsl"),
+            |source| build_pipeline(context, &pipeline_layout, source),
+        );
 
-        let pipeline = context
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    compilation_options: Default::default(),
-                    buffers: &vertex_buffers_layout,
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: Default::default(),
-                    targets: &[Some(config.view_formats[0].into())],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    cull_mode: Some(wgpu::Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            });
+        // Vertex 是 4 个 f32 的位置加 2 个 f32 的 uv，没有法线
+        let view_modes = ViewModes::new(
+            context,
+            config.view_formats[0],
+            MeshLayout {
+                stride: 6,
+                normal: None,
+                uv: Some(4),
+                index_format: wgpu::IndexFormat::Uint16,
+            },
+        );
 
         CubeScene {
+            view_modes,
             vertex_source,
             texture_source,
             uniform_buf,
@@ -157,8 +133,68 @@ impl Sandy for CubeScene {
     }
 }
 
+fn build_pipeline(
+    context: &GfxContext,
+    pipeline_layout: &PipelineLayout,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let config = context.surface_config.as_ref().unwrap();
+    let vertex_buffers_layout = [wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x2,
+                offset: 4 * 4,
+                shader_location: 1,
+            },
+        ],
+    }];
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+    context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &vertex_buffers_layout,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(config.view_formats[0].into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+}
+
 impl Painter for CubeScene {
     fn paint(&mut self, context: &gfx::GfxContext,dt:f32, time: f32) {
+        self.pipeline.update(context, |source| {
+            build_pipeline(context, &self.pipeline_layout, source)
+        });
+        let mode = self.view_modes.sync(context);
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -183,25 +219,41 @@ impl Painter for CubeScene {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("cube"),
                 occlusion_query_set: None,
             });
-            rpass.push_debug_group("Prepare data for draw.");
-            rpass.set_pipeline(&self.pipeline);
-            rpass.set_bind_group(0, &self.bind_group, &[]);
-            rpass.set_index_buffer(
-                self.vertex_source.index_buf.slice(..),
-                wgpu::IndexFormat::Uint16,
-            );
-            rpass.set_vertex_buffer(0, self.vertex_source.vertex_buf.slice(..));
-            rpass.pop_debug_group();
-            rpass.insert_debug_marker("Draw!");
-            rpass.draw_indexed(0..self.vertex_source.index_count as u32, 0, 0..1);
-            // if let Some(ref pipe) = self.pipeline_wire {
-            //     rpass.set_pipeline(pipe);
-            //     rpass.draw_indexed(0..self.vertex_source.index_count as u32, 0, 0..1);
-            // }
+            if !mode.replaces_scene() {
+                rpass.push_debug_group("Prepare data for draw.");
+                rpass.set_pipeline(&self.pipeline);
+                rpass.set_bind_group(0, &self.bind_group, &[]);
+                rpass.set_index_buffer(
+                    self.vertex_source.index_buf.slice(..),
+                    wgpu::IndexFormat::Uint16,
+                );
+                rpass.set_vertex_buffer(0, self.vertex_source.vertex_buf.slice(..));
+                rpass.pop_debug_group();
+                rpass.insert_debug_marker("Draw!");
+                rpass.draw_indexed(0..self.vertex_source.index_count as u32, 0, 0..1);
+            }
         }
+        let config = context.surface_config.as_ref().unwrap();
+        self.view_modes.render(
+            context,
+            &mut encoder,
+            &view,
+            &ViewCamera {
+                view_proj: generate_matrix(config.width as f32 / config.height as f32),
+                near: 1.0,
+                far: 10.0,
+            },
+            &[ViewMesh {
+                vertices: &self.vertex_source.vertex_buf,
+                indices: &self.vertex_source.index_buf,
+                index_count: self.vertex_source.index_count as u32,
+                model: glam::Mat4::IDENTITY,
+                viewport: None,
+            }],
+        );
 
         context.queue.submit(Some(encoder.finish()));
         frame.present();
@@ -220,7 +272,8 @@ impl Sandy for VertexBuff {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
                 contents: bytemuck::cast_slice(&vertex_data),
-                usage: wgpu::BufferUsages::VERTEX,
+                // STORAGE 给 view_modes 读
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
             });
 
         let index_buf = context
@@ -228,7 +281,7 @@ impl Sandy for VertexBuff {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
                 contents: bytemuck::cast_slice(&index_data),
-                usage: wgpu::BufferUsages::INDEX,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE,
             });
 
         VertexBuff {
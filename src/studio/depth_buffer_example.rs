@@ -1,55 +1,191 @@
-// 定义两个面片
-
-use std::borrow::Cow;
+// 深度缓冲的精度：一大块地面上铺一条只高出 ROAD_HEIGHT 的路
+// 左半边是画面，右半边是同一帧的深度按对数刻度画成灰度
+// 标准 Z 的 near 很小的时候，远处的路和地面分不清，闪成一片；
+// 反转 Z（无限远平面，Greater 比较）把浮点的精度用在远处，路一直是完整的
+// R 键或者左上角的窗口切换两种模式
+// 按 V 换调试视图，调试视图自己的深度缓冲总是标准 Z
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
 
+use glam::{Mat4, Vec3};
 use wgpu::{util::DeviceExt, IndexFormat, PipelineCompilationOptions};
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::Window,
+};
+
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::view_modes::{MeshLayout, ViewCamera, ViewMesh, ViewModes},
+};
 
-use crate::painter::{Painter, Sandy};
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// 标准 Z 的远平面，灰度图两种模式都按这个距离算全黑
+const FAR: f32 = 10000.0;
+/// 路面比地面高多少
+const ROAD_HEIGHT: f32 = 0.01;
+/// 路一直铺到多远
+const ROAD_LENGTH: f32 = 5000.0;
+/// 路上一红一白的格子，越远越长，屏幕上差不多一样大
+const TILES: usize = 120;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct DepthView {
+    near: f32,
+    far: f32,
+    reversed: u32,
+    raw: u32,
+}
 
 pub struct DepthBufferExample {
-    pipeline: wgpu::RenderPipeline,
+    /// 0 是标准 Z，1 是反转 Z，只有深度比较不一样
+    pipelines: [wgpu::RenderPipeline; 2],
+    depth_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
+    index_count: u32,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    depth_view_buffer: wgpu::Buffer,
+    depth_layout: wgpu::BindGroupLayout,
+    /// 窗口大小变了才重建，灰度图的 bind group 跟着一起
+    depth: Option<((u32, u32), wgpu::TextureView, wgpu::BindGroup)>,
+    reversed: bool,
+    /// 灰度图直接显示存的深度值，不线性化
+    raw: bool,
+    near: f32,
+    view_modes: ViewModes,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
 }
 
-fn gen_vertexes() -> [f32; 36] {
-    #[rustfmt::skip]
-    let vs = [
-        -0.3, -0.3,0.1,       0.0, 0.0, 1.0,
-        0.3, 0.3, 0.1,        0.0, 0.0, 1.0,
-        -0.3, 0.3,0.1,        0.0, 0.0, 1.0,
-
-        -0.3, 0.3,0.,       0.0, 1.0, 0.0,
-        -0.3, -0.3, 0.,      0.0, 1.0, 0.0,
-        0.3, -0.3, 0.,       0.0, 1.0, 0.0,
-    ];
-    vs
+fn quad(
+    vs: &mut Vec<[f32; 6]>,
+    indexes: &mut Vec<u16>,
+    (x0, x1): (f32, f32),
+    (z0, z1): (f32, f32),
+    y: f32,
+    color: [f32; 3],
+) {
+    let base = vs.len() as u16;
+    for (x, z) in [(x0, z0), (x1, z0), (x1, z1), (x0, z1)] {
+        vs.push([x, y, z, color[0], color[1], color[2]]);
+    }
+    // z0 在近处，从上面看是逆时针
+    indexes.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+}
+
+fn gen_geometry() -> (Vec<[f32; 6]>, Vec<u16>) {
+    let mut vs = Vec::new();
+    let mut indexes = Vec::new();
+    quad(
+        &mut vs,
+        &mut indexes,
+        (-ROAD_LENGTH, ROAD_LENGTH),
+        (10.0, -ROAD_LENGTH),
+        0.0,
+        [0.2, 0.35, 0.2],
+    );
+    // 从 z = -1 到 -ROAD_LENGTH 按等比分格子
+    let ratio = ROAD_LENGTH.powf(1.0 / TILES as f32);
+    let mut z = -1.0;
+    for i in 0..TILES {
+        let color = if i % 2 == 0 {
+            [0.8, 0.15, 0.1]
+        } else {
+            [0.9, 0.9, 0.9]
+        };
+        quad(
+            &mut vs,
+            &mut indexes,
+            (-1.5, 1.5),
+            (z, z * ratio),
+            ROAD_HEIGHT,
+            color,
+        );
+        z *= ratio;
+    }
+    (vs, indexes)
 }
-fn gen_indexes() -> [u16; 6] {
-    #[rustfmt::skip]
-    let indexes = [
-        0,1,2,    3,4,5
-    ];
-    indexes
+
+fn scene_pipeline(
+    context: &crate::gfx::GfxContext,
+    shader: &wgpu::ShaderModule,
+    layout: &wgpu::PipelineLayout,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("depth example scene"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: 6 * 4 as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x3,
+                        1 => Float32x3,
+                    ],
+                }],
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: context.surface_config.as_ref().unwrap().format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
 }
 
 impl Sandy for DepthBufferExample {
-    type Extra = ();
-    fn ready(context: &crate::gfx::GfxContext, extra: Self::Extra) -> Self {
-        let (vs, indexes) = (gen_vertexes(), gen_indexes());
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self {
+        let (vs, indexes) = gen_geometry();
         let vertex_buffer = context
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
                 contents: bytemuck::cast_slice(&vs),
-                usage: wgpu::BufferUsages::VERTEX,
+                // STORAGE 给 view_modes 读
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
             });
         let index_buffer = context
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
                 contents: bytemuck::cast_slice(&indexes),
-                usage: wgpu::BufferUsages::INDEX,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE,
             });
         let shader = context
             .device
@@ -59,83 +195,260 @@ impl Sandy for DepthBufferExample {
                     "shader/depth_buffer_example.wgsl"
                 ))),
             });
-        let pipeline = context
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: None,
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: 6 * 4 as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![
-                            0 => Float32x3,
-                            1 => Float32x3,
-                        ],
+
+        let camera_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth example camera"),
+            size: 64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("depth example camera"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     }],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: context.surface_config.as_ref().unwrap().format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: PipelineCompilationOptions::default(),
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    ..Default::default()
-                },
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
+                });
+        let camera_bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("depth example camera"),
+                layout: &camera_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                }],
+            });
+        let scene_layout = context
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("depth example scene"),
+                bind_group_layouts: &[&camera_layout],
+                push_constant_ranges: &[],
             });
+        let pipelines = [
+            scene_pipeline(context, &shader, &scene_layout, wgpu::CompareFunction::Less),
+            scene_pipeline(
+                context,
+                &shader,
+                &scene_layout,
+                wgpu::CompareFunction::Greater,
+            ),
+        ];
+
+        let depth_view_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("depth example view"),
+            size: std::mem::size_of::<DepthView>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let depth_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("depth example view"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Depth,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let depth_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("depth example view"),
+                    bind_group_layouts: &[&depth_layout],
+                    push_constant_ranges: &[],
+                });
+        let depth_pipeline =
+            context
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("depth example view"),
+                    layout: Some(&depth_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_fullscreen",
+                        buffers: &[],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_depth",
+                        targets: &[Some(context.surface_config.as_ref().unwrap().format.into())],
+                        compilation_options: PipelineCompilationOptions::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                });
+
+        // 顶点是位置 + 颜色，没有法线和 uv
+        let view_modes = ViewModes::new(
+            context,
+            context.surface_config.as_ref().unwrap().format,
+            MeshLayout {
+                stride: 6,
+                normal: None,
+                uv: None,
+                index_format: IndexFormat::Uint16,
+            },
+        );
+
         Self {
-            pipeline,
+            pipelines,
+            depth_pipeline,
             vertex_buffer,
             index_buffer,
+            index_count: indexes.len() as u32,
+            camera_buffer,
+            camera_bind_group,
+            depth_view_buffer,
+            depth_layout,
+            depth: None,
+            reversed: false,
+            raw: false,
+            near: 0.01,
+            view_modes,
+            window,
+            egui,
         }
     }
 }
 
+impl DepthBufferExample {
+    fn update_depth(&mut self, context: &crate::gfx::GfxContext) {
+        let config = context.surface_config.as_ref().unwrap();
+        let size = (config.width, config.height);
+        if self.depth.as_ref().map(|(s, _, _)| *s) != Some(size) {
+            let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("depth example depth"),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("depth example view"),
+                    layout: &self.depth_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.depth_view_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                    ],
+                });
+            self.depth = Some((size, view, bind_group));
+        }
+    }
+
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Depth")
+            .default_pos([12.0, 12.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.radio_value(&mut self.reversed, false, "standard Z (Less)");
+                ui.radio_value(
+                    &mut self.reversed,
+                    true,
+                    "reversed Z, infinite far (Greater)",
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.near, 0.001..=1.0)
+                        .logarithmic(true)
+                        .text("near"),
+                );
+                ui.checkbox(&mut self.raw, "raw depth values");
+                ui.label("R: toggle reversed Z");
+                ui.separator();
+                self.view_modes.ui(ui);
+            });
+    }
+}
+
 impl Painter for DepthBufferExample {
-    fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, _dt: f32, time: f32) {
+        let mode = self.view_modes.sync(context);
+        self.update_depth(context);
+        let (width, height) = self.depth.as_ref().unwrap().0;
+        // 左右各一半，画面和深度图用同一个比例
+        let half = width / 2;
+        let aspect = half.max(1) as f32 / height.max(1) as f32;
+        let fov = 60f32.to_radians();
+        let projection = if self.reversed {
+            Mat4::perspective_infinite_reverse_rh(fov, aspect, self.near)
+        } else {
+            Mat4::perspective_rh(fov, aspect, self.near, FAR)
+        };
+        // 左右慢慢晃，远处的闪烁更明显
+        let eye = Vec3::new((time * 0.3).sin() * 0.5, 0.4, 0.0);
+        let view_matrix = Mat4::look_at_rh(eye, Vec3::new(0.0, 0.25, -50.0), Vec3::Y);
+        let view_proj = projection * view_matrix;
+        context.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&view_proj.to_cols_array()),
+        );
+        context.queue.write_buffer(
+            &self.depth_view_buffer,
+            0,
+            bytemuck::bytes_of(&DepthView {
+                near: self.near,
+                far: FAR,
+                reversed: self.reversed as u32,
+                raw: self.raw as u32,
+            }),
+        );
+
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let depth_texture = context.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
-            size: wgpu::Extent3d {
-                width: context.surface_config.as_ref().unwrap().width,
-                height: context.surface_config.as_ref().unwrap().height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[wgpu::TextureFormat::Depth32Float],
-        });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let (_, depth_view, depth_bind_group) = self.depth.as_ref().unwrap();
 
         let mut encoder = context
             .device
@@ -149,26 +462,113 @@ impl Painter for DepthBufferExample {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.5,
+                            g: 0.7,
+                            b: 0.9,
+                            a: 1.0,
+                        }),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &depth_view,
+                    view: depth_view,
                     depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
+                        // 反转 Z 的最远处是 0
+                        load: wgpu::LoadOp::Clear(if self.reversed { 0.0 } else { 1.0 }),
                         store: wgpu::StoreOp::Store,
                     }),
                     stencil_ops: None,
                 }),
                 ..Default::default()
             });
-            render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
-            render_pass.draw_indexed(0..6, 0, 0..1);
+            if !mode.replaces_scene() {
+                render_pass.set_viewport(0.0, 0.0, half as f32, height as f32, 0.0, 1.0);
+                render_pass.set_pipeline(&self.pipelines[self.reversed as usize]);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+            }
         }
+        {
+            // 深度贴图在上一个 pass 里是附件，这里才能采样
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("depth example view"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            render_pass.set_viewport(
+                half as f32,
+                0.0,
+                (width - half) as f32,
+                height as f32,
+                0.0,
+                1.0,
+            );
+            render_pass.set_pipeline(&self.depth_pipeline);
+            render_pass.set_bind_group(0, depth_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        // 调试视图的深度比较是 Less，只能给标准 Z 的投影；只画在左半边，整个重画的模式会把右边也清掉
+        let standard = Mat4::perspective_rh(fov, aspect, self.near, FAR) * view_matrix;
+        self.view_modes.render(
+            context,
+            &mut encoder,
+            &view,
+            &ViewCamera {
+                view_proj: standard,
+                near: self.near,
+                far: FAR,
+            },
+            &[ViewMesh {
+                vertices: &self.vertex_buffer,
+                indices: &self.index_buffer,
+                index_count: self.index_count,
+                model: Mat4::IDENTITY,
+                viewport: Some(([0.0, 0.0, half as f32, height as f32], standard)),
+            }],
+        );
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [width, height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
         context.queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
+
+    fn input(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    physical_key: PhysicalKey::Code(KeyCode::KeyR),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.reversed = !self.reversed;
+        }
+    }
 }
@@ -1,10 +1,27 @@
-use std::{borrow::Cow, task::ready};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+    task::ready,
+};
 
 use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::Vec2;
 use wgpu::{util::DeviceExt, StoreOp, TextureFormat};
+use winit::{event::WindowEvent, window::Window};
 
 // some vertex and indexes and instance data
-use crate::painter::{Painter, Sandy};
+// 点一下方块可以选中它，左上角的窗口里切换拾取方式、改它的相位
+use crate::{
+    assets::HotShader,
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::picking::{pixel_to_ndc, IdBuffer, Picker},
+};
+/// 方块的半边长，和 gen_static_data 里的一致
+const QUAD_HALF: f32 = 0.1;
+/// 方块绕圈的半径，和 instance.wgsl 里的一致
+const ORBIT_RADIUS: f32 = 0.8;
 #[repr(C)]
 #[derive(Clone, Copy, Zeroable, Pod)]
 struct Instance {
@@ -52,6 +69,9 @@ struct UniformTime {
 struct Uniforms {
     delta_time: f32,
     time: f32,
+    /// 选中的实例编号 + 1，0 表示没选中
+    selected: u32,
+    _pad: u32,
 }
 
 impl Sandy for UniformTime {
@@ -64,6 +84,8 @@ impl Sandy for UniformTime {
                 contents: bytemuck::cast_slice(&[Uniforms {
                     delta_time: 0.0,
                     time: 0.0,
+                    selected: 0,
+                    _pad: 0,
                 }]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
@@ -106,8 +128,8 @@ impl Sandy for UniformTime {
     }
 }
 impl Sandy for InstanceScene {
-    type Extra = ();
-    fn ready(context: &crate::gfx::GfxContext, _: Self::Extra) -> Self {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self {
         #[allow(non_snake_case)]
         let (VERTEX_DATA, INDEX_DATA) = gen_static_data();
         let instance_data = gen_instance_data();
@@ -117,7 +139,7 @@ impl Sandy for InstanceScene {
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Instance Buffer"),
                     contents: bytemuck::cast_slice(&instance_data),
-                    usage: wgpu::BufferUsages::VERTEX,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
                 });
         let vertex_buffer = context
             .device
@@ -138,33 +160,30 @@ impl Sandy for InstanceScene {
             bind_group,
             uniform_bind_group_layout,
         } = UniformTime::ready(context, ());
-        let InstancePipeline {
-            shader_module,
-            pipeline,
-            vertex_buffer,
-            index_buffer,
-        } = InstancePipeline::ready(
+        let pipeline = InstancePipeline::ready(
             context,
             (vertex_buffer, index_buffer, uniform_bind_group_layout),
         );
 
         Self {
-            pipeline: InstancePipeline {
-                shader_module,
-                pipeline,
-                vertex_buffer,
-                index_buffer,
-            },
+            pipeline,
+            instances: instance_data,
             instances_buffer,
             uniform_buffer,
             bind_group,
+            time: 0.0,
+            picker: Picker::ready(context, ()),
+            cpu_click: None,
+            window,
+            egui,
         }
     }
 }
 
 struct InstancePipeline {
-    shader_module: wgpu::ShaderModule,
-    pipeline: wgpu::RenderPipeline,
+    /// (画到屏幕的, 写实例编号的)，改了 asset/shaders/instance.wgsl 就重建
+    pipelines: HotShader<(wgpu::RenderPipeline, wgpu::RenderPipeline)>,
+    pipeline_layout: wgpu::PipelineLayout,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
 }
@@ -176,14 +195,6 @@ impl Sandy for InstancePipeline {
         Self: Sized,
     {
         let (vertex_buffer, index_buffer, uniform_bind_group_layout) = extra;
-        let shader_module = context
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("Shader"),
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-                    "shader/instance.wgsl"
-                ))),
-            });
         let pipeline_layout =
             context
                 .device
@@ -192,82 +203,191 @@ impl Sandy for InstancePipeline {
                     bind_group_layouts: &[&uniform_bind_group_layout],
                     push_constant_ranges: &[],
                 });
-        let pipeline = context
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader_module,
-                    entry_point: "vs_main",
-                    // 这里处理顶点缓冲区的布局，而非顶点源数据
-                    // 而是在render_pass中再写入顶点数据
-                    buffers: &[
-                        wgpu::VertexBufferLayout {
-                            array_stride: 6 * 4,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &[
-                                wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Float32x3,
-                                    offset: 0,
-                                    shader_location: 0,
-                                },
-                                wgpu::VertexAttribute {
-                                    format: wgpu::VertexFormat::Float32x3,
-                                    offset: 3 * 4,
-                                    shader_location: 1,
-                                },
-                            ],
-                        },
-                        wgpu::VertexBufferLayout {
-                            array_stride: 3 * 4,
-                            step_mode: wgpu::VertexStepMode::Instance,
-                            attributes: &[wgpu::VertexAttribute {
-                                format: wgpu::VertexFormat::Float32x3,
-                                offset: 0,
-                                shader_location: 2,
-                            }],
-                        },
-                    ],
-                    compilation_options: Default::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader_module,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: context.surface_config.as_ref().unwrap().format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: Default::default(),
-                }),
-                primitive: wgpu::PrimitiveState {
-                    cull_mode: Some(wgpu::Face::Back),
-                    ..Default::default()
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-                cache: None,
-            });
+        let pipelines = HotShader::new(
+            context,
+            "asset/shaders/instance.wgsl",
+            include_str!("../../asset/shaders/instance.wgsl"),
+            |source| build_pipelines(context, &pipeline_layout, source),
+        );
 
         Self {
-            shader_module,
-            pipeline,
+            pipelines,
+            pipeline_layout,
             vertex_buffer,
             index_buffer,
         }
     }
 }
 
+/// 方块和实例两个顶点缓冲，画的和写编号的 pipeline 用同一个 shader
+fn build_pipelines(
+    context: &crate::gfx::GfxContext,
+    layout: &wgpu::PipelineLayout,
+    source: &str,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let shader_module = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+    let vertex_buffers = [
+        wgpu::VertexBufferLayout {
+            array_stride: 6 * 4,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x3,
+                    offset: 3 * 4,
+                    shader_location: 1,
+                },
+            ],
+        },
+        wgpu::VertexBufferLayout {
+            array_stride: 3 * 4,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 2,
+            }],
+        },
+    ];
+    let pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vs_main",
+                // 这里处理顶点缓冲区的布局，而非顶点源数据
+                // 而是在render_pass中再写入顶点数据
+                buffers: &vertex_buffers,
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: context.surface_config.as_ref().unwrap().format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+    let id_pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Instance Id Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vs_main",
+                buffers: &vertex_buffers,
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: "fs_id",
+                targets: &[Some(IdBuffer::target())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(IdBuffer::depth_stencil()),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+    (pipeline, id_pipeline)
+}
+
 pub struct InstanceScene {
     pipeline: InstancePipeline,
+    instances: Vec<Instance>,
     instances_buffer: wgpu::Buffer,
     uniform_buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
+    /// 上一帧的时间，CPU 拾取时按它算方块的位置
+    time: f32,
+    picker: Picker,
+    /// CPU 模式下点击的像素，paint 里拿到窗口大小再测
+    cpu_click: Option<Vec2>,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+impl InstanceScene {
+    /// 和 instance.wgsl 里 vs_main 一样算出方块中心
+    fn center(&self, instance: &Instance) -> Vec2 {
+        let angle = self.time + instance.position[0];
+        Vec2::new(angle.cos(), angle.sin()) * ORBIT_RADIUS
+    }
+
+    /// 2D 里射线沿 z 打下去，就是看点落在哪个方块里，后画的在上面
+    fn pick_cpu(&self, pixel: Vec2, size: Vec2) -> Option<u32> {
+        let ndc = pixel_to_ndc(pixel, size);
+        self.instances
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, instance)| (ndc - self.center(instance)).abs().max_element() <= QUAD_HALF)
+            .map(|(i, _)| i as u32)
+    }
+
+    fn inspector(&mut self, context: &crate::gfx::GfxContext, ui: &egui::Context) {
+        egui::Window::new("Picking")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                self.picker.ui(ui, |id| format!("quad {}", id));
+                let Some(id) = self.picker.selected else {
+                    return;
+                };
+                let center = self.center(&self.instances[id as usize]);
+                ui.label(format!("center: ({:.2}, {:.2})", center.x, center.y));
+                let instance = &mut self.instances[id as usize];
+                // position.x 在 shader 里是绕圈的相位
+                let changed = ui
+                    .add(
+                        egui::Slider::new(&mut instance.position[0], 0.0..=std::f32::consts::TAU)
+                            .text("phase"),
+                    )
+                    .changed();
+                if changed {
+                    context.queue.write_buffer(
+                        &self.instances_buffer,
+                        id as u64 * std::mem::size_of::<Instance>() as u64,
+                        bytemuck::bytes_of(instance),
+                    );
+                }
+            });
+    }
 }
 impl Painter for InstanceScene {
     fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        let pipeline = &mut self.pipeline;
+        pipeline.pipelines.update(context, |source| {
+            build_pipelines(context, &pipeline.pipeline_layout, source)
+        });
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -276,9 +396,17 @@ impl Painter for InstanceScene {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
 
+        let config = context.surface_config.as_ref().unwrap();
+        let size = Vec2::new(config.width as f32, config.height as f32);
+        if let Some(pixel) = self.cpu_click.take() {
+            self.picker.selected = self.pick_cpu(pixel, size);
+        }
+        self.time = time;
         let new_uniform = [Uniforms {
             delta_time: dt,
             time: time,
+            selected: self.picker.selected.map_or(0, |id| id + 1),
+            _pad: 0,
         }];
         let input_dt: &[u8] = bytemuck::cast_slice(&new_uniform);
         context
@@ -298,7 +426,7 @@ impl Painter for InstanceScene {
                 depth_stencil_attachment: None,
                 ..Default::default()
             });
-            rpass.set_pipeline(&self.pipeline.pipeline);
+            rpass.set_pipeline(&self.pipeline.pipelines.0);
 
             // 这里的set是传入经过buffer处理源数据后的数据
             rpass.set_vertex_buffer(0, self.pipeline.vertex_buffer.slice(..));
@@ -310,7 +438,46 @@ impl Painter for InstanceScene {
             rpass.set_vertex_buffer(1, self.instances_buffer.slice(..));
             rpass.draw_indexed(0..6, 0, 0..5);
         }
+        if self.picker.ids.requested() {
+            let mut id_pass = self.picker.ids.begin(context, &mut encoder);
+            id_pass.set_pipeline(&self.pipeline.pipelines.1);
+            id_pass.set_vertex_buffer(0, self.pipeline.vertex_buffer.slice(..));
+            id_pass.set_index_buffer(
+                self.pipeline.index_buffer.slice(..),
+                wgpu::IndexFormat::Uint16,
+            );
+            id_pass.set_bind_group(0, &self.bind_group, &[]);
+            id_pass.set_vertex_buffer(1, self.instances_buffer.slice(..));
+            id_pass.draw_indexed(0..6, 0, 0..5);
+            drop(id_pass);
+            self.picker.ids.copy(&mut encoder);
+        }
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.inspector(context, ui),
+        );
+
         context.queue.submit(Some(encoder.finish()));
         frame.present();
+        self.picker.finish(context);
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        let over_ui = self.egui.lock().unwrap().context.is_pointer_over_area();
+        if let Some(pixel) = self.picker.input(event, over_ui) {
+            self.cpu_click = Some(pixel);
+        }
     }
 }
@@ -0,0 +1,83 @@
+// 三种光照模型，和 light/mod.rs 里的 BrdfModel 对应
+// 返回的是 BRDF 本身，调用的地方再乘 radiance * n·l
+
+const PI: f32 = 3.14159265;
+
+const BRDF_LAMBERT: u32 = 0u;
+const BRDF_BLINN_PHONG: u32 = 1u;
+const BRDF_COOK_TORRANCE: u32 = 2u;
+
+struct Surface {
+    albedo: vec3<f32>,
+    roughness: f32,
+    metallic: f32,
+    // Blinn-Phong 的高光指数
+    shininess: f32,
+    normal: vec3<f32>,
+    // 从表面指向相机
+    view: vec3<f32>,
+};
+
+fn lambert(s: Surface) -> vec3<f32> {
+    return s.albedo / PI;
+}
+
+fn specular_color(s: Surface) -> vec3<f32> {
+    return mix(vec3<f32>(0.04), s.albedo, s.metallic);
+}
+
+fn blinn_phong(s: Surface, l: vec3<f32>) -> vec3<f32> {
+    let h = normalize(l + s.view);
+    // 归一化系数，让高光的总能量不随 shininess 变化
+    let normalization = (s.shininess + 8.0) / (8.0 * PI);
+    let specular = normalization * pow(max(dot(s.normal, h), 0.0), s.shininess);
+    return lambert(s) * (1.0 - s.metallic) + specular_color(s) * specular;
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return gv * gl;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+fn cook_torrance(s: Surface, l: vec3<f32>) -> vec3<f32> {
+    let h = normalize(l + s.view);
+    let n_dot_l = max(dot(s.normal, l), 1e-4);
+    let n_dot_v = max(dot(s.normal, s.view), 1e-4);
+    let n_dot_h = max(dot(s.normal, h), 0.0);
+    let roughness = clamp(s.roughness, 0.04, 1.0);
+    let f = fresnel_schlick(max(dot(h, s.view), 0.0), specular_color(s));
+    let d = distribution_ggx(n_dot_h, roughness);
+    let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+    let specular = f * d * g / (4.0 * n_dot_v * n_dot_l);
+    // 被反射掉的部分不再参与漫反射，金属没有漫反射
+    let kd = (vec3<f32>(1.0) - f) * (1.0 - s.metallic);
+    return kd * lambert(s) + specular;
+}
+
+fn brdf(model: u32, s: Surface, l: vec3<f32>) -> vec3<f32> {
+    switch model {
+        case BRDF_BLINN_PHONG: {
+            return blinn_phong(s, l);
+        }
+        case BRDF_COOK_TORRANCE: {
+            return cook_torrance(s, l);
+        }
+        default: {
+            return lambert(s);
+        }
+    }
+}
@@ -0,0 +1,522 @@
+// 级联阴影：一大片地面上摆满物体，相机在中间慢慢转
+// 平行光的阴影按距离切成几级，占阴影贴图数组的前几层
+// 左上角可以改级数、切分方式，打开每级上色的调试视图；按 V 换线框、法线这些通用的调试视图
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::{Mat4, Quat, Vec3};
+use wgpu::util::DeviceExt;
+use winit::window::Window;
+
+use super::{
+    demo::{ObjectUniform, MESH_LAYOUT, MODELS, VERTEX_STRIDE},
+    shadow::{cascade_splits, with_shadows, CameraFrustum, ShadowMap},
+    BrdfModel, DirectionalLight, Light, LightSet,
+};
+use crate::{
+    assets::{Handle, HotShader},
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::{
+        models::ModelBuffers,
+        view_modes::{ViewCamera, ViewMesh, ViewModes},
+    },
+};
+
+const SHADOW_SIZE: u32 = 2048;
+const GROUND: f32 = 60.0;
+/// 物体摆成 GRID x GRID 的格子
+const GRID: usize = 11;
+const SPACING: f32 = 6.0;
+const OBJECT_COUNT: usize = GRID * GRID + 1;
+const FOV_Y: f32 = 1.0;
+const NEAR: f32 = 0.1;
+const FAR: f32 = 200.0;
+/// 和 cascade_demo.wgsl 里远处雾的颜色过完 tone mapping 后一致
+const SKY: wgpu::Color = wgpu::Color {
+    r: 0.333,
+    g: 0.375,
+    b: 0.429,
+    a: 1.0,
+};
+
+/// 和 cascade_demo.wgsl 里的 Frame 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct FrameUniform {
+    view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 3],
+    brdf: u32,
+    ambient: [f32; 3],
+    _pad0: f32,
+    camera_forward: [f32; 3],
+    _pad1: f32,
+}
+
+/// 第 i 个物体用 models 里的哪个网格
+fn model_index(i: usize) -> usize {
+    if i == 0 {
+        0
+    } else {
+        1 + (i - 1) % 3
+    }
+}
+
+/// 和内置模型一样的顶点格式：位置 + 法线 + uv
+fn ground_buffers(context: &crate::gfx::GfxContext) -> ModelBuffers {
+    let vertices: [[f32; 8]; 4] = [
+        [-GROUND, 0.0, -GROUND, 0.0, 1.0, 0.0, 0.0, 0.0],
+        [-GROUND, 0.0, GROUND, 0.0, 1.0, 0.0, 0.0, 1.0],
+        [GROUND, 0.0, GROUND, 0.0, 1.0, 0.0, 1.0, 1.0],
+        [GROUND, 0.0, -GROUND, 0.0, 1.0, 0.0, 1.0, 0.0],
+    ];
+    let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
+    ModelBuffers::new(context, "cascade ground", &vertices, &indices)
+}
+
+pub struct CascadeDemo {
+    pipeline: HotShader<wgpu::RenderPipeline>,
+    pipeline_layout: wgpu::PipelineLayout,
+    depth_pipeline: wgpu::RenderPipeline,
+    /// 第 0 个是地面，后面是 Plane, Sphere, Cube
+    models: Vec<Arc<ModelBuffers>>,
+    /// 拿着 handle，别的地方要同样的内置模型就直接共用
+    _builtins: Vec<Handle<ModelBuffers>>,
+    light_set: LightSet,
+    shadow_map: ShadowMap,
+    frame_buffer: wgpu::Buffer,
+    frame_group: wgpu::BindGroup,
+    object_group: wgpu::BindGroup,
+    object_stride: wgpu::BufferAddress,
+    /// 每个物体的 model，object_buffer 里也有一份，调试视图用
+    transforms: Vec<Mat4>,
+    depth_view: wgpu::TextureView,
+    cascade_count: usize,
+    /// 均匀切分和对数切分的混合
+    lambda: f32,
+    /// 阴影覆盖的最远距离
+    shadow_distance: f32,
+    view_modes: ViewModes,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+impl Sandy for CascadeDemo {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let config = context.surface_config.as_ref().unwrap();
+        let builtins: Vec<_> = MODELS
+            .iter()
+            .map(|&model| context.assets.builtin(context, model))
+            .collect();
+        let mut models = vec![Arc::new(ground_buffers(context))];
+        models.extend(builtins.iter().map(|handle| handle.get().unwrap()));
+        let light_set = LightSet::new(
+            context,
+            vec![DirectionalLight {
+                direction: Vec3::new(-0.6, -1.0, -0.3),
+                color: Vec3::new(1.0, 0.95, 0.85),
+                intensity: 1.5,
+                shadow: Some(0),
+                cascades: 4,
+            }
+            .into()],
+        );
+        let shadow_map = ShadowMap::new(context, SHADOW_SIZE);
+
+        let frame_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("cascade demo frame"),
+            size: std::mem::size_of::<FrameUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let alignment =
+            context.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let object_stride =
+            wgpu::util::align_to(std::mem::size_of::<ObjectUniform>() as u64, alignment);
+
+        // 物体都不动，一开始就写好
+        let mut objects = vec![0u8; object_stride as usize * OBJECT_COUNT];
+        let mut transforms = Vec::with_capacity(OBJECT_COUNT);
+        for i in 0..OBJECT_COUNT {
+            let (model, albedo) = if i == 0 {
+                (Mat4::IDENTITY, [0.6, 0.65, 0.55])
+            } else {
+                let (gx, gz) = ((i - 1) % GRID, (i - 1) / GRID);
+                let position = Vec3::new(
+                    (gx as f32 - (GRID / 2) as f32) * SPACING,
+                    1.0,
+                    (gz as f32 - (GRID / 2) as f32) * SPACING,
+                );
+                let rotation = Quat::from_rotation_y(i as f32) * Quat::from_rotation_x(0.4);
+                let scale = [2.5, 1.5, 0.8][(i - 1) % 3];
+                let albedo = [[0.9, 0.3, 0.25], [0.95, 0.75, 0.3], [0.3, 0.55, 0.9]][(i - 1) % 3];
+                (
+                    Mat4::from_scale_rotation_translation(Vec3::splat(scale), rotation, position),
+                    albedo,
+                )
+            };
+            transforms.push(model);
+            let object = ObjectUniform {
+                model: model.to_cols_array_2d(),
+                albedo,
+                roughness: 0.6,
+                metallic: 0.0,
+                shininess: 32.0,
+                _pad: [0.0; 2],
+            };
+            let offset = i * object_stride as usize;
+            objects[offset..offset + std::mem::size_of::<ObjectUniform>()]
+                .copy_from_slice(bytemuck::bytes_of(&object));
+        }
+        let object_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("cascade demo objects"),
+                contents: &objects,
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        let uniform_layout = |dynamic: bool, size: usize| {
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: dynamic,
+                            min_binding_size: wgpu::BufferSize::new(size as u64),
+                        },
+                        count: None,
+                    }],
+                })
+        };
+        let frame_layout = uniform_layout(false, std::mem::size_of::<FrameUniform>());
+        let object_layout = uniform_layout(true, std::mem::size_of::<ObjectUniform>());
+        let frame_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("cascade demo frame"),
+                layout: &frame_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: frame_buffer.as_entire_binding(),
+                }],
+            });
+        let object_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("cascade demo objects"),
+                layout: &object_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &object_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as u64),
+                    }),
+                }],
+            });
+
+        let depth_texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("cascade demo depth"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("cascade demo"),
+                    bind_group_layouts: &[
+                        &frame_layout,
+                        &light_set.bind_group_layout,
+                        &object_layout,
+                        &shadow_map.sample_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let pipeline = HotShader::new(
+            context,
+            "asset/shaders/light/cascade_demo.wgsl",
+            include_str!("../../../asset/shaders/light/cascade_demo.wgsl"),
+            |source| build_pipeline(context, &pipeline_layout, source),
+        );
+        let depth_pipeline = shadow_map.depth_pipeline(
+            context,
+            &object_layout,
+            VERTEX_STRIDE,
+            wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        );
+
+        CascadeDemo {
+            pipeline,
+            pipeline_layout,
+            depth_pipeline,
+            models,
+            _builtins: builtins,
+            light_set,
+            shadow_map,
+            frame_buffer,
+            frame_group,
+            object_group,
+            object_stride,
+            transforms,
+            depth_view,
+            cascade_count: 4,
+            lambda: 0.75,
+            shadow_distance: 80.0,
+            view_modes: ViewModes::new(context, config.view_formats[0], MESH_LAYOUT),
+            window,
+            egui,
+        }
+    }
+}
+
+fn build_pipeline(
+    context: &crate::gfx::GfxContext,
+    layout: &wgpu::PipelineLayout,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let config = context.surface_config.as_ref().unwrap();
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cascade demo"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_shadows(source))),
+        });
+    context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("cascade demo"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: VERTEX_STRIDE,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.view_formats[0].into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+}
+
+impl CascadeDemo {
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Cascaded shadows")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.add(egui::Slider::new(&mut self.cascade_count, 1..=4).text("cascades"));
+                ui.add(egui::Slider::new(&mut self.lambda, 0.0..=1.0).text("log / uniform"));
+                ui.add(egui::Slider::new(&mut self.shadow_distance, 10.0..=FAR).text("distance"));
+                let uniform = &mut self.shadow_map.uniform;
+                ui.add(egui::Slider::new(&mut uniform.cascade_blend, 0.0..=0.5).text("blend"));
+                ui.add(egui::Slider::new(&mut uniform.pcf_radius, 0..=3).text("PCF radius"));
+                let mut debug = uniform.debug_cascades != 0;
+                ui.checkbox(&mut debug, "color cascades");
+                uniform.debug_cascades = debug as u32;
+                ui.separator();
+                self.view_modes.ui(ui);
+            });
+    }
+
+    /// 返回 view_proj，调试视图也要用
+    fn update(&mut self, context: &crate::gfx::GfxContext, time: f32) -> Mat4 {
+        let config = context.surface_config.as_ref().unwrap();
+        let aspect = config.width as f32 / config.height.max(1) as f32;
+        // 相机站在中间，原地慢慢转一圈，稍微往下看
+        let eye = Vec3::new(0.0, 6.0, 0.0);
+        let yaw = time * 0.1;
+        let forward = Vec3::new(yaw.sin(), -0.25, -yaw.cos()).normalize();
+        let view = Mat4::look_at_rh(eye, eye + forward, Vec3::Y);
+        let proj = Mat4::perspective_rh(FOV_Y, aspect, NEAR, FAR);
+        let frame = FrameUniform {
+            view_proj: (proj * view).to_cols_array_2d(),
+            camera_position: eye.to_array(),
+            brdf: BrdfModel::CookTorrance as u32,
+            ambient: [0.15, 0.17, 0.2],
+            _pad0: 0.0,
+            camera_forward: forward.to_array(),
+            _pad1: 0.0,
+        };
+        context
+            .queue
+            .write_buffer(&self.frame_buffer, 0, bytemuck::bytes_of(&frame));
+
+        let camera = CameraFrustum {
+            view,
+            fov_y: FOV_Y,
+            aspect,
+            near: NEAR,
+        };
+        let splits = cascade_splits(NEAR, self.shadow_distance, self.cascade_count, self.lambda);
+        if let Some(Light::Directional(sun)) = self.light_set.lights.first_mut() {
+            sun.cascades = self.cascade_count as u32;
+            let cascades = sun.cascades(&camera, &splits, SHADOW_SIZE);
+            self.shadow_map
+                .set_cascades(context, sun.shadow.unwrap(), &cascades);
+        }
+        self.light_set.upload(context);
+        self.shadow_map.upload(context);
+        proj * view
+    }
+
+    fn draw_models(&self, rpass: &mut wgpu::RenderPass, object_group_index: u32) {
+        for i in 0..OBJECT_COUNT {
+            let model = &self.models[model_index(i)];
+            let offset = (i as wgpu::BufferAddress * self.object_stride) as wgpu::DynamicOffset;
+            rpass.set_bind_group(object_group_index, &self.object_group, &[offset]);
+            rpass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+            rpass.set_index_buffer(model.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            rpass.draw_indexed(0..model.index_count, 0, 0..1);
+        }
+    }
+}
+
+impl Painter for CascadeDemo {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, _dt: f32, time: f32) {
+        self.pipeline.update(context, |source| {
+            build_pipeline(context, &self.pipeline_layout, source)
+        });
+        let mode = self.view_modes.sync(context);
+        let view_proj = self.update(context, time);
+
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("cascade demo"),
+            });
+
+        // 每一级一个深度 pass
+        let shaded = !mode.replaces_scene();
+        for layer in (0..self.cascade_count as u32).filter(|_| shaded) {
+            self.shadow_map
+                .render_layer(&mut encoder, &self.depth_pipeline, layer, |pass| {
+                    self.draw_models(pass, 1)
+                });
+        }
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("cascade demo"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(SKY),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: context.render_timestamps("cascade demo"),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.frame_group, &[]);
+            rpass.set_bind_group(1, &self.light_set.bind_group, &[]);
+            rpass.set_bind_group(3, &self.shadow_map.sample_group, &[]);
+            if shaded {
+                self.draw_models(&mut rpass, 2);
+            }
+        }
+        let meshes: Vec<ViewMesh> = self
+            .transforms
+            .iter()
+            .enumerate()
+            .map(|(i, &transform)| {
+                let model = &self.models[model_index(i)];
+                ViewMesh {
+                    vertices: &model.vertex_buffer,
+                    indices: &model.index_buffer,
+                    index_count: model.index_count,
+                    model: transform,
+                    viewport: None,
+                }
+            })
+            .collect();
+        let camera = ViewCamera {
+            view_proj,
+            near: NEAR,
+            far: FAR,
+        };
+        self.view_modes
+            .render(context, &mut encoder, &view, &camera, &meshes);
+
+        let config = context.surface_config.as_ref().unwrap();
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+}
@@ -0,0 +1,470 @@
+// 用 light 库照亮 ModelType 的几个模型
+// 一个平行光、一个绕圈的点光、一个从上往下的聚光
+// 左上角切换 Lambert / Blinn-Phong / Cook-Torrance 和材质参数
+// 模型和 shader 都从 assets 拿，改 asset/shaders/light/demo.wgsl 保存就会重新编
+// 按 V 换调试视图
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::{Mat4, Quat, Vec3};
+use winit::window::Window;
+
+use super::{with_lighting, BrdfModel, DirectionalLight, Light, LightSet, PointLight, SpotLight};
+use crate::{
+    assets::{Handle, HotShader},
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::{
+        models::{ModelBuffers, ModelType},
+        view_modes::{MeshLayout, ViewCamera, ViewMesh, ViewModes},
+    },
+};
+
+/// 和 MODELS 的顺序一致
+/// (位置, 缩放, 颜色)
+const OBJECTS: [([f32; 3], f32, [f32; 3]); 3] = [
+    ([-1.6, 0.0, 0.0], 2.5, [0.9, 0.3, 0.25]),
+    ([0.0, 0.0, 0.0], 1.3, [0.95, 0.75, 0.3]),
+    ([1.6, 0.0, 0.0], 0.55, [0.3, 0.55, 0.9]),
+];
+const EYE: Vec3 = Vec3::new(0.0, 1.5, 4.5);
+pub(super) const MODELS: [ModelType; 3] = [ModelType::Plane, ModelType::Sphere, ModelType::Cube];
+/// assets 的内置模型是位置 + 法线 + uv，这里只读前两个
+pub(super) const VERTEX_STRIDE: wgpu::BufferAddress = 8 * 4;
+/// 调试视图按同样的布局读内置模型
+pub(super) const MESH_LAYOUT: MeshLayout = MeshLayout {
+    stride: 8,
+    normal: Some(3),
+    uv: Some(6),
+    index_format: wgpu::IndexFormat::Uint32,
+};
+
+/// 和 demo.wgsl 里的 Frame 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(super) struct FrameUniform {
+    pub(super) view_proj: [[f32; 4]; 4],
+    pub(super) camera_position: [f32; 3],
+    pub(super) brdf: u32,
+    pub(super) ambient: [f32; 3],
+    pub(super) _pad: f32,
+}
+
+/// 和 demo.wgsl 里的 Object 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(super) struct ObjectUniform {
+    pub(super) model: [[f32; 4]; 4],
+    pub(super) albedo: [f32; 3],
+    pub(super) roughness: f32,
+    pub(super) metallic: f32,
+    pub(super) shininess: f32,
+    pub(super) _pad: [f32; 2],
+}
+
+pub struct LightingDemo {
+    pipeline: HotShader<wgpu::RenderPipeline>,
+    pipeline_layout: wgpu::PipelineLayout,
+    models: Vec<Handle<ModelBuffers>>,
+    light_set: LightSet,
+    frame_buffer: wgpu::Buffer,
+    frame_group: wgpu::BindGroup,
+    object_buffer: wgpu::Buffer,
+    object_group: wgpu::BindGroup,
+    /// 每个物体在 object_buffer 里占的字节，按 dynamic offset 对齐
+    object_stride: wgpu::BufferAddress,
+    depth_view: wgpu::TextureView,
+    brdf: BrdfModel,
+    roughness: f32,
+    metallic: f32,
+    shininess: f32,
+    view_modes: ViewModes,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+impl Sandy for LightingDemo {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let config = context.surface_config.as_ref().unwrap();
+        let models = MODELS
+            .map(|model| context.assets.builtin(context, model))
+            .to_vec();
+        let light_set = LightSet::new(
+            context,
+            vec![
+                DirectionalLight {
+                    direction: Vec3::new(-0.4, -1.0, -0.6),
+                    color: Vec3::new(1.0, 0.95, 0.85),
+                    intensity: 1.5,
+                    shadow: None,
+                    cascades: 0,
+                }
+                .into(),
+                PointLight {
+                    position: Vec3::new(0.0, 0.5, 1.5),
+                    color: Vec3::new(0.3, 0.6, 1.0),
+                    intensity: 6.0,
+                    range: 6.0,
+                }
+                .into(),
+                SpotLight {
+                    position: Vec3::new(0.0, 3.0, 1.0),
+                    direction: Vec3::new(0.0, -3.0, -1.0),
+                    color: Vec3::new(1.0, 0.5, 0.3),
+                    intensity: 25.0,
+                    range: 8.0,
+                    inner_angle: 15f32.to_radians(),
+                    outer_angle: 25f32.to_radians(),
+                    shadow: None,
+                }
+                .into(),
+            ],
+        );
+
+        let frame_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lighting frame"),
+            size: std::mem::size_of::<FrameUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let alignment =
+            context.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let object_stride =
+            wgpu::util::align_to(std::mem::size_of::<ObjectUniform>() as u64, alignment);
+        let object_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lighting objects"),
+            size: object_stride * OBJECTS.len() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_layout = |dynamic: bool, size: usize| {
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: dynamic,
+                            min_binding_size: wgpu::BufferSize::new(size as u64),
+                        },
+                        count: None,
+                    }],
+                })
+        };
+        let frame_layout = uniform_layout(false, std::mem::size_of::<FrameUniform>());
+        let object_layout = uniform_layout(true, std::mem::size_of::<ObjectUniform>());
+        let frame_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("lighting frame"),
+                layout: &frame_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: frame_buffer.as_entire_binding(),
+                }],
+            });
+        let object_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("lighting objects"),
+                layout: &object_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &object_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as u64),
+                    }),
+                }],
+            });
+
+        let depth_texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("lighting depth"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("lighting demo"),
+                    bind_group_layouts: &[
+                        &frame_layout,
+                        &light_set.bind_group_layout,
+                        &object_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let pipeline = HotShader::new(
+            context,
+            "asset/shaders/light/demo.wgsl",
+            include_str!("../../../asset/shaders/light/demo.wgsl"),
+            |source| build_pipeline(context, &pipeline_layout, source),
+        );
+
+        LightingDemo {
+            pipeline,
+            pipeline_layout,
+            models,
+            light_set,
+            frame_buffer,
+            frame_group,
+            object_buffer,
+            object_group,
+            object_stride,
+            depth_view,
+            brdf: BrdfModel::CookTorrance,
+            roughness: 0.4,
+            metallic: 0.0,
+            shininess: 64.0,
+            view_modes: ViewModes::new(context, config.view_formats[0], MESH_LAYOUT),
+            window,
+            egui,
+        }
+    }
+}
+
+fn build_pipeline(
+    context: &crate::gfx::GfxContext,
+    layout: &wgpu::PipelineLayout,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let config = context.surface_config.as_ref().unwrap();
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("lighting demo"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_lighting(source))),
+        });
+    context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("lighting demo"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: VERTEX_STRIDE,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.view_formats[0].into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+}
+
+impl LightingDemo {
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Lighting")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                for model in BrdfModel::ALL {
+                    ui.radio_value(&mut self.brdf, model, format!("{:?}", model));
+                }
+                ui.separator();
+                ui.add(egui::Slider::new(&mut self.roughness, 0.0..=1.0).text("roughness"));
+                ui.add(egui::Slider::new(&mut self.metallic, 0.0..=1.0).text("metallic"));
+                ui.add(
+                    egui::Slider::new(&mut self.shininess, 1.0..=512.0)
+                        .logarithmic(true)
+                        .text("shininess"),
+                );
+                ui.separator();
+                self.view_modes.ui(ui);
+            });
+    }
+
+    /// 返回 view_proj 和每个物体的 model，调试视图也要用
+    fn update(&mut self, context: &crate::gfx::GfxContext, time: f32) -> (Mat4, Vec<Mat4>) {
+        let config = context.surface_config.as_ref().unwrap();
+        let aspect = config.width as f32 / config.height.max(1) as f32;
+        let view = Mat4::look_at_rh(EYE, Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(45f32.to_radians(), aspect, 0.1, 100.0);
+        let frame = FrameUniform {
+            view_proj: (proj * view).to_cols_array_2d(),
+            camera_position: EYE.to_array(),
+            brdf: self.brdf as u32,
+            ambient: [0.03, 0.03, 0.04],
+            _pad: 0.0,
+        };
+        context
+            .queue
+            .write_buffer(&self.frame_buffer, 0, bytemuck::bytes_of(&frame));
+
+        let rotation = Quat::from_rotation_y(time * 0.5) * Quat::from_rotation_x(0.4);
+        let mut models = Vec::with_capacity(OBJECTS.len());
+        for (i, (position, scale, albedo)) in OBJECTS.into_iter().enumerate() {
+            let model = Mat4::from_scale_rotation_translation(
+                Vec3::splat(scale),
+                rotation,
+                Vec3::from_array(position),
+            );
+            models.push(model);
+            let object = ObjectUniform {
+                model: model.to_cols_array_2d(),
+                albedo,
+                roughness: self.roughness,
+                metallic: self.metallic,
+                shininess: self.shininess,
+                _pad: [0.0; 2],
+            };
+            context.queue.write_buffer(
+                &self.object_buffer,
+                i as wgpu::BufferAddress * self.object_stride,
+                bytemuck::bytes_of(&object),
+            );
+        }
+
+        // 点光绕着模型转
+        if let Some(Light::Point(point)) = self.light_set.lights.get_mut(1) {
+            point.position = Vec3::new(2.5 * time.cos(), 0.5, 2.5 * time.sin());
+        }
+        self.light_set.upload(context);
+        (proj * view, models)
+    }
+}
+
+impl Painter for LightingDemo {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, _dt: f32, time: f32) {
+        self.pipeline.update(context, |source| {
+            build_pipeline(context, &self.pipeline_layout, source)
+        });
+        let mode = self.view_modes.sync(context);
+        let (view_proj, transforms) = self.update(context, time);
+        let models: Vec<_> = self.models.iter().map(|m| m.get().unwrap()).collect();
+
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("lighting demo"),
+            });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("lighting demo"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.02,
+                            g: 0.02,
+                            b: 0.03,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: context.render_timestamps("lighting demo"),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.frame_group, &[]);
+            rpass.set_bind_group(1, &self.light_set.bind_group, &[]);
+            let models = if mode.replaces_scene() {
+                &[][..]
+            } else {
+                &models[..]
+            };
+            for (i, model) in models.iter().enumerate() {
+                let offset = (i as wgpu::BufferAddress * self.object_stride) as wgpu::DynamicOffset;
+                rpass.set_bind_group(2, &self.object_group, &[offset]);
+                rpass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                rpass.set_index_buffer(model.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                rpass.draw_indexed(0..model.index_count, 0, 0..1);
+            }
+        }
+        let meshes: Vec<ViewMesh> = models
+            .iter()
+            .zip(transforms)
+            .map(|(model, transform)| ViewMesh {
+                vertices: &model.vertex_buffer,
+                indices: &model.index_buffer,
+                index_count: model.index_count,
+                model: transform,
+                viewport: None,
+            })
+            .collect();
+        let camera = ViewCamera {
+            view_proj,
+            near: 0.1,
+            far: 100.0,
+        };
+        self.view_modes
+            .render(context, &mut encoder, &view, &camera, &meshes);
+
+        let config = context.surface_config.as_ref().unwrap();
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+}
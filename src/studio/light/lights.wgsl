@@ -0,0 +1,53 @@
+// 和 light/mod.rs 里的 GpuLight 对应
+// 场景自己声明 var<storage, read> lights: LightArray 的 group / binding
+
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    position: vec3<f32>,
+    kind: u32,
+    // 光照射的方向，平行光和聚光用
+    direction: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    // cos(内角), cos(外角)
+    cone: vec2<f32>,
+    // ShadowMap 里的层，-1 表示没有阴影
+    shadow_layer: i32,
+    // 大于 0 表示级联阴影，从 shadow_layer 开始连续占这么多层
+    shadow_cascades: u32,
+};
+
+struct LightArray {
+    count: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+    items: array<Light>,
+};
+
+struct LightSample {
+    // 从表面指向光源
+    direction: vec3<f32>,
+    radiance: vec3<f32>,
+};
+
+fn sample_light(light: Light, world: vec3<f32>) -> LightSample {
+    if light.kind == LIGHT_DIRECTIONAL {
+        return LightSample(normalize(-light.direction), light.color * light.intensity);
+    }
+    let to_light = light.position - world;
+    let dist = length(to_light);
+    let l = to_light / max(dist, 1e-4);
+    // 平方反比，在 range 处平滑地降到 0
+    let window = pow(clamp(1.0 - pow(dist / light.range, 4.0), 0.0, 1.0), 2.0);
+    var attenuation = window / max(dist * dist, 1e-4);
+    if light.kind == LIGHT_SPOT {
+        let cos_angle = dot(-l, normalize(light.direction));
+        attenuation *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+    }
+    return LightSample(l, light.color * light.intensity * attenuation);
+}
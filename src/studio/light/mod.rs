@@ -0,0 +1,239 @@
+// 光照模型库
+// 平行光、点光、聚光，统一转成 GpuLight 放进 storage buffer
+// lights.wgsl 负责算每个光源打到表面上的方向和 radiance
+// brdf.wgsl 里是 Lambert、Blinn-Phong、Cook-Torrance
+pub mod cascade_demo;
+pub mod demo;
+pub mod shadow;
+pub mod shadow_demo;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use serde::Deserialize;
+use wgpu::util::DeviceExt;
+
+pub const LIGHTS_WGSL: &str = include_str!("lights.wgsl");
+pub const BRDF_WGSL: &str = include_str!("brdf.wgsl");
+
+/// wgsl 没有 include，把库拼在场景 shader 前面
+pub fn with_lighting(source: &str) -> String {
+    format!("{}\n{}\n{}", LIGHTS_WGSL, BRDF_WGSL, source)
+}
+
+pub const MAX_LIGHTS: usize = 32;
+
+/// 和 brdf.wgsl 里的 BRDF_* 对应
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub enum BrdfModel {
+    Lambert = 0,
+    BlinnPhong = 1,
+    CookTorrance = 2,
+}
+
+impl BrdfModel {
+    pub const ALL: [BrdfModel; 3] = [
+        BrdfModel::Lambert,
+        BrdfModel::BlinnPhong,
+        BrdfModel::CookTorrance,
+    ];
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    /// 光照射的方向
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// 在 ShadowMap 里用哪一层，None 不投影
+    pub shadow: Option<u32>,
+    /// 大于 0 时用级联阴影，从 shadow 那层开始连续占 cascades 层
+    pub cascades: u32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// 超过这个距离就没有光
+    pub range: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    /// 弧度，内角以内全亮，到外角过渡到 0
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub shadow: Option<u32>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Light {
+    Directional(DirectionalLight),
+    Point(PointLight),
+    Spot(SpotLight),
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(light: DirectionalLight) -> Self {
+        Light::Directional(light)
+    }
+}
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Self {
+        Light::Point(light)
+    }
+}
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Light::Spot(light)
+    }
+}
+
+/// 和 lights.wgsl 里的 Light 对应，64 字节
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct GpuLight {
+    pub position: [f32; 3],
+    pub kind: u32,
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub cone: [f32; 2],
+    /// -1 表示没有阴影
+    pub shadow_layer: i32,
+    /// 0 表示单张阴影贴图
+    pub shadow_cascades: u32,
+}
+
+impl Light {
+    pub fn to_gpu(&self) -> GpuLight {
+        match *self {
+            Light::Directional(l) => GpuLight {
+                kind: 0,
+                direction: l.direction.normalize().to_array(),
+                color: l.color.to_array(),
+                intensity: l.intensity,
+                shadow_layer: shadow_layer(l.shadow),
+                shadow_cascades: l.cascades,
+                ..GpuLight::zeroed()
+            },
+            Light::Point(l) => GpuLight {
+                position: l.position.to_array(),
+                kind: 1,
+                range: l.range,
+                color: l.color.to_array(),
+                intensity: l.intensity,
+                shadow_layer: -1,
+                ..GpuLight::zeroed()
+            },
+            Light::Spot(l) => GpuLight {
+                position: l.position.to_array(),
+                kind: 2,
+                direction: l.direction.normalize().to_array(),
+                range: l.range,
+                color: l.color.to_array(),
+                intensity: l.intensity,
+                cone: [l.inner_angle.cos(), l.outer_angle.cos()],
+                shadow_layer: shadow_layer(l.shadow),
+                shadow_cascades: 0,
+            },
+        }
+    }
+}
+
+fn shadow_layer(shadow: Option<u32>) -> i32 {
+    shadow.map_or(-1, |layer| layer as i32)
+}
+
+/// lights.wgsl 里 LightArray 的头，后面紧跟 GpuLight 数组
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct LightArrayHeader {
+    count: u32,
+    _pad: [u32; 3],
+}
+
+/// 一组光源和它们在 GPU 上的 storage buffer
+/// 改完 lights 之后调 upload
+pub struct LightSet {
+    pub lights: Vec<Light>,
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl LightSet {
+    pub fn new(context: &crate::gfx::GfxContext, lights: Vec<Light>) -> Self {
+        let buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("light set"),
+                contents: &Self::bytes(&lights),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            });
+        let bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("light set bind group layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                                LightArrayHeader,
+                            >()
+                                as u64),
+                        },
+                        count: None,
+                    }],
+                });
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("light set bind group"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+        LightSet {
+            lights,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// 按 MAX_LIGHTS 开满，超出的光源丢掉
+    fn bytes(lights: &[Light]) -> Vec<u8> {
+        let count = lights.len().min(MAX_LIGHTS);
+        let mut items = vec![GpuLight::zeroed(); MAX_LIGHTS];
+        for (item, light) in items.iter_mut().zip(lights) {
+            *item = light.to_gpu();
+        }
+        let header = LightArrayHeader {
+            count: count as u32,
+            _pad: [0; 3],
+        };
+        let mut bytes = bytemuck::bytes_of(&header).to_vec();
+        bytes.extend_from_slice(bytemuck::cast_slice(&items));
+        bytes
+    }
+
+    pub fn upload(&self, context: &crate::gfx::GfxContext) {
+        context
+            .queue
+            .write_buffer(&self.buffer, 0, &Self::bytes(&self.lights));
+    }
+}
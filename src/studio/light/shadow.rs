@@ -0,0 +1,460 @@
+// 3D 阴影贴图
+// 每个投影的光源占 Depth32Float 贴图数组里的一层
+// 先用 depth_pipeline 从光的位置只画深度，再在光照 pass 里用 shadow.wgsl 比较 + PCF
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+
+use super::{DirectionalLight, SpotLight};
+
+pub const SHADOW_WGSL: &str = include_str!("shadow.wgsl");
+/// 和 shadow.wgsl 里的 MAX_SHADOW_LAYERS 一致
+pub const MAX_SHADOW_LAYERS: usize = 8;
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// 光照库 + 阴影采样，拼在场景 shader 前面
+pub fn with_shadows(source: &str) -> String {
+    super::with_lighting(&format!("{}\n{}", SHADOW_WGSL, source))
+}
+
+/// 和 shadow.wgsl 里的 ShadowUniform 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShadowUniform {
+    pub view_proj: [[[f32; 4]; 4]; MAX_SHADOW_LAYERS],
+    pub texel_size: f32,
+    pub pcf_radius: u32,
+    pub normal_bias: f32,
+    pub cascade_count: u32,
+    pub cascade_splits: [f32; 4],
+    pub cascade_texels: [f32; 4],
+    pub cascade_blend: f32,
+    pub debug_cascades: u32,
+    pub _pad: [f32; 2],
+}
+
+impl DirectionalLight {
+    /// 正交投影，把以 center 为中心、radius 为半径的球整个框进去
+    pub fn view_proj(&self, center: Vec3, radius: f32) -> Mat4 {
+        let direction = self.direction.normalize();
+        let eye = center - direction * radius * 2.0;
+        let view = Mat4::look_at_rh(eye, center, up_for(direction));
+        let proj = Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, radius * 4.0);
+        proj * view
+    }
+}
+
+impl SpotLight {
+    /// 透视投影，视角刚好是外角的两倍
+    pub fn view_proj(&self) -> Mat4 {
+        let direction = self.direction.normalize();
+        let view = Mat4::look_at_rh(self.position, self.position + direction, up_for(direction));
+        let proj = Mat4::perspective_rh(self.outer_angle * 2.0, 1.0, 0.05, self.range);
+        proj * view
+    }
+}
+
+/// 切级联用的相机参数
+pub struct CameraFrustum {
+    pub view: Mat4,
+    pub fov_y: f32,
+    pub aspect: f32,
+    pub near: f32,
+}
+
+pub struct Cascade {
+    pub view_proj: Mat4,
+    /// 这一级的远端，相机空间的深度
+    pub split: f32,
+    /// 一个 texel 在世界里多大
+    pub texel_world: f32,
+}
+
+/// 均匀切分和对数切分按 lambda 混合，lambda 越大近处分得越细
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|i| {
+            let p = i as f32 / count as f32;
+            let log = near * (far / near).powf(p);
+            let uniform = near + (far - near) * p;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+impl DirectionalLight {
+    /// 每一级用包住那段视锥的球来定正交投影的大小，相机转动时大小不变
+    /// 球心再对齐到 texel 的整数倍，相机平移时阴影边缘不会闪
+    pub fn cascades(
+        &self,
+        camera: &CameraFrustum,
+        splits: &[f32],
+        shadow_size: u32,
+    ) -> Vec<Cascade> {
+        let direction = self.direction.normalize();
+        // 光空间固定在原点，只有投影的窗口在动
+        let light_view = Mat4::look_at_rh(Vec3::ZERO, direction, up_for(direction));
+        let inverse_view = camera.view.inverse();
+        let tan_y = (camera.fov_y * 0.5).tan();
+        let tan_x = tan_y * camera.aspect;
+        let mut near = camera.near;
+        splits
+            .iter()
+            .map(|&far| {
+                let mut corners = Vec::with_capacity(8);
+                for depth in [near, far] {
+                    for (sx, sy) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                        let view_point = Vec3::new(sx * tan_x * depth, sy * tan_y * depth, -depth);
+                        corners.push(inverse_view.transform_point3(view_point));
+                    }
+                }
+                near = far;
+                let center = corners.iter().copied().sum::<Vec3>() / corners.len() as f32;
+                let radius = corners
+                    .iter()
+                    .map(|corner| corner.distance(center))
+                    .fold(0.0f32, f32::max);
+                // 半径取整一点，避免浮点误差让大小每帧抖动
+                let radius = (radius * 16.0).ceil() / 16.0;
+                let texel_world = radius * 2.0 / shadow_size as f32;
+
+                let mut center_ls = light_view.transform_point3(center);
+                center_ls.x = (center_ls.x / texel_world).floor() * texel_world;
+                center_ls.y = (center_ls.y / texel_world).floor() * texel_world;
+                // 视锥外面、朝光那一侧的物体也要投影，往光的方向多留一些
+                let proj = Mat4::orthographic_rh(
+                    center_ls.x - radius,
+                    center_ls.x + radius,
+                    center_ls.y - radius,
+                    center_ls.y + radius,
+                    -center_ls.z - radius * 3.0,
+                    -center_ls.z + radius,
+                );
+                Cascade {
+                    view_proj: proj * light_view,
+                    split: far,
+                    texel_world,
+                }
+            })
+            .collect()
+    }
+}
+
+/// 光直着朝上或朝下的时候 Y 不能当 up
+fn up_for(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+pub struct ShadowMap {
+    pub size: u32,
+    pub uniform: ShadowUniform,
+    pub texture: wgpu::Texture,
+    /// 每一层单独的 view，深度 pass 画到这里
+    pub layer_views: Vec<wgpu::TextureView>,
+    /// 深度 pass 用：每层一个 view_proj，按 dynamic offset 对齐
+    caster_buffer: wgpu::Buffer,
+    caster_stride: wgpu::BufferAddress,
+    caster_group: wgpu::BindGroup,
+    pub caster_layout: wgpu::BindGroupLayout,
+    /// 光照 pass 用：贴图数组 + 比较采样器 + ShadowUniform
+    uniform_buffer: wgpu::Buffer,
+    pub sample_layout: wgpu::BindGroupLayout,
+    pub sample_group: wgpu::BindGroup,
+}
+
+impl ShadowMap {
+    pub fn new(context: &crate::gfx::GfxContext, size: u32) -> Self {
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow map"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: MAX_SHADOW_LAYERS as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..MAX_SHADOW_LAYERS as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("shadow map layer"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        // 线性过滤 + 比较，硬件会顺便做一次 2x2 的 PCF
+        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let alignment =
+            context.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let caster_stride = wgpu::util::align_to(std::mem::size_of::<Mat4>() as u64, alignment);
+        let caster_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow casters"),
+            size: caster_stride * MAX_SHADOW_LAYERS as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let caster_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("shadow caster layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(64),
+                        },
+                        count: None,
+                    }],
+                });
+        let caster_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow casters"),
+                layout: &caster_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &caster_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(64),
+                    }),
+                }],
+            });
+
+        let uniform = ShadowUniform {
+            view_proj: [Mat4::IDENTITY.to_cols_array_2d(); MAX_SHADOW_LAYERS],
+            texel_size: 1.0 / size as f32,
+            pcf_radius: 1,
+            normal_bias: 0.02,
+            cascade_count: 0,
+            cascade_splits: [0.0; 4],
+            cascade_texels: [1.0; 4],
+            cascade_blend: 0.1,
+            debug_cascades: 0,
+            _pad: [0.0; 2],
+        };
+        let uniform_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow uniform"),
+            size: std::mem::size_of::<ShadowUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sample_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("shadow sample layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Depth,
+                                view_dimension: wgpu::TextureViewDimension::D2Array,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                                    ShadowUniform,
+                                >(
+                                )
+                                    as u64),
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let sample_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow sample group"),
+                layout: &sample_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&array_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        ShadowMap {
+            size,
+            uniform,
+            texture,
+            layer_views,
+            caster_buffer,
+            caster_stride,
+            caster_group,
+            caster_layout,
+            uniform_buffer,
+            sample_layout,
+            sample_group,
+        }
+    }
+
+    pub fn set_view_proj(&mut self, context: &crate::gfx::GfxContext, layer: u32, view_proj: Mat4) {
+        self.uniform.view_proj[layer as usize] = view_proj.to_cols_array_2d();
+        context.queue.write_buffer(
+            &self.caster_buffer,
+            layer as wgpu::BufferAddress * self.caster_stride,
+            bytemuck::cast_slice(&view_proj.to_cols_array()),
+        );
+    }
+
+    /// 把级联的矩阵写到 first_layer 开始的几层里，cascades 最多 4 级
+    pub fn set_cascades(
+        &mut self,
+        context: &crate::gfx::GfxContext,
+        first_layer: u32,
+        cascades: &[Cascade],
+    ) {
+        self.uniform.cascade_count = cascades.len() as u32;
+        for (i, cascade) in cascades.iter().enumerate() {
+            self.set_view_proj(context, first_layer + i as u32, cascade.view_proj);
+            self.uniform.cascade_splits[i] = cascade.split;
+            self.uniform.cascade_texels[i] = cascade.texel_world;
+        }
+    }
+
+    /// 改完 uniform 里的 pcf_radius / normal_bias 或者 set_view_proj 之后调
+    pub fn upload(&self, context: &crate::gfx::GfxContext) {
+        context
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    /// 只写深度的管线，group0 是光的 view_proj，group1 是场景的物体 uniform（开头是 model 矩阵）
+    /// bias 用来去掉 shadow acne，斜面上 slope_scale 起作用
+    pub fn depth_pipeline(
+        &self,
+        context: &crate::gfx::GfxContext,
+        object_layout: &wgpu::BindGroupLayout,
+        vertex_stride: wgpu::BufferAddress,
+        bias: wgpu::DepthBiasState,
+    ) -> wgpu::RenderPipeline {
+        let shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("shadow depth"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shadow_depth.wgsl"))),
+            });
+        let layout = context
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("shadow depth"),
+                bind_group_layouts: &[&self.caster_layout, object_layout],
+                push_constant_ranges: &[],
+            });
+        context
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("shadow depth"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: vertex_stride,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                    }],
+                    compilation_options: Default::default(),
+                },
+                fragment: None,
+                primitive: wgpu::PrimitiveState {
+                    // 平面两面都要投影，不剔除
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: SHADOW_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: Default::default(),
+                    bias,
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+    }
+
+    /// 开一个画到 layer 的深度 pass，draw 里自己设 group1 和 buffer
+    pub fn render_layer(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        layer: u32,
+        draw: impl FnOnce(&mut wgpu::RenderPass),
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shadow depth pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.layer_views[layer as usize],
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(pipeline);
+        let offset = (layer as wgpu::BufferAddress * self.caster_stride) as wgpu::DynamicOffset;
+        pass.set_bind_group(0, &self.caster_group, &[offset]);
+        draw(&mut pass);
+    }
+}
@@ -0,0 +1,114 @@
+// 阴影贴图的采样，和 light/shadow.rs 里的 ShadowUniform 对应
+// 场景自己声明这三个绑定：
+//   var shadow_map: texture_depth_2d_array;
+//   var shadow_sampler: sampler_comparison;
+//   var<uniform> shadows: ShadowUniform;
+
+const MAX_SHADOW_LAYERS: u32 = 8u;
+
+struct ShadowUniform {
+    view_proj: array<mat4x4<f32>, MAX_SHADOW_LAYERS>,
+    // 1 / 贴图边长
+    texel_size: f32,
+    // PCF 采 (2r+1)^2 次
+    pcf_radius: u32,
+    // 沿法线把采样点往外推，世界坐标
+    normal_bias: f32,
+    cascade_count: u32,
+    // 每一级的远端，相机空间的深度
+    cascade_splits: vec4<f32>,
+    // 每一级一个 texel 在世界里多大，normal bias 按它缩放
+    cascade_texels: vec4<f32>,
+    // 每一级最后这么大比例的距离里和下一级混合
+    cascade_blend: f32,
+    debug_cascades: u32,
+    _pad0: f32,
+    _pad1: f32,
+};
+
+fn sample_shadow_layer(layer: i32, world: vec3<f32>, normal: vec3<f32>, normal_bias: f32) -> f32 {
+    let clip = shadows.view_proj[layer] * vec4<f32>(world + normal * normal_bias, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    // 在光的视锥外面就当作照得到
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+    let r = i32(shadows.pcf_radius);
+    var sum = 0.0;
+    for (var y = -r; y <= r; y++) {
+        for (var x = -r; x <= r; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * shadows.texel_size;
+            sum += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, layer, ndc.z);
+        }
+    }
+    let taps = f32((2 * r + 1) * (2 * r + 1));
+    return sum / taps;
+}
+
+// 1 表示照得到，0 表示在阴影里
+fn shadow_factor(layer: i32, world: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if layer < 0 {
+        return 1.0;
+    }
+    return sample_shadow_layer(layer, world, normal, shadows.normal_bias);
+}
+
+fn cascade_index(view_depth: f32) -> u32 {
+    var c = 0u;
+    while c + 1u < shadows.cascade_count && view_depth > shadows.cascade_splits[c] {
+        c++;
+    }
+    return c;
+}
+
+// view_depth 是相机空间里沿视线方向的距离
+fn shadow_factor_cascaded(first_layer: i32, world: vec3<f32>, normal: vec3<f32>, view_depth: f32) -> f32 {
+    let c = cascade_index(view_depth);
+    if view_depth > shadows.cascade_splits[c] {
+        return 1.0;
+    }
+    let bias_scale = shadows.cascade_texels[c] / shadows.cascade_texels[0];
+    let current = sample_shadow_layer(first_layer + i32(c), world, normal, shadows.normal_bias * bias_scale);
+    if c + 1u >= shadows.cascade_count {
+        return current;
+    }
+    // 快到这一级的尽头时和下一级混合，避免接缝
+    let start = select(0.0, shadows.cascade_splits[max(c, 1u) - 1u], c > 0u);
+    let end = shadows.cascade_splits[c];
+    let fade = (end - view_depth) / max((end - start) * shadows.cascade_blend, 1e-4);
+    if fade >= 1.0 {
+        return current;
+    }
+    let next_bias = shadows.normal_bias * shadows.cascade_texels[c + 1u] / shadows.cascade_texels[0];
+    let next = sample_shadow_layer(first_layer + i32(c) + 1, world, normal, next_bias);
+    return mix(next, current, fade);
+}
+
+fn light_visibility(light: Light, world: vec3<f32>, normal: vec3<f32>, view_depth: f32) -> f32 {
+    if light.shadow_layer < 0 {
+        return 1.0;
+    }
+    if light.shadow_cascades > 0u {
+        return shadow_factor_cascaded(light.shadow_layer, world, normal, view_depth);
+    }
+    return shadow_factor(light.shadow_layer, world, normal);
+}
+
+// 调试用，每一级一个颜色
+fn cascade_debug_tint(view_depth: f32) -> vec3<f32> {
+    switch cascade_index(view_depth) {
+        case 0u: {
+            return vec3<f32>(1.0, 0.4, 0.4);
+        }
+        case 1u: {
+            return vec3<f32>(0.4, 1.0, 0.4);
+        }
+        case 2u: {
+            return vec3<f32>(0.4, 0.4, 1.0);
+        }
+        default: {
+            return vec3<f32>(1.0, 1.0, 0.4);
+        }
+    }
+}
@@ -0,0 +1,510 @@
+// 3D 阴影：平面、球、方块浮在地面上，平行光和聚光各占阴影贴图的一层
+// 左上角可以调 PCF 半径、深度 bias、slope scale 和 normal bias，按 V 换调试视图
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use egui::Align2;
+use glam::{Mat4, Quat, Vec3};
+use winit::window::Window;
+
+use super::{
+    demo::{FrameUniform, ObjectUniform, MESH_LAYOUT, MODELS, VERTEX_STRIDE},
+    shadow::{with_shadows, ShadowMap},
+    BrdfModel, DirectionalLight, Light, LightSet, SpotLight,
+};
+use crate::{
+    assets::{Handle, HotShader},
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::{
+        models::ModelBuffers,
+        view_modes::{ViewCamera, ViewMesh, ViewModes},
+    },
+};
+
+const SHADOW_SIZE: u32 = 2048;
+/// 地面的半边长
+const GROUND: f32 = 4.0;
+/// 第 0 个是地面，后面和 MODELS 的顺序一致：Plane, Sphere, Cube
+/// (位置, 缩放, 颜色)
+const OBJECTS: [([f32; 3], f32, [f32; 3]); 4] = [
+    ([0.0, 0.0, 0.0], 1.0, [0.8, 0.8, 0.8]),
+    ([-1.6, 1.0, 0.0], 2.5, [0.9, 0.3, 0.25]),
+    ([0.0, 1.0, 0.0], 1.3, [0.95, 0.75, 0.3]),
+    ([1.6, 1.0, 0.0], 0.55, [0.3, 0.55, 0.9]),
+];
+const EYE: Vec3 = Vec3::new(0.0, 4.0, 7.0);
+
+/// 和内置模型一样的顶点格式：位置 + 法线 + uv
+fn ground_buffers(context: &crate::gfx::GfxContext) -> ModelBuffers {
+    let vertices: [[f32; 8]; 4] = [
+        [-GROUND, 0.0, -GROUND, 0.0, 1.0, 0.0, 0.0, 0.0],
+        [-GROUND, 0.0, GROUND, 0.0, 1.0, 0.0, 0.0, 1.0],
+        [GROUND, 0.0, GROUND, 0.0, 1.0, 0.0, 1.0, 1.0],
+        [GROUND, 0.0, -GROUND, 0.0, 1.0, 0.0, 1.0, 0.0],
+    ];
+    let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
+    ModelBuffers::new(context, "ground", &vertices, &indices)
+}
+
+pub struct ShadowDemo {
+    pipeline: HotShader<wgpu::RenderPipeline>,
+    pipeline_layout: wgpu::PipelineLayout,
+    depth_pipeline: wgpu::RenderPipeline,
+    /// 调了 bias 要重建 depth_pipeline
+    bias: wgpu::DepthBiasState,
+    models: Vec<Arc<ModelBuffers>>,
+    /// 拿着 handle，别的地方要同样的内置模型就直接共用
+    _builtins: Vec<Handle<ModelBuffers>>,
+    light_set: LightSet,
+    shadow_map: ShadowMap,
+    frame_buffer: wgpu::Buffer,
+    frame_group: wgpu::BindGroup,
+    object_buffer: wgpu::Buffer,
+    object_layout: wgpu::BindGroupLayout,
+    object_group: wgpu::BindGroup,
+    object_stride: wgpu::BufferAddress,
+    depth_view: wgpu::TextureView,
+    brdf: BrdfModel,
+    view_modes: ViewModes,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+impl Sandy for ShadowDemo {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let config = context.surface_config.as_ref().unwrap();
+        let builtins: Vec<_> = MODELS
+            .iter()
+            .map(|&model| context.assets.builtin(context, model))
+            .collect();
+        let mut models = vec![Arc::new(ground_buffers(context))];
+        models.extend(builtins.iter().map(|handle| handle.get().unwrap()));
+        let light_set = LightSet::new(
+            context,
+            vec![
+                DirectionalLight {
+                    direction: Vec3::new(-0.5, -1.0, -0.4),
+                    color: Vec3::new(1.0, 0.95, 0.85),
+                    intensity: 1.2,
+                    shadow: Some(0),
+                    cascades: 0,
+                }
+                .into(),
+                SpotLight {
+                    position: Vec3::new(2.5, 4.0, 2.5),
+                    direction: Vec3::new(-2.5, -4.0, -2.5),
+                    color: Vec3::new(0.4, 0.6, 1.0),
+                    intensity: 40.0,
+                    range: 12.0,
+                    inner_angle: 20f32.to_radians(),
+                    outer_angle: 30f32.to_radians(),
+                    shadow: Some(1),
+                }
+                .into(),
+            ],
+        );
+        let shadow_map = ShadowMap::new(context, SHADOW_SIZE);
+
+        let frame_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow demo frame"),
+            size: std::mem::size_of::<FrameUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let alignment =
+            context.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let object_stride =
+            wgpu::util::align_to(std::mem::size_of::<ObjectUniform>() as u64, alignment);
+        let object_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("shadow demo objects"),
+            size: object_stride * OBJECTS.len() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let uniform_layout = |dynamic: bool, size: usize| {
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: dynamic,
+                            min_binding_size: wgpu::BufferSize::new(size as u64),
+                        },
+                        count: None,
+                    }],
+                })
+        };
+        let frame_layout = uniform_layout(false, std::mem::size_of::<FrameUniform>());
+        let object_layout = uniform_layout(true, std::mem::size_of::<ObjectUniform>());
+        let frame_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow demo frame"),
+                layout: &frame_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: frame_buffer.as_entire_binding(),
+                }],
+            });
+        let object_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow demo objects"),
+                layout: &object_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &object_buffer,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniform>() as u64),
+                    }),
+                }],
+            });
+
+        let depth_texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("shadow demo depth"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("shadow demo"),
+                    bind_group_layouts: &[
+                        &frame_layout,
+                        &light_set.bind_group_layout,
+                        &object_layout,
+                        &shadow_map.sample_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let pipeline = HotShader::new(
+            context,
+            "asset/shaders/light/shadow_demo.wgsl",
+            include_str!("../../../asset/shaders/light/shadow_demo.wgsl"),
+            |source| build_pipeline(context, &pipeline_layout, source),
+        );
+
+        let bias = wgpu::DepthBiasState {
+            constant: 2,
+            slope_scale: 2.0,
+            clamp: 0.0,
+        };
+        let depth_pipeline =
+            shadow_map.depth_pipeline(context, &object_layout, VERTEX_STRIDE, bias);
+
+        ShadowDemo {
+            pipeline,
+            pipeline_layout,
+            depth_pipeline,
+            bias,
+            models,
+            _builtins: builtins,
+            light_set,
+            shadow_map,
+            frame_buffer,
+            frame_group,
+            object_buffer,
+            object_layout,
+            object_group,
+            object_stride,
+            depth_view,
+            brdf: BrdfModel::CookTorrance,
+            view_modes: ViewModes::new(context, config.view_formats[0], MESH_LAYOUT),
+            window,
+            egui,
+        }
+    }
+}
+
+fn build_pipeline(
+    context: &crate::gfx::GfxContext,
+    layout: &wgpu::PipelineLayout,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let config = context.surface_config.as_ref().unwrap();
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shadow demo"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_shadows(source))),
+        });
+    context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow demo"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: VERTEX_STRIDE,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.view_formats[0].into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+}
+
+impl ShadowDemo {
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Shadows")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                for model in BrdfModel::ALL {
+                    ui.radio_value(&mut self.brdf, model, format!("{:?}", model));
+                }
+                ui.separator();
+                let uniform = &mut self.shadow_map.uniform;
+                ui.add(egui::Slider::new(&mut uniform.pcf_radius, 0..=4).text("PCF radius"));
+                ui.add(egui::Slider::new(&mut uniform.normal_bias, 0.0..=0.1).text("normal bias"));
+                ui.add(egui::Slider::new(&mut self.bias.constant, 0..=16).text("depth bias"));
+                ui.add(
+                    egui::Slider::new(&mut self.bias.slope_scale, 0.0..=8.0).text("slope scale"),
+                );
+                ui.separator();
+                self.view_modes.ui(ui);
+            });
+    }
+
+    /// 返回 view_proj 和每个物体的 model，调试视图也要用
+    fn update(&mut self, context: &crate::gfx::GfxContext, time: f32) -> (Mat4, Vec<Mat4>) {
+        let config = context.surface_config.as_ref().unwrap();
+        let aspect = config.width as f32 / config.height.max(1) as f32;
+        let view = Mat4::look_at_rh(EYE, Vec3::ZERO, Vec3::Y);
+        let proj = Mat4::perspective_rh(45f32.to_radians(), aspect, 0.1, 100.0);
+        let frame = FrameUniform {
+            view_proj: (proj * view).to_cols_array_2d(),
+            camera_position: EYE.to_array(),
+            brdf: self.brdf as u32,
+            ambient: [0.05, 0.05, 0.06],
+            _pad: 0.0,
+        };
+        context
+            .queue
+            .write_buffer(&self.frame_buffer, 0, bytemuck::bytes_of(&frame));
+
+        let rotation = Quat::from_rotation_y(time * 0.5) * Quat::from_rotation_x(0.4);
+        let mut models = Vec::with_capacity(OBJECTS.len());
+        for (i, (position, scale, albedo)) in OBJECTS.into_iter().enumerate() {
+            // 地面不转
+            let rotation = if i == 0 { Quat::IDENTITY } else { rotation };
+            let model = Mat4::from_scale_rotation_translation(
+                Vec3::splat(scale),
+                rotation,
+                Vec3::from_array(position),
+            );
+            models.push(model);
+            let object = ObjectUniform {
+                model: model.to_cols_array_2d(),
+                albedo,
+                roughness: 0.5,
+                metallic: 0.0,
+                shininess: 32.0,
+                _pad: [0.0; 2],
+            };
+            context.queue.write_buffer(
+                &self.object_buffer,
+                i as wgpu::BufferAddress * self.object_stride,
+                bytemuck::bytes_of(&object),
+            );
+        }
+
+        // 平行光慢慢转，阴影跟着扫过地面
+        let t = time * 0.2;
+        for light in self.light_set.lights.iter_mut() {
+            match light {
+                Light::Directional(sun) => {
+                    sun.direction = Vec3::new(t.cos() * 0.6, -1.0, t.sin() * 0.6);
+                    if let Some(layer) = sun.shadow {
+                        let view_proj = sun.view_proj(Vec3::ZERO, GROUND * 1.5);
+                        self.shadow_map.set_view_proj(context, layer, view_proj);
+                    }
+                }
+                Light::Spot(spot) => {
+                    if let Some(layer) = spot.shadow {
+                        self.shadow_map
+                            .set_view_proj(context, layer, spot.view_proj());
+                    }
+                }
+                Light::Point(_) => {}
+            }
+        }
+        self.light_set.upload(context);
+        self.shadow_map.upload(context);
+        (proj * view, models)
+    }
+
+    fn draw_models(&self, rpass: &mut wgpu::RenderPass, object_group_index: u32) {
+        for (i, model) in self.models.iter().enumerate() {
+            let offset = (i as wgpu::BufferAddress * self.object_stride) as wgpu::DynamicOffset;
+            rpass.set_bind_group(object_group_index, &self.object_group, &[offset]);
+            rpass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+            rpass.set_index_buffer(model.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            rpass.draw_indexed(0..model.index_count, 0, 0..1);
+        }
+    }
+}
+
+impl Painter for ShadowDemo {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, _dt: f32, time: f32) {
+        self.pipeline.update(context, |source| {
+            build_pipeline(context, &self.pipeline_layout, source)
+        });
+        let mode = self.view_modes.sync(context);
+        let (view_proj, transforms) = self.update(context, time);
+
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("shadow demo"),
+            });
+
+        // 每个投影的光源画一层深度
+        let shaded = !mode.replaces_scene();
+        for light in self.light_set.lights.iter().filter(|_| shaded) {
+            let layer = match light {
+                Light::Directional(l) => l.shadow,
+                Light::Spot(l) => l.shadow,
+                Light::Point(_) => None,
+            };
+            if let Some(layer) = layer {
+                self.shadow_map
+                    .render_layer(&mut encoder, &self.depth_pipeline, layer, |pass| {
+                        self.draw_models(pass, 1)
+                    });
+            }
+        }
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow demo"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.02,
+                            g: 0.02,
+                            b: 0.03,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: context.render_timestamps("shadow demo"),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.frame_group, &[]);
+            rpass.set_bind_group(1, &self.light_set.bind_group, &[]);
+            rpass.set_bind_group(3, &self.shadow_map.sample_group, &[]);
+            if shaded {
+                self.draw_models(&mut rpass, 2);
+            }
+        }
+        let meshes: Vec<ViewMesh> = self
+            .models
+            .iter()
+            .zip(transforms)
+            .map(|(model, transform)| ViewMesh {
+                vertices: &model.vertex_buffer,
+                indices: &model.index_buffer,
+                index_count: model.index_count,
+                model: transform,
+                viewport: None,
+            })
+            .collect();
+        let camera = ViewCamera {
+            view_proj,
+            near: 0.1,
+            far: 100.0,
+        };
+        self.view_modes
+            .render(context, &mut encoder, &view, &camera, &meshes);
+
+        let config = context.surface_config.as_ref().unwrap();
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let bias = self.bias;
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+        if bias != self.bias {
+            self.depth_pipeline = self.shadow_map.depth_pipeline(
+                context,
+                &self.object_layout,
+                VERTEX_STRIDE,
+                self.bias,
+            );
+        }
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+}
@@ -0,0 +1,23 @@
+// 只写深度的 pass，从光的位置看场景
+
+struct Caster {
+    view_proj: mat4x4<f32>,
+};
+
+// 场景的物体 uniform，只要求开头是 model 矩阵
+struct Object {
+    model: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> caster: Caster;
+
+@group(1)
+@binding(0)
+var<uniform> object: Object;
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return caster.view_proj * object.model * vec4<f32>(position, 1.0);
+}
@@ -0,0 +1,275 @@
+// 地图的文本格式，例子见 asset/maps/mesh_on_ray.map
+//
+//   tile 0.2
+//   legend # wall
+//   legend / wall rotation=45 scale=0.7
+//   map
+//   ####
+//   #./#
+//
+// // 开头的是注释，map 之后每行是一排格子
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{MeshInstance, Terrain};
+
+/// 空格子
+pub const EMPTY: char = '.';
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(std::io::Error),
+    /// 行号从 1 开始
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for MapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MapError::Io(e) => write!(f, "map io error: {}", e),
+            MapError::Parse { line, message } => write!(f, "map line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<std::io::Error> for MapError {
+    fn from(e: std::io::Error) -> Self {
+        MapError::Io(e)
+    }
+}
+
+/// 一个字符代表什么样的方块
+#[derive(Copy, Clone, Debug)]
+pub struct Legend {
+    pub symbol: char,
+    pub terrain: Terrain,
+    /// 角度
+    pub rotation: f32,
+    /// 相对一格的大小
+    pub scale: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct TileMap {
+    /// 一格的边长，世界坐标
+    pub tile: f32,
+    pub legend: Vec<Legend>,
+    /// 每排一样长，不够的用 EMPTY 补齐
+    pub rows: Vec<Vec<char>>,
+}
+
+impl TileMap {
+    pub fn parse(source: &str) -> Result<Self, MapError> {
+        let mut tile = 0.2;
+        let mut legend = Vec::new();
+        let mut rows: Vec<Vec<char>> = Vec::new();
+        let mut in_map = false;
+        for (i, line) in source.lines().enumerate() {
+            let error = |message: String| MapError::Parse {
+                line: i + 1,
+                message,
+            };
+            if in_map {
+                rows.push(
+                    line.chars()
+                        .map(|c| if c == ' ' { EMPTY } else { c })
+                        .collect(),
+                );
+                continue;
+            }
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match words.next().unwrap() {
+                "tile" => {
+                    let value = words.next().unwrap_or_default();
+                    tile = value
+                        .parse()
+                        .map_err(|_| error(format!("bad tile size `{}`", value)))?;
+                }
+                "legend" => {
+                    let symbol = words
+                        .next()
+                        .and_then(|w| w.chars().next())
+                        .ok_or_else(|| error("legend needs a symbol".into()))?;
+                    let name = words.next().unwrap_or_default();
+                    let terrain = Terrain::from_name(name)
+                        .ok_or_else(|| error(format!("unknown terrain `{}`", name)))?;
+                    let mut entry = Legend {
+                        symbol,
+                        terrain,
+                        rotation: 0.0,
+                        scale: 1.0,
+                    };
+                    for option in words {
+                        let (key, value) = option.split_once('=').ok_or_else(|| {
+                            error(format!("expected key=value, got `{}`", option))
+                        })?;
+                        let value: f32 = value
+                            .parse()
+                            .map_err(|_| error(format!("bad number `{}`", value)))?;
+                        match key {
+                            "rotation" => entry.rotation = value,
+                            "scale" => entry.scale = value,
+                            _ => return Err(error(format!("unknown option `{}`", key))),
+                        }
+                    }
+                    legend.retain(|l: &Legend| l.symbol != symbol);
+                    legend.push(entry);
+                }
+                "map" => in_map = true,
+                word => return Err(error(format!("unknown directive `{}`", word))),
+            }
+        }
+        // 末尾的空行不算
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in &mut rows {
+            row.resize(width, EMPTY);
+        }
+        Ok(TileMap { tile, legend, rows })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MapError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MapError> {
+        std::fs::write(path, self.to_source())?;
+        Ok(())
+    }
+
+    /// 写回文本，注释不会保留
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        writeln!(source, "tile {}", self.tile).unwrap();
+        for l in &self.legend {
+            write!(source, "legend {} {}", l.symbol, l.terrain.name()).unwrap();
+            if l.rotation != 0.0 {
+                write!(source, " rotation={}", l.rotation).unwrap();
+            }
+            if l.scale != 1.0 {
+                write!(source, " scale={}", l.scale).unwrap();
+            }
+            source.push('\n');
+        }
+        source.push_str("map\n");
+        for row in &self.rows {
+            source.extend(row.iter());
+            source.push('\n');
+        }
+        source
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.rows.resize(height, Vec::new());
+        for row in &mut self.rows {
+            row.resize(width, EMPTY);
+        }
+    }
+
+    /// 地图放在原点中间，世界坐标 -> (列, 行)
+    pub fn cell_at(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let col = (x / self.tile + self.width() as f32 * 0.5).floor();
+        let row = (self.height() as f32 * 0.5 - y / self.tile).floor();
+        if col < 0.0 || row < 0.0 {
+            return None;
+        }
+        let (col, row) = (col as usize, row as usize);
+        (col < self.width() && row < self.height()).then_some((col, row))
+    }
+
+    pub fn set(&mut self, col: usize, row: usize, symbol: char) -> bool {
+        let cell = &mut self.rows[row][col];
+        let changed = *cell != symbol;
+        *cell = symbol;
+        changed
+    }
+
+    pub fn instances(&self) -> Vec<MeshInstance> {
+        let (width, height) = (self.width() as f32, self.height() as f32);
+        let mut instances = Vec::new();
+        for (row, line) in self.rows.iter().enumerate() {
+            for (col, c) in line.iter().enumerate() {
+                // 图例里没有的字符都当作空
+                let Some(l) = self.legend.iter().find(|l| l.symbol == *c) else {
+                    continue;
+                };
+                instances.push(MeshInstance {
+                    position: [
+                        (col as f32 + 0.5 - width * 0.5) * self.tile,
+                        (height * 0.5 - row as f32 - 0.5) * self.tile,
+                        0.0,
+                    ],
+                    rotation: [0.0, 0.0, l.rotation.to_radians()],
+                    on_type: l.terrain.into(),
+                    scale: self.tile * l.scale,
+                });
+            }
+        }
+        instances
+    }
+}
+
+/// 每帧看一下文件的修改时间，变了就重新读
+pub struct MapFile {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl MapFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let mut file = MapFile {
+            path: path.into(),
+            modified: None,
+        };
+        file.modified = file.modified_time();
+        file
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok()
+    }
+
+    /// 文件改过了才返回 Some
+    pub fn poll(&mut self) -> Option<Result<TileMap, MapError>> {
+        let modified = self.modified_time();
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(TileMap::load(&self.path))
+    }
+
+    pub fn load(&mut self) -> Result<TileMap, MapError> {
+        self.modified = self.modified_time();
+        TileMap::load(&self.path)
+    }
+
+    /// 自己写的不用再读一遍
+    pub fn save(&mut self, map: &TileMap) -> Result<(), MapError> {
+        map.save(&self.path)?;
+        self.modified = self.modified_time();
+        Ok(())
+    }
+}
@@ -1,36 +1,96 @@
-use std::borrow::Cow;
+// 2D 光线投射
+// 地图由 MeshInstance 方块拼成：墙反射，地面吸收，水面折射
+// 在 CPU 上追光线（ray.rs），光路用 utils::debug_draw 画成线段
+// 左键拖动起点，右键按住朝鼠标方向瞄准
+// 地图从 asset/maps/mesh_on_ray.map 读（map.rs），文件改了自动重新加载
+// 左上角的窗口里可以切到画格子模式，改完保存回文件
+mod map;
+mod ray;
+
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use egui::Align2;
 
 use bytemuck::{Pod, Zeroable};
-use glam::Vec3;
+use glam::{Mat4, Vec2, Vec3};
 use wgpu::{util::DeviceExt, MultisampleState, PipelineCompilationOptions, PrimitiveState};
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    window::Window,
+};
+
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::{
+        bvh::Aabb,
+        debug_draw::{with_alpha, DebugDraw, WHITE},
+    },
+};
+use map::{MapFile, TileMap, EMPTY};
+use ray::{trace, Ray, Tiles};
 
-use crate::painter::{Painter, Sandy};
+const MAP_PATH: &str = "asset/maps/mesh_on_ray.map";
+/// 文件读不到时用编进来的那份
+const DEFAULT_MAP: &str = include_str!("../../../asset/maps/mesh_on_ray.map");
+/// 起点附近画个十字
+const MARKER_SIZE: f32 = 0.04;
 
-struct MeshOnRay {
+pub struct MeshOnRay {
     ray: Ray,
+    tiles: Tiles,
     mesh: (wgpu::Buffer, wgpu::Buffer),
     pipeline: wgpu::RenderPipeline,
-    ray_bind_group: wgpu::BindGroup,
-    time_uniform_buffer: wgpu::Buffer,
-    time_bind_group: wgpu::BindGroup,
-    instance_count: usize,
+    debug: DebugDraw,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
     terrain_instances_buffer: wgpu::Buffer,
-}
-#[repr(C)]
-#[derive(Pod, Zeroable, Clone, Copy)]
-struct Ray {
-    origin: [f32; 3],
-    direction: [f32; 3],
-    intensity: f32,
+    map: TileMap,
+    map_file: MapFile,
+    /// 上一次加载 / 保存的结果，显示在窗口里
+    map_status: String,
+    /// 地图改过了，下一帧重新生成 instance
+    map_dirty: bool,
+    /// 画格子模式：左键画，右键擦
+    painting: bool,
+    brush: char,
+    /// 鼠标的世界坐标
+    cursor: Vec2,
+    moving_origin: bool,
+    aiming: bool,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
 }
 
+/// 值和 ray_2d.wgsl 里 fs_main 的分支对应
+/// 加新地形时在这里、ALL、name 和 shader 里各加一项
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 enum Terrain {
     Wall = 0,
     Ground = 1,
     Water = 2,
 }
+
+impl Terrain {
+    /// 按值排好
+    const ALL: [Terrain; 3] = [Terrain::Wall, Terrain::Ground, Terrain::Water];
+
+    fn name(self) -> &'static str {
+        match self {
+            Terrain::Wall => "wall",
+            Terrain::Ground => "ground",
+            Terrain::Water => "water",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+}
 #[repr(C)]
 #[derive(Copy, Clone, Zeroable, Pod)]
 struct MeshInstance {
@@ -45,56 +105,56 @@ impl From<Terrain> for u32 {
     }
 }
 
+/// 和 ray_2d.wgsl 里的 Uniforms 对应
 #[repr(C)]
 #[derive(Clone, Copy, Zeroable, Pod)]
-struct TimeUniforms {
-    delta_time: f32,
+struct Uniforms {
     time: f32,
+    delta_time: f32,
+    aspect: f32,
+    _pad: f32,
 }
 
-fn gen() -> ([f32; 36], [u16; 6]) {
+/// 单位方块，颜色用来做一点从上到下的明暗
+fn gen() -> ([f32; 24], [u16; 6]) {
     #[rustfmt::skip]
-    let vs: [f32; 36] = [
-        -0.3, -0.3,0.1,       0.0, 0.0, 1.0,
-        0.3, 0.3, 0.1,        0.0, 0.0, 1.0,
-        -0.3, 0.3,0.1,        0.0, 0.0, 1.0,
-
-        -0.3, 0.3, -0.1,       0.0, 1.0, 0.0,
-        -0.3, -0.3, -0.1,      0.0, 1.0, 0.0,
-        0.3, -0.3, -0.1,       0.0, 1.0, 0.0,
+    let vs: [f32; 24] = [
+        -0.5, -0.5, 0.0,       0.8, 0.8, 0.8,
+        0.5, -0.5, 0.0,        0.8, 0.8, 0.8,
+        0.5, 0.5, 0.0,         1.0, 1.0, 1.0,
+        -0.5, 0.5, 0.0,        1.0, 1.0, 1.0,
     ];
-    let indexes: [u16; 6] = [0, 1, 2, 3, 4, 5];
+    let indexes: [u16; 6] = [0, 1, 2, 0, 2, 3];
     (vs, indexes)
 }
-fn gen_instances() -> Vec<MeshInstance> {
-    let mut instances = Vec::new();
-    instances.push(MeshInstance {
-        position: [0.1, 0.1, 0.0],
-        rotation: [0.0, 0.0, 0.0],
-        scale: 0.1,
-        on_type: Terrain::Wall.into(),
-    });
-
-    instances.push(MeshInstance {
-        position: [0.1, 0.2, 0.0],
-        rotation: [0.0, 0.0, 0.0],
-        scale: 0.1,
-        on_type: Terrain::Wall.into(),
-    });
-    instances
+
+fn instance_buffer(context: &crate::gfx::GfxContext, instances: &[MeshInstance]) -> wgpu::Buffer {
+    context
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Buffer"),
+            contents: bytemuck::cast_slice(instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
 }
+
 impl Sandy for MeshOnRay {
-    type Extra = ();
-    fn ready(context: &crate::gfx::GfxContext, extra: Self::Extra) -> Self
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
     where
         Self: Sized,
     {
         let ray = Ray {
-            origin: [0.0, 0.0, 0.0],
-            direction: [0.0, 0.0, 1.0],
+            origin: Vec2::new(-1.3, -0.5),
+            direction: Vec2::new(1.0, 0.35).normalize(),
             intensity: 1.0,
         };
-        let instances = gen_instances();
+        let mut map_file = MapFile::new(MAP_PATH);
+        let (map, map_status) = match map_file.load() {
+            Ok(map) => (map, format!("loaded {}", MAP_PATH)),
+            Err(e) => (TileMap::parse(DEFAULT_MAP).unwrap(), e.to_string()),
+        };
+        let instances = map.instances();
         let (vs, indexes) = gen();
         let mesh = (
             context
@@ -109,66 +169,22 @@ impl Sandy for MeshOnRay {
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("is"),
                     contents: bytemuck::cast_slice(&indexes),
-                    usage: wgpu::BufferUsages::VERTEX,
+                    usage: wgpu::BufferUsages::INDEX,
                 }),
         );
 
-        let ray_buffer = context
+        let uniform_buffer = context
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("vs"),
-                contents: bytemuck::cast_slice(&[ray]),
-                usage: wgpu::BufferUsages::VERTEX,
+                label: Some("Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[Uniforms::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
-
-        let ray_bind_group_layout =
+        let uniform_bind_group_layout =
             context
                 .device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("ray_bind_group_layout"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }],
-                });
-        let ray_bind_group = context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("ray_bind_group"),
-                layout: &ray_bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &ray_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
-                }],
-            });
-
-        let time_uniforms = TimeUniforms {
-            delta_time: 0.0,
-            time: 0.0,
-        };
-        let time_uniform_buffer =
-            context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Uniform Buffer"),
-                    contents: bytemuck::cast_slice(&[time_uniforms]),
-                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                });
-        let time_uniform_bind_group_layout =
-            context
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("Time Bind Group Layout"),
+                    label: Some("Uniform Bind Group Layout"),
                     entries: &[wgpu::BindGroupLayoutEntry {
                         binding: 0,
                         visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
@@ -180,36 +196,25 @@ impl Sandy for MeshOnRay {
                         count: None,
                     }],
                 });
-        let time_bind_group = context
+        let uniform_bind_group = context
             .device
             .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Time Bind Group"),
-                layout: &time_uniform_bind_group_layout,
+                label: Some("Uniform Bind Group"),
+                layout: &uniform_bind_group_layout,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &time_uniform_buffer,
-                        offset: 0,
-                        size: None,
-                    }),
+                    resource: uniform_buffer.as_entire_binding(),
                 }],
             });
 
-        let terrain_instances_buffer =
-            context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Terrain Buffer"),
-                    contents: bytemuck::cast_slice(&instances),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
+        let terrain_instances_buffer = instance_buffer(context, &instances);
 
         let pipeline_layout =
             context
                 .device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some("Ray Pipeline Layout"),
-                    bind_group_layouts: &[&ray_bind_group_layout, &time_uniform_bind_group_layout],
+                    bind_group_layouts: &[&uniform_bind_group_layout],
                     push_constant_ranges: &[],
                 });
 
@@ -219,14 +224,15 @@ impl Sandy for MeshOnRay {
                 label: Some("ray_2d"),
                 source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("ray_2d.wgsl"))),
             });
+        let format = context.surface_config.as_ref().unwrap().view_formats[0];
         let pipeline = context
             .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Ray Pipeline"),
+                label: Some("Terrain Pipeline"),
                 layout: Some(&pipeline_layout),
                 vertex: wgpu::VertexState {
                     module: &shader,
-                    entry_point: "main",
+                    entry_point: "vs_main",
                     buffers: &[
                         wgpu::VertexBufferLayout {
                             array_stride: 6 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
@@ -245,7 +251,8 @@ impl Sandy for MeshOnRay {
                             ],
                         },
                         wgpu::VertexBufferLayout {
-                            array_stride: 32,
+                            array_stride: std::mem::size_of::<MeshInstance>()
+                                as wgpu::BufferAddress,
                             step_mode: wgpu::VertexStepMode::Instance,
                             attributes: &[
                                 wgpu::VertexAttribute {
@@ -278,9 +285,9 @@ impl Sandy for MeshOnRay {
                 multisample: MultisampleState::default(),
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
-                    entry_point: "main",
+                    entry_point: "fs_main",
                     targets: &[Some(wgpu::ColorTargetState {
-                        format: context.surface_config.as_ref().unwrap().format,
+                        format,
                         blend: Some(wgpu::BlendState::REPLACE),
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
@@ -289,21 +296,142 @@ impl Sandy for MeshOnRay {
                 multiview: None,
                 cache: None,
             });
+        // 没有深度贴图，线都叠在地形上面
+        let debug = DebugDraw::new(context, format, None);
         MeshOnRay {
             ray,
+            tiles: Tiles::new(instances),
             mesh,
             pipeline,
-            ray_bind_group,
-            time_uniform_buffer,
-            time_bind_group,
-            instance_count: 2 as usize,
+            debug,
+            uniform_buffer,
+            uniform_bind_group,
             terrain_instances_buffer,
+            brush: map.legend.first().map_or(EMPTY, |l| l.symbol),
+            map,
+            map_file,
+            map_status,
+            map_dirty: false,
+            painting: false,
+            cursor: Vec2::ZERO,
+            moving_origin: false,
+            aiming: false,
+            window,
+            egui,
+        }
+    }
+}
+
+impl MeshOnRay {
+    fn editor(&mut self, ui: &egui::Context) {
+        egui::Window::new("Map")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.painting, "paint tiles (left paint, right erase)");
+                ui.add_enabled_ui(self.painting, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for l in &self.map.legend {
+                            let label = format!("{} {}", l.symbol, l.terrain.name());
+                            ui.radio_value(&mut self.brush, l.symbol, label);
+                        }
+                        ui.radio_value(&mut self.brush, EMPTY, "erase");
+                    });
+                });
+                ui.separator();
+
+                let (mut width, mut height) = (self.map.width(), self.map.height());
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut width).range(1..=64).prefix("w "));
+                    ui.add(egui::DragValue::new(&mut height).range(1..=64).prefix("h "));
+                });
+                if (width, height) != (self.map.width(), self.map.height()) {
+                    self.map.resize(width, height);
+                    self.map_dirty = true;
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("save").clicked() {
+                        self.map_status = match self.map_file.save(&self.map) {
+                            Ok(()) => format!("saved {}", self.map_file.path.display()),
+                            Err(e) => e.to_string(),
+                        };
+                    }
+                    if ui.button("reload").clicked() {
+                        let result = self.map_file.load();
+                        self.apply_loaded(result);
+                    }
+                });
+                ui.label(&self.map_status);
+            });
+    }
+
+    fn apply_loaded(&mut self, result: Result<TileMap, map::MapError>) {
+        match result {
+            Ok(map) => {
+                self.map = map;
+                self.map_dirty = true;
+                self.map_status = format!("loaded {}", self.map_file.path.display());
+            }
+            // 写到一半的文件读不出来，保留当前的地图
+            Err(e) => self.map_status = e.to_string(),
+        }
+    }
+
+    fn paint_cell(&mut self, symbol: char) {
+        if let Some((col, row)) = self.map.cell_at(self.cursor.x, self.cursor.y) {
+            self.map_dirty |= self.map.set(col, row, symbol);
+        }
+    }
+
+    /// 追一遍光线，连同起点的十字一起交给 debug；画格子的时候框出光标下那一格
+    fn queue_lines(&mut self) {
+        for segment in trace(self.ray, &self.tiles) {
+            // 空气里黄色，水里青色，越暗越透明
+            let color = if segment.in_water {
+                0xffffff80
+            } else {
+                0xff4ce6ff
+            };
+            self.debug.line(
+                segment.from.extend(0.0),
+                segment.to.extend(0.0),
+                with_alpha(color, segment.intensity),
+            );
+        }
+        let o = self.ray.origin.extend(0.0);
+        self.debug
+            .line(o - Vec3::X * MARKER_SIZE, o + Vec3::X * MARKER_SIZE, WHITE);
+        self.debug
+            .line(o - Vec3::Y * MARKER_SIZE, o + Vec3::Y * MARKER_SIZE, WHITE);
+        if !self.painting {
+            return;
+        }
+        if let Some((col, row)) = self.map.cell_at(self.cursor.x, self.cursor.y) {
+            let tile = self.map.tile;
+            let min = Vec2::new(
+                (col as f32 - self.map.width() as f32 * 0.5) * tile,
+                (self.map.height() as f32 * 0.5 - row as f32 - 1.0) * tile,
+            );
+            let aabb = Aabb::new(min.extend(0.0), (min + tile).extend(0.0));
+            self.debug.aabb(&aabb, WHITE);
         }
     }
 }
 
 impl Painter for MeshOnRay {
     fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        let config = context.surface_config.as_ref().unwrap();
+
+        if let Some(result) = self.map_file.poll() {
+            self.apply_loaded(result);
+        }
+        if self.map_dirty {
+            self.tiles = Tiles::new(self.map.instances());
+            self.terrain_instances_buffer = instance_buffer(context, &self.tiles.instances);
+            self.map_dirty = false;
+        }
+
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -314,14 +442,21 @@ impl Painter for MeshOnRay {
                 label: Some("Render Encoder"),
             });
 
+        // 世界坐标 y 在 [-1, 1]，x 按屏幕比例，和 ray_2d.wgsl 的 world_to_clip 一样
+        let aspect = config.width as f32 / config.height.max(1) as f32;
         context.queue.write_buffer(
-            &self.time_uniform_buffer,
+            &self.uniform_buffer,
             0,
-            bytemuck::cast_slice(&[TimeUniforms {
-                delta_time: dt,
+            bytemuck::cast_slice(&[Uniforms {
                 time,
+                delta_time: dt,
+                aspect,
+                _pad: 0.0,
             }]),
         );
+        self.queue_lines();
+        self.debug
+            .prepare(context, Mat4::from_scale(Vec3::new(1.0 / aspect, 1.0, 1.0)));
 
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -340,18 +475,78 @@ impl Painter for MeshOnRay {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("mesh on ray"),
                 occlusion_query_set: None,
             });
             render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_bind_group(0, &self.ray_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.time_bind_group, &[]);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.mesh.0.slice(..));
             render_pass.set_vertex_buffer(1, self.terrain_instances_buffer.slice(..));
             render_pass.set_index_buffer(self.mesh.1.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..6, 0, 0..self.instance_count as u32);
+            if !self.tiles.instances.is_empty() {
+                render_pass.draw_indexed(0..6, 0, 0..self.tiles.instances.len() as u32);
+            }
         }
+        self.debug.render(&mut encoder, &view, None);
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.editor(ui),
+        );
+
         context.queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
+
+    fn input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                // 像素 -> 世界坐标，y 在 [-1, 1]，x 按屏幕比例
+                let size = self.window.inner_size();
+                let (w, h) = (size.width.max(1) as f32, size.height.max(1) as f32);
+                let x = position.x as f32 / w * 2.0 - 1.0;
+                let y = 1.0 - position.y as f32 / h * 2.0;
+                self.cursor = Vec2::new(x * w / h, y);
+            }
+            WindowEvent::MouseInput { button, state, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                // 点在 egui 窗口上的不算
+                if pressed && self.egui.lock().unwrap().context.wants_pointer_input() {
+                    return;
+                }
+                match button {
+                    MouseButton::Left => self.moving_origin = pressed,
+                    MouseButton::Right => self.aiming = pressed,
+                    _ => {}
+                }
+            }
+            _ => return,
+        }
+        if self.painting {
+            if self.moving_origin {
+                self.paint_cell(self.brush);
+            } else if self.aiming {
+                self.paint_cell(EMPTY);
+            }
+            return;
+        }
+        if self.moving_origin {
+            self.ray.origin = self.cursor;
+        }
+        let to_cursor = self.cursor - self.ray.origin;
+        if self.aiming && to_cursor.length_squared() > 1e-6 {
+            self.ray.direction = to_cursor.normalize();
+        }
+    }
 }
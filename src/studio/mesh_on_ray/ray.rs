@@ -0,0 +1,295 @@
+use glam::{Mat2, Vec2, Vec3};
+
+use super::{MeshInstance, Terrain};
+use crate::utils::bvh::{Aabb, Bvh};
+
+/// 水的折射率，空气当作 1
+const WATER_IOR: f32 = 1.33;
+/// 墙每反射一次剩下的强度
+const WALL_REFLECTANCE: f32 = 0.8;
+/// 强度低于这个就不追了
+const MIN_INTENSITY: f32 = 0.02;
+/// 一条光线最多分出这么多段
+pub const MAX_SEGMENTS: usize = 256;
+const MAX_DEPTH: u32 = 16;
+/// 什么都没打到时画多长
+const MISS_LENGTH: f32 = 10.0;
+const EPSILON: f32 = 1e-4;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Vec2,
+    pub direction: Vec2,
+    pub intensity: f32,
+}
+
+/// 光路上的一段，画线用
+#[derive(Copy, Clone, Debug)]
+pub struct Segment {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub intensity: f32,
+    pub in_water: bool,
+}
+
+/// 地图上的方块和它们的 BVH，地图改了要整个重建
+pub struct Tiles {
+    pub instances: Vec<MeshInstance>,
+    bvh: Bvh,
+}
+
+impl Tiles {
+    pub fn new(instances: Vec<MeshInstance>) -> Self {
+        let bounds: Vec<Aabb> = instances.iter().map(MeshInstance::bounds).collect();
+        let bvh = Bvh::build(&bounds);
+        Tiles { instances, bvh }
+    }
+
+    /// 包含 p 的方块
+    fn at(&self, p: Vec2) -> impl Iterator<Item = &MeshInstance> {
+        let mut found = Vec::new();
+        let point = Aabb::new(p.extend(0.0), p.extend(0.0));
+        self.bvh.query_aabb(&point, |i| {
+            let tile = &self.instances[i as usize];
+            if tile.contains(p) {
+                found.push(tile);
+            }
+        });
+        found.into_iter()
+    }
+}
+
+struct Hit {
+    t: f32,
+    point: Vec2,
+    /// 方块朝外的法线
+    normal: Vec2,
+    terrain: Terrain,
+}
+
+impl MeshInstance {
+    fn terrain(&self) -> Terrain {
+        Terrain::ALL[self.on_type as usize]
+    }
+
+    /// 2D 里只用绕 z 的旋转
+    fn rotation(&self) -> Mat2 {
+        Mat2::from_angle(self.rotation[2])
+    }
+
+    fn center(&self) -> Vec2 {
+        Vec2::new(self.position[0], self.position[1])
+    }
+
+    /// 转过的方块的包围盒，z 给点厚度，射线的 z 分量是 0 时 slab 里不会出 NaN
+    fn bounds(&self) -> Aabb {
+        let (sin, cos) = self.rotation[2].sin_cos();
+        let half = self.scale * 0.5 * (sin.abs() + cos.abs());
+        let center = self.center().extend(0.0);
+        let extent = Vec3::new(half, half, 1.0);
+        Aabb::new(center - extent, center + extent)
+    }
+
+    fn contains(&self, p: Vec2) -> bool {
+        let local = self.rotation().transpose() * (p - self.center());
+        local.abs().max_element() < self.scale * 0.5
+    }
+
+    /// 转到方块自己的坐标系里做 slab 求交，返回 (t, 朝外的法线)
+    fn intersect(&self, ray: &Ray) -> Option<(f32, Vec2)> {
+        let rotation = self.rotation();
+        let inverse = rotation.transpose();
+        let o = inverse * (ray.origin - self.center());
+        let d = inverse * ray.direction;
+        let half = self.scale * 0.5;
+
+        let t1 = (Vec2::splat(-half) - o) / d;
+        let t2 = (Vec2::splat(half) - o) / d;
+        let t_min = t1.min(t2);
+        let t_max = t1.max(t2);
+        let t_near = t_min.max_element();
+        let t_far = t_max.min_element();
+        if t_near > t_far || t_far < EPSILON {
+            return None;
+        }
+        // 从外面打进来用近的那个面，起点在里面就用出去的那个面
+        let (t, axis, sign) = if t_near > EPSILON {
+            let axis = if t_min.x > t_min.y { 0 } else { 1 };
+            (t_near, axis, -d[axis].signum())
+        } else {
+            let axis = if t_max.x < t_max.y { 0 } else { 1 };
+            (t_far, axis, d[axis].signum())
+        };
+        let mut normal = Vec2::ZERO;
+        normal[axis] = sign;
+        Some((t, rotation * normal))
+    }
+}
+
+fn nearest_hit(ray: &Ray, tiles: &Tiles) -> Option<Hit> {
+    let mut best: Option<Hit> = None;
+    let origin = ray.origin.extend(0.0);
+    let direction = ray.direction.extend(0.0);
+    tiles
+        .bvh
+        .traverse_ray(origin, direction, f32::INFINITY, |i, t_max| {
+            let tile = &tiles.instances[i as usize];
+            let Some((t, normal)) = tile.intersect(ray) else {
+                return t_max;
+            };
+            let terrain = tile.terrain();
+            // 水和墙贴在一起时同一个 t 算墙
+            let closer = match &best {
+                None => true,
+                Some(best) => {
+                    t < best.t - EPSILON
+                        || (t < best.t + EPSILON
+                            && matches!(best.terrain, Terrain::Water)
+                            && !matches!(terrain, Terrain::Water))
+                }
+            };
+            if closer {
+                best = Some(Hit {
+                    t,
+                    point: ray.origin + ray.direction * t,
+                    normal,
+                    terrain,
+                });
+            }
+            // 留一点余量，贴着的那块墙还要能测到
+            best.as_ref().map_or(t_max, |best| best.t + EPSILON)
+        });
+    best
+}
+
+fn water_at(tiles: &Tiles, p: Vec2) -> bool {
+    tiles
+        .at(p)
+        .any(|tile| matches!(tile.terrain(), Terrain::Water))
+}
+
+fn reflect(d: Vec2, n: Vec2) -> Vec2 {
+    d - 2.0 * d.dot(n) * n
+}
+
+/// n 和 d 相对，eta = n1 / n2，全反射时返回 None
+fn refract(d: Vec2, n: Vec2, eta: f32) -> Option<Vec2> {
+    let cos_i = -d.dot(n);
+    let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+    if k < 0.0 {
+        return None;
+    }
+    Some(eta * d + (eta * cos_i - k.sqrt()) * n)
+}
+
+/// Schlick 近似的反射比例
+fn schlick(cos_i: f32, n1: f32, n2: f32) -> f32 {
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos_i).powi(5)
+}
+
+/// 在方块里追一条光线
+/// 墙反射，地面吸收，进出水面时按菲涅耳分成反射和折射两条
+pub fn trace(ray: Ray, tiles: &Tiles) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    // 起点在墙或者地面里面就什么都不画
+    if tiles
+        .at(ray.origin)
+        .any(|tile| !matches!(tile.terrain(), Terrain::Water))
+    {
+        return segments;
+    }
+
+    let mut stack = vec![(ray, water_at(tiles, ray.origin), 0)];
+    while let Some((ray, in_water, depth)) = stack.pop() {
+        if ray.intensity < MIN_INTENSITY || depth > MAX_DEPTH || segments.len() >= MAX_SEGMENTS {
+            continue;
+        }
+        let Some(hit) = nearest_hit(&ray, tiles) else {
+            segments.push(Segment {
+                from: ray.origin,
+                to: ray.origin + ray.direction * MISS_LENGTH,
+                intensity: ray.intensity,
+                in_water,
+            });
+            continue;
+        };
+        segments.push(Segment {
+            from: ray.origin,
+            to: hit.point,
+            intensity: ray.intensity,
+            in_water,
+        });
+
+        // 法线翻到和光线相对的一侧
+        let n = if ray.direction.dot(hit.normal) < 0.0 {
+            hit.normal
+        } else {
+            -hit.normal
+        };
+        match hit.terrain {
+            Terrain::Wall => {
+                stack.push((
+                    Ray {
+                        origin: hit.point + n * EPSILON,
+                        direction: reflect(ray.direction, n),
+                        intensity: ray.intensity * WALL_REFLECTANCE,
+                    },
+                    in_water,
+                    depth + 1,
+                ));
+            }
+            Terrain::Ground => {}
+            Terrain::Water => {
+                // 两块水贴在一起时中间没有界面，直接穿过去
+                let to_water = water_at(tiles, hit.point + ray.direction * EPSILON * 10.0);
+                if to_water == in_water {
+                    stack.push((
+                        Ray {
+                            origin: hit.point + ray.direction * EPSILON,
+                            ..ray
+                        },
+                        in_water,
+                        depth,
+                    ));
+                    continue;
+                }
+                let (n1, n2) = if in_water {
+                    (WATER_IOR, 1.0)
+                } else {
+                    (1.0, WATER_IOR)
+                };
+                let reflected = Ray {
+                    origin: hit.point + n * EPSILON,
+                    direction: reflect(ray.direction, n),
+                    intensity: ray.intensity,
+                };
+                match refract(ray.direction, n, n1 / n2) {
+                    Some(direction) => {
+                        let r = schlick(-ray.direction.dot(n), n1, n2);
+                        stack.push((
+                            Ray {
+                                intensity: ray.intensity * r,
+                                ..reflected
+                            },
+                            in_water,
+                            depth + 1,
+                        ));
+                        stack.push((
+                            Ray {
+                                origin: hit.point - n * EPSILON,
+                                direction,
+                                intensity: ray.intensity * (1.0 - r),
+                            },
+                            to_water,
+                            depth + 1,
+                        ));
+                    }
+                    // 全反射
+                    None => stack.push((reflected, in_water, depth + 1)),
+                }
+            }
+        }
+    }
+    segments
+}
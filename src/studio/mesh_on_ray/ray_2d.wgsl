@@ -1,21 +1,21 @@
-struct TimeUniforms { 
-    @location(0) dt:f32,
-    @location(1) time:f32,
-};
-struct RayUniforms { 
-    @location(0) origin:vec3<f32>,
-    @location(1) direction:vec3<f32>,
-    @location(2) intensity:f32,
+struct Uniforms {
+    time: f32,
+    delta_time: f32,
+    // 宽 / 高，世界坐标 y 在 [-1, 1]，x 按这个比例
+    aspect: f32,
+    _pad: f32,
 };
 
-@group(0) @binding(0) var<uniform> time_uniforms: Uniforms;
-@group(1) @binding(0) var<uniform> ray_uniforms: RayUniforms;
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
 
 struct VertexInput {
     @location(0) position: vec3f,
     @location(1) color: vec3f,
 };
 
+// 同样的两个@location(0),
+// 这个location的意义是看当用在什么的结构体中才
+// 代表着当前用处的0位置的数据
 struct InstanceInput {
     @location(2) position: vec3f,
     @location(3) rotation: vec3f,
@@ -26,15 +26,13 @@ struct InstanceInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
     @location(0) color: vec3f,
+    @location(1) world: vec2f,
+    @location(2) @interpolate(flat) on_type: u32,
 };
 
-// 同样的两个@location(0), 
-// 这个location的意义是看当用在什么的结构体中才
-// 代表着当前用处的0位置的数据
-
-struct InstanceInput {
-    @location(2) position: vec3f,
-};
+fn world_to_clip(p: vec2f) -> vec4f {
+    return vec4f(p.x / uniforms.aspect, p.y, 0.0, 1.0);
+}
 
 @vertex
 fn vs_main(
@@ -44,12 +42,35 @@ fn vs_main(
     var out: VertexOutput;
     out.color = model.color;
     // 正常显示从vertex的position和instance里面的scale和rotation
-    out.clip_position = vec4f(rotated_position, 1.0);
+    // 2D 里只用绕 z 的旋转
+    let c = cos(instance.rotation.z);
+    let s = sin(instance.rotation.z);
+    let p = model.position.xy * instance.scale;
+    let rotated_position = vec2f(p.x * c - p.y * s, p.x * s + p.y * c) + instance.position.xy;
+    out.clip_position = world_to_clip(rotated_position);
+    out.world = rotated_position;
+    out.on_type = instance.on_type;
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
-    
+    var base: vec3f;
+    switch in.on_type {
+        // Wall
+        case 0u: {
+            base = vec3f(0.55, 0.55, 0.6);
+        }
+        // Ground
+        case 1u: {
+            base = vec3f(0.35, 0.25, 0.15);
+        }
+        // Water，加一点随时间流动的波纹
+        default: {
+            let wave = sin(in.world.x * 20.0 + uniforms.time * 2.0) * sin(in.world.y * 16.0 - uniforms.time * 1.5);
+            base = vec3f(0.1, 0.3, 0.6) + vec3f(0.05, 0.08, 0.1) * wave;
+        }
+    }
+    let animated_color = base * in.color;
     return vec4f(animated_color, 1.0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,596 @@
+// 计算着色器里的渐进式路径追踪，给光栅化的光照场景当参考答案
+// 每帧每像素一个样本，在两张 Rgba32Float 的累积贴图之间来回写
+// 相机一动或者参数一改就从头累积，最后 ACES tonemap 到屏幕
+// 左键拖动转相机，滚轮缩放
+mod scene;
+
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, WindowEvent},
+    window::Window,
+};
+
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::{
+        bvh::with_bvh,
+        camera::OrbitCamera,
+        globals::{with_globals, Globals},
+    },
+};
+use scene::TraceScene;
+
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// 和 path_trace.wgsl 里的 Params 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, PartialEq)]
+struct TraceParams {
+    sample_index: u32,
+    max_bounces: u32,
+    sphere_count: u32,
+    triangle_count: u32,
+    sky_intensity: f32,
+    clamp_radiance: f32,
+    _pad: [f32; 2],
+}
+
+/// 和 tonemap.wgsl 里的 Tonemap 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct TonemapParams {
+    exposure: f32,
+    _pad: [f32; 3],
+}
+
+/// 两张累积贴图，第 i 个 trace_group 读 i 写 1 - i
+struct Accumulation {
+    size: (u32, u32),
+    trace_groups: [wgpu::BindGroup; 2],
+    tonemap_groups: [wgpu::BindGroup; 2],
+}
+
+impl Accumulation {
+    fn new(
+        context: &crate::gfx::GfxContext,
+        trace_layout: &wgpu::BindGroupLayout,
+        tonemap_layout: &wgpu::BindGroupLayout,
+        tonemap_buffer: &wgpu::Buffer,
+        (width, height): (u32, u32),
+    ) -> Self {
+        let views = [0, 1].map(|_| {
+            context
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("path tracer accumulation"),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: ACCUMULATION_FORMAT,
+                    usage: wgpu::TextureUsages::STORAGE_BINDING
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let trace_groups = [0, 1].map(|i| {
+            context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("path tracer accumulation"),
+                    layout: trace_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&views[i]),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&views[1 - i]),
+                        },
+                    ],
+                })
+        });
+        let tonemap_groups = [0, 1].map(|i| {
+            context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("path tracer tonemap"),
+                    layout: tonemap_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&views[i]),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: tonemap_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+        });
+        Accumulation {
+            size: (width, height),
+            trace_groups,
+            tonemap_groups,
+        }
+    }
+}
+
+pub struct PathTracer {
+    globals: Globals,
+    camera: OrbitCamera,
+    params: TraceParams,
+    params_buffer: wgpu::Buffer,
+    scene_group: wgpu::BindGroup,
+    tonemap: TonemapParams,
+    tonemap_buffer: wgpu::Buffer,
+    trace_pipeline: wgpu::ComputePipeline,
+    tonemap_pipeline: wgpu::RenderPipeline,
+    trace_layout: wgpu::BindGroupLayout,
+    tonemap_layout: wgpu::BindGroupLayout,
+    accumulation: Accumulation,
+    /// 这一帧读哪一张累积贴图
+    current: usize,
+    /// 上一帧的参数，变了就重置
+    last_params: TraceParams,
+    /// 攒够这么多样本就停，0 表示不停
+    max_samples: u32,
+    /// BVH 节点数，显示用
+    bvh_size: usize,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+fn storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn storage_buffer<T: Pod + Zeroable>(
+    context: &crate::gfx::GfxContext,
+    label: &str,
+    items: &[T],
+) -> wgpu::Buffer {
+    // 空的 storage buffer 不能绑定，至少放一个
+    let zero = [T::zeroed()];
+    let contents = if items.is_empty() { &zero[..] } else { items };
+    context
+        .device
+        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(contents),
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+}
+
+impl Sandy for PathTracer {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let config = context.surface_config.as_ref().unwrap();
+        let globals = Globals::ready(context, ());
+        let mut camera = OrbitCamera::new(Vec3::ZERO, 7.0);
+        camera.yaw = 0.0;
+        camera.pitch = 0.0;
+        camera.fov_y = 0.75;
+
+        let mut scene = TraceScene::cornell();
+        let bvh = scene.build_bvh();
+        let params = TraceParams {
+            sample_index: 0,
+            max_bounces: 6,
+            sphere_count: scene.spheres.len() as u32,
+            triangle_count: scene.triangles.len() as u32,
+            sky_intensity: 0.3,
+            clamp_radiance: 20.0,
+            _pad: [0.0; 2],
+        };
+        let params_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("path tracer params"),
+            size: std::mem::size_of::<TraceParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let materials = storage_buffer(context, "path tracer materials", &scene.materials);
+        let spheres = storage_buffer(context, "path tracer spheres", &scene.spheres);
+        let triangles = storage_buffer(context, "path tracer triangles", &scene.triangles);
+        let bvh_nodes = storage_buffer(context, "path tracer bvh", &bvh.nodes);
+
+        let scene_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("path tracer scene"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        storage_entry(1),
+                        storage_entry(2),
+                        storage_entry(3),
+                        storage_entry(4),
+                    ],
+                });
+        let scene_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("path tracer scene"),
+                layout: &scene_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: materials.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: spheres.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: triangles.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: bvh_nodes.as_entire_binding(),
+                    },
+                ],
+            });
+        let trace_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("path tracer accumulation"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::StorageTexture {
+                                access: wgpu::StorageTextureAccess::WriteOnly,
+                                format: ACCUMULATION_FORMAT,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+        let tonemap_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("path tracer tonemap"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let tonemap = TonemapParams {
+            exposure: 1.0,
+            _pad: [0.0; 3],
+        };
+        let tonemap_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("path tracer tonemap"),
+                contents: bytemuck::bytes_of(&tonemap),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let trace_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("path trace"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Owned(with_globals(&with_bvh(
+                    include_str!("path_trace.wgsl"),
+                )))),
+            });
+        let trace_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("path trace"),
+                    bind_group_layouts: &[&globals.bind_group_layout, &scene_layout, &trace_layout],
+                    push_constant_ranges: &[],
+                });
+        let trace_pipeline =
+            context
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some("path trace"),
+                    layout: Some(&trace_pipeline_layout),
+                    module: &trace_shader,
+                    entry_point: "cs_main",
+                    compilation_options: Default::default(),
+                    cache: None,
+                });
+
+        let tonemap_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("path tracer tonemap"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("tonemap.wgsl"))),
+            });
+        let tonemap_pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("path tracer tonemap"),
+                    bind_group_layouts: &[&tonemap_layout],
+                    push_constant_ranges: &[],
+                });
+        let tonemap_pipeline =
+            context
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("path tracer tonemap"),
+                    layout: Some(&tonemap_pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &tonemap_shader,
+                        entry_point: "vs_main",
+                        buffers: &[],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &tonemap_shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(config.view_formats[0].into())],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                });
+
+        let accumulation = Accumulation::new(
+            context,
+            &trace_layout,
+            &tonemap_layout,
+            &tonemap_buffer,
+            (config.width, config.height),
+        );
+
+        PathTracer {
+            globals,
+            camera,
+            params,
+            params_buffer,
+            scene_group,
+            tonemap,
+            tonemap_buffer,
+            trace_pipeline,
+            tonemap_pipeline,
+            trace_layout,
+            tonemap_layout,
+            accumulation,
+            current: 0,
+            last_params: params,
+            max_samples: 0,
+            bvh_size: bvh.nodes.len(),
+            window,
+            egui,
+        }
+    }
+}
+
+impl PathTracer {
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Path tracer")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.label(format!("samples: {}", self.params.sample_index));
+                ui.label(format!(
+                    "{} spheres, {} triangles, {} BVH nodes",
+                    self.params.sphere_count, self.params.triangle_count, self.bvh_size
+                ));
+                ui.add(egui::Slider::new(&mut self.params.max_bounces, 1..=16).text("bounces"));
+                ui.add(egui::Slider::new(&mut self.params.sky_intensity, 0.0..=2.0).text("sky"));
+                ui.add(
+                    egui::Slider::new(&mut self.params.clamp_radiance, 1.0..=100.0)
+                        .logarithmic(true)
+                        .text("clamp"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.tonemap.exposure, 0.1..=8.0)
+                        .logarithmic(true)
+                        .text("exposure"),
+                );
+                ui.add(egui::Slider::new(&mut self.max_samples, 0..=4096).text("stop after"));
+                if ui.button("reset").clicked() {
+                    self.params.sample_index = 0;
+                }
+            });
+    }
+
+    fn reset(&mut self) {
+        self.params.sample_index = 0;
+    }
+}
+
+impl Painter for PathTracer {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        let config = context.surface_config.as_ref().unwrap();
+        let size = (config.width, config.height);
+        if size != self.accumulation.size {
+            self.accumulation = Accumulation::new(
+                context,
+                &self.trace_layout,
+                &self.tonemap_layout,
+                &self.tonemap_buffer,
+                size,
+            );
+            self.reset();
+        }
+        // 除了样本序号以外的参数变了也要重来
+        let compare = TraceParams {
+            sample_index: self.last_params.sample_index,
+            ..self.params
+        };
+        if compare != self.last_params {
+            self.reset();
+        }
+
+        self.globals.update(context, &self.camera, dt, time);
+        context
+            .queue
+            .write_buffer(&self.tonemap_buffer, 0, bytemuck::bytes_of(&self.tonemap));
+
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("path tracer"),
+            });
+
+        let done = self.max_samples > 0 && self.params.sample_index >= self.max_samples;
+        if !done {
+            context
+                .queue
+                .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("path trace"),
+                timestamp_writes: context.compute_timestamps("path trace"),
+            });
+            cpass.set_pipeline(&self.trace_pipeline);
+            cpass.set_bind_group(0, &self.globals.bind_group, &[]);
+            cpass.set_bind_group(1, &self.scene_group, &[]);
+            cpass.set_bind_group(2, &self.accumulation.trace_groups[self.current], &[]);
+            cpass.dispatch_workgroups(size.0.div_ceil(8), size.1.div_ceil(8), 1);
+            drop(cpass);
+
+            self.current = 1 - self.current;
+            self.last_params = self.params;
+            self.params.sample_index += 1;
+        }
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("path tracer tonemap"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: context.render_timestamps("path tracer tonemap"),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.tonemap_pipeline);
+            rpass.set_bind_group(0, &self.accumulation.tonemap_groups[self.current], &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        // 在 egui 窗口上按下和滚动不动相机，松开总要传过去
+        let starts = matches!(
+            event,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            } | WindowEvent::MouseWheel { .. }
+        );
+        if starts && self.egui.lock().unwrap().context.is_pointer_over_area() {
+            return;
+        }
+        if self.camera.input(event) {
+            self.reset();
+        }
+    }
+
+    fn counters(&self) -> Vec<(&'static str, f64)> {
+        vec![("samples", self.params.sample_index as f64)]
+    }
+}
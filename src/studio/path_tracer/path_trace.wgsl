@@ -0,0 +1,237 @@
+// 渐进式路径追踪，前面拼上 utils/globals.wgsl 和 utils/bvh.wgsl
+// 三角形按 BVH 的叶子顺序排好，球不多，直接挨个测
+// 每帧每个像素追一条路径，和上一帧的平均值混合后写到另一张累积贴图
+
+const DIFFUSE: u32 = 0u;
+const METAL: u32 = 1u;
+const GLASS: u32 = 2u;
+const PI: f32 = 3.14159265;
+const NO_HIT: f32 = 1e30;
+
+struct Params {
+    // 从上次重置开始的第几个样本，0 表示重新开始
+    sample_index: u32,
+    max_bounces: u32,
+    sphere_count: u32,
+    triangle_count: u32,
+    sky_intensity: f32,
+    // 单个样本的亮度上限，压萤火虫
+    clamp_radiance: f32,
+    _pad0: f32,
+    _pad1: f32,
+};
+
+struct Material {
+    albedo: vec3<f32>,
+    kind: u32,
+    emission: vec3<f32>,
+    // METAL 是粗糙度，GLASS 是折射率
+    param: f32,
+};
+
+struct Sphere {
+    center: vec3<f32>,
+    radius: f32,
+    material: u32,
+};
+
+struct Triangle {
+    v0: vec3<f32>,
+    material: u32,
+    v1: vec3<f32>,
+    v2: vec3<f32>,
+};
+
+@group(1) @binding(0) var<uniform> params: Params;
+@group(1) @binding(1) var<storage, read> materials: array<Material>;
+@group(1) @binding(2) var<storage, read> spheres: array<Sphere>;
+@group(1) @binding(3) var<storage, read> triangles: array<Triangle>;
+@group(1) @binding(4) var<storage, read> bvh_nodes: array<BvhNode>;
+
+@group(2) @binding(0) var previous: texture_2d<f32>;
+@group(2) @binding(1) var accumulation: texture_storage_2d<rgba32float, write>;
+
+var<private> rng_state: u32;
+
+fn pcg_hash(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn rand() -> f32 {
+    rng_state = pcg_hash(rng_state);
+    return f32(rng_state) / 4294967296.0;
+}
+
+fn random_unit_vector() -> vec3<f32> {
+    let z = rand() * 2.0 - 1.0;
+    let a = rand() * 2.0 * PI;
+    let r = sqrt(1.0 - z * z);
+    return vec3<f32>(r * cos(a), r * sin(a), z);
+}
+
+struct Hit {
+    t: f32,
+    normal: vec3<f32>,
+    material: u32,
+};
+
+fn hit_sphere(s: Sphere, ro: vec3<f32>, rd: vec3<f32>, t_max: f32) -> f32 {
+    let oc = ro - s.center;
+    let b = dot(oc, rd);
+    let c = dot(oc, oc) - s.radius * s.radius;
+    let h = b * b - c;
+    if h < 0.0 {
+        return NO_HIT;
+    }
+    let sq = sqrt(h);
+    var t = -b - sq;
+    if t < 1e-4 {
+        t = -b + sq;
+    }
+    if t < 1e-4 || t > t_max {
+        return NO_HIT;
+    }
+    return t;
+}
+
+// Möller–Trumbore
+fn hit_triangle(tri: Triangle, ro: vec3<f32>, rd: vec3<f32>, t_max: f32) -> f32 {
+    let e1 = tri.v1 - tri.v0;
+    let e2 = tri.v2 - tri.v0;
+    let p = cross(rd, e2);
+    let det = dot(e1, p);
+    if abs(det) < 1e-8 {
+        return NO_HIT;
+    }
+    let inv = 1.0 / det;
+    let s = ro - tri.v0;
+    let u = dot(s, p) * inv;
+    if u < 0.0 || u > 1.0 {
+        return NO_HIT;
+    }
+    let q = cross(s, e1);
+    let v = dot(rd, q) * inv;
+    if v < 0.0 || u + v > 1.0 {
+        return NO_HIT;
+    }
+    let t = dot(e2, q) * inv;
+    if t < 1e-4 || t > t_max {
+        return NO_HIT;
+    }
+    return t;
+}
+
+fn bvh_hit(index: u32, ro: vec3<f32>, rd: vec3<f32>, t_max: f32) -> f32 {
+    return hit_triangle(triangles[index], ro, rd, t_max);
+}
+
+fn intersect(ro: vec3<f32>, rd: vec3<f32>) -> Hit {
+    var hit = Hit(NO_HIT, vec3<f32>(0.0), 0u);
+    for (var i = 0u; i < params.sphere_count; i++) {
+        let s = spheres[i];
+        let t = hit_sphere(s, ro, rd, hit.t);
+        if t < hit.t {
+            hit = Hit(t, (ro + rd * t - s.center) / s.radius, s.material);
+        }
+    }
+    if params.triangle_count > 0u {
+        let found = bvh_traverse(ro, rd, hit.t);
+        if found.index != BVH_NONE {
+            let tri = triangles[found.index];
+            hit = Hit(found.t, normalize(cross(tri.v1 - tri.v0, tri.v2 - tri.v0)), tri.material);
+        }
+    }
+    return hit;
+}
+
+fn sky(rd: vec3<f32>) -> vec3<f32> {
+    let t = clamp(rd.y * 0.5 + 0.5, 0.0, 1.0);
+    return mix(vec3<f32>(0.6, 0.6, 0.6), vec3<f32>(0.5, 0.7, 1.0), t) * params.sky_intensity;
+}
+
+fn schlick(cos_i: f32, eta: f32) -> f32 {
+    let r0 = pow((1.0 - eta) / (1.0 + eta), 2.0);
+    return r0 + (1.0 - r0) * pow(1.0 - cos_i, 5.0);
+}
+
+fn trace(origin: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    var ro = origin;
+    var rd = direction;
+    var radiance = vec3<f32>(0.0);
+    var throughput = vec3<f32>(1.0);
+    for (var bounce = 0u; bounce <= params.max_bounces; bounce++) {
+        let hit = intersect(ro, rd);
+        if hit.t >= NO_HIT {
+            radiance += throughput * sky(rd);
+            break;
+        }
+        let m = materials[hit.material];
+        radiance += throughput * m.emission;
+
+        let front = dot(rd, hit.normal) < 0.0;
+        let n = select(-hit.normal, hit.normal, front);
+        let p = ro + rd * hit.t;
+        switch m.kind {
+            case METAL: {
+                // 反射方向加一点随机的抖动
+                rd = normalize(reflect(rd, n) + random_unit_vector() * m.param);
+                if dot(rd, n) <= 0.0 {
+                    return radiance;
+                }
+                ro = p + n * 1e-4;
+            }
+            case GLASS: {
+                // 按菲涅耳随机选反射还是折射
+                let eta = select(m.param, 1.0 / m.param, front);
+                let cos_i = min(dot(-rd, n), 1.0);
+                let sin_t2 = eta * eta * (1.0 - cos_i * cos_i);
+                if sin_t2 > 1.0 || rand() < schlick(cos_i, m.param) {
+                    rd = reflect(rd, n);
+                    ro = p + n * 1e-4;
+                } else {
+                    rd = refract(rd, n, eta);
+                    ro = p - n * 1e-4;
+                }
+            }
+            case DIFFUSE, default: {
+                // 余弦分布，pdf 和 cos / PI 抵消，只剩 albedo
+                rd = normalize(n + random_unit_vector());
+                ro = p + n * 1e-4;
+            }
+        }
+        throughput *= m.albedo;
+
+        // 俄罗斯轮盘赌，贡献太小的路径提前结束
+        if bounce > 2u {
+            let survive = max(throughput.x, max(throughput.y, throughput.z));
+            if rand() > survive {
+                break;
+            }
+            throughput /= survive;
+        }
+    }
+    return min(radiance, vec3<f32>(params.clamp_radiance));
+}
+
+@compute
+@workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(accumulation);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    rng_state = pcg_hash(id.x + id.y * size.x) ^ pcg_hash(params.sample_index * 9781u + 1u);
+
+    // 像素内随机抖动，顺便抗锯齿
+    let uv = (vec2<f32>(id.xy) + vec2<f32>(rand(), rand())) / vec2<f32>(size);
+    let sample = trace(globals.camera_position, camera_ray(uv));
+
+    var result = sample;
+    if params.sample_index > 0u {
+        let prev = textureLoad(previous, id.xy, 0).rgb;
+        result = mix(prev, sample, 1.0 / f32(params.sample_index + 1u));
+    }
+    textureStore(accumulation, id.xy, vec4<f32>(result, 1.0));
+}
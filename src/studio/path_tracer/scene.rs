@@ -0,0 +1,197 @@
+// 路径追踪的场景：材质、解析球、从 utils::models 转出来的三角形
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+
+use crate::utils::{
+    bvh::{Aabb, Bvh},
+    models::ModelType,
+};
+
+pub const DIFFUSE: u32 = 0;
+pub const METAL: u32 = 1;
+pub const GLASS: u32 = 2;
+
+/// 和 path_trace.wgsl 里的 Material 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct Material {
+    pub albedo: [f32; 3],
+    pub kind: u32,
+    pub emission: [f32; 3],
+    /// METAL 是粗糙度，GLASS 是折射率
+    pub param: f32,
+}
+
+impl Material {
+    pub fn diffuse(albedo: [f32; 3]) -> Self {
+        Material {
+            albedo,
+            kind: DIFFUSE,
+            emission: [0.0; 3],
+            param: 0.0,
+        }
+    }
+
+    pub fn metal(albedo: [f32; 3], roughness: f32) -> Self {
+        Material {
+            albedo,
+            kind: METAL,
+            emission: [0.0; 3],
+            param: roughness,
+        }
+    }
+
+    pub fn glass(ior: f32) -> Self {
+        Material {
+            albedo: [1.0; 3],
+            kind: GLASS,
+            emission: [0.0; 3],
+            param: ior,
+        }
+    }
+
+    pub fn light(emission: [f32; 3]) -> Self {
+        Material {
+            albedo: [0.0; 3],
+            kind: DIFFUSE,
+            emission,
+            param: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct GpuSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+    pub material: u32,
+    pub _pad: [u32; 3],
+}
+
+/// 世界空间的三角形，法线在 shader 里按顶点算
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct GpuTriangle {
+    pub v0: [f32; 3],
+    pub material: u32,
+    pub v1: [f32; 3],
+    pub _pad0: u32,
+    pub v2: [f32; 3],
+    pub _pad1: u32,
+}
+
+#[derive(Default)]
+pub struct TraceScene {
+    pub materials: Vec<Material>,
+    pub spheres: Vec<GpuSphere>,
+    pub triangles: Vec<GpuTriangle>,
+}
+
+impl TraceScene {
+    pub fn material(&mut self, material: Material) -> u32 {
+        self.materials.push(material);
+        self.materials.len() as u32 - 1
+    }
+
+    pub fn sphere(&mut self, center: Vec3, radius: f32, material: u32) {
+        self.spheres.push(GpuSphere {
+            center: center.to_array(),
+            radius,
+            material,
+            _pad: [0; 3],
+        });
+    }
+
+    /// 把模型的三角形变换到世界空间后加进来
+    pub fn mesh(&mut self, model: ModelType, transform: Mat4, material: u32) {
+        for [v0, v1, v2] in model.triangles(transform) {
+            self.triangles.push(GpuTriangle {
+                v0: v0.to_array(),
+                material,
+                v1: v1.to_array(),
+                _pad0: 0,
+                v2: v2.to_array(),
+                _pad1: 0,
+            });
+        }
+    }
+
+    /// 给三角形建 BVH，并把 triangles 按叶子顺序重排，这样才能和节点一起传给 shader
+    pub fn build_bvh(&mut self) -> Bvh {
+        let bounds: Vec<Aabb> = self
+            .triangles
+            .iter()
+            .map(|t| Aabb::from_points(&[t.v0, t.v1, t.v2].map(Vec3::from)))
+            .collect();
+        let bvh = Bvh::build(&bounds);
+        self.triangles = bvh
+            .indices
+            .iter()
+            .map(|&i| self.triangles[i as usize])
+            .collect();
+        bvh
+    }
+
+    /// 一个类似 Cornell box 的房间，顶上开一盏灯
+    pub fn cornell() -> Self {
+        let mut scene = TraceScene::default();
+        let white = scene.material(Material::diffuse([0.73, 0.73, 0.73]));
+        let red = scene.material(Material::diffuse([0.65, 0.05, 0.05]));
+        let green = scene.material(Material::diffuse([0.12, 0.45, 0.15]));
+        let light = scene.material(Material::light([15.0, 14.0, 12.0]));
+        let gold = scene.material(Material::metal([0.95, 0.75, 0.35], 0.15));
+        let glass = scene.material(Material::glass(1.5));
+        let blue = scene.material(Material::diffuse([0.2, 0.3, 0.8]));
+
+        // Cube 是 [-1, 1]，缩放成薄板当墙
+        let slab = |center: Vec3, half: Vec3| {
+            Mat4::from_scale_rotation_translation(half, glam::Quat::IDENTITY, center)
+        };
+        scene.mesh(
+            ModelType::Cube,
+            slab(Vec3::new(0.0, -2.05, 0.0), Vec3::new(2.0, 0.05, 2.0)),
+            white,
+        );
+        scene.mesh(
+            ModelType::Cube,
+            slab(Vec3::new(0.0, 2.05, 0.0), Vec3::new(2.0, 0.05, 2.0)),
+            white,
+        );
+        scene.mesh(
+            ModelType::Cube,
+            slab(Vec3::new(0.0, 0.0, -2.05), Vec3::new(2.0, 2.0, 0.05)),
+            white,
+        );
+        scene.mesh(
+            ModelType::Cube,
+            slab(Vec3::new(-2.05, 0.0, 0.0), Vec3::new(0.05, 2.0, 2.0)),
+            red,
+        );
+        scene.mesh(
+            ModelType::Cube,
+            slab(Vec3::new(2.05, 0.0, 0.0), Vec3::new(0.05, 2.0, 2.0)),
+            green,
+        );
+        // 灯是嵌在天花板里的一块薄板
+        scene.mesh(
+            ModelType::Cube,
+            slab(Vec3::new(0.0, 1.99, 0.0), Vec3::new(0.5, 0.02, 0.5)),
+            light,
+        );
+        scene.mesh(
+            ModelType::Cube,
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(0.55, 1.1, 0.55),
+                glam::Quat::from_rotation_y(0.4),
+                Vec3::new(-0.8, -0.9, -0.7),
+            ),
+            white,
+        );
+
+        scene.sphere(Vec3::new(0.9, -1.4, 0.6), 0.6, glass);
+        scene.sphere(Vec3::new(0.7, -1.6, -1.2), 0.4, gold);
+        scene.sphere(Vec3::new(-0.6, -1.7, 1.1), 0.3, blue);
+        scene
+    }
+}
@@ -0,0 +1,33 @@
+// 把累积的 HDR 结果压到屏幕上
+
+struct Tonemap {
+    exposure: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+};
+
+@group(0) @binding(0) var accumulation: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> tonemap: Tonemap;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// ACES 的拟合曲线
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let hdr = textureLoad(accumulation, vec2<u32>(position.xy), 0).rgb;
+    return vec4<f32>(aces(hdr * tonemap.exposure), 1.0);
+}
@@ -0,0 +1,572 @@
+// 场景描述文件，RON 格式，直接反序列化成下面这些 *Desc，例子见 asset/scenes/demo.scene
+//
+//   (
+//       camera: (eye: (4, 3, 6), target: (0, 0.5, 0), fov: 50),
+//       lights: [Directional(direction: (-1, -2, -1), intensity: 3)],
+//       textures: [(name: "checker", source: Checker(8))],
+//       shaders: [(name: "wobble", path: "wobble.wgsl")],
+//       materials: [(name: "gold", color: (1, 0.8, 0.3), metallic: 1, brdf: CookTorrance)],
+//       objects: [(name: "ball", mesh: "sphere", material: "gold", translate: (0, 1, 0))],
+//   )
+//
+// 没写的字段用默认值；Option 的字段直接写值，不用包 Some(..)
+// color 可以写 rgb 或者 rgba，scale 只写一个数就是等比缩放，rotate 是 YXZ 欧拉角的度数
+// 路径相对场景文件所在的目录；parent 要写在前面的物体，其它名字随便引用，但要存在
+use std::path::{Path, PathBuf};
+
+use glam::{EulerRot, Quat, Vec3};
+use serde::Deserialize;
+
+use crate::{
+    assets::Asset,
+    gfx::GfxContext,
+    studio::light::{BrdfModel, DirectionalLight, Light, PointLight, SpotLight},
+    utils::{models::ModelType, scene_graph::Transform},
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, std::io::Error),
+    /// ron 报的语法或者字段错误，行列从 1 开始
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// 读进来了但是名字对不上
+    Invalid(String),
+    /// 场景本身读进来了，但引用的文件或者 shader 有问题
+    Resource(String),
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            SceneError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "scene {}:{}: {}", line, column, message),
+            SceneError::Invalid(message) | SceneError::Resource(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl From<ron::error::SpannedError> for SceneError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        SceneError::Parse {
+            line: e.position.line,
+            column: e.position.col,
+            message: e.code.to_string(),
+        }
+    }
+}
+
+/// 写 rgb 的时候 alpha 是 1
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum Color {
+    Rgb([f32; 3]),
+    Rgba([f32; 4]),
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Rgb([r, g, b]) => [r, g, b, 1.0],
+            Color::Rgba(rgba) => rgba,
+        }
+    }
+}
+
+impl From<Color> for Vec3 {
+    fn from(color: Color) -> Self {
+        Vec3::from_slice(&<[f32; 4]>::from(color)[..3])
+    }
+}
+
+fn rgba<'de, D: serde::Deserializer<'de>>(d: D) -> Result<[f32; 4], D::Error> {
+    Color::deserialize(d).map(Into::into)
+}
+
+fn rgb<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Vec3, D::Error> {
+    Color::deserialize(d).map(Into::into)
+}
+
+/// 一个数就是等比缩放
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Scale {
+    Uniform(f32),
+    Xyz(Vec3),
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Scale::Uniform(1.0)
+    }
+}
+
+impl From<Scale> for Vec3 {
+    fn from(scale: Scale) -> Self {
+        match scale {
+            Scale::Uniform(s) => Vec3::splat(s),
+            Scale::Xyz(v) => v,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CameraDesc {
+    pub eye: Vec3,
+    pub target: Vec3,
+    /// 竖直方向的视角，度
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        CameraDesc {
+            eye: Vec3::new(4.0, 3.0, 6.0),
+            target: Vec3::ZERO,
+            fov: 50.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+fn white() -> Vec3 {
+    Vec3::ONE
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn down() -> Vec3 {
+    Vec3::NEG_Y
+}
+
+fn ten() -> f32 {
+    10.0
+}
+
+fn twenty() -> f32 {
+    20.0
+}
+
+fn thirty() -> f32 {
+    30.0
+}
+
+/// 场景文件里的光源，不带阴影；spot 的角度是度
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub enum LightDesc {
+    Directional {
+        #[serde(default = "down")]
+        direction: Vec3,
+        #[serde(default = "white", deserialize_with = "rgb")]
+        color: Vec3,
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+    Point {
+        #[serde(default)]
+        position: Vec3,
+        #[serde(default = "white", deserialize_with = "rgb")]
+        color: Vec3,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "ten")]
+        range: f32,
+    },
+    Spot {
+        #[serde(default)]
+        position: Vec3,
+        #[serde(default = "down")]
+        direction: Vec3,
+        #[serde(default = "white", deserialize_with = "rgb")]
+        color: Vec3,
+        #[serde(default = "one")]
+        intensity: f32,
+        #[serde(default = "ten")]
+        range: f32,
+        #[serde(default = "twenty")]
+        inner: f32,
+        #[serde(default = "thirty")]
+        outer: f32,
+    },
+}
+
+impl From<LightDesc> for Light {
+    fn from(desc: LightDesc) -> Self {
+        match desc {
+            LightDesc::Directional {
+                direction,
+                color,
+                intensity,
+            } => DirectionalLight {
+                direction,
+                color,
+                intensity,
+                shadow: None,
+                cascades: 0,
+            }
+            .into(),
+            LightDesc::Point {
+                position,
+                color,
+                intensity,
+                range,
+            } => PointLight {
+                position,
+                color,
+                intensity,
+                range,
+            }
+            .into(),
+            LightDesc::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                range,
+                inner,
+                outer,
+            } => SpotLight {
+                position,
+                direction,
+                color,
+                intensity,
+                range,
+                inner_angle: inner.to_radians(),
+                outer_angle: outer.to_radians(),
+                shadow: None,
+            }
+            .into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum TextureSource {
+    Path(PathBuf),
+    /// 程序生成的黑白格子，数字是一行几格
+    Checker(u32),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TextureDesc {
+    pub name: String,
+    pub source: TextureSource,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShaderDesc {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum MeshSource {
+    /// 内置的模型不用写，直接用名字引用
+    #[serde(skip)]
+    Builtin(ModelType),
+    Obj(PathBuf),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MeshDesc {
+    pub name: String,
+    pub source: MeshSource,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MaterialDesc {
+    pub name: String,
+    #[serde(deserialize_with = "rgba")]
+    pub color: [f32; 4],
+    pub texture: Option<String>,
+    /// None 用内置的 lit shader
+    pub shader: Option<String>,
+    pub brdf: BrdfModel,
+    pub roughness: f32,
+    pub metallic: f32,
+    pub shininess: f32,
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        MaterialDesc {
+            name: String::new(),
+            color: [1.0; 4],
+            texture: None,
+            shader: None,
+            brdf: BrdfModel::CookTorrance,
+            roughness: 0.5,
+            metallic: 0.0,
+            shininess: 32.0,
+        }
+    }
+}
+
+impl MaterialDesc {
+    pub fn new(name: String) -> Self {
+        MaterialDesc {
+            name,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ObjectDesc {
+    pub name: String,
+    /// 没有 mesh 的只是个分组节点
+    pub mesh: Option<String>,
+    pub material: Option<String>,
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub translate: Vec3,
+    /// YXZ 欧拉角，度
+    #[serde(default)]
+    pub rotate: Vec3,
+    #[serde(default)]
+    pub scale: Scale,
+    /// 原样传给 shader 的 object.params
+    #[serde(default)]
+    pub params: [f32; 4],
+}
+
+impl ObjectDesc {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.translate)
+            .with_rotation(Quat::from_euler(
+                EulerRot::YXZ,
+                self.rotate.y.to_radians(),
+                self.rotate.x.to_radians(),
+                self.rotate.z.to_radians(),
+            ))
+            .with_scale(self.scale.into())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SceneDesc {
+    pub camera: CameraDesc,
+    #[serde(deserialize_with = "rgb")]
+    pub ambient: Vec3,
+    #[serde(deserialize_with = "rgb")]
+    pub clear: Vec3,
+    pub lights: Vec<LightDesc>,
+    pub textures: Vec<TextureDesc>,
+    pub shaders: Vec<ShaderDesc>,
+    /// 读完以后前面会补上内置的模型
+    pub meshes: Vec<MeshDesc>,
+    pub materials: Vec<MaterialDesc>,
+    pub objects: Vec<ObjectDesc>,
+}
+
+impl Default for SceneDesc {
+    fn default() -> Self {
+        SceneDesc {
+            camera: CameraDesc::default(),
+            ambient: Vec3::splat(0.03),
+            clear: Vec3::new(0.05, 0.05, 0.07),
+            lights: Vec::new(),
+            textures: Vec::new(),
+            shaders: Vec::new(),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            objects: Vec::new(),
+        }
+    }
+}
+
+/// 名字不能空也不能重复
+fn check_unique(kind: &str, names: &[&str]) -> Result<(), SceneError> {
+    for (i, name) in names.iter().enumerate() {
+        if name.is_empty() {
+            return Err(SceneError::Invalid(format!("{} without a name", kind)));
+        }
+        if names[..i].contains(name) {
+            return Err(SceneError::Invalid(format!(
+                "{} `{}` already exists",
+                kind, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 引用的名字要在 names 里
+fn check_ref(kind: &str, names: &[&str], name: &Option<String>) -> Result<(), SceneError> {
+    match name {
+        Some(name) if !names.contains(&name.as_str()) => {
+            Err(SceneError::Invalid(format!("unknown {} `{}`", kind, name)))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl SceneDesc {
+    /// dir 是相对路径的起点
+    pub fn parse(source: &str, dir: &Path) -> Result<Self, SceneError> {
+        let mut scene: SceneDesc = ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_str(source)?;
+        // 文件里同名的 mesh 盖掉内置的
+        for model in [ModelType::Plane, ModelType::Sphere, ModelType::Cube] {
+            if !scene.meshes.iter().any(|m| m.name == model.name()) {
+                scene.meshes.insert(
+                    0,
+                    MeshDesc {
+                        name: model.name().to_string(),
+                        source: MeshSource::Builtin(model),
+                    },
+                );
+            }
+        }
+        scene.validate()?;
+        for t in &mut scene.textures {
+            if let TextureSource::Path(path) = &mut t.source {
+                *path = dir.join(&path);
+            }
+        }
+        for s in &mut scene.shaders {
+            s.path = dir.join(&s.path);
+        }
+        for m in &mut scene.meshes {
+            if let MeshSource::Obj(path) = &mut m.source {
+                *path = dir.join(&path);
+            }
+        }
+        Ok(scene)
+    }
+
+    fn validate(&self) -> Result<(), SceneError> {
+        let textures: Vec<&str> = self.textures.iter().map(|t| t.name.as_str()).collect();
+        let shaders: Vec<&str> = self.shaders.iter().map(|s| s.name.as_str()).collect();
+        let meshes: Vec<&str> = self.meshes.iter().map(|m| m.name.as_str()).collect();
+        let materials: Vec<&str> = self.materials.iter().map(|m| m.name.as_str()).collect();
+        let objects: Vec<&str> = self.objects.iter().map(|o| o.name.as_str()).collect();
+        check_unique("texture", &textures)?;
+        check_unique("shader", &shaders)?;
+        check_unique("mesh", &meshes)?;
+        check_unique("material", &materials)?;
+        check_unique("object", &objects)?;
+        for m in &self.materials {
+            check_ref("texture", &textures, &m.texture)?;
+            check_ref("shader", &shaders, &m.shader)?;
+        }
+        for (i, o) in self.objects.iter().enumerate() {
+            check_ref("mesh", &meshes, &o.mesh)?;
+            check_ref("material", &materials, &o.material)?;
+            // 父节点要先建好，只在前面找
+            check_ref("object", &objects[..i], &o.parent)?;
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SceneError> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_path_buf(), e))?;
+        Self::parse(&source, path.parent().unwrap_or(Path::new(".")))
+    }
+}
+
+/// 场景文件也交给 assets 读和盯着，和贴图、shader 一样改了就重新读，读坏了报错继续用旧的
+impl Asset for SceneDesc {
+    type Decoded = SceneDesc;
+    const KIND: &'static str = "scene";
+
+    fn decode(path: &Path) -> Result<Self::Decoded, String> {
+        Self::load(path).map_err(|e| e.to_string())
+    }
+
+    fn upload(_context: &GfxContext, _path: &Path, desc: Self::Decoded) -> Result<Self, String> {
+        Ok(desc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<SceneDesc, SceneError> {
+        SceneDesc::parse(source, Path::new("dir"))
+    }
+
+    #[test]
+    fn demo_scene_parses() {
+        let scene = SceneDesc::load(Path::new("asset/scenes/demo.scene")).unwrap();
+        assert_eq!(scene.camera.fov, 45.0);
+        assert_eq!(scene.lights.len(), 2);
+        assert_eq!(scene.objects.len(), 6);
+        // 三个内置的加上 prism
+        assert_eq!(scene.meshes.len(), 4);
+        let ball = scene.objects.iter().find(|o| o.name == "ball").unwrap();
+        assert_eq!(ball.parent.as_deref(), Some("table"));
+        assert_eq!(Vec3::from(ball.scale), Vec3::splat(0.9));
+        let plastic = scene
+            .materials
+            .iter()
+            .find(|m| m.name == "plastic")
+            .unwrap();
+        assert_eq!(plastic.brdf, BrdfModel::BlinnPhong);
+        assert_eq!(plastic.color, [0.8, 0.2, 0.15, 1.0]);
+    }
+
+    #[test]
+    fn defaults_and_paths() {
+        let scene = parse(
+            r#"(
+                shaders: [(name: "s", path: "s.wgsl")],
+                meshes: [(name: "cube", source: Obj("cube.obj"))],
+                materials: [(name: "m", color: (1, 0, 0, 0.5), shader: "s")],
+                objects: [(name: "a", mesh: "cube", material: "m")],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(scene.camera, CameraDesc::default());
+        assert_eq!(scene.shaders[0].path, Path::new("dir/s.wgsl"));
+        // 同名的 obj 换掉了内置的 cube
+        assert_eq!(scene.meshes.len(), 3);
+        assert!(matches!(
+            &scene.meshes.iter().find(|m| m.name == "cube").unwrap().source,
+            MeshSource::Obj(path) if path == Path::new("dir/cube.obj")
+        ));
+        assert_eq!(scene.materials[0].color, [1.0, 0.0, 0.0, 0.5]);
+        assert_eq!(scene.materials[0].roughness, 0.5);
+        assert_eq!(Vec3::from(scene.objects[0].scale), Vec3::ONE);
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let e = parse(r#"(objects: [(name: "a", material: "missing")])"#).unwrap_err();
+        assert!(matches!(e, SceneError::Invalid(_)), "{}", e);
+        // parent 只能是前面的物体
+        let e = parse(r#"(objects: [(name: "a", parent: "b"), (name: "b")])"#).unwrap_err();
+        assert!(matches!(e, SceneError::Invalid(_)), "{}", e);
+        let e = parse(r#"(objects: [(name: "a"), (name: "a")])"#).unwrap_err();
+        assert!(matches!(e, SceneError::Invalid(_)), "{}", e);
+    }
+
+    #[test]
+    fn syntax_errors_have_a_position() {
+        let e = parse("(\n    camera: (fov: 45),\n    lights: [Sun()],\n)").unwrap_err();
+        let SceneError::Parse { line, .. } = e else {
+            panic!("{}", e);
+        };
+        assert_eq!(line, 3);
+    }
+}
@@ -0,0 +1,14 @@
+// 材质不指定 shader 的时候用这个
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    return object_vertex(in);
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front: bool) -> @location(0) vec4<f32> {
+    let albedo = object_albedo(in.uv);
+    // 平面两面都要能看到
+    let normal = normalize(select(-in.normal, in.normal, front));
+    return vec4<f32>(tonemap(shade(albedo.rgb, in.world, normal)), albedo.a);
+}
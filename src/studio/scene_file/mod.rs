@@ -0,0 +1,955 @@
+// 从场景文件搭出来的场景，不用写 Painter 也不用重新编译，文件格式见 format.rs
+// 左键拖动转相机，滚轮缩放
+mod format;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::{Mat4, Vec2, Vec3};
+use winit::{
+    event::{ElementState, WindowEvent},
+    window::Window,
+};
+
+use super::light::{with_lighting, Light, LightSet};
+use crate::{
+    assets::{AssetSet, AssetSetState, Handle, Shader},
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    text::{font::Font, TextEffects, TextMode, TextRenderer, DEJAVU_SANS},
+    texture::{MipGeneration, SamplerOptions, TextureBundle, TextureOptions},
+    utils::{
+        camera::OrbitCamera,
+        debug_draw::{with_alpha, DebugDraw, GRAY, YELLOW},
+        globals::{with_globals, Globals},
+        models::ModelBuffers,
+        scene_graph::{transform_ui, NodeId, Renderable, SceneGraph},
+        view_modes::{MeshLayout, ViewCamera, ViewMesh, ViewModes},
+    },
+};
+use format::{CameraDesc, MaterialDesc, MeshSource, SceneDesc, SceneError, TextureSource};
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// 和 prelude.wgsl 里的 Object 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct ObjectUniform {
+    model: [[f32; 4]; 4],
+    normal: [[f32; 4]; 4],
+    color: [f32; 4],
+    params: [f32; 4],
+    ambient: [f32; 3],
+    brdf: u32,
+    roughness: f32,
+    metallic: f32,
+    shininess: f32,
+    _pad: f32,
+}
+
+/// 一个要画的物体，按节点存
+struct Draw {
+    uniform: ObjectUniform,
+    buffer: wgpu::Buffer,
+    /// 物体 uniform，group(2)
+    object_group: wgpu::BindGroup,
+    /// LoadedScene::textures 里的下标
+    texture: usize,
+    /// LoadedScene::pipelines 里的下标
+    pipeline: usize,
+}
+
+/// 读进来的场景描述和它要的资源
+struct SceneSource {
+    desc: Arc<SceneDesc>,
+    /// 和 desc.textures 一一对应，程序生成的是 None
+    textures: Vec<Option<Handle<TextureBundle>>>,
+    meshes: HashMap<String, Handle<ModelBuffers>>,
+    /// 和 desc.shaders 一一对应
+    shaders: Vec<Handle<Shader>>,
+}
+
+/// 一次成功的搭建，重新读的时候整个换掉；新的搭不起来或者 shader 编不过就接着画旧的
+struct LoadedScene {
+    source: Rc<SceneSource>,
+    /// source 里的所有资源，看它们有没有重新加载
+    assets: AssetSet,
+    graph: SceneGraph,
+    draws: HashMap<NodeId, Draw>,
+    /// 0 是 lit.wgsl，后面按 desc.shaders 的顺序
+    pipelines: Vec<wgpu::RenderPipeline>,
+    /// 0 是白色，后面按 desc.textures 的顺序
+    textures: Vec<wgpu::BindGroup>,
+    lights: LightSet,
+}
+
+pub struct SceneFileScene {
+    /// 场景文件本身，assets 改了会重新读
+    file: Handle<SceneDesc>,
+    /// 上次拿来搭场景的 file.generation()，还有上次报过的读取错误
+    generation: u64,
+    file_error: Option<String>,
+    scene: Option<LoadedScene>,
+    /// 读好了描述，还在等资源
+    pending: Option<(Rc<SceneSource>, AssetSet)>,
+    /// 最近一次加载的结果
+    status: Result<String, String>,
+    /// 点了 reload，下一帧 paint 里读
+    reload_requested: bool,
+    /// 检查器里选中的节点
+    selected: Option<NodeId>,
+    /// context.assets.stats()，每帧抄一份给 HUD
+    asset_stats: Vec<(&'static str, usize, usize)>,
+    /// 在物体上标名字
+    show_labels: bool,
+    /// 平滑过的帧率
+    fps: f32,
+    /// MSDF 模式画物体名字和帧率，带黑色描边，什么背景上都看得清
+    text: TextRenderer,
+    /// 网格、光源、坐标轴这些辅助线
+    show_gizmos: bool,
+    /// 冻结时相机的 view_proj
+    frozen_frustum: Option<Mat4>,
+    /// 打开 gizmos 画地面网格、光源位置和选中物体的坐标轴；冻结视锥以后转开相机能看到它
+    debug: DebugDraw,
+    /// 线框、法线这些调试视图，按 V 或者在 HUD 里切
+    view_modes: ViewModes,
+    globals: Globals,
+    camera: OrbitCamera,
+    object_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    depth: Option<((u32, u32), wgpu::TextureView)>,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+fn camera_from(desc: &CameraDesc) -> OrbitCamera {
+    let offset = desc.eye - desc.target;
+    let mut camera = OrbitCamera::new(desc.target, offset.length().max(0.01));
+    let dir = offset.normalize_or(glam::Vec3::Z);
+    camera.yaw = dir.x.atan2(dir.z);
+    camera.pitch = dir.y.clamp(-1.0, 1.0).asin();
+    camera.fov_y = desc.fov.to_radians();
+    camera.near = desc.near;
+    camera.far = desc.far;
+    camera
+}
+
+/// 1x1 的白色，没有贴图的材质用
+/// 光源的颜色可能超过 1，按最大的分量归一化
+fn light_color(color: Vec3) -> u32 {
+    let [r, g, b] = (color / color.max_element().max(1e-4))
+        .to_array()
+        .map(|c| (c * 255.0) as u32);
+    0xff000000 | b << 16 | g << 8 | r
+}
+
+fn white_texture(context: &crate::gfx::GfxContext) -> TextureBundle {
+    let image = image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4]));
+    TextureBundle::from_image(
+        context,
+        image.into(),
+        TextureOptions {
+            label: Some("scene white"),
+            mips: MipGeneration::None,
+            ..Default::default()
+        },
+    )
+}
+
+fn texture_options(label: &str) -> TextureOptions<'_> {
+    TextureOptions {
+        label: Some(label),
+        sampler: SamplerOptions {
+            address_mode: wgpu::AddressMode::Repeat,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn checker_texture(context: &crate::gfx::GfxContext, cells: u32) -> TextureBundle {
+    const SIZE: u32 = 256;
+    let image = image::RgbaImage::from_fn(SIZE, SIZE, |x, y| {
+        let odd = (x * cells / SIZE + y * cells / SIZE) % 2 == 1;
+        image::Rgba(if odd { [60, 60, 60, 255] } else { [230; 4] })
+    });
+    TextureBundle::from_image(context, image.into(), texture_options("scene checker"))
+}
+
+/// 编译失败返回错误信息
+fn build_pipeline(
+    context: &crate::gfx::GfxContext,
+    layout: &wgpu::PipelineLayout,
+    label: &str,
+    source: &str,
+) -> Result<wgpu::RenderPipeline, String> {
+    let source = with_globals(&with_lighting(&format!(
+        "{}\n{}",
+        include_str!("prelude.wgsl"),
+        source
+    )));
+    context
+        .device
+        .push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+    let format = context.surface_config.as_ref().unwrap().view_formats[0];
+    let pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: 8 * 4,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+    match pollster::block_on(context.device.pop_error_scope()) {
+        Some(e) => Err(e.to_string()),
+        None => Ok(pipeline),
+    }
+}
+
+impl SceneFileScene {
+    /// 照着读好的描述向 assets 要资源，不等它们读完
+    fn request(
+        context: &crate::gfx::GfxContext,
+        desc: Arc<SceneDesc>,
+    ) -> (Rc<SceneSource>, AssetSet) {
+        let assets = &context.assets;
+        let mut set = AssetSet::default();
+        let textures = desc
+            .textures
+            .iter()
+            .map(|t| match &t.source {
+                TextureSource::Path(path) => {
+                    let handle = assets.texture(path);
+                    set.add(&handle);
+                    Some(handle)
+                }
+                TextureSource::Checker(_) => None,
+            })
+            .collect();
+        let meshes = desc
+            .meshes
+            .iter()
+            .map(|m| {
+                let handle = match &m.source {
+                    MeshSource::Builtin(model) => assets.mesh(format!("builtin:{}", model.name())),
+                    MeshSource::Obj(path) => assets.mesh(path),
+                };
+                set.add(&handle);
+                (m.name.clone(), handle)
+            })
+            .collect();
+        let shaders = desc
+            .shaders
+            .iter()
+            .map(|s| {
+                let handle = assets.shader(&s.path);
+                set.add(&handle);
+                handle
+            })
+            .collect();
+        let source = SceneSource {
+            desc,
+            textures,
+            meshes,
+            shaders,
+        };
+        (Rc::new(source), set)
+    }
+
+    /// 资源都到齐以后搭场景，资源重新加载以后也走这里
+    /// object 变成 SceneGraph 的节点，有 mesh 的挂 Renderable，另外记一份物体的 uniform 和贴图；
+    /// material 的 shader 拼上 prelude.wgsl 编成 pipeline，没写就用 lit.wgsl；light 放进 LightSet
+    fn build(
+        &self,
+        context: &crate::gfx::GfxContext,
+        source: Rc<SceneSource>,
+        assets: AssetSet,
+    ) -> Result<LoadedScene, SceneError> {
+        let desc = &source.desc;
+        let resource = |what: &str, name: &str, e: &dyn std::fmt::Display| {
+            SceneError::Resource(format!("{} `{}`: {}", what, name, e))
+        };
+
+        let mut textures = vec![white_texture(context).bind_group(context, &self.texture_layout)];
+        for (t, handle) in desc.textures.iter().zip(&source.textures) {
+            let group = match (&t.source, handle) {
+                (TextureSource::Checker(cells), _) => {
+                    checker_texture(context, *cells).bind_group(context, &self.texture_layout)
+                }
+                (TextureSource::Path(_), handle) => handle
+                    .as_ref()
+                    .unwrap()
+                    .get()
+                    .unwrap()
+                    .bind_group(context, &self.texture_layout),
+            };
+            textures.push(group);
+        }
+
+        let lights = LightSet::new(context, desc.lights.iter().map(|&l| l.into()).collect());
+        let layout = context
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("scene file"),
+                bind_group_layouts: &[
+                    &self.globals.bind_group_layout,
+                    &lights.bind_group_layout,
+                    &self.object_layout,
+                    &self.texture_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let mut pipelines =
+            vec![
+                build_pipeline(context, &layout, "scene lit", include_str!("lit.wgsl"))
+                    .map_err(|e| resource("shader", "lit", &e))?,
+            ];
+        for (s, handle) in desc.shaders.iter().zip(&source.shaders) {
+            let shader = handle.get().unwrap();
+            pipelines.push(
+                build_pipeline(context, &layout, &s.name, &shader.source)
+                    .map_err(|e| resource("shader", &s.name, &e))?,
+            );
+        }
+
+        let default_material = MaterialDesc::new("default".into());
+        let mut graph = SceneGraph::new();
+        let mut draws = HashMap::new();
+        for o in &desc.objects {
+            // 格式里检查过 parent 已经声明了
+            let parent = o.parent.as_deref().and_then(|p| graph.find(p));
+            let node = graph.add(o.name.clone(), o.transform(), parent);
+            let Some(mesh) = &o.mesh else {
+                continue;
+            };
+            graph.set_renderable(
+                node,
+                Some(Renderable {
+                    model: source.meshes[mesh].get().unwrap(),
+                }),
+            );
+            let material = o
+                .material
+                .as_ref()
+                .and_then(|name| desc.materials.iter().find(|m| &m.name == name))
+                .unwrap_or(&default_material);
+            let uniform = ObjectUniform {
+                color: material.color,
+                params: o.params,
+                ambient: desc.ambient.to_array(),
+                brdf: material.brdf as u32,
+                roughness: material.roughness,
+                metallic: material.metallic,
+                shininess: material.shininess,
+                ..ObjectUniform::zeroed()
+            };
+            let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&o.name),
+                size: std::mem::size_of::<ObjectUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let object_group = context
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some(&o.name),
+                    layout: &self.object_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+            draws.insert(
+                node,
+                Draw {
+                    uniform,
+                    buffer,
+                    object_group,
+                    texture: material.texture.as_ref().map_or(0, |name| {
+                        desc.textures.iter().position(|t| &t.name == name).unwrap() + 1
+                    }),
+                    pipeline: material.shader.as_ref().map_or(0, |name| {
+                        desc.shaders.iter().position(|s| &s.name == name).unwrap() + 1
+                    }),
+                },
+            );
+        }
+
+        Ok(LoadedScene {
+            source,
+            assets,
+            graph,
+            draws,
+            pipelines,
+            textures,
+            lights,
+        })
+    }
+
+    /// assets 读好了新的场景文件或者点了 reload：照最新的描述重新要资源，在后台读
+    /// 场景文件读坏了就报错，接着画旧的
+    fn sync_file(&mut self, context: &crate::gfx::GfxContext) {
+        let generation = self.file.generation();
+        if generation != self.generation || std::mem::take(&mut self.reload_requested) {
+            self.generation = generation;
+            if let Some(desc) = self.file.get() {
+                self.pending = Some(Self::request(context, desc));
+            }
+        }
+        let error = self.file.error();
+        if error != self.file_error {
+            if let Some(e) = &error {
+                self.status = Err(e.clone());
+            }
+            self.file_error = error;
+        }
+    }
+
+    /// 等的资源到齐了就搭起来，换掉旧的场景
+    fn finish_pending(&mut self, context: &crate::gfx::GfxContext) {
+        let Some((source, assets)) = &self.pending else {
+            return;
+        };
+        match assets.state() {
+            AssetSetState::Loading(ready, total) => {
+                self.status = Ok(format!("loading assets {}/{}", ready, total));
+                return;
+            }
+            AssetSetState::Failed(e) => {
+                self.status = Err(e);
+                self.pending = None;
+                return;
+            }
+            AssetSetState::Ready => {}
+        }
+        let (source, mut assets) = (source.clone(), assets.clone());
+        self.pending = None;
+        // 记下现在的 generation，之后变了才算重新加载
+        assets.changed();
+        match self.build(context, source, assets) {
+            Ok(scene) => {
+                let same_camera = self
+                    .scene
+                    .as_ref()
+                    .is_some_and(|old| old.source.desc.camera == scene.source.desc.camera);
+                // 文件里的相机没变就不打断用户拖好的视角
+                if !same_camera {
+                    self.camera = camera_from(&scene.source.desc.camera);
+                }
+                self.status = Ok(format!(
+                    "loaded {} ({} objects)",
+                    self.file.path().display(),
+                    scene.source.desc.objects.len()
+                ));
+                self.selected = None;
+                self.scene = Some(scene);
+            }
+            Err(e) => self.status = Err(e.to_string()),
+        }
+    }
+
+    /// assets 重新加载了场景用到的文件，用同一份描述重新搭
+    fn rebuild_changed(&mut self, context: &crate::gfx::GfxContext) {
+        let Some(scene) = &mut self.scene else {
+            return;
+        };
+        if !scene.assets.changed() {
+            return;
+        }
+        let (source, assets) = (scene.source.clone(), scene.assets.clone());
+        match self.build(context, source, assets) {
+            Ok(scene) => {
+                // 节点按同样的顺序加的，选中的还是同一个
+                self.status = Ok(format!("reloaded assets of {}", self.file.path().display()));
+                self.scene = Some(scene);
+            }
+            Err(e) => self.status = Err(e.to_string()),
+        }
+    }
+
+    /// 窗口大小变了重建深度贴图
+    fn update_depth(&mut self, context: &crate::gfx::GfxContext) {
+        let config = context.surface_config.as_ref().unwrap();
+        let size = (config.width, config.height);
+        if self.depth.as_ref().map(|(s, _)| *s) != Some(size) {
+            let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("scene file depth"),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.depth = Some((size, view));
+        }
+    }
+
+    fn hierarchy_ui(&mut self, ui: &mut egui::Ui, node: NodeId) {
+        let Some(scene) = &self.scene else {
+            return;
+        };
+        let graph = &scene.graph;
+        if ui
+            .selectable_label(self.selected == Some(node), graph.name(node))
+            .clicked()
+        {
+            self.selected = Some(node);
+        }
+        let children = graph.children(node).to_vec();
+        ui.indent(node.index(), |ui| {
+            for child in children {
+                self.hierarchy_ui(ui, child);
+            }
+        });
+    }
+
+    /// 左下角的帧率，物体上的名字，选中的那个标黄
+    fn queue_text(&mut self, context: &crate::gfx::GfxContext, dt: f32) {
+        // 平滑一下，不然数字一直在跳
+        self.fps += (1.0 / dt.max(1e-4) - self.fps) * 0.05;
+        let config = context.surface_config.as_ref().unwrap();
+        let fps = format!("{:.0} fps", self.fps);
+        let extent = self.text.measure(&fps, 18.0);
+        self.text.screen(
+            Vec2::new(12.0, config.height as f32 - extent.y - 8.0),
+            18.0,
+            0xffffffff,
+            &fps,
+        );
+        if let Some(scene) = self.scene.as_ref().filter(|_| self.show_labels) {
+            for (node, world, _) in scene.graph.renderables() {
+                let color = if self.selected == Some(node) {
+                    0xff40e0ff
+                } else {
+                    0xffe0e0e0
+                };
+                let name = scene.graph.name(node);
+                self.text.world(world.w_axis.truncate(), 14.0, color, name);
+            }
+        }
+        let view_proj = Mat4::from_cols_array_2d(&self.globals.uniform.view_proj);
+        self.text.prepare(context, view_proj, self.camera.view());
+    }
+
+    /// 网格和冻结的视锥会被物体挡住，光源和选中物体的坐标轴总在最上面
+    fn queue_debug(&mut self, context: &crate::gfx::GfxContext) {
+        let view_proj = Mat4::from_cols_array_2d(&self.globals.uniform.view_proj);
+        let debug = &mut self.debug;
+        if let Some(frozen) = self.frozen_frustum {
+            debug.frustum(frozen, YELLOW);
+        }
+        if let Some(scene) = self.scene.as_ref().filter(|_| self.show_gizmos) {
+            debug.depth_test = true;
+            debug.grid(Vec3::ZERO, 20, 1.0, with_alpha(GRAY, 0.5));
+            debug.depth_test = false;
+            for light in &scene.lights.lights {
+                match *light {
+                    // 平行光没有位置，在原点上方画一根箭头表示方向
+                    Light::Directional(l) => {
+                        let direction = l.direction.normalize();
+                        let to = Vec3::Y * 2.0;
+                        debug.arrow(to - direction, to, light_color(l.color));
+                    }
+                    Light::Point(l) => {
+                        debug.cross(l.position, 0.2, light_color(l.color));
+                        debug.sphere(l.position, l.range, with_alpha(light_color(l.color), 0.3));
+                    }
+                    Light::Spot(l) => {
+                        let color = light_color(l.color);
+                        let direction = l.direction.normalize();
+                        let center = l.position + direction * l.range;
+                        let radius = l.range * l.outer_angle.tan();
+                        debug.cross(l.position, 0.2, color);
+                        debug.circle(center, direction, radius, with_alpha(color, 0.5));
+                        let (u, v) = direction.any_orthonormal_pair();
+                        for side in [u, -u, v, -v] {
+                            debug.line(l.position, center + side * radius, with_alpha(color, 0.5));
+                        }
+                    }
+                }
+            }
+            if let Some(node) = self.selected {
+                debug.axes(scene.graph.world(node), 1.0);
+            }
+            debug.depth_test = true;
+        }
+        debug.prepare(context, view_proj);
+    }
+
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Scene file")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .vscroll(true)
+            .show(ui, |ui| {
+                match &self.status {
+                    Ok(status) => ui.label(status),
+                    Err(error) => ui.colored_label(egui::Color32::RED, error),
+                };
+                ui.horizontal(|ui| {
+                    if ui.button("reload").clicked() {
+                        self.reload_requested = true;
+                    }
+                    if ui.button("reset camera").clicked() {
+                        if let Some(scene) = &self.scene {
+                            self.camera = camera_from(&scene.source.desc.camera);
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.show_labels, "labels");
+                    ui.label(format!("{} glyphs cached", self.text.cached_glyphs()));
+                    if self.text.atlas_full() {
+                        ui.colored_label(egui::Color32::YELLOW, "atlas full");
+                    }
+                });
+                self.view_modes.ui(ui);
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.show_gizmos, "gizmos");
+                    let frozen = self.frozen_frustum.is_some();
+                    if ui
+                        .button(if frozen {
+                            "unfreeze frustum"
+                        } else {
+                            "freeze frustum"
+                        })
+                        .clicked()
+                    {
+                        self.frozen_frustum = match frozen {
+                            true => None,
+                            false => {
+                                Some(Mat4::from_cols_array_2d(&self.globals.uniform.view_proj))
+                            }
+                        };
+                    }
+                    ui.label(format!("{} lines", self.debug.line_count()));
+                });
+                for (kind, alive, loading) in &self.asset_stats {
+                    ui.label(format!("{}: {} ({} loading)", kind, alive, loading));
+                }
+                let Some(scene) = &self.scene else {
+                    return;
+                };
+                ui.label(format!(
+                    "{} lights, {} pipelines",
+                    scene.source.desc.lights.len(),
+                    scene.pipelines.len()
+                ));
+                // 重新加载失败的资源，还在用旧的
+                for error in scene.assets.errors() {
+                    ui.colored_label(egui::Color32::YELLOW, error);
+                }
+                ui.separator();
+                let roots = scene.graph.roots().to_vec();
+                for root in roots {
+                    self.hierarchy_ui(ui, root);
+                }
+                if let (Some(node), Some(scene)) = (self.selected, &mut self.scene) {
+                    ui.separator();
+                    ui.label(scene.graph.name(node).to_string());
+                    // 只改内存里的，重新读文件就没了
+                    let mut local = *scene.graph.local(node);
+                    if transform_ui(ui, &mut local) {
+                        scene.graph.set_local(node, local);
+                    }
+                }
+            });
+    }
+}
+
+impl Sandy for SceneFileScene {
+    /// 最后一个是场景文件的路径
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>, &'static str);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui, path): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let object_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("scene object"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<ObjectUniform>() as u64,
+                            ),
+                        },
+                        count: None,
+                    }],
+                });
+        let mut text = TextRenderer::new(
+            context,
+            context.surface_config.as_ref().unwrap().format,
+            Font::load(DEJAVU_SANS).unwrap(),
+            TextMode::Msdf,
+        );
+        text.effects = TextEffects {
+            outline_width: 0.1,
+            ..Default::default()
+        };
+        let debug = DebugDraw::new(
+            context,
+            context.surface_config.as_ref().unwrap().view_formats[0],
+            Some(DEPTH_FORMAT),
+        );
+        // 和 ModelBuffers 的 Asset 实现一样：位置、法线、uv，u32 索引
+        let view_modes = ViewModes::new(
+            context,
+            context.surface_config.as_ref().unwrap().view_formats[0],
+            MeshLayout {
+                stride: 8,
+                normal: Some(3),
+                uv: Some(6),
+                index_format: wgpu::IndexFormat::Uint32,
+            },
+        );
+        let file = context.assets.load(path);
+        SceneFileScene {
+            status: Ok(format!("loading {}", file.path().display())),
+            file,
+            generation: 0,
+            file_error: None,
+            scene: None,
+            pending: None,
+            asset_stats: Vec::new(),
+            show_labels: true,
+            fps: 60.0,
+            text,
+            show_gizmos: true,
+            frozen_frustum: None,
+            debug,
+            view_modes,
+            reload_requested: false,
+            selected: None,
+            globals: Globals::ready(context, ()),
+            camera: camera_from(&CameraDesc::default()),
+            object_layout,
+            texture_layout: TextureBundle::bind_group_layout(context),
+            depth: None,
+            window,
+            egui,
+        }
+    }
+}
+
+impl Painter for SceneFileScene {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        self.sync_file(context);
+        self.finish_pending(context);
+        self.rebuild_changed(context);
+        let mode = self.view_modes.sync(context);
+        self.asset_stats = context.assets.stats();
+        self.globals.update(context, &self.camera, dt, time);
+        if let Some(scene) = &mut self.scene {
+            scene.graph.update();
+            for (node, world, _) in scene.graph.renderables() {
+                let draw = scene.draws.get_mut(&node).unwrap();
+                draw.uniform.model = world.to_cols_array_2d();
+                draw.uniform.normal = world.inverse().transpose().to_cols_array_2d();
+                context
+                    .queue
+                    .write_buffer(&draw.buffer, 0, bytemuck::bytes_of(&draw.uniform));
+            }
+        }
+        self.queue_text(context, dt);
+        self.queue_debug(context);
+
+        // 3D 的东西（场景、调试视图、调试线）画到 sRGB 的 view 上，pipeline 都按 view_formats[0] 建；
+        // 字和 egui 叠在 surface 自己格式的 view 上
+        let config = context.surface_config.as_ref().unwrap();
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(config.view_formats[0]),
+            ..Default::default()
+        });
+        let overlay_view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("scene file"),
+            });
+        let clear = self
+            .scene
+            .as_ref()
+            .map_or([0.0; 3], |scene| scene.source.desc.clear.to_array());
+        self.update_depth(context);
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("scene file"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: clear[0] as f64,
+                            g: clear[1] as f64,
+                            b: clear[2] as f64,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.as_ref().unwrap().1,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        // 调试线还要拿它做深度测试
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: context.render_timestamps("scene file"),
+                occlusion_query_set: None,
+            });
+            if let Some(scene) = self.scene.as_ref().filter(|_| !mode.replaces_scene()) {
+                rpass.set_bind_group(0, &self.globals.bind_group, &[]);
+                rpass.set_bind_group(1, &scene.lights.bind_group, &[]);
+                for (node, _, renderable) in scene.graph.renderables() {
+                    let draw = &scene.draws[&node];
+                    let model = &renderable.model;
+                    rpass.set_pipeline(&scene.pipelines[draw.pipeline]);
+                    rpass.set_bind_group(2, &draw.object_group, &[]);
+                    rpass.set_bind_group(3, &scene.textures[draw.texture], &[]);
+                    rpass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                    rpass.set_index_buffer(model.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    rpass.draw_indexed(0..model.index_count, 0, 0..1);
+                }
+            }
+        }
+        if let Some(scene) = &self.scene {
+            let meshes: Vec<ViewMesh> = scene
+                .graph
+                .renderables()
+                .map(|(_, world, renderable)| ViewMesh {
+                    vertices: &renderable.model.vertex_buffer,
+                    indices: &renderable.model.index_buffer,
+                    index_count: renderable.model.index_count,
+                    model: world,
+                    viewport: None,
+                })
+                .collect();
+            let camera = ViewCamera {
+                view_proj: Mat4::from_cols_array_2d(&self.globals.uniform.view_proj),
+                near: self.camera.near,
+                far: self.camera.far,
+            };
+            self.view_modes
+                .render(context, &mut encoder, &view, &camera, &meshes);
+        }
+        self.debug
+            .render(&mut encoder, &view, Some(&self.depth.as_ref().unwrap().1));
+        {
+            // 字不做深度测试，单独一个 pass 叠在上面
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("scene file text"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &overlay_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: context.render_timestamps("scene file text"),
+                occlusion_query_set: None,
+            });
+            self.text.draw(&mut rpass);
+        }
+
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &overlay_view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        // 在 egui 窗口上按下和滚动不动相机，松开总要传过去
+        let starts = matches!(
+            event,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            } | WindowEvent::MouseWheel { .. }
+        );
+        if starts && self.egui.lock().unwrap().context.is_pointer_over_area() {
+            return;
+        }
+        self.camera.input(event);
+    }
+
+    fn counters(&self) -> Vec<(&'static str, f64)> {
+        let objects = self.scene.as_ref().map_or(0, |scene| scene.draws.len());
+        vec![("objects", objects as f64)]
+    }
+}
@@ -0,0 +1,83 @@
+// 场景文件里的 shader 前面都会拼上 globals.wgsl、lights.wgsl、brdf.wgsl 和这个文件
+// group(0) 是 Globals，group(1) 光源，group(2) 物体，group(3) 材质的贴图
+// shader 自己写 vs_main 和 fs_main，不想改顶点就直接 return object_vertex(in)
+
+// 和 scene_file/mod.rs 里的 ObjectUniform 对应
+struct Object {
+    model: mat4x4<f32>,
+    // model 的逆转置，变换法线用
+    normal: mat4x4<f32>,
+    // 材质颜色
+    color: vec4<f32>,
+    // 场景文件里 object 的 params，随便用
+    params: vec4<f32>,
+    ambient: vec3<f32>,
+    brdf: u32,
+    roughness: f32,
+    metallic: f32,
+    shininess: f32,
+    _pad: f32,
+};
+
+@group(1)
+@binding(0)
+var<storage, read> lights: LightArray;
+
+@group(2)
+@binding(0)
+var<uniform> object: Object;
+
+@group(3)
+@binding(0)
+var material_texture: texture_2d<f32>;
+@group(3)
+@binding(1)
+var material_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+fn object_vertex(in: VertexInput) -> VertexOutput {
+    let world = object.model * vec4<f32>(in.position, 1.0);
+    let normal = (object.normal * vec4<f32>(in.normal, 0.0)).xyz;
+    return VertexOutput(globals.view_proj * world, world.xyz, normal, in.uv);
+}
+
+// 材质颜色乘贴图
+fn object_albedo(uv: vec2<f32>) -> vec4<f32> {
+    return object.color * textureSample(material_texture, material_sampler, uv);
+}
+
+// 环境光加上所有光源，用物体材质里的 BRDF 参数
+fn shade(albedo: vec3<f32>, world: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let surface = Surface(
+        albedo,
+        object.roughness,
+        object.metallic,
+        object.shininess,
+        normal,
+        normalize(globals.camera_position - world),
+    );
+    var color = object.ambient * albedo;
+    for (var i = 0u; i < lights.count; i++) {
+        let sample = sample_light(lights.items[i], world);
+        let n_dot_l = max(dot(normal, sample.direction), 0.0);
+        color += brdf(object.brdf, surface, sample.direction) * sample.radiance * n_dot_l;
+    }
+    return color;
+}
+
+// Reinhard
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    return color / (color + vec3<f32>(1.0));
+}
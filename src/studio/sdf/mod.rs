@@ -0,0 +1,392 @@
+// SDF 光线步进
+// 全屏三角形，片元里沿着相机射线步进 map 函数
+// map 由 scene.rs 里的场景描述生成，egui 里改了就重新拼 shader、重建 pipeline
+// 软阴影、AO、步数之类的参数放 uniform，不用重新编译
+// 左键拖动转相机，滚轮缩放
+mod scene;
+
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
+
+use bytemuck::{Pod, Zeroable};
+use egui::Align2;
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, WindowEvent},
+    window::Window,
+};
+
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    utils::{
+        camera::OrbitCamera,
+        globals::{with_globals, Globals},
+    },
+};
+use scene::{Op, Primitive, SdfScene, Shape};
+
+/// 和 raymarch.wgsl 里的 Params 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Params {
+    sun_direction: [f32; 3],
+    shadow_k: f32,
+    ao_strength: f32,
+    max_steps: u32,
+    max_distance: f32,
+    show_steps: u32,
+}
+
+pub struct SdfRaymarch {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    globals: Globals,
+    camera: OrbitCamera,
+    params: Params,
+    params_buffer: wgpu::Buffer,
+    params_group: wgpu::BindGroup,
+    scene: SdfScene,
+    /// 当前 pipeline 是从这个场景编出来的
+    compiled: SdfScene,
+    /// 最近一次编译的错误
+    error: Option<String>,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+/// 编译失败返回错误信息，调用方继续用旧的 pipeline
+fn build_pipeline(
+    context: &crate::gfx::GfxContext,
+    layout: &wgpu::PipelineLayout,
+    scene: &SdfScene,
+) -> Result<wgpu::RenderPipeline, String> {
+    let source = with_globals(&format!(
+        "{}\n{}",
+        include_str!("raymarch.wgsl"),
+        scene.to_wgsl()
+    ));
+    context
+        .device
+        .push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("sdf raymarch"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+    let format = context.surface_config.as_ref().unwrap().view_formats[0];
+    let pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("sdf raymarch"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+    match pollster::block_on(context.device.pop_error_scope()) {
+        Some(e) => Err(e.to_string()),
+        None => Ok(pipeline),
+    }
+}
+
+impl Sandy for SdfRaymarch {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let globals = Globals::ready(context, ());
+        let mut camera = OrbitCamera::new(Vec3::new(0.0, 0.0, 0.0), 6.0);
+        camera.far = 200.0;
+        let params = Params {
+            sun_direction: [0.6, 0.8, 0.4],
+            shadow_k: 12.0,
+            ao_strength: 3.0,
+            max_steps: 128,
+            max_distance: 60.0,
+            show_steps: 0,
+        };
+        let params_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("sdf params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let params_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("sdf params"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        let params_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sdf params"),
+                layout: &params_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                }],
+            });
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("sdf raymarch"),
+                    bind_group_layouts: &[&globals.bind_group_layout, &params_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let scene = SdfScene::default();
+        let pipeline = build_pipeline(context, &pipeline_layout, &scene).unwrap();
+        SdfRaymarch {
+            pipeline,
+            pipeline_layout,
+            globals,
+            camera,
+            params,
+            params_buffer,
+            params_group,
+            compiled: scene.clone(),
+            scene,
+            error: None,
+            window,
+            egui,
+        }
+    }
+}
+
+fn vec3_editor(ui: &mut egui::Ui, label: &str, v: &mut Vec3, speed: f32) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(&mut v.x).speed(speed));
+        ui.add(egui::DragValue::new(&mut v.y).speed(speed));
+        ui.add(egui::DragValue::new(&mut v.z).speed(speed));
+    });
+}
+
+fn primitive_editor(ui: &mut egui::Ui, i: usize, prim: &mut Primitive) {
+    let mut shape_index = prim.shape.index();
+    egui::ComboBox::from_id_salt(("shape", i))
+        .selected_text(prim.shape.name())
+        .show_ui(ui, |ui| {
+            for (j, name) in Shape::NAMES.iter().enumerate() {
+                ui.selectable_value(&mut shape_index, j, *name);
+            }
+        });
+    if shape_index != prim.shape.index() {
+        prim.shape = Shape::from_index(shape_index);
+    }
+    match &mut prim.shape {
+        Shape::Sphere { radius } => {
+            ui.add(egui::Slider::new(radius, 0.05..=3.0).text("radius"));
+        }
+        Shape::Box { size, rounding } => {
+            vec3_editor(ui, "half size", size, 0.01);
+            ui.add(egui::Slider::new(rounding, 0.0..=0.5).text("rounding"));
+        }
+        Shape::Torus { major, minor } => {
+            ui.add(egui::Slider::new(major, 0.05..=3.0).text("major"));
+            ui.add(egui::Slider::new(minor, 0.01..=1.0).text("minor"));
+        }
+        Shape::Capsule { height, radius } | Shape::Cylinder { height, radius } => {
+            ui.add(egui::Slider::new(height, 0.0..=4.0).text("height"));
+            ui.add(egui::Slider::new(radius, 0.05..=2.0).text("radius"));
+        }
+    }
+    // 第一个没有东西可以合并
+    ui.add_enabled_ui(i > 0, |ui| {
+        egui::ComboBox::from_id_salt(("op", i))
+            .selected_text(prim.op.name())
+            .show_ui(ui, |ui| {
+                for op in Op::ALL {
+                    ui.selectable_value(&mut prim.op, op, op.name());
+                }
+            });
+        if prim.op == Op::SmoothUnion {
+            ui.add(egui::Slider::new(&mut prim.smoothness, 0.01..=1.0).text("smoothness"));
+        }
+    });
+    vec3_editor(ui, "position", &mut prim.position, 0.02);
+    vec3_editor(ui, "rotation", &mut prim.rotation, 1.0);
+    ui.horizontal(|ui| {
+        ui.color_edit_button_rgb(&mut prim.color);
+        ui.checkbox(&mut prim.bob, "bob");
+    });
+}
+
+impl SdfRaymarch {
+    fn editor(&mut self, ui: &egui::Context) {
+        egui::Window::new("SDF")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .vscroll(true)
+            .show(ui, |ui| {
+                let params = &mut self.params;
+                ui.add(egui::Slider::new(&mut params.shadow_k, 1.0..=64.0).text("shadow hardness"));
+                ui.add(egui::Slider::new(&mut params.ao_strength, 0.0..=8.0).text("AO"));
+                ui.add(egui::Slider::new(&mut params.max_steps, 16..=512).text("max steps"));
+                ui.add(
+                    egui::Slider::new(&mut params.max_distance, 5.0..=200.0).text("max distance"),
+                );
+                let mut sun = Vec3::from(params.sun_direction);
+                vec3_editor(ui, "sun", &mut sun, 0.01);
+                params.sun_direction = sun.to_array();
+                let mut show_steps = params.show_steps != 0;
+                ui.checkbox(&mut show_steps, "show step count");
+                params.show_steps = show_steps as u32;
+                ui.checkbox(&mut self.scene.ground, "ground");
+                ui.separator();
+
+                let mut removed = None;
+                let mut moved_up = None;
+                for (i, prim) in self.scene.primitives.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.collapsing(format!("{} {}", i, prim.shape.name()), |ui| {
+                            primitive_editor(ui, i, prim);
+                            ui.horizontal(|ui| {
+                                if ui.button("up").clicked() && i > 0 {
+                                    moved_up = Some(i);
+                                }
+                                if ui.button("remove").clicked() {
+                                    removed = Some(i);
+                                }
+                            });
+                        });
+                    });
+                }
+                if let Some(i) = moved_up {
+                    self.scene.primitives.swap(i - 1, i);
+                }
+                if let Some(i) = removed {
+                    self.scene.primitives.remove(i);
+                }
+                if ui.button("add primitive").clicked() {
+                    self.scene.primitives.push(Primitive::new(
+                        Shape::from_index(0),
+                        Vec3::new(0.0, 1.5, 0.0),
+                        [0.8, 0.8, 0.8],
+                    ));
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+    }
+}
+
+impl Painter for SdfRaymarch {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        // 场景描述变了就重新生成 shader
+        if self.scene != self.compiled {
+            match build_pipeline(context, &self.pipeline_layout, &self.scene) {
+                Ok(pipeline) => {
+                    self.pipeline = pipeline;
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+            self.compiled = self.scene.clone();
+        }
+        self.globals.update(context, &self.camera, dt, time);
+        context
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("sdf raymarch"),
+            });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("sdf raymarch"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: context.render_timestamps("sdf raymarch"),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.globals.bind_group, &[]);
+            rpass.set_bind_group(1, &self.params_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        let config = context.surface_config.as_ref().unwrap();
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.editor(ui),
+        );
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        // 在 egui 窗口上按下和滚动不动相机，松开总要传过去
+        let starts = matches!(
+            event,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            } | WindowEvent::MouseWheel { .. }
+        );
+        if starts && self.egui.lock().unwrap().context.is_pointer_over_area() {
+            return;
+        }
+        self.camera.input(event);
+    }
+}
@@ -0,0 +1,166 @@
+// SDF 光线步进，前面拼上 utils/globals.wgsl，后面拼上 scene.rs 生成的 map 函数
+
+struct Params {
+    // 指向太阳
+    sun_direction: vec3<f32>,
+    // 软阴影的硬度，越大越硬
+    shadow_k: f32,
+    ao_strength: f32,
+    max_steps: u32,
+    max_distance: f32,
+    // 调试：按步数上色
+    show_steps: u32,
+};
+
+@group(1)
+@binding(0)
+var<uniform> params: Params;
+
+// 基本形状，p 都在物体自己的坐标系里
+
+fn sd_sphere(p: vec3<f32>, r: f32) -> f32 {
+    return length(p) - r;
+}
+
+fn sd_box(p: vec3<f32>, b: vec3<f32>, rounding: f32) -> f32 {
+    let q = abs(p) - b + rounding;
+    return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0) - rounding;
+}
+
+fn sd_torus(p: vec3<f32>, major: f32, minor: f32) -> f32 {
+    let q = vec2<f32>(length(p.xz) - major, p.y);
+    return length(q) - minor;
+}
+
+fn sd_capsule(p: vec3<f32>, height: f32, r: f32) -> f32 {
+    let h = height * 0.5;
+    let q = vec3<f32>(p.x, p.y - clamp(p.y, -h, h), p.z);
+    return length(q) - r;
+}
+
+fn sd_cylinder(p: vec3<f32>, height: f32, r: f32) -> f32 {
+    let d = abs(vec2<f32>(length(p.xz), p.y)) - vec2<f32>(r, height * 0.5);
+    return min(max(d.x, d.y), 0.0) + length(max(d, vec2<f32>(0.0)));
+}
+
+// 布尔运算，x 是距离，yzw 是颜色
+
+fn op_union(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    return select(b, a, a.x < b.x);
+}
+
+fn op_subtract(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    // 挖出来的面用被挖的那个的颜色
+    return select(a, vec4<f32>(-b.x, a.yzw), -b.x > a.x);
+}
+
+fn op_intersect(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    return select(b, a, a.x > b.x);
+}
+
+fn op_smooth_union(a: vec4<f32>, b: vec4<f32>, k: f32) -> vec4<f32> {
+    let h = clamp(0.5 + 0.5 * (b.x - a.x) / k, 0.0, 1.0);
+    let d = mix(b.x, a.x, h) - k * h * (1.0 - h);
+    return vec4<f32>(d, mix(b.yzw, a.yzw, h));
+}
+
+// 地面的棋盘格
+fn ground_color(p: vec3<f32>) -> vec3<f32> {
+    let c = (i32(floor(p.x)) + i32(floor(p.z))) & 1;
+    return select(vec3<f32>(0.75), vec3<f32>(0.55), c == 1);
+}
+
+struct March {
+    t: f32,
+    steps: u32,
+    hit: bool,
+};
+
+fn march(ro: vec3<f32>, rd: vec3<f32>) -> March {
+    var t = 0.0;
+    for (var i = 0u; i < params.max_steps; i++) {
+        let d = map(ro + rd * t).x;
+        if d < 0.0005 * t {
+            return March(t, i, true);
+        }
+        t += d;
+        if t > params.max_distance {
+            return March(t, i, false);
+        }
+    }
+    return March(t, params.max_steps, false);
+}
+
+fn normal_at(p: vec3<f32>) -> vec3<f32> {
+    // 四面体上取四个点求梯度
+    let e = vec2<f32>(1.0, -1.0) * 0.0005;
+    return normalize(
+        e.xyy * map(p + e.xyy).x + e.yyx * map(p + e.yyx).x + e.yxy * map(p + e.yxy).x + e.xxx * map(p + e.xxx).x
+    );
+}
+
+// 沿着光的方向走，记下离表面最近的比例，得到半影
+fn soft_shadow(ro: vec3<f32>, rd: vec3<f32>) -> f32 {
+    var result = 1.0;
+    var t = 0.02;
+    for (var i = 0; i < 64; i++) {
+        let h = map(ro + rd * t).x;
+        result = min(result, params.shadow_k * h / t);
+        t += clamp(h, 0.01, 0.5);
+        if result < 0.001 || t > 20.0 {
+            break;
+        }
+    }
+    return clamp(result, 0.0, 1.0);
+}
+
+// 沿法线取几个点，离表面越近说明被挡得越多
+fn ambient_occlusion(p: vec3<f32>, n: vec3<f32>) -> f32 {
+    var occlusion = 0.0;
+    var weight = 1.0;
+    for (var i = 1; i <= 5; i++) {
+        let h = 0.03 * f32(i * i);
+        occlusion += (h - map(p + n * h).x) * weight;
+        weight *= 0.7;
+    }
+    return clamp(1.0 - params.ao_strength * occlusion, 0.0, 1.0);
+}
+
+fn sky(rd: vec3<f32>) -> vec3<f32> {
+    return mix(vec3<f32>(0.8, 0.85, 0.9), vec3<f32>(0.35, 0.5, 0.8), clamp(rd.y * 2.0, 0.0, 1.0));
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // 一个盖住屏幕的大三角形
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / globals.resolution;
+    let ro = globals.camera_position;
+    let rd = camera_ray(uv);
+    let m = march(ro, rd);
+    if params.show_steps != 0u {
+        let heat = f32(m.steps) / f32(params.max_steps);
+        return vec4<f32>(heat, 1.0 - abs(heat * 2.0 - 1.0), 1.0 - heat, 1.0);
+    }
+    if !m.hit {
+        return vec4<f32>(sky(rd), 1.0);
+    }
+
+    let p = ro + rd * m.t;
+    let n = normal_at(p);
+    let albedo = map(p).yzw;
+    let l = normalize(params.sun_direction);
+    let diffuse = max(dot(n, l), 0.0) * soft_shadow(p + n * 0.002, l);
+    let ao = ambient_occlusion(p, n);
+    let h = normalize(l - rd);
+    let specular = pow(max(dot(n, h), 0.0), 32.0) * diffuse * 0.3;
+    var color = albedo * (diffuse * vec3<f32>(1.0, 0.95, 0.85) + ao * 0.3 * sky(n)) + specular;
+    // 远处淡到天空色
+    color = mix(color, sky(rd), 1.0 - exp(-0.0015 * m.t * m.t));
+    return vec4<f32>(color, 1.0);
+}
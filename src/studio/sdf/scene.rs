@@ -0,0 +1,270 @@
+// SDF 场景的描述，编辑器改的是这里，再生成 WGSL 里的 map 函数
+use std::fmt::Write as _;
+
+use glam::{EulerRot, Mat3, Vec3};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        size: Vec3,
+        rounding: f32,
+    },
+    Torus {
+        major: f32,
+        minor: f32,
+    },
+    /// 竖着的胶囊
+    Capsule {
+        height: f32,
+        radius: f32,
+    },
+    Cylinder {
+        height: f32,
+        radius: f32,
+    },
+}
+
+impl Shape {
+    pub const NAMES: [&'static str; 5] = ["sphere", "box", "torus", "capsule", "cylinder"];
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[self.index()]
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Shape::Sphere { .. } => 0,
+            Shape::Box { .. } => 1,
+            Shape::Torus { .. } => 2,
+            Shape::Capsule { .. } => 3,
+            Shape::Cylinder { .. } => 4,
+        }
+    }
+
+    /// 按下标给一个默认大小的形状，编辑器切换类型用
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            0 => Shape::Sphere { radius: 0.8 },
+            1 => Shape::Box {
+                size: Vec3::splat(0.6),
+                rounding: 0.05,
+            },
+            2 => Shape::Torus {
+                major: 0.8,
+                minor: 0.25,
+            },
+            3 => Shape::Capsule {
+                height: 1.0,
+                radius: 0.3,
+            },
+            _ => Shape::Cylinder {
+                height: 0.8,
+                radius: 0.4,
+            },
+        }
+    }
+
+    /// 生成的 WGSL 表达式，q 是物体自己坐标系里的点
+    fn wgsl(&self, q: &str) -> String {
+        match *self {
+            Shape::Sphere { radius } => format!("sd_sphere({}, {})", q, f(radius)),
+            Shape::Box { size, rounding } => {
+                format!("sd_box({}, {}, {})", q, vec3(size), f(rounding))
+            }
+            Shape::Torus { major, minor } => {
+                format!("sd_torus({}, {}, {})", q, f(major), f(minor))
+            }
+            Shape::Capsule { height, radius } => {
+                format!("sd_capsule({}, {}, {})", q, f(height), f(radius))
+            }
+            Shape::Cylinder { height, radius } => {
+                format!("sd_cylinder({}, {}, {})", q, f(height), f(radius))
+            }
+        }
+    }
+}
+
+/// 和前面所有物体合起来的方式
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Op {
+    Union,
+    Subtract,
+    Intersect,
+    SmoothUnion,
+}
+
+impl Op {
+    pub const ALL: [Op; 4] = [Op::Union, Op::Subtract, Op::Intersect, Op::SmoothUnion];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Union => "union",
+            Op::Subtract => "subtract",
+            Op::Intersect => "intersect",
+            Op::SmoothUnion => "smooth union",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Primitive {
+    pub shape: Shape,
+    pub op: Op,
+    /// SmoothUnion 的混合半径
+    pub smoothness: f32,
+    pub position: Vec3,
+    /// 角度，XYZ 顺序
+    pub rotation: Vec3,
+    pub color: [f32; 3],
+    /// 随时间上下浮动
+    pub bob: bool,
+}
+
+impl Primitive {
+    pub fn new(shape: Shape, position: Vec3, color: [f32; 3]) -> Self {
+        Primitive {
+            shape,
+            op: Op::Union,
+            smoothness: 0.3,
+            position,
+            rotation: Vec3::ZERO,
+            color,
+            bob: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SdfScene {
+    /// 按顺序一个一个和前面的结果合并
+    pub primitives: Vec<Primitive>,
+    pub ground: bool,
+}
+
+impl Default for SdfScene {
+    fn default() -> Self {
+        let mut hollow = Primitive::new(
+            Shape::Sphere { radius: 0.75 },
+            Vec3::new(0.0, 0.6, 0.0),
+            [0.9, 0.4, 0.3],
+        );
+        hollow.op = Op::Subtract;
+        let mut blob = Primitive::new(
+            Shape::Sphere { radius: 0.5 },
+            Vec3::new(1.8, 0.3, 0.6),
+            [0.3, 0.5, 0.9],
+        );
+        blob.op = Op::SmoothUnion;
+        blob.bob = true;
+        let mut torus = Primitive::new(
+            Shape::Torus {
+                major: 0.7,
+                minor: 0.2,
+            },
+            Vec3::new(-2.0, 0.2, 0.5),
+            [0.4, 0.8, 0.4],
+        );
+        torus.rotation = Vec3::new(60.0, 0.0, 20.0);
+        SdfScene {
+            primitives: vec![
+                Primitive::new(
+                    Shape::Box {
+                        size: Vec3::splat(0.6),
+                        rounding: 0.05,
+                    },
+                    Vec3::new(0.0, 0.0, 0.0),
+                    [0.9, 0.8, 0.3],
+                ),
+                hollow,
+                Primitive::new(
+                    Shape::Capsule {
+                        height: 0.8,
+                        radius: 0.35,
+                    },
+                    Vec3::new(1.8, -0.2, 0.6),
+                    [0.3, 0.5, 0.9],
+                ),
+                blob,
+                torus,
+            ],
+            ground: true,
+        }
+    }
+}
+
+fn f(x: f32) -> String {
+    // Debug 格式总是带小数点，WGSL 里是浮点字面量
+    format!("{:?}", x)
+}
+
+fn vec3(v: Vec3) -> String {
+    format!("vec3<f32>({}, {}, {})", f(v.x), f(v.y), f(v.z))
+}
+
+fn mat3(m: Mat3) -> String {
+    let c = m.to_cols_array();
+    let items: Vec<String> = c.iter().map(|x| f(*x)).collect();
+    format!("mat3x3<f32>({})", items.join(", "))
+}
+
+impl SdfScene {
+    /// 生成 fn map(p) -> vec4，x 是距离，yzw 是颜色
+    pub fn to_wgsl(&self) -> String {
+        let mut code = String::new();
+        code.push_str("fn map(p: vec3<f32>) -> vec4<f32> {\n");
+        code.push_str("    var d = vec4<f32>(1e5, 0.0, 0.0, 0.0);\n");
+        for (i, prim) in self.primitives.iter().enumerate() {
+            let a = prim.rotation * std::f32::consts::PI / 180.0;
+            // 转回物体自己的坐标系用逆矩阵，旋转矩阵的逆就是转置
+            let inverse = Mat3::from_euler(EulerRot::XYZ, a.x, a.y, a.z).transpose();
+            let mut center = vec3(prim.position);
+            if prim.bob {
+                write!(
+                    center,
+                    " + vec3<f32>(0.0, sin(globals.time * 1.5 + {}.0) * 0.3, 0.0)",
+                    i
+                )
+                .unwrap();
+            }
+            writeln!(code, "    // {} {}", i, prim.shape.name()).unwrap();
+            writeln!(
+                code,
+                "    let q{} = {} * (p - ({}));",
+                i,
+                mat3(inverse),
+                center
+            )
+            .unwrap();
+            let [r, g, b] = prim.color;
+            let shape = format!(
+                "vec4<f32>({}, {}, {}, {})",
+                prim.shape.wgsl(&format!("q{}", i)),
+                f(r),
+                f(g),
+                f(b)
+            );
+            let combined = match prim.op {
+                _ if i == 0 => shape,
+                Op::Union => format!("op_union(d, {})", shape),
+                Op::Subtract => format!("op_subtract(d, {})", shape),
+                Op::Intersect => format!("op_intersect(d, {})", shape),
+                Op::SmoothUnion => {
+                    format!(
+                        "op_smooth_union(d, {}, {})",
+                        shape,
+                        f(prim.smoothness.max(1e-3))
+                    )
+                }
+            };
+            writeln!(code, "    d = {};", combined).unwrap();
+        }
+        if self.ground {
+            code.push_str("    d = op_union(d, vec4<f32>(p.y + 1.0, ground_color(p)));\n");
+        }
+        code.push_str("    return d;\n}\n");
+        code
+    }
+}
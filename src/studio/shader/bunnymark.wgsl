@@ -3,6 +3,8 @@ struct Globals {
     size: vec2<f32>,
     _pad0: u32,
     _pad1: u32,
+    // 朝右、朝左两张图在图集里的 uv
+    uv: array<vec4<f32>, 2>,
 };
 
 struct Locals {
@@ -10,8 +12,6 @@ struct Locals {
     velocity: vec2<f32>,
     color: u32,
     _pad0: u32,
-    _pad1: u32,
-    _pad2: u32,
 };
 
 @group(0)
@@ -31,10 +31,12 @@ struct VertexOutput {
 @vertex
 fn vs_main(@builtin(vertex_index) vi: u32) -> VertexOutput {
     let tc = vec2<f32>(f32(vi & 1u), 0.5 * f32(vi & 2u));
-    let offset = vec2<f32>(tc.x * globals.size.x, tc.y * globals.size.y);
+    let offset = (tc - 0.5) * globals.size;
     let pos = globals.mvp * vec4<f32>(locals.position + offset, 0.0, 1.0);
+    let uv = globals.uv[u32(locals.velocity.x < 0.0)];
+    let tex_coords = mix(uv.xy, uv.zw, vec2<f32>(tc.x, 1.0 - tc.y));
     let color = vec4<f32>((vec4<u32>(locals.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
-    return VertexOutput(pos, tc, color);
+    return VertexOutput(pos, tex_coords, color);
 }
 
 @group(0)
@@ -0,0 +1,46 @@
+struct SimParams {
+    extent: vec2<f32>,
+    delta: f32,
+    gravity: f32,
+    bunny_size: f32,
+    count: u32,
+    _pad0: u32,
+    _pad1: u32,
+};
+
+struct Bunny {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+    color: u32,
+    _pad0: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> params: SimParams;
+
+@group(0)
+@binding(1)
+var<storage, read_write> bunnies: array<Bunny>;
+
+// 和 Bunny::update_data 一样的物理
+@compute
+@workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.count {
+        return;
+    }
+    var bunny = bunnies[i];
+    bunny.position += bunny.velocity * params.delta;
+    bunny.velocity.y += params.gravity * params.delta;
+    let half_size = 0.5 * params.bunny_size;
+    if (bunny.velocity.x > 0.0 && bunny.position.x + half_size > params.extent.x)
+        || (bunny.velocity.x < 0.0 && bunny.position.x - half_size < 0.0) {
+        bunny.velocity.x *= -1.0;
+    }
+    if bunny.velocity.y < 0.0 && bunny.position.y < half_size {
+        bunny.velocity.y *= -1.0;
+    }
+    bunnies[i] = bunny;
+}
@@ -1,3 +1,13 @@
+// 左半边正常画场景，右半边把同一帧的深度画成灰度
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> camera: Camera;
+
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec4<f32>,
@@ -10,7 +20,7 @@ fn vs_main(
 ) -> VertexOutput {
     var result: VertexOutput;
     result.color = vec4f(color, 1.0);
-    result.position = vec4f(position,1.0);
+    result.position = camera.view_proj * vec4f(position, 1.0);
     return result;
 }
 
@@ -18,3 +28,47 @@ fn vs_main(
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     return in.color;
 }
+
+struct DepthView {
+    near: f32,
+    // 标准 Z 的远平面，反转 Z 时只用来定灰度的范围
+    far: f32,
+    reversed: u32,
+    // 不做线性化，直接看深度缓冲里存的值
+    raw: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> depth_view: DepthView;
+@group(0)
+@binding(1)
+var depth: texture_depth_2d;
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vi: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vi << 1u) & 2u), f32(vi & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_depth(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // viewport 在右半边，往左挪半个屏幕读同一个像素的深度
+    let half = textureDimensions(depth).x / 2u;
+    let d = textureLoad(depth, vec2<u32>(u32(position.x) - half, u32(position.y)), 0);
+    if depth_view.raw != 0u {
+        return vec4<f32>(vec3<f32>(d), 1.0);
+    }
+    let near = depth_view.near;
+    let far = depth_view.far;
+    var z: f32;
+    if depth_view.reversed != 0u {
+        // 无限远平面的反转 Z：d = near / z，天空是 0
+        z = near / max(d, 1e-30);
+    } else {
+        z = near * far / (far - d * (far - near));
+    }
+    // 距离跨好几个数量级，按对数刻度，近白远黑
+    let t = log(z / near) / log(far / near);
+    return vec4<f32>(vec3<f32>(1.0 - clamp(t, 0.0, 1.0)), 1.0);
+}
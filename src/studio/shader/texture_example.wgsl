@@ -25,5 +25,5 @@ var sam: sampler;
 
 @fragment
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSampleLevel(tex, sam, vertex.tex_coords, 0.0);
+    return textureSample(tex, sam, vertex.tex_coords);
 }
@@ -1,22 +1,46 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
+use egui::Align2;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4Swizzles};
 use std::{
     any::Any,
     array,
     borrow::{Borrow, Cow},
     cell::RefCell,
     rc::Rc,
+    sync::{Arc, Mutex},
 };
 use wgpu::{util::DeviceExt, MultisampleState, PipelineCompilationOptions, PipelineLayout};
+use winit::{event::WindowEvent, window::Window};
 
 /// here I wanna basicly scene of shader playground and contain some basic element
+/// 点一下四个视口里的物体可以选中它，左上角的窗口里切换拾取方式
+/// 模型是 assets 里的内置模型，shader 在 asset/shaders/shader_playground.wgsl，改了保存就重新编
+/// 按 V 换调试视图，四个视口各画各的
 use crate::{
+    assets::{Handle, HotShader},
+    egui::EguiRenderer,
     painter::{Painter, Sandy},
-    utils::models::{gen_plane, gen_sphere, ModelBuffers, ModelType},
+    utils::{
+        bvh::TriangleMesh,
+        models::{gen_plane, gen_sphere, ModelBuffers, ModelType},
+        picking::{ndc_ray, pixel_to_ndc, IdBuffer, Picker},
+        scene_graph::{transform_ui, NodeId, Renderable, SceneGraph, Transform},
+        view_modes::{MeshLayout, ViewCamera, ViewMesh, ViewModes},
+    },
 };
+const MODEL_NAMES: [&str; 4] = ["plane", "sphere", "cube", "plane"];
+/// 选中的物体往这个颜色上混
+const HIGHLIGHT: [f32; 4] = [1.0, 0.5, 0.0, 0.6];
+
 pub struct ShaderPlaygroundScene {
-    pipeline: wgpu::RenderPipeline,
-    model_buffers: Vec<ModelBuffers>,
+    /// (画到屏幕的, 写物体编号的)，同一个 shader
+    pipelines: HotShader<(wgpu::RenderPipeline, wgpu::RenderPipeline)>,
+    /// 两个 plane 拿到的是同一个
+    _builtins: Vec<Handle<ModelBuffers>>,
+    /// stage 下面挂着四个模型，每个视口画一个
+    graph: SceneGraph,
+    stage: NodeId,
+    objects: [NodeId; 4],
     env_matrix_uniform_buffer: wgpu::Buffer,
     time_uniform_buffer: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
@@ -25,6 +49,14 @@ pub struct ShaderPlaygroundScene {
     env_matrix: [EnvUniforms; 4],
     depth_texture: wgpu::Texture,
     depth_view: wgpu::TextureView,
+    /// CPU 拾取用，和 objects 一一对应，都在模型空间里
+    meshes: Vec<TriangleMesh>,
+    picker: Picker,
+    /// CPU 模式下点击的像素，paint 里拿到窗口大小再求交
+    cpu_click: Option<Vec2>,
+    view_modes: ViewModes,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
 }
 
 #[repr(C)]
@@ -33,7 +65,11 @@ struct EnvUniforms {
     world: [f32; 16],
     view: [f32; 16],
     proj: [f32; 16],
-    _padding: [u8; 64], // 添加填充以达到 256 字节
+    /// rgb 是高亮颜色，a 是混合比例，没选中时是 0
+    highlight: [f32; 4],
+    /// 画到 ID 缓冲里的编号，已经加过 1
+    id: u32,
+    _padding: [u32; 11], // 添加填充以达到 256 字节
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
@@ -62,18 +98,34 @@ fn gen_env_world() -> EnvUniforms {
         ],
         view,
         proj,
-        _padding: [0; 64],
+        highlight: [0.0; 4],
+        id: 0,
+        _padding: [0; 11],
     }
 }
 
 // 由于开发的内容较多，所以分阶段，先一个一个实现，开watch
 impl Sandy for ShaderPlaygroundScene {
-    type Extra = ();
-    fn ready(context: &crate::gfx::GfxContext, extra: Self::Extra) -> Self
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
     where
         Self: Sized,
     {
-        let buffers = ModelType::create_all_buffers(context);
+        let mut graph = SceneGraph::new();
+        let stage = graph.add("stage", Transform::IDENTITY, None);
+        let builtins: Vec<_> = ModelType::iterator()
+            .map(|model| context.assets.builtin(context, model))
+            .collect();
+        let mut buffers = builtins.iter();
+        let objects = MODEL_NAMES.map(|name| {
+            let node = graph.add(name, Transform::IDENTITY, Some(stage));
+            let model = buffers.next().unwrap().get().unwrap();
+            graph.set_renderable(node, Some(Renderable { model }));
+            node
+        });
+        let meshes = ModelType::iterator()
+            .map(|model_type| TriangleMesh::from_model(model_type, Mat4::IDENTITY))
+            .collect();
 
         let UniformThing {
             env_matrix_uniform_buffer,
@@ -84,15 +136,6 @@ impl Sandy for ShaderPlaygroundScene {
             env_matrix,
         } = UniformThing::ready(context, ());
 
-        let shader = context
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("shader"),
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-                    "shader_playgroud_scene/base.wgsl"
-                ))),
-            });
-
         // depth on frame and depth texture
         let depth_texture = context.device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Depth Texture"),
@@ -110,58 +153,214 @@ impl Sandy for ShaderPlaygroundScene {
         });
         let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let pipeline = context
-            .device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &shader,
-                    entry_point: "vs_main",
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: 6 * 4 as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
-                    }],
-                    compilation_options: PipelineCompilationOptions::default(),
-                },
-                primitive: wgpu::PrimitiveState::default(),
-                depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth32Float,
-                    depth_write_enabled: true,
-                    depth_compare: wgpu::CompareFunction::Less,
-                    stencil: wgpu::StencilState::default(),
-                    bias: wgpu::DepthBiasState::default(),
-                }),
-                multisample: MultisampleState::default(),
-                fragment: Some(wgpu::FragmentState {
-                    module: &shader,
-                    entry_point: "fs_main",
-                    compilation_options: PipelineCompilationOptions::default(),
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: context.surface_config.as_ref().unwrap().format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                multiview: None,
-                cache: None,
-            });
+        let pipelines = HotShader::new(
+            context,
+            "asset/shaders/shader_playground.wgsl",
+            include_str!("../../asset/shaders/shader_playground.wgsl"),
+            |source| build_pipelines(context, &pipeline_layout, source),
+        );
+        // 内置模型是位置 + 法线 + uv
+        let view_modes = ViewModes::new(
+            context,
+            context.surface_config.as_ref().unwrap().format,
+            MeshLayout {
+                stride: 8,
+                normal: Some(3),
+                uv: Some(6),
+                index_format: wgpu::IndexFormat::Uint32,
+            },
+        );
         Self {
-            pipeline,
+            pipelines,
+            _builtins: builtins,
             env_matrix_uniform_buffer,
             time_uniform_buffer,
             uniform_bind_group: bind_group,
             uniform_pipeline_layout: pipeline_layout,
             uniform_bind_group_layout: bind_group_layout,
             env_matrix,
-            model_buffers: buffers,
+            graph,
+            stage,
+            objects,
             depth_texture,
             depth_view,
+            meshes,
+            picker: Picker::ready(context, ()),
+            cpu_click: None,
+            view_modes,
+            window,
+            egui,
         }
     }
 }
 
+/// 内置模型是位置 + 法线 + uv，只读前两个
+fn build_pipelines(
+    context: &crate::gfx::GfxContext,
+    layout: &PipelineLayout,
+    source: &str,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+    let vertex = wgpu::VertexState {
+        module: &shader,
+        entry_point: "vs_main",
+        buffers: &[wgpu::VertexBufferLayout {
+            array_stride: 8 * 4 as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+        }],
+        compilation_options: PipelineCompilationOptions::default(),
+    };
+    let pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(layout),
+            vertex: vertex.clone(),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: context.surface_config.as_ref().unwrap().format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+    // 和上面一样的顶点，片元输出物体编号
+    let id_pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("playground ids"),
+            layout: Some(layout),
+            vertex,
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(IdBuffer::depth_stencil()),
+            multisample: MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_id",
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(IdBuffer::target())],
+            }),
+            multiview: None,
+            cache: None,
+        });
+    (pipeline, id_pipeline)
+}
+
+impl ShaderPlaygroundScene {
+    fn viewport(i: usize, width: f32, height: f32) -> (f32, f32, f32, f32) {
+        let (x, y) = match i {
+            0 => (0.0, 0.0),
+            1 => (0.5, 0.0),
+            2 => (0.0, 0.5),
+            3 => (0.5, 0.5),
+            _ => unreachable!(),
+        };
+        (x * width, y * height, width / 2.0, height / 2.0)
+    }
+
+    /// 先看点在哪个视口里，再用那个视口的矩阵反投影出射线去打它的模型
+    fn pick_cpu(&self, pixel: Vec2, width: f32, height: f32) -> Option<u32> {
+        let i = (pixel.x >= width / 2.0) as usize + 2 * (pixel.y >= height / 2.0) as usize;
+        let (x, y, w, h) = Self::viewport(i, width, height);
+        let env = &self.env_matrix[i];
+        let view_proj = Mat4::from_cols_array(&env.proj)
+            * Mat4::from_cols_array(&env.view)
+            * Mat4::from_cols_array(&env.world);
+        let ndc = pixel_to_ndc(pixel - Vec2::new(x, y), Vec2::new(w, h));
+        let (origin, direction) = ndc_ray(view_proj.inverse(), ndc);
+        self.meshes[i]
+            .intersect(origin, direction, 1.0)
+            .map(|_| i as u32)
+    }
+
+    /// 按层级列出节点，返回这次点中的模型编号
+    fn hierarchy_ui(&self, ui: &mut egui::Ui, node: NodeId) -> Option<u32> {
+        let object = self
+            .objects
+            .iter()
+            .position(|&o| o == node)
+            .map(|i| i as u32);
+        let selected = object.is_some() && object == self.picker.selected;
+        let mut clicked = ui
+            .selectable_label(selected, self.graph.name(node))
+            .clicked()
+            .then_some(object)
+            .flatten();
+        ui.indent(node.index(), |ui| {
+            for &child in self.graph.children(node) {
+                clicked = self.hierarchy_ui(ui, child).or(clicked);
+            }
+        });
+        clicked
+    }
+
+    fn inspector(&mut self, ui: &egui::Context) {
+        egui::Window::new("Picking")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.collapsing("stage", |ui| {
+                    let mut local = *self.graph.local(self.stage);
+                    if transform_ui(ui, &mut local) {
+                        self.graph.set_local(self.stage, local);
+                    }
+                });
+                ui.collapsing("hierarchy", |ui| {
+                    for &root in self.graph.roots() {
+                        if let Some(id) = self.hierarchy_ui(ui, root) {
+                            self.picker.selected = Some(id);
+                        }
+                    }
+                });
+                self.view_modes.ui(ui);
+                self.picker
+                    .ui(ui, |id| MODEL_NAMES[id as usize].to_string());
+                if let Some(id) = self.picker.selected {
+                    let i = id as usize;
+                    let eye = Mat4::from_cols_array(&self.env_matrix[i].view)
+                        .inverse()
+                        .col(3)
+                        .xyz();
+                    ui.label(format!("triangles: {}", self.meshes[i].triangles.len()));
+                    let node = self.objects[i];
+                    // 挂在 stage 下面就跟着 stage 一起变换
+                    let mut follow = self.graph.parent(node) == Some(self.stage);
+                    if ui.checkbox(&mut follow, "follow stage").changed() {
+                        self.graph.set_parent(node, follow.then_some(self.stage));
+                    }
+                    let mut local = *self.graph.local(node);
+                    if transform_ui(ui, &mut local) {
+                        self.graph.set_local(node, local);
+                    }
+                    ui.label(format!(
+                        "camera: ({:.2}, {:.2}, {:.2})",
+                        eye.x, eye.y, eye.z
+                    ));
+                }
+            });
+    }
+}
+
 trait Live: Any {
     fn update(&mut self, dt: f32, rate: f32, i: i32);
 }
@@ -187,6 +386,10 @@ impl Live for ShaderPlaygroundScene {
 
 impl Painter for ShaderPlaygroundScene {
     fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        self.pipelines.update(context, |source| {
+            build_pipelines(context, &self.uniform_pipeline_layout, source)
+        });
+        let mode = self.view_modes.sync(context);
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -199,6 +402,19 @@ impl Painter for ShaderPlaygroundScene {
 
         let config = context.surface_config.as_ref().unwrap();
         let (width, height) = (config.width, config.height);
+        if let Some(pixel) = self.cpu_click.take() {
+            self.picker.selected = self.pick_cpu(pixel, width as f32, height as f32);
+        }
+        self.graph.update();
+        for (i, env) in self.env_matrix.iter_mut().enumerate() {
+            env.world = self.graph.world(self.objects[i]).to_cols_array();
+            env.id = i as u32 + 1;
+            env.highlight = if self.picker.selected == Some(i as u32) {
+                HIGHLIGHT
+            } else {
+                [0.0; 4]
+            };
+        }
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -220,59 +436,107 @@ impl Painter for ShaderPlaygroundScene {
                 }),
                 ..Default::default()
             });
-            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_pipeline(&self.pipelines.0);
             for i in 0..4 {
                 self.update(dt, (i as f32 + 1.) * 0.5, i);
                 let dynamic_offset =
                     (i as usize * std::mem::size_of::<EnvUniforms>()) as wgpu::BufferAddress;
 
-                let (x, y) = match i {
-                    0 => (0.0, 0.0),
-                    1 => (0.5, 0.0),
-                    2 => (0.0, 0.5),
-                    3 => (0.5, 0.5),
-                    _ => unreachable!(),
-                };
+                let (x, y, w, h) = Self::viewport(i as usize, width as f32, height as f32);
                 render_pass.set_bind_group(0, &self.uniform_bind_group, &[dynamic_offset as u32]);
                 context.queue.write_buffer(
                     &self.env_matrix_uniform_buffer,
                     dynamic_offset,
                     bytemuck::cast_slice(&[self.env_matrix[i as usize]]),
                 );
-                render_pass.set_viewport(
-                    x * width as f32,
-                    y * height as f32,
-                    width as f32 / 2.0,
-                    height as f32 / 2.0,
-                    0.0,
-                    1.0,
-                );
-                render_pass.set_vertex_buffer(
-                    0,
-                    self.model_buffers
-                        .get(i as usize)
-                        .unwrap()
-                        .vertex_buffer
-                        .slice(..),
-                );
+                render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+                if mode.replaces_scene() {
+                    continue;
+                }
+                let Some(renderable) = self.graph.renderable(self.objects[i as usize]) else {
+                    continue;
+                };
+                render_pass.set_vertex_buffer(0, renderable.model.vertex_buffer.slice(..));
                 render_pass.set_index_buffer(
-                    self.model_buffers
-                        .get(i as usize)
-                        .unwrap()
-                        .index_buffer
-                        .slice(..),
-                    wgpu::IndexFormat::Uint16,
-                );
-                render_pass.draw_indexed(
-                    0..self.model_buffers.get(i as usize).unwrap().index_count,
-                    0,
-                    0..1,
+                    renderable.model.index_buffer.slice(..),
+                    wgpu::IndexFormat::Uint32,
                 );
+                render_pass.draw_indexed(0..renderable.model.index_count, 0, 0..1);
+            }
+        }
+
+        let meshes: Vec<ViewMesh> = self
+            .objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &node)| {
+                let renderable = self.graph.renderable(node)?;
+                let env = &self.env_matrix[i];
+                let (x, y, w, h) = Self::viewport(i, width as f32, height as f32);
+                let view_proj = Mat4::from_cols_array(&env.proj) * Mat4::from_cols_array(&env.view);
+                Some(ViewMesh {
+                    vertices: &renderable.model.vertex_buffer,
+                    indices: &renderable.model.index_buffer,
+                    index_count: renderable.model.index_count,
+                    model: self.graph.world(node),
+                    viewport: Some(([x, y, w, h], view_proj)),
+                })
+            })
+            .collect();
+        // 每个视口的 view_proj 放在 ViewMesh 里，这里只用 near / far
+        let camera = ViewCamera {
+            view_proj: Mat4::IDENTITY,
+            near: 0.1,
+            far: 100.0,
+        };
+        self.view_modes
+            .render(context, &mut encoder, &view, &camera, &meshes);
+
+        if self.picker.ids.requested() {
+            let mut id_pass = self.picker.ids.begin(context, &mut encoder);
+            id_pass.set_pipeline(&self.pipelines.1);
+            for (i, &node) in self.objects.iter().enumerate() {
+                let Some(Renderable { model: buffers }) = self.graph.renderable(node) else {
+                    continue;
+                };
+                let dynamic_offset = (i * std::mem::size_of::<EnvUniforms>()) as u32;
+                let (x, y, w, h) = Self::viewport(i, width as f32, height as f32);
+                id_pass.set_bind_group(0, &self.uniform_bind_group, &[dynamic_offset]);
+                id_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+                id_pass.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
+                id_pass.set_index_buffer(buffers.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                id_pass.draw_indexed(0..buffers.index_count, 0, 0..1);
             }
+            drop(id_pass);
+            self.picker.ids.copy(&mut encoder);
         }
 
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [width, height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.inspector(ui),
+        );
+
         context.queue.submit(std::iter::once(encoder.finish()));
         frame.present();
+        self.picker.finish(context);
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        let over_ui = self.egui.lock().unwrap().context.is_pointer_over_area();
+        if let Some(pixel) = self.picker.input(event, over_ui) {
+            self.cpu_click = Some(pixel);
+        }
     }
 }
 
@@ -0,0 +1,294 @@
+// 2D 画布上的文字，左键拖动平移，滚轮以光标为中心缩放
+// 字按画布单位摆（TextRenderer::world_sized），放大以后看 Bitmap 和 Msdf 两种模式的区别：
+// 位图按固定字号画好，放大就糊；距离场按距离重新算边缘，放多大都是锐的
+// 左上角的窗口里调描边、发光、阴影，只有 Msdf 模式有
+use std::sync::{Arc, Mutex};
+
+use egui::Align2;
+use glam::{Mat4, Vec2, Vec3};
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    window::Window,
+};
+
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+    text::{font::Font, TextEffects, TextMode, TextRenderer, DEJAVU_SANS, MAX_EFFECT},
+};
+
+/// 画布上的几段字，(一个 em 多少画布单位, 内容)
+const LINES: [(f32, &str); 5] = [
+    (0.3, "Signed distance fields"),
+    (0.12, "The quick brown fox jumps over the lazy dog"),
+    (0.06, "AVAWAY Ta Te Yo \u{2014} kerning from the kern table"),
+    (0.03, "Zoom in: bitmap glyphs blur, MSDF corners stay sharp"),
+    (
+        0.012,
+        "tiny text 0123456789 !@#$%&*() \u{e9}\u{e0}\u{fc}\u{f1}",
+    ),
+];
+
+pub struct TextDemo {
+    mode: TextMode,
+    bitmap: TextRenderer,
+    msdf: TextRenderer,
+    effects: TextEffects,
+    /// 屏幕中心对着的画布坐标
+    pan: Vec2,
+    /// 1 的时候屏幕高度是 2 个画布单位
+    zoom: f32,
+    cursor: Vec2,
+    dragging: bool,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
+}
+
+fn color32(c: [f32; 4]) -> egui::Color32 {
+    let [r, g, b, a] = c.map(|v| (v * 255.0).round() as u8);
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+fn color_edit(ui: &mut egui::Ui, color: &mut [f32; 4]) {
+    let mut c = color32(*color);
+    if ui.color_edit_button_srgba(&mut c).changed() {
+        *color = c.to_srgba_unmultiplied().map(|v| v as f32 / 255.0);
+    }
+}
+
+impl TextDemo {
+    fn screen_size(&self) -> Vec2 {
+        let size = self.window.inner_size();
+        Vec2::new(size.width.max(1) as f32, size.height.max(1) as f32)
+    }
+
+    /// 一个像素多少画布单位
+    fn units_per_pixel(&self) -> f32 {
+        2.0 / (self.screen_size().y * self.zoom)
+    }
+
+    /// 屏幕像素（y 朝下）-> 画布坐标（y 朝上）
+    fn to_canvas(&self, pixel: Vec2) -> Vec2 {
+        let offset = pixel - self.screen_size() * 0.5;
+        self.pan + Vec2::new(offset.x, -offset.y) * self.units_per_pixel()
+    }
+
+    fn view_proj(&self) -> Mat4 {
+        let half = self.screen_size() * 0.5 * self.units_per_pixel();
+        Mat4::orthographic_rh(
+            self.pan.x - half.x,
+            self.pan.x + half.x,
+            self.pan.y - half.y,
+            self.pan.y + half.y,
+            -1.0,
+            1.0,
+        )
+    }
+
+    fn hud(&mut self, ui: &egui::Context) {
+        egui::Window::new("Text")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.mode, TextMode::Bitmap, "bitmap");
+                    ui.radio_value(&mut self.mode, TextMode::Msdf, "msdf");
+                });
+                ui.horizontal(|ui| {
+                    ui.label(format!("zoom {:.2}x", self.zoom));
+                    if ui.button("reset view").clicked() {
+                        self.pan = Vec2::new(1.2, -0.2);
+                        self.zoom = 0.8;
+                    }
+                });
+                ui.add_enabled_ui(self.mode == TextMode::Msdf, |ui| {
+                    let effects = &mut self.effects;
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut effects.outline_width, 0.0..=MAX_EFFECT)
+                                .text("outline"),
+                        );
+                        color_edit(ui, &mut effects.outline_color);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut effects.glow_radius, 0.0..=MAX_EFFECT)
+                                .text("glow"),
+                        );
+                        color_edit(ui, &mut effects.glow_color);
+                    });
+                    let [x, y] = &mut effects.shadow_offset;
+                    ui.add(egui::Slider::new(x, -MAX_EFFECT..=MAX_EFFECT).text("shadow x"));
+                    ui.add(egui::Slider::new(y, -MAX_EFFECT..=MAX_EFFECT).text("shadow y"));
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut effects.shadow_softness, 0.0..=MAX_EFFECT)
+                                .text("softness"),
+                        );
+                        color_edit(ui, &mut effects.shadow_color);
+                    });
+                });
+                let renderer = match self.mode {
+                    TextMode::Bitmap => &self.bitmap,
+                    TextMode::Msdf => &self.msdf,
+                };
+                ui.label(format!("{} glyphs cached", renderer.cached_glyphs()));
+                if renderer.atlas_full() {
+                    ui.colored_label(egui::Color32::YELLOW, "glyph atlas is full, text dropped");
+                }
+            });
+    }
+}
+
+impl Sandy for TextDemo {
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
+
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let format = context.surface_config.as_ref().unwrap().format;
+        let renderer =
+            |mode| TextRenderer::new(context, format, Font::load(DEJAVU_SANS).unwrap(), mode);
+        TextDemo {
+            mode: TextMode::Msdf,
+            bitmap: renderer(TextMode::Bitmap),
+            msdf: renderer(TextMode::Msdf),
+            effects: TextEffects {
+                outline_width: 0.04,
+                shadow_offset: [0.05, 0.06],
+                shadow_softness: 0.05,
+                shadow_color: [0.0, 0.0, 0.0, 0.6],
+                ..Default::default()
+            },
+            pan: Vec2::new(1.2, -0.2),
+            zoom: 0.8,
+            cursor: Vec2::ZERO,
+            dragging: false,
+            window,
+            egui,
+        }
+    }
+}
+
+impl Painter for TextDemo {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, _dt: f32, _time: f32) {
+        let view_proj = self.view_proj();
+        let mode = self.mode;
+        let zoom = self.zoom;
+        self.msdf.effects = self.effects;
+        let text = match mode {
+            TextMode::Bitmap => &mut self.bitmap,
+            TextMode::Msdf => &mut self.msdf,
+        };
+        let mut y = 0.0;
+        for (size, line) in LINES {
+            text.world_sized(Vec3::new(0.0, y, 0.0), size, 0xfff0f0f0, line);
+            y -= text.measure(line, size).y * 1.2;
+        }
+        // 屏幕空间的字不跟着缩放，放在左下角
+        let status = format!("{:?}, zoom {:.2}x", mode, zoom);
+        let height = self.window.inner_size().height as f32;
+        let extent = text.measure(&status, 16.0);
+        text.screen(
+            Vec2::new(12.0, height - extent.y - 8.0),
+            16.0,
+            0xff80e0ff,
+            &status,
+        );
+        text.prepare(context, view_proj, Mat4::IDENTITY);
+
+        let frame = context.surface.get_current_texture().unwrap();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = context
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("text demo"),
+            });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("text demo"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.08,
+                            g: 0.1,
+                            b: 0.16,
+                            a: 1.0,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: context.render_timestamps("text demo"),
+                occlusion_query_set: None,
+            });
+            text.draw(&mut rpass);
+        }
+
+        let config = context.surface_config.as_ref().unwrap();
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut encoder,
+            &window,
+            &view,
+            screen_descriptor,
+            |ui| self.hud(ui),
+        );
+
+        context.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    fn input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = Vec2::new(position.x as f32, position.y as f32);
+                if self.dragging {
+                    let delta = (cursor - self.cursor) * self.units_per_pixel();
+                    self.pan += Vec2::new(-delta.x, delta.y);
+                }
+                self.cursor = cursor;
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                // 在 egui 窗口上按下不拖画布，松开总要处理
+                if pressed && self.egui.lock().unwrap().context.is_pointer_over_area() {
+                    return;
+                }
+                self.dragging = pressed;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                if self.egui.lock().unwrap().context.is_pointer_over_area() {
+                    return;
+                }
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 50.0,
+                };
+                // 光标下的那一点缩放前后不动
+                let before = self.to_canvas(self.cursor);
+                self.zoom = (self.zoom * 1.15f32.powf(lines)).clamp(0.05, 400.0);
+                let after = self.to_canvas(self.cursor);
+                self.pan += before - after;
+            }
+            _ => {}
+        }
+    }
+}
@@ -2,7 +2,10 @@ use std::borrow::Cow;
 
 use wgpu::util::DeviceExt;
 
-use crate::painter::{Painter, Sandy};
+use crate::{
+    painter::{Painter, Sandy},
+    texture::{MipGeneration, TextureBundle, TextureOptions},
+};
 
 pub struct TextureExample {
     pub bind_group: wgpu::BindGroup,
@@ -12,52 +15,17 @@ pub struct TextureExample {
 impl Sandy for TextureExample {
     type Extra = ();
     fn ready(context: &crate::gfx::GfxContext, _extra: Self::Extra) -> Self {
-        let texture = {
-            let img_data = include_bytes!("../icon512.png");
-            let decoder = png::Decoder::new(std::io::Cursor::new(img_data));
-            let mut reader = decoder.read_info().unwrap();
-            let mut buf = vec![0; reader.output_buffer_size()];
-            let info = reader.next_frame(&mut buf).unwrap();
-
-            let size = wgpu::Extent3d {
-                width: info.width,
-                height: info.height,
-                depth_or_array_layers: 1,
-            };
-            let texture: wgpu::Texture = context.device.create_texture(&wgpu::TextureDescriptor {
-                label: None,
-                size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-            context.queue.write_texture(
-                texture.as_image_copy(),
-                &buf,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(info.width * 4),
-                    rows_per_image: None,
-                },
-                size,
-            );
-            texture
-        };
-
-        // 会放到bind group里
-        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("my icon sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        // level 0 上传后 GPU 逐级生成 mip，三线性采样
+        let texture = TextureBundle::from_bytes(
+            context,
+            include_bytes!("../icon512.png"),
+            TextureOptions {
+                label: Some("my icon"),
+                mips: MipGeneration::Gpu,
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
         #[rustfmt::skip]
         let just_4_vertices : [f32;30] = [
@@ -93,48 +61,9 @@ impl Sandy for TextureExample {
             ],
         }];
 
-        let bind_group_layout =
-            context
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                multisampled: false,
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                    label: Some("icon bind group layout"),
-                });
-        let bind_group = context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                        ),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&sampler),
-                    },
-                ],
-                label: Some("icon bind group"),
-            });
+        // 会放到bind group里
+        let bind_group_layout = TextureBundle::bind_group_layout(context);
+        let bind_group = texture.bind_group(context, &bind_group_layout);
         let shader = context
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -219,7 +148,7 @@ impl Painter for TextureExample {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("texture example"),
                 occlusion_query_set: None,
             });
             rpass.set_pipeline(&self.pipeline);
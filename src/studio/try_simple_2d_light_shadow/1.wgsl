@@ -1,17 +1,75 @@
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) vertex_position: vec4<f32>,
+// 1D 极坐标阴影贴图
+// 一行一个光源，每个 texel 是从光源出发的一个角度，存最近遮挡物的距离 / radius
+
+struct Light {
+    position: vec2<f32>,
+    radius: f32,
+    intensity: f32,
+    color: vec3<f32>,
+    falloff: f32,
+};
+
+struct ShadowParams {
+    aspect: f32,
+    segment_count: u32,
+    // 0 硬阴影，1 PCF
+    filter_mode: u32,
+    softness: f32,
+    // 阴影贴图的宽度，也就是一圈分成多少个角度
+    resolution: u32,
+    light_count: u32,
+    _pad0: u32,
+    _pad1: u32,
 };
 
 @group(0)
 @binding(0)
-var<uniform> light_position: vec2<f32>;
+var<storage, read> lights: array<Light>;
+
+@group(1)
+@binding(0)
+var<storage, read> segments: array<vec4<f32>>;
+@group(1)
+@binding(1)
+var<uniform> params: ShadowParams;
+
+const PI: f32 = 3.14159265;
 
 @vertex
-fn vs_main(@location(0) position: vec3<f32>) -> VertexOutput {
-    var out: VertexOutput;
-    let light_position_4d = vec4<f32>(light_position, -0.1, 1.0);
-    out.vertex_position = vec4<f32>(position, 1.0);
-    out.clip_position = light_position_4d * out.vertex_position;
-    return out;
-}
\ No newline at end of file
+fn vs_main(@builtin(vertex_index) vi: u32) -> @builtin(position) vec4<f32> {
+    // 覆盖整个目标的大三角形
+    let uv = vec2<f32>(f32((vi << 1u) & 2u), f32(vi & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn cross2(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+@fragment
+fn fs_main(@builtin(position) frag: vec4<f32>) -> @location(0) f32 {
+    let row = u32(frag.y);
+    if row >= params.light_count {
+        return 1.0;
+    }
+    let light = lights[row];
+    let angle = frag.x / f32(params.resolution) * 2.0 * PI - PI;
+    let dir = vec2<f32>(cos(angle), sin(angle));
+    var nearest = light.radius;
+    for (var i = 0u; i < params.segment_count; i++) {
+        let segment = segments[i];
+        let a = segment.xy;
+        let e = segment.zw - segment.xy;
+        let denom = cross2(dir, e);
+        if abs(denom) < 1e-6 {
+            continue;
+        }
+        let to_a = a - light.position;
+        let t = cross2(to_a, e) / denom;
+        let s = cross2(to_a, dir) / denom;
+        if t > 0.0 && s >= 0.0 && s <= 1.0 {
+            nearest = min(nearest, t);
+        }
+    }
+    return nearest / light.radius;
+}
@@ -1,30 +1,105 @@
-struct VertexInput {
-    @location(0) pos: vec2<f32>,
-    @location(1) tex_coords: vec2<f32>,
+// 主 pass：地板按阴影贴图着色，再把遮挡物画上去
+
+struct Light {
+    position: vec2<f32>,
+    radius: f32,
+    intensity: f32,
+    color: vec3<f32>,
+    falloff: f32,
+};
+
+struct ShadowParams {
+    aspect: f32,
+    segment_count: u32,
+    // 0 硬阴影，1 PCF
+    filter_mode: u32,
+    softness: f32,
+    resolution: u32,
+    light_count: u32,
+    _pad0: u32,
+    _pad1: u32,
 };
 
-struct VertexOutput {
+@group(0)
+@binding(0)
+var<storage, read> lights: array<Light>;
+
+@group(1)
+@binding(0)
+var shadow_map: texture_2d<f32>;
+@group(1)
+@binding(1)
+var<uniform> params: ShadowParams;
+
+const PI: f32 = 3.14159265;
+const BIAS: f32 = 0.002;
+const PCF_TAPS: i32 = 4;
+const AMBIENT: vec3<f32> = vec3<f32>(0.06, 0.07, 0.1);
+
+struct FloorOutput {
     @builtin(position) position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
+    @location(0) world: vec2<f32>,
 };
 
 @vertex
-fn vs_main(@location(0) position: vec2<f32>,@location(1) tex_coords: vec2<f32>) -> VertexOutput {
-    var out: VertexOutput;
-    out.position = vec4<f32>( position, 0.0, 1.0);
-    out.tex_coords = tex_coords;
-    return out;
+fn vs_floor(@builtin(vertex_index) vi: u32) -> FloorOutput {
+    let uv = vec2<f32>(f32((vi << 1u) & 2u), f32(vi & 2u));
+    let ndc = uv * 2.0 - 1.0;
+    return FloorOutput(vec4<f32>(ndc, 0.0, 1.0), vec2<f32>(ndc.x * params.aspect, ndc.y));
 }
 
-@group(0)
-@binding(0)
-var depth_texture: texture_depth_2d;
-@group(0)
-@binding(1)
-var depth_sampler: sampler_comparison;
+// 1 表示照得到，0 表示在阴影里
+fn lit(row: u32, index: i32, depth: f32) -> f32 {
+    let resolution = i32(params.resolution);
+    let wrapped = ((index % resolution) + resolution) % resolution;
+    let stored = textureLoad(shadow_map, vec2<i32>(wrapped, i32(row)), 0).r;
+    return select(1.0, 0.0, depth > stored + BIAS);
+}
+
+fn shadow(row: u32, light: Light, world: vec2<f32>) -> f32 {
+    let to_frag = world - light.position;
+    let dist = length(to_frag);
+    let depth = dist / light.radius;
+    let texels_per_radian = f32(params.resolution) / (2.0 * PI);
+    let u = (atan2(to_frag.y, to_frag.x) + PI) * texels_per_radian;
+    if params.filter_mode == 0u {
+        return lit(row, i32(floor(u)), depth);
+    }
+    // PCF：在角度方向上多采几次取平均
+    // 同样的模糊宽度离光越远对应的角度越小
+    let spread = params.softness / max(dist, 0.05) * texels_per_radian / f32(PCF_TAPS);
+    var sum = 0.0;
+    for (var k = -PCF_TAPS; k <= PCF_TAPS; k++) {
+        sum += lit(row, i32(floor(u + f32(k) * spread)), depth);
+    }
+    return sum / f32(2 * PCF_TAPS + 1);
+}
+
+@fragment
+fn fs_floor(in: FloorOutput) -> @location(0) vec4<f32> {
+    // 地板上淡淡的格子，方便看出阴影的形状
+    let cell = floor(in.world * 8.0);
+    let checker = select(0.8, 0.9, (i32(cell.x + cell.y) & 1) == 0);
+    // 每个光源单独算阴影，结果直接相加
+    var radiance = vec3<f32>(0.0);
+    for (var i = 0u; i < params.light_count; i++) {
+        let light = lights[i];
+        let dist = length(in.world - light.position);
+        if dist >= light.radius {
+            continue;
+        }
+        let attenuation = pow(1.0 - dist / light.radius, light.falloff);
+        radiance += light.color * light.intensity * attenuation * shadow(i, light, in.world);
+    }
+    return vec4<f32>(vec3<f32>(checker) * (AMBIENT + radiance), 1.0);
+}
+
+@vertex
+fn vs_occluder(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position.x / params.aspect, position.y, 0.0, 1.0);
+}
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let depth = textureSampleCompare(depth_texture, depth_sampler, in.tex_coords, 0.5);
-    return vec4<f32>(vec3<f32>(depth), 1.0);
-}
\ No newline at end of file
+fn fs_occluder() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.05, 0.05, 0.06, 1.0);
+}
@@ -1,24 +1,58 @@
+use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 
-pub struct Light {
+/// 阴影贴图按这个数量分行，一行一个光源
+pub const MAX_LIGHTS: usize = 16;
+
+/// 和 1.wgsl / 2.wgsl 里的 Light 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct LightData {
+    pub position: [f32; 2],
+    /// 超过这个距离就没有光，也是阴影贴图里深度的归一化长度
+    pub radius: f32,
+    pub intensity: f32,
+    pub color: [f32; 3],
+    /// 衰减曲线 (1 - d / radius) ^ falloff
+    pub falloff: f32,
+}
+
+impl LightData {
+    pub fn new(position: [f32; 2], color: [f32; 3]) -> Self {
+        LightData {
+            position,
+            radius: 1.2,
+            intensity: 1.0,
+            color,
+            falloff: 2.0,
+        }
+    }
+}
+
+/// 所有光源放在一个 storage buffer 里，主 pass 里逐个累加
+pub struct Lights {
+    pub lights: Vec<LightData>,
     pub light_buffer: wgpu::Buffer,
     pub light_bind_group: wgpu::BindGroup,
     pub light_bind_group_layout: wgpu::BindGroupLayout,
 }
 
-impl Light {
+impl Lights {
     pub fn new(context: &crate::gfx::GfxContext) -> Self {
-        // as uniform
-        // light position
-        // light color
-        // light intensity
+        let lights = vec![
+            LightData::new([-0.3, -0.2], [1.0, 0.85, 0.6]),
+            LightData::new([0.4, 0.3], [0.4, 0.6, 1.0]),
+        ];
 
+        // 按最大数量一次开好，之后只 write_buffer
+        let mut contents = vec![LightData::zeroed(); MAX_LIGHTS];
+        contents[..lights.len()].copy_from_slice(&lights);
         let buffer = context
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("lgiht buffer"),
-                contents: bytemuck::cast_slice(&[-0.1f32,-0.1f32]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                label: Some("lights buffer"),
+                contents: bytemuck::cast_slice(&contents),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
             });
         let bind_group_layout =
             context
@@ -26,10 +60,12 @@ impl Light {
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                     entries: &[wgpu::BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
-                            min_binding_size: wgpu::BufferSize::new(8),
-                            ty: wgpu::BufferBindingType::Uniform,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<LightData>() as u64,
+                            ),
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
                         },
                         count: None,
@@ -42,19 +78,44 @@ impl Light {
                 layout: &bind_group_layout,
                 entries: &[wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                        buffer: &buffer,
-                        offset: 0,
-                        size: None,
-                    }),
+                    resource: buffer.as_entire_binding(),
                 }],
                 label: Some("light bind group"),
             });
 
-        Light {
+        Lights {
+            lights,
             light_buffer: buffer,
             light_bind_group: bind_group,
             light_bind_group_layout: bind_group_layout,
         }
     }
+
+    pub fn upload(&self, context: &crate::gfx::GfxContext) {
+        context
+            .queue
+            .write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&self.lights));
+    }
+
+    /// 满了就不加
+    pub fn add(&mut self, light: LightData) {
+        if self.lights.len() < MAX_LIGHTS {
+            self.lights.push(light);
+        }
+    }
+
+    /// 离 position 最近且在 pick_radius 以内的光源
+    pub fn pick(&self, position: [f32; 2], pick_radius: f32) -> Option<usize> {
+        self.lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| {
+                let dx = light.position[0] - position[0];
+                let dy = light.position[1] - position[1];
+                (i, dx * dx + dy * dy)
+            })
+            .filter(|(_, d2)| *d2 <= pick_radius * pick_radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
 }
@@ -1,31 +1,77 @@
 use wgpu::util::DeviceExt;
 
+/// 场景里挡光的方块，(中心, 半宽高)，单位是世界坐标
+/// 世界坐标 y 在 [-1, 1]，x 按屏幕比例拉伸
+const OCCLUDERS: [([f32; 2], [f32; 2]); 5] = [
+    ([0.0, 0.0], [0.12, 0.12]),
+    ([-0.6, 0.4], [0.08, 0.25]),
+    ([0.55, -0.35], [0.2, 0.05]),
+    ([-0.45, -0.5], [0.1, 0.1]),
+    ([0.7, 0.55], [0.06, 0.06]),
+];
+
+/// 遮挡物，画的时候用三角形，算阴影的时候用边
 pub struct Mesh {
     pub vbuffer: wgpu::Buffer,
     pub ibuffer: wgpu::Buffer,
+    pub index_count: u32,
+    /// 每条边 (a.x, a.y, b.x, b.y)，给阴影贴图的 pass 做射线求交
+    pub segment_buffer: wgpu::Buffer,
+    pub segment_count: u32,
 }
 impl Mesh {
     pub fn new(context: &crate::gfx::GfxContext) -> Self {
-        let v = gen_v();
-        let i: [u16; 6] = [0u16, 2, 1, 1, 2, 3];
+        let (v, i, segments) = gen_v();
         let vbuffer = context
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("square"),
+                label: Some("occluders"),
                 contents: bytemuck::cast_slice(&v),
                 usage: wgpu::BufferUsages::VERTEX,
             });
         let ibuffer = context
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("square index"),
+                label: Some("occluders index"),
                 contents: bytemuck::cast_slice(&i),
                 usage: wgpu::BufferUsages::INDEX,
             });
-        Mesh { vbuffer, ibuffer }
+        let segment_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("occluder segments"),
+                contents: bytemuck::cast_slice(&segments),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        Mesh {
+            vbuffer,
+            ibuffer,
+            index_count: i.len() as u32,
+            segment_buffer,
+            segment_count: segments.len() as u32,
+        }
     }
 }
 
-fn gen_v() -> [f32; 8] {
-    [0., 0., 0.0, 0.5, 0.5, 0.0, 0.5, 0.5]
+fn gen_v() -> (Vec<[f32; 2]>, Vec<u16>, Vec<[f32; 4]>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut segments = Vec::new();
+    for ([cx, cy], [hx, hy]) in OCCLUDERS {
+        let base = vertices.len() as u16;
+        // 逆时针
+        let corners = [
+            [cx - hx, cy - hy],
+            [cx + hx, cy - hy],
+            [cx + hx, cy + hy],
+            [cx - hx, cy + hy],
+        ];
+        vertices.extend_from_slice(&corners);
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        for k in 0..4 {
+            let (a, b) = (corners[k], corners[(k + 1) % 4]);
+            segments.push([a[0], a[1], b[0], b[1]]);
+        }
+    }
+    (vertices, indices, segments)
 }
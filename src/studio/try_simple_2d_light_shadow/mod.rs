@@ -1,42 +1,117 @@
-// one mesh or instance(later)
-// one light
-// one shadow
+// 一组遮挡物 mesh
+// 多个点光源，放在 storage buffer 里
+// 1D 极坐标阴影贴图：一行一个光源，先对每个角度求最近的遮挡距离，再在主 pass 里比较
+// 左键拖动光源，H 切换硬阴影 / PCF，egui 里加减光源和改参数
 mod light;
 mod mesh;
 mod shadow;
-use std::rc::Rc;
 
-use crate::painter::{Painter, Sandy};
-use light::Light;
+use std::sync::{Arc, Mutex};
+
+use egui::Align2;
+use winit::{
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::Window,
+};
+
+use crate::{
+    egui::EguiRenderer,
+    painter::{Painter, Sandy},
+};
+use light::{LightData, Lights, MAX_LIGHTS};
 use mesh::Mesh;
 use shadow::Shadow;
 
+/// 鼠标离光源多近才算点中，世界坐标
+const PICK_RADIUS: f32 = 0.08;
+
 pub struct Simple2DLightShadow {
     pub mesh: Mesh,
-    pub light: Light,
+    pub lights: Lights,
     pub shadow: Shadow,
+    /// 鼠标的世界坐标
+    cursor: [f32; 2],
+    /// 正在拖的光源
+    dragging: Option<usize>,
+    window: Arc<Window>,
+    egui: Arc<Mutex<EguiRenderer>>,
 }
 
 impl Sandy for Simple2DLightShadow {
-    type Extra = ();
+    type Extra = (Arc<Window>, Arc<Mutex<EguiRenderer>>);
 
-    fn ready(context: &crate::gfx::GfxContext, extra: Self::Extra) -> Self
+    fn ready(context: &crate::gfx::GfxContext, (window, egui): Self::Extra) -> Self
     where
         Self: Sized,
     {
         let mesh = Mesh::new(context);
-        let light = Light::new(context);
-        let shadow = Shadow::ready(context, &mesh,&light);
+        let lights = Lights::new(context);
+        let shadow = Shadow::ready(context, &mesh, &lights);
         Simple2DLightShadow {
             mesh,
-            light,
+            lights,
             shadow,
+            cursor: [0.0, 0.0],
+            dragging: None,
+            window,
+            egui,
         }
     }
 }
 
+impl Simple2DLightShadow {
+    fn editor(&mut self, ui: &egui::Context) {
+        egui::Window::new("Lights")
+            .anchor(Align2::LEFT_TOP, [0.0, 0.0])
+            .resizable(false)
+            .show(ui, |ui| {
+                let mut pcf = self.shadow.params.filter_mode == 1;
+                ui.checkbox(&mut pcf, "PCF (H)");
+                self.shadow.params.filter_mode = pcf as u32;
+                ui.add(
+                    egui::Slider::new(&mut self.shadow.params.softness, 0.0..=0.2).text("softness"),
+                );
+                ui.separator();
+
+                let mut removed = None;
+                for (i, light) in self.lights.lights.iter_mut().enumerate() {
+                    ui.collapsing(format!("light {}", i), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_rgb(&mut light.color);
+                            if ui.button("remove").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                        ui.add(egui::Slider::new(&mut light.radius, 0.1..=3.0).text("radius"));
+                        ui.add(
+                            egui::Slider::new(&mut light.intensity, 0.0..=4.0).text("intensity"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut light.falloff, 0.25..=8.0)
+                                .logarithmic(true)
+                                .text("falloff"),
+                        );
+                    });
+                }
+                if let Some(i) = removed {
+                    self.lights.lights.remove(i);
+                    self.dragging = None;
+                }
+
+                ui.add_enabled_ui(self.lights.lights.len() < MAX_LIGHTS, |ui| {
+                    if ui.button("add light").clicked() {
+                        self.lights.add(LightData::new([0.0, 0.5], [1.0, 1.0, 1.0]));
+                    }
+                });
+            });
+    }
+}
+
 impl Painter for Simple2DLightShadow {
-    fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+    fn paint(&mut self, context: &crate::gfx::GfxContext, _dt: f32, _time: f32) {
+        let config = context.surface_config.as_ref().unwrap();
+
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -47,15 +122,78 @@ impl Painter for Simple2DLightShadow {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
-        let mut encoder = self.shadow.paint(
-            context,
-            &self.mesh,
-            encoder,
+
+        // 先跑 egui，这一帧的修改马上就能用上
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [config.width, config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let mut ui_encoder =
+            context
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("light editor"),
+                });
+        let egui = self.egui.clone();
+        let window = self.window.clone();
+        egui.lock().unwrap().draw(
+            &context.device,
+            &context.queue,
+            &mut ui_encoder,
+            &window,
             &view,
-            &self.light
+            screen_descriptor,
+            |ui| self.editor(ui),
         );
 
-        context.queue.submit(std::iter::once(encoder.finish()));
+        self.lights.upload(context);
+        self.shadow.params.aspect = config.width as f32 / config.height.max(1) as f32;
+        self.shadow.params.light_count = self.lights.lights.len() as u32;
+        self.shadow.write_params(context);
+        encoder = self.shadow.paint(&self.mesh, encoder, &view, &self.lights);
+
+        context
+            .queue
+            .submit([encoder.finish(), ui_encoder.finish()]);
         frame.present();
     }
+
+    fn input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyH),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.shadow.params.filter_mode ^= 1,
+            WindowEvent::CursorMoved { position, .. } => {
+                // 像素 -> 世界坐标，y 在 [-1, 1]，x 按屏幕比例
+                let size = self.window.inner_size();
+                let (w, h) = (size.width.max(1) as f32, size.height.max(1) as f32);
+                let x = position.x as f32 / w * 2.0 - 1.0;
+                let y = 1.0 - position.y as f32 / h * 2.0;
+                self.cursor = [x * w / h, y];
+                if let Some(i) = self.dragging {
+                    self.lights.lights[i].position = self.cursor;
+                }
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    // 点在 egui 窗口上的不算
+                    if !self.egui.lock().unwrap().context.wants_pointer_input() {
+                        self.dragging = self.lights.pick(self.cursor, PICK_RADIUS);
+                    }
+                }
+                ElementState::Released => self.dragging = None,
+            },
+            _ => {}
+        }
+    }
 }
@@ -1,108 +1,172 @@
-use std::{borrow::Cow, rc::Rc};
+use std::borrow::Cow;
 
-use wgpu::{
-    core::{device, pipeline},
-    util::DeviceExt,
-    BindGroupDescriptor, BindGroupEntry, CommandEncoder, CompareFunction, Extent3d,
-    PipelineCompilationOptions, SamplerBindingType, StoreOp, TextureDimension,
-};
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, CommandEncoder, PipelineCompilationOptions};
 
 use crate::gfx::GfxContext;
 
 use super::{
-    light::{self, Light},
+    light::{Lights, MAX_LIGHTS},
     mesh::Mesh,
 };
 
+/// 一圈分成多少个角度，贴图的高是 MAX_LIGHTS，一行一个光源
+pub const SHADOW_RES: u32 = 1024;
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+/// 和 1.wgsl / 2.wgsl 里的 ShadowParams 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct ShadowParams {
+    pub aspect: f32,
+    pub segment_count: u32,
+    /// 0 硬阴影，1 PCF
+    pub filter_mode: u32,
+    /// PCF 模糊的宽度，世界坐标
+    pub softness: f32,
+    pub resolution: u32,
+    pub light_count: u32,
+    pub _pad: [u32; 2],
+}
+
 pub struct Shadow {
+    pub params: ShadowParams,
+    pub params_buffer: wgpu::Buffer,
     pub shadow_pipeline: wgpu::RenderPipeline,
-    pub main_pipeline: wgpu::RenderPipeline,
-    pub depth_texture: wgpu::Texture,
-    pub sampler: wgpu::Sampler,
-    pub bind_group: wgpu::BindGroup,
-    pub depth_view: wgpu::TextureView,
+    pub floor_pipeline: wgpu::RenderPipeline,
+    pub occluder_pipeline: wgpu::RenderPipeline,
+    pub shadow_texture: wgpu::Texture,
+    pub shadow_view: wgpu::TextureView,
+    /// 阴影贴图 pass 用：遮挡物的边 + 参数
+    pub segment_bind_group: wgpu::BindGroup,
+    /// 主 pass 用：阴影贴图 + 参数
+    pub main_bind_group: wgpu::BindGroup,
 }
 
 impl Shadow {
-    pub fn ready(context: &GfxContext, mesh: &Mesh, light: &Light) -> Self {
-        let depth_texture = context.device.create_texture(&wgpu::TextureDescriptor {
+    pub fn ready(context: &GfxContext, mesh: &Mesh, lights: &Lights) -> Self {
+        let shadow_texture = context.device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
-                width: 256,
-                height: 256,
+                width: SHADOW_RES,
+                height: MAX_LIGHTS as u32,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
+            format: SHADOW_FORMAT,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: Some("depth_texture"),
+            label: Some("polar shadow maps"),
             view_formats: &[],
         });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let shadow_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("shadow sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            lod_min_clamp: 0.0,
-            lod_max_clamp: 100.0,
-            compare: Some(wgpu::CompareFunction::Less),
-            anisotropy_clamp: 1,
-            border_color: None,
-        });
+        let params = ShadowParams {
+            aspect: 1.0,
+            segment_count: mesh.segment_count,
+            filter_mode: 1,
+            softness: 0.04,
+            resolution: SHADOW_RES,
+            light_count: lights.lights.len() as u32,
+            _pad: [0; 2],
+        };
+        let params_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("shadow params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let params_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ShadowParams>() as u64),
+            },
+            count: None,
+        };
 
-        let bind_group_layout =
+        let segment_layout =
             context
                 .device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("main pass bind group layout"),
+                    label: Some("shadow pass bind group layout"),
                     entries: &[
                         wgpu::BindGroupLayoutEntry {
                             binding: 0,
                             visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                sample_type: wgpu::TextureSampleType::Depth,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                multisampled: false,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
                             },
                             count: None,
                         },
+                        params_entry(1),
+                    ],
+                });
+        let segment_bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("shadow pass bind group"),
+                layout: &segment_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: mesh.segment_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        // R32Float 不能过滤，主 pass 里用 textureLoad 自己做 PCF
+        let main_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("main pass bind group layout"),
+                    entries: &[
                         wgpu::BindGroupLayoutEntry {
-                            binding: 1,
+                            binding: 0,
                             visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(SamplerBindingType::Comparison),
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
                             count: None,
                         },
+                        params_entry(1),
                     ],
                 });
-        let bind_group = context
+        let main_bind_group = context
             .device
             .create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("main pass bind group"),
-                layout: &bind_group_layout,
+                layout: &main_layout,
                 entries: &[
-                    BindGroupEntry {
+                    wgpu::BindGroupEntry {
                         binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&depth_view),
+                        resource: wgpu::BindingResource::TextureView(&shadow_view),
                     },
-                    BindGroupEntry {
+                    wgpu::BindGroupEntry {
                         binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&sampler),
+                        resource: params_buffer.as_entire_binding(),
                     },
                 ],
             });
-        let ready_depth_in_light_texture_shader =
-            context
-                .device
-                .create_shader_module(wgpu::ShaderModuleDescriptor {
-                    label: Some("ready_depth_in_light_texture_shader"),
-                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("1.wgsl"))),
-                });
+
+        let shadow_shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("1d shadow map shader"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("1.wgsl"))),
+            });
         let main_shader = context
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -110,154 +174,131 @@ impl Shadow {
                 source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("2.wgsl"))),
             });
 
-        let main_pipeline_layout =
-            context
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("shadow pipeline layout out"),
-                    bind_group_layouts: &[&bind_group_layout],
-                    push_constant_ranges: &[],
-                });
         let shadow_pipeline_layout =
             context
                 .device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some("shadow pipeline layout"),
-                    bind_group_layouts: &[&light.light_bind_group_layout],
+                    bind_group_layouts: &[&lights.light_bind_group_layout, &segment_layout],
                     push_constant_ranges: &[],
                 });
-        let shadow_pipeline =
+        let main_pipeline_layout =
             context
                 .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("shadow_pipeline"),
-                    layout: Some(&shadow_pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &ready_depth_in_light_texture_shader,
-                        entry_point: "vs_main",
-                        buffers: &[wgpu::VertexBufferLayout {
-                            array_stride: 2 * 4,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &[wgpu::VertexAttribute {
-                                format: wgpu::VertexFormat::Float32x2,
-                                offset: 0,
-                                shader_location: 0,
-                            }],
-                        }],
-                        compilation_options: PipelineCompilationOptions::default(),
-                    },
-                    fragment: None,
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        conservative: false,
-                        unclipped_depth: false,
-                    },
-                    depth_stencil: Some(wgpu::DepthStencilState {
-                        format: wgpu::TextureFormat::Depth32Float,
-                        depth_write_enabled: true,
-                        depth_compare: CompareFunction::Less,
-                        stencil: Default::default(),
-                        bias: Default::default(),
-                    }),
-                    multisample: wgpu::MultisampleState::default(),
-                    multiview: None,
-                    cache: None,
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("main pipeline layout"),
+                    bind_group_layouts: &[&lights.light_bind_group_layout, &main_layout],
+                    push_constant_ranges: &[],
                 });
 
-        let main_pipeline =
+        let fullscreen = |label, layout, module, vs, fs, format, buffers| {
             context
                 .device
                 .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("main_pipeline"),
-                    layout: Some(&main_pipeline_layout),
+                    label: Some(label),
+                    layout: Some(layout),
                     vertex: wgpu::VertexState {
-                        module: &main_shader,
-                        entry_point: "vs_main",
-                        buffers: &[wgpu::VertexBufferLayout {
-                            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                            step_mode: wgpu::VertexStepMode::Vertex,
-                            attributes: &[
-                                wgpu::VertexAttribute {
-                                    offset: 0,
-                                    shader_location: 0,
-                                    format: wgpu::VertexFormat::Float32x2,
-                                },
-                                wgpu::VertexAttribute {
-                                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                                    shader_location: 1,
-                                    format: wgpu::VertexFormat::Float32x2,
-                                },
-                            ],
-                        }],
+                        module,
+                        entry_point: vs,
+                        buffers,
                         compilation_options: PipelineCompilationOptions::default(),
                     },
                     fragment: Some(wgpu::FragmentState {
-                        module: &main_shader,
-                        entry_point: "fs_main",
+                        module,
+                        entry_point: fs,
                         targets: &[Some(wgpu::ColorTargetState {
-                            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+                            format,
                             blend: None,
                             write_mask: wgpu::ColorWrites::ALL,
                         })],
                         compilation_options: PipelineCompilationOptions::default(),
                     }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        conservative: false,
-                        unclipped_depth: false,
-                    },
+                    primitive: wgpu::PrimitiveState::default(),
                     depth_stencil: None,
                     multisample: wgpu::MultisampleState::default(),
                     multiview: None,
                     cache: None,
-                });
+                })
+        };
+        let surface_format = context.surface_config.as_ref().unwrap().view_formats[0];
+        let occluder_buffers = [wgpu::VertexBufferLayout {
+            array_stride: 2 * 4,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+        }];
+        let shadow_pipeline = fullscreen(
+            "shadow_pipeline",
+            &shadow_pipeline_layout,
+            &shadow_shader,
+            "vs_main",
+            "fs_main",
+            SHADOW_FORMAT,
+            &[],
+        );
+        let floor_pipeline = fullscreen(
+            "floor_pipeline",
+            &main_pipeline_layout,
+            &main_shader,
+            "vs_floor",
+            "fs_floor",
+            surface_format,
+            &[],
+        );
+        let occluder_pipeline = fullscreen(
+            "occluder_pipeline",
+            &main_pipeline_layout,
+            &main_shader,
+            "vs_occluder",
+            "fs_occluder",
+            surface_format,
+            &occluder_buffers,
+        );
 
         Self {
+            params,
+            params_buffer,
             shadow_pipeline,
-            main_pipeline,
-            depth_texture,
-            sampler,
-            bind_group,
-            depth_view,
+            floor_pipeline,
+            occluder_pipeline,
+            shadow_texture,
+            shadow_view,
+            segment_bind_group,
+            main_bind_group,
         }
     }
 
-    pub fn paint<'a>(
+    pub fn write_params(&self, context: &GfxContext) {
+        context
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&self.params));
+    }
+
+    pub fn paint(
         &self,
-        context: &GfxContext,
         mesh: &Mesh,
         mut encoder: CommandEncoder,
-        view: &'a wgpu::TextureView,
-        light: &Light,
+        view: &wgpu::TextureView,
+        lights: &Lights,
     ) -> wgpu::CommandEncoder {
         {
             let mut shadow_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("shadow pass"),
-                color_attachments: &[],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.shadow_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
                         store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
+                    },
+                })],
+                depth_stencil_attachment: None,
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
             shadow_pass.set_pipeline(&self.shadow_pipeline);
-            shadow_pass.set_bind_group(0, &light.light_bind_group, &[]);
-            shadow_pass.set_vertex_buffer(0, mesh.vbuffer.slice(..));
-            shadow_pass.set_index_buffer(mesh.ibuffer.slice(..), wgpu::IndexFormat::Uint16);
-            shadow_pass.draw_indexed(0..6, 0, 0..1);
+            shadow_pass.set_bind_group(0, &lights.light_bind_group, &[]);
+            shadow_pass.set_bind_group(1, &self.segment_bind_group, &[]);
+            shadow_pass.draw(0..3, 0..1);
         }
 
         {
@@ -280,46 +321,15 @@ impl Shadow {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
-            let vertices = [
-                Vertex {
-                    position: [-10.0, -10.0],
-                    tex_coords: [0.0, 0.0],
-                },
-                Vertex {
-                    position: [-10.0, 10.0],
-                    tex_coords: [0.0, 1.0],
-                },
-                Vertex {
-                    position: [10.0, -10.0],
-                    tex_coords: [1.0, 0.0],
-                },
-                Vertex {
-                    position: [10.0, 10.0],
-                    tex_coords: [1.0, 1.0],
-                },
-            ];
-
-            let vertex_buffer =
-                context
-                    .device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Vertex Buffer"),
-                        contents: bytemuck::cast_slice(&vertices),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    });
-            main_pass.set_pipeline(&self.main_pipeline);
-            main_pass.set_bind_group(0, &self.bind_group, &[]);
-            main_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            main_pass.set_bind_group(0, &lights.light_bind_group, &[]);
+            main_pass.set_bind_group(1, &self.main_bind_group, &[]);
+            main_pass.set_pipeline(&self.floor_pipeline);
+            main_pass.draw(0..3, 0..1);
+            main_pass.set_pipeline(&self.occluder_pipeline);
+            main_pass.set_vertex_buffer(0, mesh.vbuffer.slice(..));
             main_pass.set_index_buffer(mesh.ibuffer.slice(..), wgpu::IndexFormat::Uint16);
-            main_pass.draw_indexed(0..6, 0, 0..1);
+            main_pass.draw_indexed(0..mesh.index_count, 0, 0..1);
         }
         encoder
     }
 }
-
-#[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 2],
-    tex_coords: [f32; 2],
-}
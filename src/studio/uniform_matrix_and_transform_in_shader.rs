@@ -4,7 +4,10 @@ use bytemuck::{Pod, Zeroable};
 use cgmath::{perspective, Deg, Matrix4, Point3, Vector3};
 use wgpu::util::DeviceExt;
 
-use crate::painter::{Painter, Sandy};
+use crate::{
+    painter::{Painter, Sandy},
+    utils::view_modes::{MeshLayout, ViewCamera, ViewMesh, ViewModes},
+};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Pod, Zeroable)]
@@ -117,6 +120,9 @@ pub struct UniformMatrixAtGpu {
     pub bind_group: wgpu::BindGroup,
     pub pipeline: wgpu::RenderPipeline,
     pub time_uniform_buffer: wgpu::Buffer,
+    /// 按 V 换调试视图
+    pub(crate) view_modes: ViewModes,
+    view_proj: glam::Mat4,
 }
 
 impl Sandy for UniformMatrixAtGpu {
@@ -127,6 +133,7 @@ impl Sandy for UniformMatrixAtGpu {
         Self: Sized,
     {
         let vertex_data = gen_vertexes();
+        let env = gen_env_world();
         let index_data = gen_indexes();
 
         let vertex_buffer = context
@@ -134,7 +141,8 @@ impl Sandy for UniformMatrixAtGpu {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
                 contents: bytemuck::cast_slice(vertex_data),
-                usage: wgpu::BufferUsages::VERTEX,
+                // STORAGE 给 view_modes 读
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
             });
 
         let indexes_buffer = context
@@ -142,7 +150,7 @@ impl Sandy for UniformMatrixAtGpu {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
                 contents: bytemuck::cast_slice(index_data),
-                usage: wgpu::BufferUsages::INDEX,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE,
             });
 
         let env_matrix_uniform_buffer =
@@ -150,7 +158,7 @@ impl Sandy for UniformMatrixAtGpu {
                 .device
                 .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("Veenv_matrix_uniform_bufferrtex Buffer"),
-                    contents: bytemuck::cast_slice(&[gen_env_world()]),
+                    contents: bytemuck::cast_slice(&[env]),
                     usage: wgpu::BufferUsages::UNIFORM,
                 });
         let time_uniform_buffer =
@@ -280,18 +288,34 @@ impl Sandy for UniformMatrixAtGpu {
                 multiview: None,
                 cache: None,
             });
+        // 顶点是位置 + 两个 f32，shader 里当颜色用，调试视图当 uv 看
+        let view_modes = ViewModes::new(
+            context,
+            context.surface_config.as_ref().unwrap().format,
+            MeshLayout {
+                stride: 5,
+                normal: None,
+                uv: Some(3),
+                index_format: wgpu::IndexFormat::Uint16,
+            },
+        );
+        let view_proj =
+            glam::Mat4::from_cols_array_2d(&env.proj) * glam::Mat4::from_cols_array_2d(&env.view);
         Self {
             vertex_buffer,
             indexes_buffer,
             bind_group,
             pipeline,
             time_uniform_buffer,
+            view_modes,
+            view_proj,
         }
     }
 }
 
 impl Painter for UniformMatrixAtGpu {
     fn paint(&mut self, context: &crate::gfx::GfxContext, dt: f32, time: f32) {
+        let mode = self.view_modes.sync(context);
         let frame = context.surface.get_current_texture().unwrap();
         let view = frame
             .texture
@@ -323,15 +347,39 @@ impl Painter for UniformMatrixAtGpu {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: context.render_timestamps("uniform matrix"),
                 occlusion_query_set: None,
             });
-            render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.indexes_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..36, 0, 0..1);
+            if !mode.replaces_scene() {
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(0, &self.bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(self.indexes_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..36, 0, 0..1);
+            }
         }
+        // 和 shader 里 vs_main 对顶点做的缩放一样，world 是单位矩阵
+        let scale = time.sin() * 0.5 + 1.5;
+        let model =
+            glam::Mat4::from_scale(glam::Vec3::new(time.sin() * scale, time.cos() * scale, 1.0));
+        self.view_modes.render(
+            context,
+            &mut encoder,
+            &view,
+            &ViewCamera {
+                view_proj: self.view_proj,
+                near: 0.1,
+                far: 100.0,
+            },
+            &[ViewMesh {
+                vertices: &self.vertex_buffer,
+                indices: &self.indexes_buffer,
+                index_count: 36,
+                model,
+                viewport: None,
+            }],
+        );
         context.queue.submit(std::iter::once(encoder.finish()));
         frame.present();
     }
@@ -0,0 +1,615 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::{gfx, texture::SamplerOptions};
+
+pub(crate) mod font;
+mod msdf;
+mod raster;
+
+use font::Font;
+
+// 不靠 egui 的文字绘制，HUD、帧率、坐标轴标签都可以用
+// 两种模式：
+//   Bitmap：字形按用到的字号现画成覆盖率位图，塞进一张 R8 图集，一个纹素对一个像素
+//   Msdf：每个字形只按 MSDF_SIZE 生成一次多通道距离场（msdf.rs），放多大都清楚，还能加描边、发光、阴影
+// 每个字是一条实例数据，整批一次 draw
+// 三种摆法：屏幕像素（左上角是原点）、锚在世界里一点上按像素摆、在世界里按世界单位摆（朝着相机）
+
+pub(crate) const DEJAVU_SANS: &str = "asset/DejaVuSans.ttf";
+
+const ATLAS_SIZE: u32 = 1024;
+/// 图集里字形之间的空隙
+const PADDING: u32 = 1;
+/// Msdf 模式下距离场按多少像素一个 em 生成
+const MSDF_SIZE: f32 = 32.0;
+/// Bitmap 模式下按世界单位摆的字按这个字号画，放大了会糊
+const WORLD_BITMAP_SIZE: f32 = 32.0;
+/// 描边、发光、阴影最多能伸出字形多远，em
+pub(crate) const MAX_EFFECT: f32 = msdf::RANGE / MSDF_SIZE;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum TextMode {
+    Bitmap,
+    Msdf,
+}
+
+/// 只有 Msdf 模式认；长度都按 em 算，加起来别超过 MAX_EFFECT
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TextEffects {
+    pub(crate) outline_width: f32,
+    pub(crate) outline_color: [f32; 4],
+    pub(crate) glow_radius: f32,
+    pub(crate) glow_color: [f32; 4],
+    /// y 朝下
+    pub(crate) shadow_offset: [f32; 2],
+    pub(crate) shadow_softness: f32,
+    pub(crate) shadow_color: [f32; 4],
+}
+
+impl Default for TextEffects {
+    fn default() -> Self {
+        TextEffects {
+            outline_width: 0.0,
+            outline_color: [0.0, 0.0, 0.0, 1.0],
+            glow_radius: 0.0,
+            glow_color: [1.0, 0.8, 0.3, 0.8],
+            shadow_offset: [0.0, 0.0],
+            shadow_softness: 0.0,
+            shadow_color: [0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// 和 text.wgsl 里的 TextGlobals 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct TextGlobals {
+    view_proj: [[f32; 4]; 4],
+    /// 相机的右和上，按世界单位摆的字用，w 不用
+    right: [f32; 4],
+    up: [f32; 4],
+    screen: [f32; 2],
+    /// 距离场编码的总跨度，em
+    sdf_range: f32,
+    /// 图集里一个 em 多少纹素
+    sdf_em: f32,
+    outline_color: [f32; 4],
+    glow_color: [f32; 4],
+    shadow_color: [f32; 4],
+    shadow_offset: [f32; 2],
+    outline_width: f32,
+    glow_radius: f32,
+    shadow_softness: f32,
+    _pad: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct GlyphInstance {
+    anchor: [f32; 3],
+    /// 0 屏幕，1 锚在世界里按像素摆，2 按世界单位摆
+    space: u32,
+    /// 字形左上角相对 anchor 的偏移，space 是 2 的时候是世界单位，不然是像素
+    offset: [f32; 2],
+    size: [f32; 2],
+    uv: [f32; 4],
+    /// 0xAABBGGRR
+    color: u32,
+}
+
+impl GlyphInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Uint32,
+        2 => Float32x2,
+        3 => Float32x2,
+        4 => Float32x4,
+        5 => Uint32,
+    ];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GlyphInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// 图集里的一个字形
+#[derive(Copy, Clone)]
+struct CachedGlyph {
+    offset: Vec2,
+    size: Vec2,
+    uv: [f32; 4],
+}
+
+/// 一行一行往下摆的图集，满了清掉重来，同一批字放不下就不再清，见 TextRenderer::prepare
+struct GlyphAtlas {
+    mode: TextMode,
+    texture: wgpu::Texture,
+    /// 当前这一行的起点和行高
+    cursor: (u32, u32),
+    row_height: u32,
+    /// (字形, 生成时的字号) -> 图集里的位置，没有轮廓的字是 None
+    glyphs: HashMap<(u16, u32), Option<CachedGlyph>>,
+    /// 有字放不下了，清掉之前新的字都不用再画
+    full: bool,
+}
+
+impl GlyphAtlas {
+    fn clear(&mut self) {
+        self.cursor = (0, 0);
+        self.row_height = 0;
+        self.glyphs.clear();
+        self.full = false;
+    }
+
+    /// 放不下返回 Err
+    fn get(
+        &mut self,
+        context: &gfx::GfxContext,
+        font: &Font,
+        id: u16,
+        size: u32,
+    ) -> Result<Option<CachedGlyph>, ()> {
+        if let Some(glyph) = self.glyphs.get(&(id, size)) {
+            return Ok(*glyph);
+        }
+        if self.full {
+            return Err(());
+        }
+        // 坏字形当成空白，不至于整行都画不出来
+        let scale = font.scale(size as f32);
+        let bitmap = font.outline(id).ok().and_then(|outline| match self.mode {
+            TextMode::Bitmap => raster::rasterize(&outline, scale),
+            TextMode::Msdf => msdf::generate(&outline, scale),
+        });
+        let Some(bitmap) = bitmap else {
+            self.glyphs.insert((id, size), None);
+            return Ok(None);
+        };
+
+        let (mut x, mut y) = self.cursor;
+        if x + bitmap.width + PADDING > ATLAS_SIZE {
+            (x, y) = (0, y + self.row_height);
+            self.row_height = 0;
+        }
+        if y + bitmap.height + PADDING > ATLAS_SIZE || bitmap.width + PADDING > ATLAS_SIZE {
+            self.full = true;
+            return Err(());
+        }
+        self.cursor = (x + bitmap.width + PADDING, y);
+        self.row_height = self.row_height.max(bitmap.height + PADDING);
+
+        context.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bitmap.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bitmap.width * self.mode.bytes_per_pixel()),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: bitmap.width,
+                height: bitmap.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        let atlas = ATLAS_SIZE as f32;
+        let glyph = CachedGlyph {
+            offset: bitmap.offset,
+            size: Vec2::new(bitmap.width as f32, bitmap.height as f32),
+            uv: [
+                x as f32 / atlas,
+                y as f32 / atlas,
+                (x + bitmap.width) as f32 / atlas,
+                (y + bitmap.height) as f32 / atlas,
+            ],
+        };
+        self.glyphs.insert((id, size), Some(glyph));
+        Ok(Some(glyph))
+    }
+}
+
+impl TextMode {
+    fn format(self) -> wgpu::TextureFormat {
+        match self {
+            TextMode::Bitmap => wgpu::TextureFormat::R8Unorm,
+            TextMode::Msdf => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    fn bytes_per_pixel(self) -> u32 {
+        match self {
+            TextMode::Bitmap => 1,
+            TextMode::Msdf => 4,
+        }
+    }
+
+    /// 一段字号为 size 的字，字形从图集里哪个字号拿
+    fn raster_size(self, space: u32, size: f32) -> f32 {
+        match (self, space) {
+            (TextMode::Msdf, _) => MSDF_SIZE,
+            (TextMode::Bitmap, 2) => WORLD_BITMAP_SIZE,
+            (TextMode::Bitmap, _) => size,
+        }
+    }
+}
+
+/// 这一帧要画的一段字，prepare 的时候才排版
+struct Label {
+    anchor: Vec3,
+    space: u32,
+    /// 文字框左上角相对 anchor 的偏移，单位和 size 一样
+    offset: Vec2,
+    /// 一个 em 多大，space 是 2 的时候是世界单位，不然是像素
+    size: f32,
+    color: u32,
+    text: String,
+}
+
+pub(crate) struct TextRenderer {
+    pub(crate) font: Font,
+    pub(crate) effects: TextEffects,
+    atlas: GlyphAtlas,
+    pipeline: wgpu::RenderPipeline,
+    globals_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    capacity: usize,
+    labels: Vec<Label>,
+    instances: Vec<GlyphInstance>,
+    /// 上一帧有字因为图集满了没画出来，变成 true 的那一帧打印一次
+    atlas_full: bool,
+    /// 上次因为放不下清图集的时候要画的字形，要的还是这些就不再清
+    overflowed: Option<HashSet<(u16, u32)>>,
+}
+
+impl TextRenderer {
+    pub(crate) fn new(
+        context: &gfx::GfxContext,
+        format: wgpu::TextureFormat,
+        font: Font,
+        mode: TextMode,
+    ) -> Self {
+        let globals_buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text globals"),
+            size: std::mem::size_of::<TextGlobals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyph atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: mode.format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = match mode {
+            // 字形摆在整像素上，一个纹素对一个像素
+            TextMode::Bitmap => SamplerOptions::nearest(),
+            // 距离场要线性插值
+            TextMode::Msdf => SamplerOptions::default(),
+        }
+        .create_sampler(context, Some("glyph atlas"));
+
+        let bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("text bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                });
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("text bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: globals_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("text pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+        let shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("text shader"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("text/text.wgsl"))),
+            });
+        let pipeline = context
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("text pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: Default::default(),
+                    buffers: &[GlyphInstance::layout()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: match mode {
+                        TextMode::Bitmap => "fs_bitmap",
+                        TextMode::Msdf => "fs_msdf",
+                    },
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        let capacity = 256;
+        Self {
+            font,
+            effects: TextEffects::default(),
+            atlas: GlyphAtlas {
+                mode,
+                texture,
+                cursor: (0, 0),
+                row_height: 0,
+                glyphs: HashMap::new(),
+                full: false,
+            },
+            pipeline,
+            globals_buffer,
+            bind_group,
+            instance_buffer: Self::create_instance_buffer(context, capacity),
+            capacity,
+            labels: Vec::new(),
+            instances: Vec::with_capacity(capacity),
+            atlas_full: false,
+            overflowed: None,
+        }
+    }
+
+    fn create_instance_buffer(context: &gfx::GfxContext, capacity: usize) -> wgpu::Buffer {
+        context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("glyph instances"),
+            size: (capacity * std::mem::size_of::<GlyphInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Bitmap 模式下按像素摆的字号取整，图集里才能复用
+    fn snap_size(&self, space: u32, size: f32) -> f32 {
+        match (self.atlas.mode, space) {
+            (TextMode::Bitmap, 0 | 1) => size.round().max(1.0),
+            _ => size,
+        }
+    }
+
+    /// 文字框大小，单位和 size 一样
+    pub(crate) fn measure(&self, text: &str, size: f32) -> Vec2 {
+        self.font.measure(text, self.snap_size(0, size))
+    }
+
+    /// position 是文字框左上角的屏幕像素坐标
+    pub(crate) fn screen(&mut self, position: Vec2, size: f32, color: u32, text: &str) {
+        self.labels.push(Label {
+            anchor: position.round().extend(0.0),
+            space: 0,
+            offset: Vec2::ZERO,
+            size: self.snap_size(0, size),
+            color,
+            text: text.to_owned(),
+        });
+    }
+
+    /// 标在世界空间的一点上，按像素摆，文字框底边中点对着这个点
+    pub(crate) fn world(&mut self, position: Vec3, size: f32, color: u32, text: &str) {
+        let extent = self.measure(text, size);
+        self.labels.push(Label {
+            anchor: position,
+            space: 1,
+            offset: Vec2::new(-extent.x * 0.5, -extent.y).round(),
+            size: self.snap_size(1, size),
+            color,
+            text: text.to_owned(),
+        });
+    }
+
+    /// 在世界里按世界单位摆，size 是一个 em 多长，朝着相机；position 是文字框左上角
+    pub(crate) fn world_sized(&mut self, position: Vec3, size: f32, color: u32, text: &str) {
+        self.labels.push(Label {
+            anchor: position,
+            space: 2,
+            offset: Vec2::ZERO,
+            size,
+            color,
+            text: text.to_owned(),
+        });
+    }
+
+    /// 图集里缓存了多少个字形
+    pub(crate) fn cached_glyphs(&self) -> usize {
+        self.atlas.glyphs.len()
+    }
+
+    /// 图集满了、上一帧有字被丢掉的时候为 true，给 HUD 显示
+    pub(crate) fn atlas_full(&self) -> bool {
+        self.atlas_full
+    }
+
+    /// 在 render pass 之前调：排版、把新字形画进图集、写实例；这一帧排的字到这里就清空了
+    /// view 只用来让 world_sized 的字朝着相机，2D 场景给 IDENTITY
+    pub(crate) fn prepare(&mut self, context: &gfx::GfxContext, view_proj: Mat4, view: Mat4) {
+        let config = context.surface_config.as_ref().unwrap();
+        let effects = self.effects;
+        let globals = TextGlobals {
+            view_proj: view_proj.to_cols_array_2d(),
+            right: view.row(0).to_array(),
+            up: view.row(1).to_array(),
+            screen: [config.width as f32, config.height as f32],
+            sdf_range: 2.0 * msdf::RANGE / MSDF_SIZE,
+            sdf_em: MSDF_SIZE,
+            outline_color: effects.outline_color,
+            glow_color: effects.glow_color,
+            shadow_color: effects.shadow_color,
+            shadow_offset: effects.shadow_offset,
+            outline_width: effects.outline_width,
+            glow_radius: effects.glow_radius,
+            shadow_softness: effects.shadow_softness,
+            _pad: [0.0; 3],
+        };
+        context
+            .queue
+            .write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&globals));
+
+        // 图集满了就清掉从头排一遍；清过一次还放不下，只要要画的字形没变就不再清，
+        // 放不下的字先不画，不然每帧都要把所有字形重新生成一遍
+        let requested = self.build_instances(context);
+        let full = match requested {
+            None => false,
+            Some(requested) if self.overflowed.as_ref() == Some(&requested) => true,
+            Some(requested) => {
+                self.atlas.clear();
+                let full = self.build_instances(context).is_some();
+                self.overflowed = Some(requested);
+                full
+            }
+        };
+        if full && !self.atlas_full {
+            println!("glyph atlas is full, some text is dropped");
+        }
+        self.atlas_full = full;
+        self.labels.clear();
+
+        if self.instances.len() > self.capacity {
+            self.capacity = self.instances.len().next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(context, self.capacity);
+        }
+        context.queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.instances),
+        );
+    }
+
+    /// 放不下的字跳过；都放下了返回 None，不然返回这一帧要的所有字形
+    fn build_instances(&mut self, context: &gfx::GfxContext) -> Option<HashSet<(u16, u32)>> {
+        self.instances.clear();
+        let mut requested = HashSet::new();
+        let mut dropped = false;
+        let mode = self.atlas.mode;
+        for label in &self.labels {
+            let raster_size = mode.raster_size(label.space, label.size);
+            // 图集里的像素换算到这段字的单位
+            let scale = label.size / raster_size;
+            // 覆盖率位图按原大小画的时候对齐到整像素
+            let snap = mode == TextMode::Bitmap && label.space != 2;
+            let (glyphs, _) = self.font.layout(&label.text, label.size);
+            for placed in glyphs {
+                requested.insert((placed.id, raster_size as u32));
+                let Ok(glyph) = self
+                    .atlas
+                    .get(context, &self.font, placed.id, raster_size as u32)
+                else {
+                    dropped = true;
+                    continue;
+                };
+                let Some(glyph) = glyph else {
+                    continue;
+                };
+                let offset = label.offset + placed.position + glyph.offset * scale;
+                self.instances.push(GlyphInstance {
+                    anchor: label.anchor.to_array(),
+                    space: label.space,
+                    offset: if snap { offset.round() } else { offset }.to_array(),
+                    size: (glyph.size * scale).to_array(),
+                    uv: glyph.uv,
+                    color: label.color,
+                });
+            }
+        }
+        dropped.then_some(requested)
+    }
+
+    pub(crate) fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        if self.instances.is_empty() {
+            return;
+        }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        rpass.draw(0..4, 0..self.instances.len() as u32);
+    }
+}
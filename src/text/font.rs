@@ -0,0 +1,612 @@
+// 很小的 TrueType 读取，够 asset/DejaVuSans.ttf 用
+// 只认 cmap（格式 4 / 12）、glyf / loca 里的二次曲线轮廓（包括组合字形）、
+// hmtx 的前进宽度和 kern 表格式 0 的字距；不跑 hinting，也不管 GPOS 和连字
+// 轮廓给的是字体单位，y 朝上；排版给的是像素，y 朝下
+use std::{collections::HashMap, path::Path};
+
+use glam::Vec2;
+
+#[derive(Debug)]
+pub(crate) enum FontError {
+    Io(std::io::Error),
+    Format(String),
+}
+
+impl std::fmt::Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "font io error: {}", e),
+            FontError::Format(message) => write!(f, "bad font: {}", message),
+        }
+    }
+}
+
+impl From<std::io::Error> for FontError {
+    fn from(e: std::io::Error) -> Self {
+        FontError::Io(e)
+    }
+}
+
+/// 大端读取，越界报错而不是 panic
+#[derive(Copy, Clone)]
+struct Bytes<'a>(&'a [u8]);
+
+impl Bytes<'_> {
+    fn get<const N: usize>(&self, offset: usize) -> Result<[u8; N], FontError> {
+        self.0
+            .get(offset..offset + N)
+            .map(|b| b.try_into().unwrap())
+            .ok_or_else(|| FontError::Format(format!("read past the end at {}", offset)))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, FontError> {
+        Ok(u16::from_be_bytes(self.get(offset)?))
+    }
+
+    fn i16(&self, offset: usize) -> Result<i16, FontError> {
+        Ok(i16::from_be_bytes(self.get(offset)?))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, FontError> {
+        Ok(u32::from_be_bytes(self.get(offset)?))
+    }
+}
+
+/// 轮廓上的一段，字体单位
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum Segment {
+    Line(Vec2, Vec2),
+    /// 起点、控制点、终点
+    Quad(Vec2, Vec2, Vec2),
+}
+
+impl Segment {
+    pub(crate) fn map_points(self, f: impl Fn(Vec2) -> Vec2) -> Segment {
+        match self {
+            Segment::Line(a, b) => Segment::Line(f(a), f(b)),
+            Segment::Quad(a, c, b) => Segment::Quad(f(a), f(c), f(b)),
+        }
+    }
+}
+
+/// 一个字形的轮廓，每个 contour 首尾相接
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Outline {
+    pub(crate) min: Vec2,
+    pub(crate) max: Vec2,
+    pub(crate) contours: Vec<Vec<Segment>>,
+}
+
+/// 排好的一个字，position 是它在基线上的笔位置，相对文字框左上角
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct PlacedGlyph {
+    pub(crate) id: u16,
+    pub(crate) position: Vec2,
+}
+
+pub(crate) struct Font {
+    data: Vec<u8>,
+    glyf: usize,
+    loca: usize,
+    long_loca: bool,
+    hmtx: usize,
+    h_metrics: u16,
+    glyph_count: u16,
+    pub(crate) units_per_em: f32,
+    /// 下面三个是字体单位，descender 是负的
+    pub(crate) ascender: f32,
+    pub(crate) descender: f32,
+    pub(crate) line_gap: f32,
+    chars: HashMap<char, u16>,
+    kerning: HashMap<(u16, u16), i16>,
+}
+
+/// 组合字形最多套几层，坏字体里的环不至于把栈撑爆
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+impl Font {
+    pub(crate) fn load(path: impl AsRef<Path>) -> Result<Self, FontError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub(crate) fn from_bytes(data: Vec<u8>) -> Result<Self, FontError> {
+        let bytes = Bytes(&data);
+        let mut tables = HashMap::new();
+        for i in 0..bytes.u16(4)? as usize {
+            let record = 12 + 16 * i;
+            let tag: [u8; 4] = bytes.get(record)?;
+            tables.insert(tag, bytes.u32(record + 8)? as usize);
+        }
+        let table = |tag: &[u8; 4]| {
+            tables.get(tag).copied().ok_or_else(|| {
+                FontError::Format(format!("missing `{}` table", String::from_utf8_lossy(tag)))
+            })
+        };
+
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let cmap = table(b"cmap")?;
+        let mut font = Font {
+            glyf: table(b"glyf")?,
+            loca: table(b"loca")?,
+            long_loca: bytes.i16(head + 50)? != 0,
+            hmtx: table(b"hmtx")?,
+            h_metrics: bytes.u16(hhea + 34)?,
+            glyph_count: bytes.u16(table(b"maxp")? + 4)?,
+            units_per_em: bytes.u16(head + 18)? as f32,
+            ascender: bytes.i16(hhea + 4)? as f32,
+            descender: bytes.i16(hhea + 6)? as f32,
+            line_gap: bytes.i16(hhea + 8)? as f32,
+            chars: read_cmap(bytes, cmap)?,
+            kerning: match tables.get(b"kern") {
+                Some(&kern) => read_kern(bytes, kern)?,
+                None => HashMap::new(),
+            },
+            data: Vec::new(),
+        };
+        font.data = data;
+        Ok(font)
+    }
+
+    fn bytes(&self) -> Bytes<'_> {
+        Bytes(&self.data)
+    }
+
+    /// 没有这个字的时候给 0 号字形（一般是个方框）
+    pub(crate) fn glyph_id(&self, c: char) -> u16 {
+        self.chars.get(&c).copied().unwrap_or(0)
+    }
+
+    /// 字体单位
+    pub(crate) fn advance(&self, id: u16) -> f32 {
+        let index = id.min(self.h_metrics.saturating_sub(1)) as usize;
+        self.bytes().u16(self.hmtx + 4 * index).unwrap_or(0) as f32
+    }
+
+    /// 字体单位，一般是负的
+    pub(crate) fn kerning(&self, left: u16, right: u16) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0) as f32
+    }
+
+    /// 字体单位到 size 像素的缩放
+    pub(crate) fn scale(&self, size: f32) -> f32 {
+        size / self.units_per_em
+    }
+
+    /// 像素
+    pub(crate) fn line_height(&self, size: f32) -> f32 {
+        (self.ascender - self.descender + self.line_gap) * self.scale(size)
+    }
+
+    fn glyph_range(&self, id: u16) -> Result<(usize, usize), FontError> {
+        if id >= self.glyph_count {
+            return Err(FontError::Format(format!("glyph {} out of range", id)));
+        }
+        let bytes = self.bytes();
+        let id = id as usize;
+        let (start, end) = if self.long_loca {
+            (
+                bytes.u32(self.loca + 4 * id)? as usize,
+                bytes.u32(self.loca + 4 * id + 4)? as usize,
+            )
+        } else {
+            (
+                bytes.u16(self.loca + 2 * id)? as usize * 2,
+                bytes.u16(self.loca + 2 * id + 2)? as usize * 2,
+            )
+        };
+        Ok((self.glyf + start, self.glyf + end))
+    }
+
+    /// 空格之类没有轮廓的字给空的 Outline
+    pub(crate) fn outline(&self, id: u16) -> Result<Outline, FontError> {
+        self.outline_at_depth(id, 0)
+    }
+
+    fn outline_at_depth(&self, id: u16, depth: u32) -> Result<Outline, FontError> {
+        let (start, end) = self.glyph_range(id)?;
+        if start == end {
+            return Ok(Outline::default());
+        }
+        let bytes = self.bytes();
+        let contour_count = bytes.i16(start)?;
+        let min = Vec2::new(bytes.i16(start + 2)? as f32, bytes.i16(start + 4)? as f32);
+        let max = Vec2::new(bytes.i16(start + 6)? as f32, bytes.i16(start + 8)? as f32);
+        let contours = if contour_count >= 0 {
+            simple_contours(bytes, start + 10, contour_count as usize)?
+        } else {
+            if depth >= MAX_COMPONENT_DEPTH {
+                return Err(FontError::Format(format!("glyph {} nests too deep", id)));
+            }
+            self.composite_contours(start + 10, depth)?
+        };
+        Ok(Outline { min, max, contours })
+    }
+
+    /// 组合字形：几个别的字形各自变换以后拼起来，比如带声调的字母
+    fn composite_contours(
+        &self,
+        mut offset: usize,
+        depth: u32,
+    ) -> Result<Vec<Vec<Segment>>, FontError> {
+        const ARGS_ARE_WORDS: u16 = 0x1;
+        const ARGS_ARE_XY: u16 = 0x2;
+        const HAS_SCALE: u16 = 0x8;
+        const MORE_COMPONENTS: u16 = 0x20;
+        const HAS_XY_SCALE: u16 = 0x40;
+        const HAS_2X2: u16 = 0x80;
+
+        let bytes = self.bytes();
+        let f2dot14 = |offset: usize| Ok::<_, FontError>(bytes.i16(offset)? as f32 / 16384.0);
+        let mut contours = Vec::new();
+        loop {
+            let flags = bytes.u16(offset)?;
+            let component = bytes.u16(offset + 2)?;
+            offset += 4;
+            let (dx, dy) = if flags & ARGS_ARE_WORDS != 0 {
+                offset += 4;
+                (bytes.i16(offset - 4)? as f32, bytes.i16(offset - 2)? as f32)
+            } else {
+                offset += 2;
+                (
+                    bytes.get::<1>(offset - 2)?[0] as i8 as f32,
+                    bytes.get::<1>(offset - 1)?[0] as i8 as f32,
+                )
+            };
+            // 按点对齐的组合很少见，当成不偏移
+            let translation = if flags & ARGS_ARE_XY != 0 {
+                Vec2::new(dx, dy)
+            } else {
+                Vec2::ZERO
+            };
+            let (x_axis, y_axis) = if flags & HAS_SCALE != 0 {
+                let s = f2dot14(offset)?;
+                offset += 2;
+                (Vec2::new(s, 0.0), Vec2::new(0.0, s))
+            } else if flags & HAS_XY_SCALE != 0 {
+                let (sx, sy) = (f2dot14(offset)?, f2dot14(offset + 2)?);
+                offset += 4;
+                (Vec2::new(sx, 0.0), Vec2::new(0.0, sy))
+            } else if flags & HAS_2X2 != 0 {
+                let x_axis = Vec2::new(f2dot14(offset)?, f2dot14(offset + 2)?);
+                let y_axis = Vec2::new(f2dot14(offset + 4)?, f2dot14(offset + 6)?);
+                offset += 8;
+                (x_axis, y_axis)
+            } else {
+                (Vec2::X, Vec2::Y)
+            };
+
+            let outline = self.outline_at_depth(component, depth + 1)?;
+            let transform = |p: Vec2| x_axis * p.x + y_axis * p.y + translation;
+            contours.extend(
+                outline
+                    .contours
+                    .into_iter()
+                    .map(|c| c.into_iter().map(|s| s.map_points(transform)).collect()),
+            );
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(contours);
+            }
+        }
+    }
+
+    /// 排成若干行，\n 换行；返回每个字的位置和整个文字框的大小，像素
+    pub(crate) fn layout(&self, text: &str, size: f32) -> (Vec<PlacedGlyph>, Vec2) {
+        let scale = self.scale(size);
+        let line_height = self.line_height(size);
+        let mut glyphs = Vec::with_capacity(text.len());
+        let mut pen = Vec2::new(0.0, self.ascender * scale);
+        let mut width: f32 = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    width = width.max(pen.x);
+                    pen = Vec2::new(0.0, pen.y + line_height);
+                    previous = None;
+                    continue;
+                }
+                '\t' => {
+                    pen.x += self.advance(self.glyph_id(' ')) * scale * 4.0;
+                    previous = None;
+                    continue;
+                }
+                c if c.is_control() => continue,
+                _ => {}
+            }
+            let id = self.glyph_id(c);
+            if let Some(previous) = previous {
+                pen.x += self.kerning(previous, id) * scale;
+            }
+            glyphs.push(PlacedGlyph { id, position: pen });
+            pen.x += self.advance(id) * scale;
+            previous = Some(id);
+        }
+        width = width.max(pen.x);
+        let height = pen.y - self.ascender * scale + line_height;
+        (glyphs, Vec2::new(width, height))
+    }
+
+    pub(crate) fn measure(&self, text: &str, size: f32) -> Vec2 {
+        self.layout(text, size).1
+    }
+}
+
+/// glyf 里的简单字形：每个轮廓一串点，标了在不在曲线上
+fn simple_contours(
+    bytes: Bytes,
+    offset: usize,
+    contour_count: usize,
+) -> Result<Vec<Vec<Segment>>, FontError> {
+    const ON_CURVE: u8 = 0x1;
+    const X_SHORT: u8 = 0x2;
+    const Y_SHORT: u8 = 0x4;
+    const REPEAT: u8 = 0x8;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+    let mut ends = Vec::with_capacity(contour_count);
+    for i in 0..contour_count {
+        ends.push(bytes.u16(offset + 2 * i)? as usize);
+    }
+    let point_count = ends.last().map_or(0, |end| end + 1);
+    let instructions = bytes.u16(offset + 2 * contour_count)? as usize;
+    let mut cursor = offset + 2 * contour_count + 2 + instructions;
+
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let [flag] = bytes.get(cursor)?;
+        cursor += 1;
+        flags.push(flag);
+        if flag & REPEAT != 0 {
+            let [count] = bytes.get(cursor)?;
+            cursor += 1;
+            flags.extend(std::iter::repeat(flag).take(count as usize));
+        }
+    }
+    flags.truncate(point_count);
+
+    // x 全部读完才轮到 y，坐标都是相对上一个点的
+    let mut read_axis = |short: u8, same_or_positive: u8| {
+        let mut value = 0i32;
+        let mut values = Vec::with_capacity(point_count);
+        for &flag in &flags {
+            if flag & short != 0 {
+                let [delta] = bytes.get(cursor)?;
+                cursor += 1;
+                value += if flag & same_or_positive != 0 {
+                    delta as i32
+                } else {
+                    -(delta as i32)
+                };
+            } else if flag & same_or_positive == 0 {
+                value += bytes.i16(cursor)? as i32;
+                cursor += 2;
+            }
+            values.push(value as f32);
+        }
+        Ok::<_, FontError>(values)
+    };
+    let xs = read_axis(X_SHORT, X_SAME_OR_POSITIVE)?;
+    let ys = read_axis(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+    let mut contours = Vec::with_capacity(contour_count);
+    let mut start = 0;
+    for end in ends {
+        if end < start || end >= point_count {
+            return Err(FontError::Format("bad contour end".into()));
+        }
+        let points: Vec<(Vec2, bool)> = (start..=end)
+            .map(|i| (Vec2::new(xs[i], ys[i]), flags[i] & ON_CURVE != 0))
+            .collect();
+        contours.push(contour_segments(&points));
+        start = end + 1;
+    }
+    Ok(contours)
+}
+
+/// 两个相邻的控制点中间隐含一个曲线上的点
+fn contour_segments(points: &[(Vec2, bool)]) -> Vec<Segment> {
+    let n = points.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    // 从一个曲线上的点出发；全是控制点的话从头尾两个控制点的中点出发
+    let (start, first) = match points.iter().position(|&(_, on)| on) {
+        Some(i) => (points[i].0, i + 1),
+        None => ((points[n - 1].0 + points[0].0) * 0.5, 0),
+    };
+    let count = if first == 0 { n } else { n - 1 };
+    let mut segments = Vec::with_capacity(n);
+    let mut previous = start;
+    let mut control: Option<Vec2> = None;
+    for k in 0..count {
+        let (point, on) = points[(first + k) % n];
+        match (on, control) {
+            (true, Some(c)) => {
+                segments.push(Segment::Quad(previous, c, point));
+                previous = point;
+                control = None;
+            }
+            (true, None) => {
+                segments.push(Segment::Line(previous, point));
+                previous = point;
+            }
+            (false, Some(c)) => {
+                let middle = (c + point) * 0.5;
+                segments.push(Segment::Quad(previous, c, middle));
+                previous = middle;
+                control = Some(point);
+            }
+            (false, None) => control = Some(point),
+        }
+    }
+    match control {
+        Some(c) => segments.push(Segment::Quad(previous, c, start)),
+        None if previous != start => segments.push(Segment::Line(previous, start)),
+        None => {}
+    }
+    segments
+}
+
+/// 优先用 Unicode 全平面的格式 12，没有的话用 BMP 的格式 4
+fn read_cmap(bytes: Bytes, cmap: usize) -> Result<HashMap<char, u16>, FontError> {
+    let mut best = None;
+    for i in 0..bytes.u16(cmap + 2)? as usize {
+        let record = cmap + 4 + 8 * i;
+        let platform = bytes.u16(record)?;
+        let encoding = bytes.u16(record + 2)?;
+        let subtable = cmap + bytes.u32(record + 4)? as usize;
+        let format = bytes.u16(subtable)?;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        if !unicode || (format != 4 && format != 12) {
+            continue;
+        }
+        if best.map_or(true, |(best_format, _)| format > best_format) {
+            best = Some((format, subtable));
+        }
+    }
+    let Some((format, subtable)) = best else {
+        return Err(FontError::Format("no unicode cmap".into()));
+    };
+
+    let mut chars = HashMap::new();
+    if format == 12 {
+        for i in 0..bytes.u32(subtable + 12)? as usize {
+            let group = subtable + 16 + 12 * i;
+            let (first, last) = (bytes.u32(group)?, bytes.u32(group + 4)?);
+            let glyph = bytes.u32(group + 8)?;
+            for code in first..=last {
+                if let Some(c) = char::from_u32(code) {
+                    chars.insert(c, (glyph + code - first) as u16);
+                }
+            }
+        }
+        return Ok(chars);
+    }
+
+    let segments = bytes.u16(subtable + 6)? as usize / 2;
+    let ends = subtable + 14;
+    let starts = ends + 2 * segments + 2;
+    let deltas = starts + 2 * segments;
+    let range_offsets = deltas + 2 * segments;
+    for i in 0..segments {
+        let (first, last) = (bytes.u16(starts + 2 * i)?, bytes.u16(ends + 2 * i)?);
+        let delta = bytes.u16(deltas + 2 * i)?;
+        let range_offset = bytes.u16(range_offsets + 2 * i)? as usize;
+        for code in first..=last.min(0xfffe) {
+            let glyph = if range_offset == 0 {
+                code.wrapping_add(delta)
+            } else {
+                // range_offset 是从它自己的位置算起的字节偏移
+                let address = range_offsets + 2 * i + range_offset + 2 * (code - first) as usize;
+                match bytes.u16(address)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                }
+            };
+            if let Some(c) = char::from_u32(code as u32).filter(|_| glyph != 0) {
+                chars.insert(c, glyph);
+            }
+        }
+    }
+    Ok(chars)
+}
+
+/// 老式 kern 表里横排的格式 0 子表，一对字形一个值
+fn read_kern(bytes: Bytes, kern: usize) -> Result<HashMap<(u16, u16), i16>, FontError> {
+    const HORIZONTAL: u16 = 0x1;
+    const MINIMUM: u16 = 0x2;
+    const CROSS_STREAM: u16 = 0x4;
+
+    let mut pairs = HashMap::new();
+    if bytes.u16(kern)? != 0 {
+        // Apple 的新版 kern 表，DejaVu 用不到
+        return Ok(pairs);
+    }
+    let mut subtable = kern + 4;
+    for _ in 0..bytes.u16(kern + 2)? {
+        let length = bytes.u16(subtable + 2)? as usize;
+        let coverage = bytes.u16(subtable + 4)?;
+        let format = coverage >> 8;
+        if format != 0 {
+            subtable += length;
+            continue;
+        }
+        let count = bytes.u16(subtable + 6)? as usize;
+        if coverage & (HORIZONTAL | MINIMUM | CROSS_STREAM) == HORIZONTAL {
+            for i in 0..count {
+                let pair = subtable + 14 + 6 * i;
+                pairs.insert(
+                    (bytes.u16(pair)?, bytes.u16(pair + 2)?),
+                    bytes.i16(pair + 4)?,
+                );
+            }
+        }
+        // 大表的 length 会溢出 u16，按对数算
+        subtable += 14 + 6 * count;
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::DEJAVU_SANS;
+
+    fn font() -> Font {
+        Font::load(DEJAVU_SANS).unwrap()
+    }
+
+    #[test]
+    fn maps_chars_to_glyphs() {
+        let font = font();
+        assert_ne!(font.glyph_id('A'), 0);
+        assert_ne!(font.glyph_id('A'), font.glyph_id('V'));
+        // DejaVu Sans 没有汉字
+        assert_eq!(font.glyph_id('中'), 0);
+    }
+
+    #[test]
+    fn reads_kern_pairs() {
+        let font = font();
+        let (a, v) = (font.glyph_id('A'), font.glyph_id('V'));
+        assert!(font.kerning(a, v) < 0.0);
+        // 没有的字对是 0
+        assert_eq!(font.kerning(a, 0), 0.0);
+    }
+
+    #[test]
+    fn composite_glyph_has_every_component() {
+        let font = font();
+        let id = font.glyph_id('é');
+        let (start, _) = font.glyph_range(id).unwrap();
+        assert!(
+            font.bytes().i16(start).unwrap() < 0,
+            "é should be composite"
+        );
+        let outline = font.outline(id).unwrap();
+        let plain = font.outline(font.glyph_id('e')).unwrap();
+        assert!(outline.contours.len() > 1);
+        assert!(outline.contours.len() > plain.contours.len());
+        // 重音在 e 上面
+        assert!(outline.max.y > plain.max.y);
+    }
+
+    #[test]
+    fn measure_applies_kerning() {
+        let font = font();
+        let size = 32.0;
+        let advances = (font.advance(font.glyph_id('A')) + font.advance(font.glyph_id('V')))
+            * font.scale(size);
+        let extent = font.measure("AV", size);
+        assert!(extent.x < advances);
+        assert_eq!(extent.y, font.line_height(size));
+        let (glyphs, _) = font.layout("A\nV", size);
+        assert_eq!(glyphs[1].position.x, 0.0);
+        assert_eq!(
+            glyphs[1].position.y - glyphs[0].position.y,
+            font.line_height(size)
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// 多通道有向距离场（MSDF），放大多少倍字形的拐角都是尖的
+// 做法和 msdfgen 一样：
+//   1. 每个轮廓按拐角给边上色，相邻两种颜色只共用一个通道，拐角两边的边落在不同通道里
+//   2. 每个通道只看带这个通道的边，取最近那条边的伪距离（最近点落在端点外面时沿切线延长）
+//   3. shader 里取 rgb 的中位数，拐角处两个通道各管一边，中位数刚好还原出尖角
+// alpha 里存真正的有向距离，发光和阴影用它，外面是圆的
+// 最后把中位数和真距离正负号对不上的像素换成真距离，去掉大部分瑕疵
+// 距离里面为正，编码成 0.5 + d / (2 * RANGE)，d 是像素
+use glam::Vec2;
+
+use super::{
+    font::{Outline, Segment},
+    raster::{flatten, Bitmap},
+};
+
+/// 距离场往字形外面延伸多少像素，描边、发光最宽也就这么多
+pub(crate) const RANGE: f32 = 6.0;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const WHITE: u8 = RED | GREEN | BLUE;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+
+/// 方向变化的 sin 超过 sin(3) 就算拐角，和 msdfgen 的默认值一样
+const CORNER_THRESHOLD: f32 = 0.141;
+
+/// 一条上了色的边，曲线已经拆成折线，首尾是边真正的端点
+struct Edge {
+    color: u8,
+    points: Vec<Vec2>,
+}
+
+/// 某一点到一条边的距离
+#[derive(Copy, Clone)]
+struct EdgeDistance {
+    /// 到边上最近点的距离，带正负
+    distance: f32,
+    /// 最近点在端点上时看连线和边的夹角，两条边一样近的时候选更垂直的那条
+    dot: f32,
+    /// 端点外面沿切线延长以后的距离
+    pseudo: f32,
+}
+
+impl EdgeDistance {
+    const FAR: EdgeDistance = EdgeDistance {
+        distance: -f32::MAX,
+        dot: 1.0,
+        pseudo: -f32::MAX,
+    };
+
+    fn closer_than(&self, other: &EdgeDistance) -> bool {
+        let (a, b) = (self.distance.abs(), other.distance.abs());
+        a < b - 1e-5 || (a <= b + 1e-5 && self.dot < other.dot)
+    }
+}
+
+fn start_direction(segment: &Segment) -> Vec2 {
+    match *segment {
+        Segment::Line(a, b) => b - a,
+        Segment::Quad(a, c, _) if c != a => c - a,
+        Segment::Quad(a, _, b) => b - a,
+    }
+}
+
+fn end_direction(segment: &Segment) -> Vec2 {
+    match *segment {
+        Segment::Line(a, b) => b - a,
+        Segment::Quad(_, c, b) if c != b => b - c,
+        Segment::Quad(a, _, b) => b - a,
+    }
+}
+
+fn is_corner(incoming: Vec2, outgoing: Vec2) -> bool {
+    let (a, b) = (incoming.normalize_or_zero(), outgoing.normalize_or_zero());
+    a.dot(b) <= 0.0 || a.perp_dot(b).abs() > CORNER_THRESHOLD
+}
+
+/// 按 青 -> 品红 -> 黄 轮着换，跳过 banned
+fn next_color(color: u8, banned: u8) -> u8 {
+    const CYCLE: [u8; 3] = [CYAN, MAGENTA, YELLOW];
+    let index = CYCLE.iter().position(|&c| c == color).unwrap_or(0);
+    (1..3)
+        .map(|k| CYCLE[(index + k) % 3])
+        .find(|&c| c != banned)
+        .unwrap()
+}
+
+/// 一个轮廓里每段边的颜色
+fn color_contour(segments: &[Segment]) -> Vec<u8> {
+    let n = segments.len();
+    let corners: Vec<usize> = (0..n)
+        .filter(|&i| {
+            is_corner(
+                end_direction(&segments[(i + n - 1) % n]),
+                start_direction(&segments[i]),
+            )
+        })
+        .collect();
+    let mut colors = vec![WHITE; n];
+    match corners.len() {
+        // 光滑的轮廓三个通道都一样，就是普通的距离场
+        0 => {}
+        // 只有一个拐角（比如水滴形）：轮廓分成三截，拐角两边的两截颜色不同
+        1 if n >= 3 => {
+            let corner = corners[0];
+            for i in 0..n {
+                let third = (3.0 + 2.875 * i as f32 / (n - 1) as f32 - 1.4375 + 0.5) as usize - 2;
+                colors[(corner + i) % n] = [MAGENTA, WHITE, YELLOW][third.min(2)];
+            }
+        }
+        // 段数太少没法分，只好不管这个拐角
+        1 => {}
+        _ => {
+            let start = corners[0];
+            let initial = CYAN;
+            let mut color = initial;
+            let mut spline = 0;
+            for i in 0..n {
+                let index = (start + i) % n;
+                if spline + 1 < corners.len() && corners[spline + 1] == index {
+                    spline += 1;
+                    // 最后一截又挨着第一截，不能和它同色
+                    let banned = if spline == corners.len() - 1 {
+                        initial
+                    } else {
+                        0
+                    };
+                    color = next_color(color, banned);
+                }
+                colors[index] = color;
+            }
+        }
+    }
+    colors
+}
+
+fn edge_distance(edge: &Edge, p: Vec2) -> EdgeDistance {
+    let last = edge.points.len() - 2;
+    let mut best: Option<(usize, f32, f32, Vec2)> = None;
+    for (k, piece) in edge.points.windows(2).enumerate() {
+        let (a, b) = (piece[0], piece[1]);
+        let ab = b - a;
+        let length_squared = ab.length_squared();
+        if length_squared == 0.0 {
+            continue;
+        }
+        let t = (p - a).dot(ab) / length_squared;
+        let q = a + ab * t.clamp(0.0, 1.0);
+        let d = p.distance(q);
+        if best.map_or(true, |(_, _, best_d, _)| d < best_d) {
+            best = Some((k, t, d, q));
+        }
+    }
+    let Some((k, t, d, q)) = best else {
+        return EdgeDistance::FAR;
+    };
+    let (a, b) = (edge.points[k], edge.points[k + 1]);
+    let direction = (b - a).normalize();
+    // TrueType 的外轮廓在 y 朝上的字体坐标里是顺时针，翻成 y 朝下以后 perp_dot 为正的一侧是里面
+    let side = if direction.perp_dot(p - q) > 0.0 {
+        1.0
+    } else {
+        -1.0
+    };
+    let dot = if t <= 0.0 || t >= 1.0 {
+        direction.dot((p - q).normalize_or_zero()).abs()
+    } else {
+        0.0
+    };
+    let distance = side * d;
+    // 只在整条边的两头延长，中间的折线接缝不算
+    let outside_ends = (k == 0 && t < 0.0) || (k == last && t > 1.0);
+    let pseudo = match outside_ends {
+        true => {
+            let perpendicular = direction.perp_dot(p - a);
+            if perpendicular.abs() <= d {
+                perpendicular
+            } else {
+                distance
+            }
+        }
+        false => distance,
+    };
+    EdgeDistance {
+        distance,
+        dot,
+        pseudo,
+    }
+}
+
+/// 非零环绕规则
+fn winding(edges: &[Edge], p: Vec2) -> i32 {
+    let mut winding = 0;
+    for edge in edges {
+        for piece in edge.points.windows(2) {
+            let (a, b) = (piece[0], piece[1]);
+            if (a.y <= p.y) != (b.y <= p.y) {
+                let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if x > p.x {
+                    winding += if b.y > a.y { 1 } else { -1 };
+                }
+            }
+        }
+    }
+    winding
+}
+
+fn encode(distance: f32) -> u8 {
+    ((0.5 + distance / (2.0 * RANGE)).clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// scale 是字体单位到像素的缩放；没有轮廓的字返回 None
+pub(crate) fn generate(outline: &Outline, scale: f32) -> Option<Bitmap> {
+    if outline.contours.is_empty() {
+        return None;
+    }
+    let pad = RANGE.ceil() + 1.0;
+    let left = (outline.min.x * scale).floor() - pad;
+    let top = (-outline.max.y * scale).floor() - pad;
+    let width = ((outline.max.x * scale).ceil() + pad - left) as usize;
+    let height = ((-outline.min.y * scale).ceil() + pad - top) as usize;
+    let to_pixel = |p: Vec2| Vec2::new(p.x * scale - left, -p.y * scale - top);
+
+    let mut edges = Vec::new();
+    for contour in &outline.contours {
+        for (segment, color) in contour.iter().zip(color_contour(contour)) {
+            let mut points = Vec::new();
+            flatten(segment.map_points(to_pixel), |a, b| {
+                if points.is_empty() {
+                    points.push(a);
+                }
+                points.push(b);
+            });
+            edges.push(Edge { color, points });
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let mut channels = [EdgeDistance::FAR; 3];
+            let mut nearest = f32::MAX;
+            for edge in &edges {
+                let distance = edge_distance(edge, p);
+                nearest = nearest.min(distance.distance.abs());
+                for (i, channel) in channels.iter_mut().enumerate() {
+                    if edge.color & (1 << i) != 0 && distance.closer_than(channel) {
+                        *channel = distance;
+                    }
+                }
+            }
+            let inside = winding(&edges, p) != 0;
+            let true_distance = if inside { nearest } else { -nearest };
+            let [r, g, b] = channels.map(|c| c.pseudo);
+            let [r, g, b] = if (median(r, g, b) > 0.0) != inside {
+                [true_distance; 3]
+            } else {
+                [r, g, b]
+            };
+            pixels.extend_from_slice(&[encode(r), encode(g), encode(b), encode(true_distance)]);
+        }
+    }
+    Some(Bitmap {
+        width: width as u32,
+        height: height as u32,
+        offset: Vec2::new(left, top),
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{font::Font, DEJAVU_SANS};
+
+    /// (r, g, b, a) 里 rgb 的中值和 a 都解成带符号的距离，里面是正的
+    fn sample(bitmap: &Bitmap, x: u32, y: u32) -> (f32, f32) {
+        let i = ((y * bitmap.width + x) * 4) as usize;
+        let decode = |v: u8| (v as f32 / 255.0 - 0.5) * 2.0 * RANGE;
+        let [r, g, b, a] = [0, 1, 2, 3].map(|c| decode(bitmap.pixels[i + c]));
+        (median(r, g, b), a)
+    }
+
+    #[test]
+    fn signs_match_inside_and_outside() {
+        let font = Font::load(DEJAVU_SANS).unwrap();
+        // I 是一个实心竖条，O 的中间是洞
+        let bar = font.outline(font.glyph_id('I')).unwrap();
+        let ring = font.outline(font.glyph_id('O')).unwrap();
+        let scale = font.scale(32.0);
+        let bar = generate(&bar, scale).unwrap();
+        let ring = generate(&ring, scale).unwrap();
+
+        let (median, true_distance) = sample(&bar, bar.width / 2, bar.height / 2);
+        assert!(median > 0.0 && true_distance > 0.0);
+        for (x, y) in [(0, 0), (bar.width - 1, bar.height - 1)] {
+            let (median, true_distance) = sample(&bar, x, y);
+            assert!(median < 0.0 && true_distance < 0.0);
+        }
+        let (median, true_distance) = sample(&ring, ring.width / 2, ring.height / 2);
+        assert!(median < 0.0 && true_distance < 0.0);
+    }
+}
@@ -0,0 +1,175 @@
+// 把字形轮廓画成覆盖率位图
+// 曲线先拆成折线，每条线段把它扫过的面积累加到格子里（带方向），最后按行前缀和
+// 闭合轮廓的正负抵消掉，剩下的绝对值就是每个像素被盖住的比例，非零环绕规则
+use glam::Vec2;
+
+use super::font::{Outline, Segment};
+
+/// 折线和曲线之间最多差多少像素
+const TOLERANCE: f32 = 0.1;
+
+/// 一个字形的位图，覆盖率一字节一个像素，msdf 四字节一个像素
+pub(crate) struct Bitmap {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// 位图左上角相对基线上笔位置的偏移，像素，y 朝下
+    pub(crate) offset: Vec2,
+    pub(crate) pixels: Vec<u8>,
+}
+
+/// 把二次曲线拆成线段，段数按曲线弯的程度定
+pub(crate) fn flatten(segment: Segment, mut line: impl FnMut(Vec2, Vec2)) {
+    match segment {
+        Segment::Line(a, b) => line(a, b),
+        Segment::Quad(a, c, b) => {
+            let bend = (a - 2.0 * c + b).length();
+            let count = ((bend / (8.0 * TOLERANCE)).sqrt().ceil() as usize).clamp(1, 64);
+            let mut previous = a;
+            for i in 1..=count {
+                let t = i as f32 / count as f32;
+                let point = a.lerp(c, t).lerp(c.lerp(b, t), t);
+                line(previous, point);
+                previous = point;
+            }
+        }
+    }
+}
+
+/// scale 是字体单位到像素的缩放；没有轮廓的字返回 None
+pub(crate) fn rasterize(outline: &Outline, scale: f32) -> Option<Bitmap> {
+    if outline.contours.is_empty() {
+        return None;
+    }
+    // 四周多留一个像素，线段碰不到边
+    let left = (outline.min.x * scale).floor() - 1.0;
+    let top = (-outline.max.y * scale).floor() - 1.0;
+    let width = ((outline.max.x * scale).ceil() + 1.0 - left) as usize;
+    let height = ((-outline.min.y * scale).ceil() + 1.0 - top) as usize;
+    let to_pixel = |p: Vec2| Vec2::new(p.x * scale - left, -p.y * scale - top);
+
+    let mut accumulation = Accumulation::new(width, height);
+    for contour in &outline.contours {
+        for &segment in contour {
+            flatten(segment.map_points(to_pixel), |a, b| accumulation.line(a, b));
+        }
+    }
+    Some(Bitmap {
+        width: width as u32,
+        height: height as u32,
+        offset: Vec2::new(left, top),
+        pixels: accumulation.coverage(),
+    })
+}
+
+struct Accumulation {
+    width: usize,
+    height: usize,
+    /// 末尾多几个，最右边的线段会写到下一格
+    cells: Vec<f32>,
+}
+
+impl Accumulation {
+    fn new(width: usize, height: usize) -> Self {
+        Accumulation {
+            width,
+            height,
+            cells: vec![0.0; width * height + 4],
+        }
+    }
+
+    /// 像素坐标，y 朝下
+    fn line(&mut self, from: Vec2, to: Vec2) {
+        if (from.y - to.y).abs() < f32::EPSILON {
+            return;
+        }
+        let (direction, from, to) = if from.y < to.y {
+            (1.0, from, to)
+        } else {
+            (-1.0, to, from)
+        };
+        let dxdy = (to.x - from.x) / (to.y - from.y);
+        let mut x = from.x;
+        if from.y < 0.0 {
+            x -= from.y * dxdy;
+        }
+        let first_row = from.y.max(0.0) as usize;
+        let last_row = (to.y.ceil() as usize).min(self.height);
+        for row in first_row..last_row {
+            let start = row * self.width;
+            let dy = ((row + 1) as f32).min(to.y) - (row as f32).max(from.y);
+            let next_x = x + dxdy * dy;
+            let d = dy * direction;
+            let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+            let x0_floor = x0.floor();
+            let x0i = x0_floor.max(0.0) as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil.max(0.0) as usize;
+            if x1i <= x0i + 1 {
+                // 这一行只落在一个像素里
+                let middle = 0.5 * (x + next_x) - x0_floor;
+                self.cells[start + x0i] += d - d * middle;
+                self.cells[start + x0i + 1] += d * middle;
+            } else {
+                // 跨了好几个像素，两头是三角形，中间每格一样多
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+                self.cells[start + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.cells[start + x0i + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.cells[start + x0i + 1] += d * (a1 - a0);
+                    for xi in x0i + 2..x1i - 1 {
+                        self.cells[start + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.cells[start + x1i - 1] += d * (1.0 - a2 - am);
+                }
+                self.cells[start + x1i] += d * am;
+            }
+            x = next_x;
+        }
+    }
+
+    fn coverage(&self) -> Vec<u8> {
+        let mut sum = 0.0;
+        self.cells[..self.width * self.height]
+            .iter()
+            .map(|cell| {
+                sum += cell;
+                (sum.abs().min(1.0) * 255.0 + 0.5) as u8
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::{font::Font, DEJAVU_SANS};
+
+    #[test]
+    fn covers_inside_not_outside() {
+        let font = Font::load(DEJAVU_SANS).unwrap();
+        let scale = font.scale(32.0);
+        let coverage = |c: char, x: u32, y: u32| {
+            let bitmap = rasterize(&font.outline(font.glyph_id(c)).unwrap(), scale).unwrap();
+            let (x, y) = (x.min(bitmap.width - 1), y.min(bitmap.height - 1));
+            bitmap.pixels[(y * bitmap.width + x) as usize]
+        };
+        let center = |c: char| {
+            let bitmap = rasterize(&font.outline(font.glyph_id(c)).unwrap(), scale).unwrap();
+            (bitmap.width / 2, bitmap.height / 2)
+        };
+        let (x, y) = center('I');
+        assert_eq!(coverage('I', x, y), 255);
+        assert_eq!(coverage('I', 0, 0), 0);
+        // O 中间的洞按非零环绕规则是空的
+        let (x, y) = center('O');
+        assert_eq!(coverage('O', x, y), 0);
+        assert!(rasterize(&font.outline(font.glyph_id(' ')).unwrap(), scale).is_none());
+    }
+}
@@ -0,0 +1,137 @@
+// 字形一个实例一个四边形
+// space 0：anchor 就是屏幕像素坐标，左上角是原点
+// space 1：先把世界里的 anchor 投到屏幕上，再按像素摆
+// space 2：在世界里按世界单位摆，沿着相机的右和上展开
+// fs_bitmap 读覆盖率；fs_msdf 读多通道距离场，rgb 取中位数是字形，a 是真距离，发光和阴影用
+
+struct TextGlobals {
+    view_proj: mat4x4<f32>,
+    right: vec4<f32>,
+    up: vec4<f32>,
+    // 像素
+    screen: vec2<f32>,
+    // 距离场编码的总跨度，em
+    sdf_range: f32,
+    // 图集里一个 em 多少纹素
+    sdf_em: f32,
+    outline_color: vec4<f32>,
+    glow_color: vec4<f32>,
+    shadow_color: vec4<f32>,
+    // 下面几个都是 em
+    shadow_offset: vec2<f32>,
+    outline_width: f32,
+    glow_radius: f32,
+    shadow_softness: f32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> globals: TextGlobals;
+
+struct GlyphInput {
+    @location(0) anchor: vec3<f32>,
+    @location(1) space: u32,
+    // 字形左上角相对 anchor 的偏移
+    @location(2) offset: vec2<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) uv: vec4<f32>,
+    @location(5) color: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    // 这个字形在图集里的范围，阴影偏出去的时候不能采到隔壁
+    @location(2) @interpolate(flat) rect: vec4<f32>,
+};
+
+fn pixel_to_clip(pixel: vec2<f32>) -> vec4<f32> {
+    return vec4<f32>(pixel.x / globals.screen.x * 2.0 - 1.0, 1.0 - pixel.y / globals.screen.y * 2.0, 0.0, 1.0);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vi: u32, glyph: GlyphInput) -> VertexOutput {
+    let corner = vec2<f32>(f32(vi & 1u), 0.5 * f32(vi & 2u));
+    let local = glyph.offset + corner * glyph.size;
+    var position: vec4<f32>;
+    if glyph.space == 0u {
+        position = pixel_to_clip(glyph.anchor.xy + local);
+    } else if glyph.space == 1u {
+        let clip = globals.view_proj * vec4<f32>(glyph.anchor, 1.0);
+        // 在相机后面的直接扔到裁剪范围外
+        if clip.w <= 0.0 {
+            return VertexOutput(vec4<f32>(0.0, 0.0, 2.0, 1.0), vec2<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0));
+        }
+        let ndc = clip.xy / clip.w;
+        // 对齐到整像素，字形位图才不会被插值糊掉
+        let anchor = floor(vec2<f32>(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * globals.screen + 0.5);
+        position = pixel_to_clip(anchor + local);
+    } else {
+        // local 的 y 朝下
+        let world = glyph.anchor + globals.right.xyz * local.x - globals.up.xyz * local.y;
+        position = globals.view_proj * vec4<f32>(world, 1.0);
+    }
+    let uv = mix(glyph.uv.xy, glyph.uv.zw, corner);
+    let color = vec4<f32>((vec4<u32>(glyph.color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+    return VertexOutput(position, uv, color, glyph.uv);
+}
+
+@group(0)
+@binding(1)
+var atlas: texture_2d<f32>;
+@group(0)
+@binding(2)
+var atlas_sampler: sampler;
+
+@fragment
+fn fs_bitmap(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSampleLevel(atlas, atlas_sampler, vertex.uv, 0.0).r;
+    return vec4<f32>(vertex.color.rgb, vertex.color.a * coverage);
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    return max(min(a, b), min(max(a, b), c));
+}
+
+// 到字形边缘的距离，em，里面为正
+fn glyph_distance(field: vec4<f32>) -> f32 {
+    return (median(field.r, field.g, field.b) - 0.5) * globals.sdf_range;
+}
+
+// 把 src 垫在 dst 下面，都是预乘过的
+fn under(dst: vec4<f32>, src: vec4<f32>) -> vec4<f32> {
+    return dst + src * (1.0 - dst.a);
+}
+
+@fragment
+fn fs_msdf(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let atlas_size = vec2<f32>(textureDimensions(atlas));
+    // 屏幕上一个像素跨了几个纹素，换成一个 em 占几个像素，边缘永远抗锯齿一个像素宽
+    let texels = fwidth(vertex.uv * atlas_size);
+    let px_per_em = globals.sdf_em / max(0.5 * (texels.x + texels.y), 1e-4);
+
+    let field = textureSampleLevel(atlas, atlas_sampler, vertex.uv, 0.0);
+    let d = glyph_distance(field);
+    let fill = clamp(d * px_per_em + 0.5, 0.0, 1.0);
+    let outlined = clamp((d + globals.outline_width) * px_per_em + 0.5, 0.0, 1.0);
+    let ring = (outlined - fill) * globals.outline_color.a;
+    var color = vec4<f32>(vertex.color.rgb, 1.0) * vertex.color.a * fill
+        + vec4<f32>(globals.outline_color.rgb, 1.0) * ring;
+
+    // 发光用真距离，拐角外面是圆的
+    let outside = max(-(field.a - 0.5) * globals.sdf_range - globals.outline_width, 0.0);
+    let falloff = 1.0 - clamp(outside / max(globals.glow_radius, 1e-4), 0.0, 1.0);
+    let glow = select(0.0, falloff * falloff, globals.glow_radius > 0.0) * globals.glow_color.a;
+    color = under(color, vec4<f32>(globals.glow_color.rgb, 1.0) * glow);
+
+    // 阴影是往反方向采一次，softness 是边缘过渡的宽度
+    let shadow_uv = clamp(vertex.uv - globals.shadow_offset * globals.sdf_em / atlas_size, vertex.rect.xy, vertex.rect.zw);
+    let shadow_d = glyph_distance(textureSampleLevel(atlas, atlas_sampler, shadow_uv, 0.0)) + globals.outline_width;
+    let softness = max(globals.shadow_softness, 1.0 / px_per_em);
+    let shadow = clamp(shadow_d / softness + 0.5, 0.0, 1.0) * globals.shadow_color.a;
+    color = under(color, vec4<f32>(globals.shadow_color.rgb, 1.0) * shadow);
+
+    // 管线用的是普通的 alpha 混合，去掉预乘
+    return vec4<f32>(color.rgb / max(color.a, 1e-4), color.a);
+}
@@ -0,0 +1,477 @@
+use std::{borrow::Cow, path::Path};
+
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::gfx;
+
+mod astc;
+mod block_decode;
+mod bptc;
+pub(crate) mod compressed;
+
+// 纹理加载
+// 从 bytes 或者磁盘读取 PNG / JPEG / HDR / KTX2 / DDS，生成完整的 mip 链，
+// 返回 texture + view + sampler 的一整套
+
+/// 生成 mip 链的方式
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) enum MipGeneration {
+    /// 只有 level 0
+    None,
+    /// CPU 上逐级缩小后一次上传
+    Cpu,
+    /// 上传 level 0，再用 render pass 逐级 blit
+    Gpu,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct SamplerOptions {
+    pub(crate) mag_filter: wgpu::FilterMode,
+    pub(crate) min_filter: wgpu::FilterMode,
+    pub(crate) mipmap_filter: wgpu::FilterMode,
+    pub(crate) address_mode: wgpu::AddressMode,
+    pub(crate) anisotropy_clamp: u16,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        // 三线性过滤
+        Self {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            address_mode: wgpu::AddressMode::ClampToEdge,
+            anisotropy_clamp: 1,
+        }
+    }
+}
+
+impl SamplerOptions {
+    pub(crate) fn nearest() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn create_sampler(
+        &self,
+        context: &gfx::GfxContext,
+        label: Option<&str>,
+    ) -> wgpu::Sampler {
+        context.device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            // 各向异性要求三个 filter 都是 Linear
+            anisotropy_clamp: self.anisotropy_clamp,
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TextureOptions<'a> {
+    pub(crate) label: Option<&'a str>,
+    /// 颜色贴图用 sRGB，法线/数据贴图用线性
+    pub(crate) srgb: bool,
+    pub(crate) mips: MipGeneration,
+    pub(crate) sampler: SamplerOptions,
+}
+
+impl Default for TextureOptions<'_> {
+    fn default() -> Self {
+        Self {
+            label: None,
+            srgb: true,
+            mips: MipGeneration::Gpu,
+            sampler: SamplerOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum TextureError {
+    Io(std::io::Error),
+    Decode(image::ImageError),
+    /// KTX2 / DDS 容器本身有问题
+    Container(String),
+    /// 格式能认出来，但既没有设备支持也没有 CPU 解码
+    Unsupported(String),
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Io(e) => write!(f, "texture io error: {}", e),
+            TextureError::Decode(e) => write!(f, "texture decode error: {}", e),
+            TextureError::Container(e) => write!(f, "texture container error: {}", e),
+            TextureError::Unsupported(e) => write!(f, "unsupported texture format: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for TextureError {
+    fn from(e: std::io::Error) -> Self {
+        TextureError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Decode(e)
+    }
+}
+
+pub(crate) struct TextureBundle {
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    pub(crate) sampler: wgpu::Sampler,
+    pub(crate) size: wgpu::Extent3d,
+    pub(crate) format: wgpu::TextureFormat,
+    /// 整条 mip 链在显存里大概占多少字节
+    pub(crate) byte_size: u64,
+}
+
+pub(crate) fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+pub(crate) fn mip_chain_byte_size(
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+) -> u64 {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+    (0..mip_level_count)
+        .map(|level| {
+            let level_size = size
+                .mip_level_size(level, wgpu::TextureDimension::D2)
+                .physical_size(format);
+            (level_size.width / block_width) as u64
+                * (level_size.height / block_height) as u64
+                * block_size
+        })
+        .sum()
+}
+
+impl TextureBundle {
+    pub(crate) fn from_path(
+        context: &gfx::GfxContext,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(context, &bytes, options)
+    }
+
+    /// 格式由文件头判断，PNG / JPEG / Radiance HDR，或者 KTX2 / DDS 容器
+    pub(crate) fn from_bytes(
+        context: &gfx::GfxContext,
+        bytes: &[u8],
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        if compressed::is_container(bytes) {
+            let image = compressed::CompressedImage::parse(bytes, options.srgb)?;
+            return Self::from_compressed_image(context, image, options);
+        }
+        let image = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(context, image, options))
+    }
+
+    pub(crate) fn from_image(
+        context: &gfx::GfxContext,
+        image: DynamicImage,
+        options: TextureOptions,
+    ) -> Self {
+        let hdr = matches!(
+            image,
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+        );
+        // HDR 用 Rgba16Float，Rgba32Float 默认不能线性过滤
+        let format = match (hdr, options.srgb) {
+            (true, _) => wgpu::TextureFormat::Rgba16Float,
+            (false, true) => wgpu::TextureFormat::Rgba8UnormSrgb,
+            (false, false) => wgpu::TextureFormat::Rgba8Unorm,
+        };
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        };
+        let mip_level_count = match options.mips {
+            MipGeneration::None => 1,
+            MipGeneration::Cpu | MipGeneration::Gpu => mip_level_count(size.width, size.height),
+        };
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if options.mips == MipGeneration::Gpu {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: options.label,
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+
+        let uploaded_levels = match options.mips {
+            MipGeneration::Cpu => mip_level_count,
+            MipGeneration::None | MipGeneration::Gpu => 1,
+        };
+        let mut level_image = image;
+        for level in 0..uploaded_levels {
+            if level > 0 {
+                let (w, h) = ((size.width >> level).max(1), (size.height >> level).max(1));
+                level_image = level_image.resize_exact(w, h, FilterType::Triangle);
+            }
+            write_level(context, &texture, level, &level_image, hdr);
+        }
+
+        if options.mips == MipGeneration::Gpu {
+            let mut encoder =
+                context
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("mipmap encoder"),
+                    });
+            generate_mipmaps(context, &mut encoder, &texture, format, mip_level_count);
+            context.queue.submit(Some(encoder.finish()));
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = options.sampler.create_sampler(context, options.label);
+        Self {
+            texture,
+            view,
+            sampler,
+            size,
+            format,
+            byte_size: mip_chain_byte_size(format, size, mip_level_count),
+        }
+    }
+
+    /// 给 shader 用的标准布局: binding 0 纹理，binding 1 采样器
+    pub(crate) fn bind_group_layout(context: &gfx::GfxContext) -> wgpu::BindGroupLayout {
+        context
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture bundle bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            })
+    }
+
+    pub(crate) fn bind_group(
+        &self,
+        context: &gfx::GfxContext,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("texture bundle bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&self.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            })
+    }
+}
+
+fn write_level(
+    context: &gfx::GfxContext,
+    texture: &wgpu::Texture,
+    level: u32,
+    image: &DynamicImage,
+    hdr: bool,
+) {
+    let (width, height) = (image.width(), image.height());
+    let (bytes, bytes_per_pixel) = if hdr {
+        let texels: Vec<u16> = image
+            .to_rgba32f()
+            .into_raw()
+            .into_iter()
+            .map(f32_to_f16)
+            .collect();
+        (bytemuck::cast_slice(&texels).to_vec(), 8)
+    } else {
+        (image.to_rgba8().into_raw(), 4)
+    };
+    context.queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &bytes,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * bytes_per_pixel),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// 每一级都画一个全屏三角形，从上一级线性采样
+pub(crate) fn generate_mipmaps(
+    context: &gfx::GfxContext,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    mip_level_count: u32,
+) {
+    let shader = context
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("blit shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("texture/blit.wgsl"))),
+        });
+    let pipeline = context
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("blit pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+    let bind_group_layout = pipeline.get_bind_group_layout(0);
+    let sampler = context.device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("mip sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+
+    let views = (0..mip_level_count)
+        .map(|mip| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("mip"),
+                base_mip_level: mip,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for target_mip in 1..mip_level_count as usize {
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[target_mip - 1]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+                label: None,
+            });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("mip pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &views[target_mip],
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+/// IEEE 754 half，舍入到最近
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x007F_FFFF;
+    if exponent == 0xFF {
+        // inf / nan
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        // subnormal
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let rounded = (mantissa + (1 << (shift - 1))) >> shift;
+        return sign | rounded as u16;
+    }
+    let rounded = (mantissa + 0x0000_1000) >> 13;
+    // 尾数进位会自然进到指数
+    sign | (((half_exponent as u32) << 10) + rounded) as u16
+}
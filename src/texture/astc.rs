@@ -0,0 +1,976 @@
+// ASTC 的 CPU 解码，按规范和 astcenc 的解码路径写的
+// LDR 解成 16 位 unorm 再取高 8 位，HDR 解成 half float
+
+use super::f32_to_f16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Profile {
+    Ldr,
+    Srgb,
+    Hdr,
+}
+
+/// 解一个块，out 按块内行优先，LDR 每像素 4 字节，HDR 每像素 8 字节
+pub(super) fn decode(block: &[u8], bw: usize, bh: usize, profile: Profile, out: &mut [u8]) {
+    let bits = u128::from_le_bytes(block[0..16].try_into().unwrap());
+    let texels = decode_texels(bits, bw, bh, profile).unwrap_or_else(|| {
+        // 非法块按规范输出错误色（品红）
+        let error = if profile == Profile::Hdr {
+            [0x3C00, 0, 0x3C00, 0x3C00]
+        } else {
+            [0xFFFF, 0, 0xFFFF, 0xFFFF]
+        };
+        vec![error; bw * bh]
+    });
+    for (i, texel) in texels.iter().enumerate() {
+        if profile == Profile::Hdr {
+            for (c, v) in texel.iter().enumerate() {
+                out[i * 8 + c * 2..i * 8 + c * 2 + 2].copy_from_slice(&v.to_le_bytes());
+            }
+        } else {
+            for (c, v) in texel.iter().enumerate() {
+                out[i * 4 + c] = (v >> 8) as u8;
+            }
+        }
+    }
+}
+
+fn get(bits: u128, start: u32, count: u32) -> u32 {
+    if count == 0 || start >= 128 {
+        return 0;
+    }
+    ((bits >> start) & ((1u128 << count) - 1)) as u32
+}
+
+/// 返回的值 LDR 是 16 位 unorm，HDR 是 half float 的位模式
+fn decode_texels(bits: u128, bw: usize, bh: usize, profile: Profile) -> Option<Vec<[u16; 4]>> {
+    let texel_count = bw * bh;
+    if get(bits, 0, 9) == 0x1FC {
+        return void_extent(bits, texel_count, profile);
+    }
+
+    let mode = block_mode(get(bits, 0, 11))?;
+    let (gw, gh) = (mode.grid_width, mode.grid_height);
+    let planes = if mode.dual_plane { 2 } else { 1 };
+    let weight_count = gw * gh * planes;
+    if gw > bw || gh > bh || weight_count > 64 {
+        return None;
+    }
+    let weight_bits = ise_bits(weight_count, mode.weight_levels);
+    if !(24..=96).contains(&weight_bits) {
+        return None;
+    }
+
+    let partitions = get(bits, 11, 2) as usize + 1;
+    if partitions == 4 && mode.dual_plane {
+        return None;
+    }
+    let mut below_weights = 128 - weight_bits;
+    let mut cems = [0u32; 4];
+    let (color_start, partition_index) = if partitions == 1 {
+        cems[0] = get(bits, 13, 4);
+        (17, 0)
+    } else {
+        let low = get(bits, 23, 6);
+        if low & 3 == 0 {
+            cems = [(low >> 2) & 0xF; 4];
+        } else {
+            // 各分区的端点模式不同，高位放在权重前面
+            let high_bits = 3 * partitions as u32 - 4;
+            below_weights -= high_bits;
+            let encoded = low | get(bits, below_weights, high_bits) << 6;
+            let base = (encoded & 3) - 1;
+            for (i, cem) in cems.iter_mut().enumerate().take(partitions) {
+                let class = ((encoded >> (2 + i)) & 1) + base;
+                let low = (encoded >> (2 + partitions + 2 * i)) & 3;
+                *cem = class << 2 | low;
+            }
+        }
+        (29, get(bits, 13, 10))
+    };
+    let component = if mode.dual_plane {
+        below_weights -= 2;
+        Some(get(bits, below_weights, 2) as usize)
+    } else {
+        None
+    };
+
+    let color_count: usize = cems[..partitions]
+        .iter()
+        .map(|cem| ((cem >> 2) + 1) as usize * 2)
+        .sum();
+    if color_count > 18 || below_weights < color_start {
+        return None;
+    }
+    let color_bits = below_weights - color_start;
+    let color_levels = *COLOR_LEVELS
+        .iter()
+        .rev()
+        .find(|&&levels| ise_bits(color_count, levels) <= color_bits)?;
+    if color_levels < 6 {
+        return None;
+    }
+    let colors: Vec<u32> = decode_ise(bits, color_start, color_count, color_levels)
+        .into_iter()
+        .map(|(digit, low)| unquantize_color(color_levels, digit, low))
+        .collect();
+
+    let mut endpoints = Vec::with_capacity(partitions);
+    let mut offset = 0;
+    for &cem in &cems[..partitions] {
+        let count = ((cem >> 2) + 1) as usize * 2;
+        let endpoint = unpack_endpoints(cem, &colors[offset..offset + count], profile);
+        if endpoint.hdr_rgb && profile != Profile::Hdr {
+            return None;
+        }
+        endpoints.push(endpoint);
+        offset += count;
+    }
+
+    let weights: Vec<u32> = decode_ise(bits.reverse_bits(), 0, weight_count, mode.weight_levels)
+        .into_iter()
+        .map(|(digit, low)| unquantize_weight(mode.weight_levels, digit, low))
+        .collect();
+    let plane_weights = infill(&weights, gw, gh, bw, bh, planes);
+
+    let small_block = texel_count < 31;
+    Some(
+        (0..texel_count)
+            .map(|i| {
+                let p = if partitions == 1 {
+                    0
+                } else {
+                    select_partition(partition_index, i % bw, i / bw, partitions, small_block)
+                };
+                let endpoint = &endpoints[p];
+                std::array::from_fn(|c| {
+                    let w = if component == Some(c) {
+                        plane_weights[1][i]
+                    } else {
+                        plane_weights[0][i]
+                    } as i32;
+                    let value = (endpoint.e0[c] * (64 - w) + endpoint.e1[c] * w + 32) >> 6;
+                    let hdr = if c == 3 {
+                        endpoint.hdr_alpha
+                    } else {
+                        endpoint.hdr_rgb
+                    };
+                    match profile {
+                        Profile::Hdr if hdr => lns_to_f16(value as u32),
+                        Profile::Hdr => unorm16_to_f16(value as u32),
+                        _ => value as u16,
+                    }
+                })
+            })
+            .collect(),
+    )
+}
+
+/// 整块一个颜色
+fn void_extent(bits: u128, texel_count: usize, profile: Profile) -> Option<Vec<[u16; 4]>> {
+    let hdr = get(bits, 9, 1) == 1;
+    let color: [u16; 4] = std::array::from_fn(|c| get(bits, 64 + 16 * c as u32, 16) as u16);
+    let color = match profile {
+        Profile::Hdr if hdr => color,
+        Profile::Hdr => color.map(|c| unorm16_to_f16(c as u32)),
+        _ if hdr => return None,
+        _ => color,
+    };
+    Some(vec![color; texel_count])
+}
+
+struct BlockMode {
+    grid_width: usize,
+    grid_height: usize,
+    dual_plane: bool,
+    weight_levels: u32,
+}
+
+const WEIGHT_LEVELS: [u32; 12] = [2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32];
+const COLOR_LEVELS: [u32; 21] = [
+    2, 3, 4, 5, 6, 8, 10, 12, 16, 20, 24, 32, 40, 48, 64, 80, 96, 128, 160, 192, 256,
+];
+
+fn block_mode(mode: u32) -> Option<BlockMode> {
+    let a = (mode >> 5) & 3;
+    let mut dual_plane = (mode >> 10) & 1 == 1;
+    let mut high_precision = (mode >> 9) & 1;
+    let mut r = (mode >> 4) & 1;
+    let (x, y) = if mode & 3 != 0 {
+        r |= (mode & 3) << 1;
+        let b = (mode >> 7) & 3;
+        match (mode >> 2) & 3 {
+            0 => (b + 4, a + 2),
+            1 => (b + 8, a + 2),
+            2 => (a + 2, b + 8),
+            _ if mode & 0x100 != 0 => ((b & 1) + 2, a + 2),
+            _ => (a + 2, (b & 1) + 6),
+        }
+    } else {
+        if (mode >> 2) & 3 == 0 {
+            return None;
+        }
+        r |= ((mode >> 2) & 3) << 1;
+        let b = (mode >> 9) & 3;
+        match (mode >> 7) & 3 {
+            0 => (12, a + 2),
+            1 => (a + 2, 12),
+            2 => {
+                dual_plane = false;
+                high_precision = 0;
+                (a + 6, b + 6)
+            }
+            _ => match a {
+                0 => (6, 10),
+                1 => (10, 6),
+                _ => return None,
+            },
+        }
+    };
+    Some(BlockMode {
+        grid_width: x as usize,
+        grid_height: y as usize,
+        dual_plane,
+        weight_levels: WEIGHT_LEVELS[(r - 2 + 6 * high_precision) as usize],
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Digit {
+    Bits,
+    Trit,
+    Quint,
+}
+
+/// 量化级数拆成 trit / quint 和低位的位数
+fn ise_shape(levels: u32) -> (Digit, u32) {
+    if levels % 3 == 0 {
+        (Digit::Trit, (levels / 3).trailing_zeros())
+    } else if levels % 5 == 0 {
+        (Digit::Quint, (levels / 5).trailing_zeros())
+    } else {
+        (Digit::Bits, levels.trailing_zeros())
+    }
+}
+
+fn ise_bits(count: usize, levels: u32) -> u32 {
+    let count = count as u32;
+    let (digit, bits) = ise_shape(levels);
+    count * bits
+        + match digit {
+            Digit::Bits => 0,
+            Digit::Trit => (8 * count + 4) / 5,
+            Digit::Quint => (7 * count + 2) / 3,
+        }
+}
+
+/// 读出 (trit / quint, 低位)，超出编码长度的位当 0
+fn decode_ise(bits: u128, start: u32, count: usize, levels: u32) -> Vec<(u32, u32)> {
+    let end = start + ise_bits(count, levels);
+    let bits = if end >= 128 {
+        bits
+    } else {
+        bits & ((1u128 << end) - 1)
+    };
+    let (digit, m) = ise_shape(levels);
+    // 每组里低位和 trit / quint 的位交错存放
+    let (group, packed_bits): (usize, &[u32]) = match digit {
+        Digit::Bits => (1, &[0]),
+        Digit::Trit => (5, &[2, 2, 1, 2, 1]),
+        Digit::Quint => (3, &[3, 2, 2]),
+    };
+    let mut pos = start;
+    let mut values = Vec::with_capacity(count);
+    while values.len() < count {
+        let mut low = [0u32; 5];
+        let mut packed = 0;
+        let mut shift = 0;
+        for (i, &n) in packed_bits.iter().enumerate() {
+            low[i] = get(bits, pos, m);
+            pos += m;
+            packed |= get(bits, pos, n) << shift;
+            pos += n;
+            shift += n;
+        }
+        let digits = match digit {
+            Digit::Bits => vec![0],
+            Digit::Trit => trits(packed).to_vec(),
+            Digit::Quint => quints(packed).to_vec(),
+        };
+        for i in 0..group {
+            if values.len() < count {
+                values.push((digits[i], low[i]));
+            }
+        }
+    }
+    values
+}
+
+fn trits(t: u32) -> [u32; 5] {
+    let bit = |i: u32| (t >> i) & 1;
+    let (c, t3, t4) = if (t >> 2) & 7 == 7 {
+        ((t >> 5) << 2 | (t & 3), 2, 2)
+    } else if (t >> 5) & 3 == 3 {
+        (t & 0x1F, bit(7), 2)
+    } else {
+        (t & 0x1F, (t >> 5) & 3, bit(7))
+    };
+    let cbit = |i: u32| (c >> i) & 1;
+    let (t0, t1, t2) = if c & 3 == 3 {
+        (cbit(3) << 1 | (cbit(2) & !cbit(3) & 1), cbit(4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        (
+            cbit(1) << 1 | (cbit(0) & !cbit(1) & 1),
+            (c >> 2) & 3,
+            cbit(4),
+        )
+    };
+    [t0, t1, t2, t3, t4]
+}
+
+fn quints(q: u32) -> [u32; 3] {
+    let bit = |i: u32| (q >> i) & 1;
+    if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+        let not0 = !bit(0) & 1;
+        let q2 = bit(0) << 2 | (bit(4) & not0) << 1 | (bit(3) & not0);
+        return [4, 4, q2];
+    }
+    let (c, q2) = if (q >> 1) & 3 == 3 {
+        (((q >> 3) & 3) << 3 | (!(q >> 5) & 3) << 1 | bit(0), 4)
+    } else {
+        (q & 0x1F, (q >> 5) & 3)
+    };
+    if c & 7 == 5 {
+        [(c >> 3) & 3, 4, q2]
+    } else {
+        [c & 7, (c >> 3) & 3, q2]
+    }
+}
+
+/// 把 bits 位的值复制高位扩展到 target 位
+fn replicate(value: u32, bits: u32, target: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < target {
+        result = result << bits | value;
+        filled += bits;
+    }
+    result >> (filled - target)
+}
+
+fn unquantize_weight(levels: u32, digit: u32, low: u32) -> u32 {
+    let (kind, m) = ise_shape(levels);
+    let b = (low >> 1) & 1;
+    let c = (low >> 2) & 1;
+    let value = match (kind, m) {
+        (Digit::Bits, _) => replicate(low, m, 6),
+        // 这两种直接查表，不用再补 1
+        (Digit::Trit, 0) => return [0, 32, 64][digit as usize],
+        (Digit::Quint, 0) => return [0, 16, 32, 48, 64][digit as usize],
+        _ => {
+            let a = if low & 1 == 1 { 0x7F } else { 0 };
+            let (base, scale) = match (kind, m) {
+                (Digit::Trit, 1) => (0, 50),
+                (Digit::Quint, 1) => (0, 28),
+                (Digit::Trit, 2) => (b * 0x45, 23),
+                (Digit::Quint, 2) => (b * 0x42, 13),
+                _ => (c * 0x42 + b * 0x21, 11),
+            };
+            let t = (digit * scale + base) ^ a;
+            (a & 0x20) | (t >> 2)
+        }
+    };
+    if value > 32 {
+        value + 1
+    } else {
+        value
+    }
+}
+
+fn unquantize_color(levels: u32, digit: u32, low: u32) -> u32 {
+    let (kind, m) = ise_shape(levels);
+    if kind == Digit::Bits {
+        return replicate(low, m, 8);
+    }
+    let bit = |i: u32| (low >> i) & 1;
+    let (b, c, d, e, f) = (bit(1), bit(2), bit(3), bit(4), bit(5));
+    let a = if low & 1 == 1 { 0x1FF } else { 0 };
+    let (base, scale) = match (kind, m) {
+        (Digit::Trit, 1) => (0, 204),
+        (Digit::Quint, 1) => (0, 113),
+        (Digit::Trit, 2) => (b * 0x116, 93),
+        (Digit::Quint, 2) => (b * 0x10C, 54),
+        (Digit::Trit, 3) => (c * 0x10A + b * 0x85, 44),
+        (Digit::Quint, 3) => (c * 0x105 + b * 0x82, 26),
+        (Digit::Trit, 4) => (d * 0x104 + c * 0x82 + b * 0x41, 22),
+        (Digit::Quint, 4) => (d * 0x102 + c * 0x81 + b * 0x40, 13),
+        (Digit::Trit, 5) => (e * 0x102 + d * 0x81 + c * 0x40 + b * 0x20, 11),
+        (Digit::Quint, 5) => (e * 0x101 + d * 0x80 + c * 0x40 + b * 0x20, 6),
+        _ => (f * 0x101 + e * 0x80 + d * 0x40 + c * 0x20 + b * 0x10, 5),
+    };
+    let t = (digit * scale + base) ^ a;
+    (a & 0x80) | (t >> 2)
+}
+
+/// 两个端点，16 位；HDR 通道是 LNS 编码
+struct Endpoints {
+    e0: [i32; 4],
+    e1: [i32; 4],
+    hdr_rgb: bool,
+    hdr_alpha: bool,
+}
+
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let b = (b >> 1) | (a & 0x80);
+    let a = (a >> 1) & 0x3F;
+    let a = if a & 0x20 != 0 { a - 0x40 } else { a };
+    (a, b)
+}
+
+fn blue_contract(c: [i32; 4]) -> [i32; 4] {
+    [(c[0] + c[2]) >> 1, (c[1] + c[2]) >> 1, c[2], c[3]]
+}
+
+fn unpack_endpoints(cem: u32, colors: &[u32], profile: Profile) -> Endpoints {
+    let v: Vec<i32> = colors.iter().map(|&c| c as i32).collect();
+    let ldr = |e0: [i32; 4], e1: [i32; 4]| {
+        // sRGB 的 8 位端点补 0x80，其他按 257 倍扩到 16 位
+        let expand = |c: [i32; 4]| {
+            c.map(|c| {
+                let c = c.clamp(0, 255);
+                if profile == Profile::Srgb {
+                    c << 8 | 0x80
+                } else {
+                    c * 257
+                }
+            })
+        };
+        Endpoints {
+            e0: expand(e0),
+            e1: expand(e1),
+            hdr_rgb: false,
+            hdr_alpha: false,
+        }
+    };
+    let hdr = |e0: [i32; 3], e1: [i32; 3], alpha: Option<([i32; 2], bool)>| {
+        let (alpha, hdr_alpha) = alpha.unwrap_or(([0x7800, 0x7800], true));
+        Endpoints {
+            e0: [e0[0], e0[1], e0[2], alpha[0]],
+            e1: [e1[0], e1[1], e1[2], alpha[1]],
+            hdr_rgb: true,
+            hdr_alpha,
+        }
+    };
+    match cem {
+        0 => ldr([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            ldr([l0, l0, l0, 255], [l1, l1, l1, 255])
+        }
+        2 => {
+            let (y0, y1) = if v[1] >= v[0] {
+                (v[0] << 4, v[1] << 4)
+            } else {
+                ((v[1] << 4) + 8, (v[0] << 4) - 8)
+            };
+            hdr([y0 << 4; 3], [y1 << 4; 3], None)
+        }
+        3 => {
+            let (y0, d) = if v[0] & 0x80 != 0 {
+                ((v[1] & 0xE0) << 4 | (v[0] & 0x7F) << 2, (v[1] & 0x1F) << 2)
+            } else {
+                ((v[1] & 0xF0) << 4 | (v[0] & 0x7F) << 1, (v[1] & 0x0F) << 1)
+            };
+            let y1 = (y0 + d).min(0xFFF);
+            hdr([y0 << 4; 3], [y1 << 4; 3], None)
+        }
+        4 => ldr([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        5 => {
+            let (d0, l) = bit_transfer_signed(v[1], v[0]);
+            let (d1, a) = bit_transfer_signed(v[3], v[2]);
+            ldr([l, l, l, a], [l + d0, l + d0, l + d0, a + d1])
+        }
+        6 => {
+            let s = |c: i32| (c * v[3]) >> 8;
+            ldr([s(v[0]), s(v[1]), s(v[2]), 255], [v[0], v[1], v[2], 255])
+        }
+        7 => {
+            let (e0, e1) = hdr_rgbo(&v);
+            hdr(e0, e1, None)
+        }
+        8 | 12 => {
+            let (a0, a1) = if cem == 12 { (v[6], v[7]) } else { (255, 255) };
+            let e0 = [v[0], v[2], v[4], a0];
+            let e1 = [v[1], v[3], v[5], a1];
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ldr(e0, e1)
+            } else {
+                ldr(blue_contract(e1), blue_contract(e0))
+            }
+        }
+        9 | 13 => {
+            let mut base = [0; 4];
+            let mut offset = [0; 4];
+            let channels = if cem == 13 { 4 } else { 3 };
+            for c in 0..channels {
+                (offset[c], base[c]) = bit_transfer_signed(v[c * 2 + 1], v[c * 2]);
+            }
+            if cem == 9 {
+                base[3] = 255;
+            }
+            let sum: [i32; 4] = std::array::from_fn(|c| base[c] + offset[c]);
+            if offset[0] + offset[1] + offset[2] >= 0 {
+                ldr(base, sum)
+            } else {
+                ldr(blue_contract(sum), blue_contract(base))
+            }
+        }
+        10 => {
+            let s = |c: i32| (c * v[3]) >> 8;
+            ldr([s(v[0]), s(v[1]), s(v[2]), v[4]], [v[0], v[1], v[2], v[5]])
+        }
+        11 => {
+            let (e0, e1) = hdr_rgb(&v);
+            hdr(e0, e1, None)
+        }
+        14 => {
+            let (e0, e1) = hdr_rgb(&v);
+            // alpha 是 LDR 的，按 unorm16 处理
+            hdr(e0, e1, Some(([v[6] * 257, v[7] * 257], false)))
+        }
+        _ => {
+            let (e0, e1) = hdr_rgb(&v);
+            hdr(e0, e1, Some((hdr_alpha(v[6], v[7]), true)))
+        }
+    }
+}
+
+/// 端点模式 7：HDR RGB 基色加缩放
+fn hdr_rgbo(v: &[i32]) -> ([i32; 3], [i32; 3]) {
+    let mode_value = ((v[0] & 0xC0) >> 6) | ((v[1] & 0x80) >> 5) | ((v[2] & 0x80) >> 4);
+    let (major, mode) = if mode_value & 0xC != 0xC {
+        (mode_value >> 2, mode_value & 3)
+    } else if mode_value != 0xF {
+        (mode_value & 3, 4)
+    } else {
+        (0, 5)
+    };
+    let (mut red, mut green, mut blue, mut scale) =
+        (v[0] & 0x3F, v[1] & 0x1F, v[2] & 0x1F, v[3] & 0x1F);
+    let bit = |value: i32, i: i32| (value >> i) & 1;
+    let (bit0, bit1, bit2, bit3) = (bit(v[1], 6), bit(v[1], 5), bit(v[2], 6), bit(v[2], 5));
+    let (bit4, bit5, bit6) = (bit(v[3], 7), bit(v[3], 6), bit(v[3], 5));
+    let one_hot = 1 << mode;
+    if one_hot & 0x30 != 0 {
+        green |= bit0 << 6;
+        blue |= bit2 << 6;
+    }
+    if one_hot & 0x3A != 0 {
+        green |= bit1 << 5;
+        blue |= bit3 << 5;
+    }
+    if one_hot & 0x3D != 0 {
+        scale |= bit6 << 5;
+    }
+    if one_hot & 0x2D != 0 {
+        scale |= bit5 << 6;
+    }
+    if one_hot & 0x04 != 0 {
+        scale |= bit4 << 7;
+        red |= bit3 << 6;
+    }
+    if one_hot & 0x3B != 0 {
+        red |= bit4 << 6;
+    }
+    if one_hot & 0x10 != 0 {
+        red |= bit5 << 7;
+    }
+    if one_hot & 0x0F != 0 {
+        red |= bit2 << 7;
+    }
+    if one_hot & 0x05 != 0 {
+        red |= bit1 << 8 | bit0 << 9;
+    }
+    if one_hot & 0x0A != 0 {
+        red |= bit0 << 8;
+    }
+    if one_hot & 0x02 != 0 {
+        red |= bit6 << 9 | bit5 << 10;
+    }
+    if one_hot & 0x01 != 0 {
+        red |= bit3 << 10;
+    }
+
+    let shift = [1, 1, 2, 3, 4, 5][mode as usize];
+    red <<= shift;
+    green <<= shift;
+    blue <<= shift;
+    scale <<= shift;
+    // 模式 0~4 的绿和蓝存的是和红的差
+    if mode != 5 {
+        green = red - green;
+        blue = red - blue;
+    }
+    match major {
+        1 => std::mem::swap(&mut red, &mut green),
+        2 => std::mem::swap(&mut red, &mut blue),
+        _ => {}
+    }
+    let e1 = [red, green, blue].map(|c| c.clamp(0, 0xFFF) << 4);
+    let e0 = [red - scale, green - scale, blue - scale].map(|c| c.clamp(0, 0xFFF) << 4);
+    (e0, e1)
+}
+
+/// 端点模式 11、14、15 的 HDR RGB 部分
+fn hdr_rgb(v: &[i32]) -> ([i32; 3], [i32; 3]) {
+    let bit = |value: i32, i: i32| (value >> i) & 1;
+    let mode_value = bit(v[1], 7) | bit(v[2], 7) << 1 | bit(v[3], 7) << 2;
+    let major = bit(v[4], 7) | bit(v[5], 7) << 1;
+    if major == 3 {
+        return (
+            [v[0] << 8, v[2] << 8, (v[4] & 0x7F) << 9],
+            [v[1] << 8, v[3] << 8, (v[5] & 0x7F) << 9],
+        );
+    }
+
+    let mut a = v[0] | (v[1] & 0x40) << 2;
+    let (mut b0, mut b1, mut c) = (v[2] & 0x3F, v[3] & 0x3F, v[1] & 0x3F);
+    let (mut d0, mut d1) = (v[4] & 0x7F, v[5] & 0x7F);
+    let d_bits = [7, 6, 7, 6, 5, 6, 5, 6][mode_value as usize];
+    let (bit0, bit1, bit2, bit3) = (bit(v[2], 6), bit(v[3], 6), bit(v[4], 6), bit(v[5], 6));
+    let (bit4, bit5) = (bit(v[4], 5), bit(v[5], 5));
+    let one_hot = 1 << mode_value;
+    if one_hot & 0xA4 != 0 {
+        a |= bit0 << 9;
+    }
+    if one_hot & 0x8 != 0 {
+        a |= bit2 << 9;
+    }
+    if one_hot & 0x50 != 0 {
+        a |= bit4 << 9 | bit5 << 10;
+    }
+    if one_hot & 0xA0 != 0 {
+        a |= bit1 << 10;
+    }
+    if one_hot & 0xC0 != 0 {
+        a |= bit2 << 11;
+    }
+    if one_hot & 0x4 != 0 {
+        c |= bit1 << 6;
+    }
+    if one_hot & 0xE8 != 0 {
+        c |= bit3 << 6;
+    }
+    if one_hot & 0x20 != 0 {
+        c |= bit2 << 7;
+    }
+    if one_hot & 0x5B != 0 {
+        b0 |= bit0 << 6;
+        b1 |= bit1 << 6;
+    }
+    if one_hot & 0x12 != 0 {
+        b0 |= bit2 << 7;
+        b1 |= bit3 << 7;
+    }
+    if one_hot & 0xAF != 0 {
+        d0 |= bit4 << 5;
+        d1 |= bit5 << 5;
+    }
+    if one_hot & 0x5 != 0 {
+        d0 |= bit2 << 6;
+        d1 |= bit3 << 6;
+    }
+    let extend = 32 - d_bits;
+    let (d0, d1) = ((d0 << extend) >> extend, (d1 << extend) >> extend);
+
+    let shift = (mode_value >> 1) ^ 3;
+    let [a, b0, b1, c, d0, d1] = [a, b0, b1, c, d0, d1].map(|x| x << shift);
+    let mut e1 = [a, a - b0, a - b1].map(|x| x.clamp(0, 0xFFF));
+    let mut e0 = [a - c, a - b0 - c - d0, a - b1 - c - d1].map(|x| x.clamp(0, 0xFFF));
+    match major {
+        1 => {
+            e0.swap(0, 1);
+            e1.swap(0, 1);
+        }
+        2 => {
+            e0.swap(0, 2);
+            e1.swap(0, 2);
+        }
+        _ => {}
+    }
+    (e0.map(|x| x << 4), e1.map(|x| x << 4))
+}
+
+/// 端点模式 15 的 HDR alpha
+fn hdr_alpha(v6: i32, v7: i32) -> [i32; 2] {
+    let selector = ((v6 >> 7) & 1) | ((v7 >> 6) & 2);
+    let (mut v6, mut v7) = (v6 & 0x7F, v7 & 0x7F);
+    if selector == 3 {
+        return [v6 << 9, v7 << 9];
+    }
+    v6 |= (v7 << (selector + 1)) & 0x780;
+    v7 &= 0x3F >> selector;
+    v7 ^= 32 >> selector;
+    v7 -= 32 >> selector;
+    v6 <<= 4 - selector;
+    v7 <<= 4 - selector;
+    v7 = (v7 + v6).clamp(0, 0xFFF);
+    [v6 << 4, v7 << 4]
+}
+
+/// 权重网格双线性插值到块内每个像素，每个平面一组
+fn infill(
+    weights: &[u32],
+    gw: usize,
+    gh: usize,
+    bw: usize,
+    bh: usize,
+    planes: usize,
+) -> Vec<Vec<u32>> {
+    let ds = (1024 + bw / 2) / (bw - 1);
+    let dt = (1024 + bh / 2) / (bh - 1);
+    (0..planes)
+        .map(|plane| {
+            let weight = |i: usize| weights.get(i * planes + plane).copied().unwrap_or(0);
+            (0..bw * bh)
+                .map(|i| {
+                    let (s, t) = (i % bw, i / bw);
+                    let gs = (ds * s * (gw - 1) + 32) >> 6;
+                    let gt = (dt * t * (gh - 1) + 32) >> 6;
+                    let (js, fs) = (gs >> 4, (gs & 0xF) as u32);
+                    let (jt, ft) = (gt >> 4, (gt & 0xF) as u32);
+                    let v0 = js + jt * gw;
+                    let w11 = (fs * ft + 8) >> 4;
+                    let w10 = ft - w11;
+                    let w01 = fs - w11;
+                    let w00 = 16 - fs - ft + w11;
+                    (weight(v0) * w00
+                        + weight(v0 + 1) * w01
+                        + weight(v0 + gw) * w10
+                        + weight(v0 + gw + 1) * w11
+                        + 8)
+                        >> 4
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn hash52(mut p: u32) -> u32 {
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+/// 按分区号的哈希算像素属于哪个分区
+fn select_partition(seed: u32, x: usize, y: usize, partitions: usize, small_block: bool) -> usize {
+    let (x, y) = if small_block {
+        (x << 1, y << 1)
+    } else {
+        (x, y)
+    };
+    let (x, y) = (x as u32, y as u32);
+    let seed = seed + (partitions as u32 - 1) * 1024;
+    let rnum = hash52(seed);
+    let partitions3 = if partitions == 3 { 6 } else { 5 };
+    let odd = if seed & 2 != 0 { 4 } else { 5 };
+    let (sh1, sh2) = if seed & 1 != 0 {
+        (odd, partitions3)
+    } else {
+        (partitions3, odd)
+    };
+    // 块是二维的，z 恒为 0，只用得上前 8 个种子
+    let seeds: [u32; 8] = std::array::from_fn(|i| {
+        let s = (rnum >> (i * 4)) & 0xF;
+        (s * s) >> if i % 2 == 0 { sh1 } else { sh2 }
+    });
+    let a = (seeds[0] * x + seeds[1] * y + (rnum >> 14)) & 0x3F;
+    let b = (seeds[2] * x + seeds[3] * y + (rnum >> 10)) & 0x3F;
+    let c = if partitions < 3 {
+        0
+    } else {
+        (seeds[4] * x + seeds[5] * y + (rnum >> 6)) & 0x3F
+    };
+    let d = if partitions < 4 {
+        0
+    } else {
+        (seeds[6] * x + seeds[7] * y + (rnum >> 2)) & 0x3F
+    };
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+/// HDR 端点的 LNS 值转 half float
+fn lns_to_f16(c: u32) -> u16 {
+    let e = c >> 11;
+    let m = c & 0x7FF;
+    let mt = if m < 512 {
+        3 * m
+    } else if m < 1536 {
+        4 * m - 512
+    } else {
+        5 * m - 2048
+    };
+    ((e << 10) + (mt >> 3)).min(0x7BFF) as u16
+}
+
+fn unorm16_to_f16(c: u32) -> u16 {
+    if c >= 0xFFFF {
+        0x3C00
+    } else {
+        f32_to_f16(c as f32 / 65535.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn trits_cover_every_tuple() {
+        let tuples: HashSet<[u32; 5]> = (0..256).map(trits).collect();
+        assert_eq!(tuples.len(), 243);
+        assert!(tuples.iter().flatten().all(|&t| t < 3));
+    }
+
+    #[test]
+    fn quints_cover_every_tuple() {
+        let tuples: HashSet<[u32; 3]> = (0..128).map(quints).collect();
+        assert_eq!(tuples.len(), 125);
+        assert!(tuples.iter().flatten().all(|&q| q < 5));
+    }
+
+    /// 每个量化级数的所有编码解出来互不相同，并且均匀铺满整个范围
+    fn check_unquantized(levels: u32, max: u32, unquantize: fn(u32, u32, u32) -> u32) {
+        let (digit, bits) = ise_shape(levels);
+        let digits = match digit {
+            Digit::Bits => 1,
+            Digit::Trit => 3,
+            Digit::Quint => 5,
+        };
+        let mut values: Vec<u32> = (0..digits)
+            .flat_map(|d| (0..1 << bits).map(move |low| unquantize(levels, d, low)))
+            .collect();
+        values.sort();
+        values.dedup();
+        assert_eq!(values.len(), levels as usize, "{levels} levels");
+        assert_eq!(
+            (values[0], *values.last().unwrap()),
+            (0, max),
+            "{levels} levels"
+        );
+        let step = max as f32 / (levels - 1) as f32;
+        for pair in values.windows(2) {
+            let gap = (pair[1] - pair[0]) as f32;
+            assert!(
+                (gap - step).abs() <= step * 0.5 + 1.0,
+                "{levels} levels: {pair:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unquantized_colors_span_full_range() {
+        for levels in COLOR_LEVELS.into_iter().filter(|&l| l >= 6) {
+            check_unquantized(levels, 255, unquantize_color);
+        }
+    }
+
+    #[test]
+    fn unquantized_weights_span_full_range() {
+        for levels in WEIGHT_LEVELS {
+            check_unquantized(levels, 64, unquantize_weight);
+        }
+    }
+
+    fn void_extent_block(hdr: bool, color: [u16; 4]) -> [u8; 16] {
+        let mut bits: u128 = 0x1FC | (hdr as u128) << 9;
+        // 范围坐标全 1 表示没有范围
+        bits |= ((1u128 << 52) - 1) << 12;
+        for (c, v) in color.iter().enumerate() {
+            bits |= (*v as u128) << (64 + 16 * c);
+        }
+        bits.to_le_bytes()
+    }
+
+    #[test]
+    fn ldr_void_extent_fills_block() {
+        let block = void_extent_block(false, [0xFFFF, 0x8000, 0, 0xFFFF]);
+        let mut out = [0u8; 16 * 4];
+        decode(&block, 4, 4, Profile::Ldr, &mut out);
+        assert!(out.chunks(4).all(|t| t == [255, 128, 0, 255]));
+
+        let mut out = [0u8; 16 * 8];
+        decode(&block, 4, 4, Profile::Hdr, &mut out);
+        assert_eq!(&out[0..8], &[0x00, 0x3C, 0x00, 0x38, 0, 0, 0x00, 0x3C]);
+    }
+
+    #[test]
+    fn hdr_void_extent_is_error_in_ldr() {
+        let block = void_extent_block(true, [0x4000, 0x3C00, 0, 0x3C00]);
+        let mut out = [0u8; 16 * 4];
+        decode(&block, 4, 4, Profile::Ldr, &mut out);
+        assert!(out.chunks(4).all(|t| t == [255, 0, 255, 255]));
+
+        let mut out = [0u8; 16 * 8];
+        decode(&block, 4, 4, Profile::Hdr, &mut out);
+        assert_eq!(&out[0..8], &[0x00, 0x40, 0x00, 0x3C, 0, 0, 0x00, 0x3C]);
+    }
+
+    #[test]
+    fn rgb_direct_block_interpolates_weights() {
+        // 模式 0x42：4x4 权重网格，每个权重 2 位；单分区，端点模式 8
+        let mut bits: u128 = 0x42 | 8 << 13;
+        for (i, v) in [255u128, 0, 0, 0, 0, 255].iter().enumerate() {
+            bits |= v << (17 + 8 * i);
+        }
+        // 权重从块的最高位倒着读：像素 1 是 3，像素 2 是 1
+        bits |= 0b11 << 124;
+        bits |= 1 << 123;
+        let mut out = [0u8; 16 * 4];
+        decode(&bits.to_le_bytes(), 4, 4, Profile::Ldr, &mut out);
+        assert_eq!(out[0..4], [255, 0, 0, 255]);
+        assert_eq!(out[4..8], [0, 0, 255, 255]);
+        assert_eq!(out[8..12], [171, 0, 84, 255]);
+        assert_eq!(out[12..16], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn reserved_block_mode_is_error_color() {
+        let mut out = [0u8; 16 * 4];
+        decode(&[0; 16], 4, 4, Profile::Ldr, &mut out);
+        assert!(out.chunks(4).all(|t| t == [255, 0, 255, 255]));
+    }
+
+    #[test]
+    fn partitions_stay_in_range() {
+        for partitions in 2..=4 {
+            let mut used = HashSet::new();
+            for seed in 0..1024 {
+                for i in 0..16 {
+                    used.insert(select_partition(seed, i % 4, i / 4, partitions, true));
+                }
+            }
+            assert_eq!(used, (0..partitions).collect());
+        }
+    }
+}
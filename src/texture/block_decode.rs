@@ -0,0 +1,562 @@
+use super::astc::{self, Profile};
+use super::bptc;
+use super::TextureError;
+
+// 块压缩格式的 CPU 解码，设备没有对应 feature 时的退路
+// LDR 格式解成 RGBA8，BC6H 和 HDR 的 ASTC 解成 Rgba16Float
+
+type Block = [[u8; 4]; 16];
+
+/// 解一个块，写到块内行优先的像素里
+type BlockDecoder = Box<dyn Fn(&[u8], &mut [u8])>;
+
+fn ldr(decode_block: fn(&[u8]) -> Block) -> BlockDecoder {
+    Box::new(move |block, out| out.copy_from_slice(bytemuck::cast_slice(&decode_block(block)[..])))
+}
+
+/// 解码一级 mip，返回像素数据和解码后的格式
+pub(crate) fn decode(
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<(Vec<u8>, wgpu::TextureFormat), TextureError> {
+    use wgpu::TextureFormat as F;
+    if !format.is_compressed() {
+        return Ok((data.to_vec(), format));
+    }
+    let srgb = format.is_srgb();
+    let color = if srgb {
+        F::Rgba8UnormSrgb
+    } else {
+        F::Rgba8Unorm
+    };
+    let (decode_block, decoded): (BlockDecoder, _) = match format {
+        F::Bc1RgbaUnorm | F::Bc1RgbaUnormSrgb => (ldr(|b| bc1(b, false)), color),
+        F::Bc2RgbaUnorm | F::Bc2RgbaUnormSrgb => (ldr(bc2), color),
+        F::Bc3RgbaUnorm | F::Bc3RgbaUnormSrgb => (ldr(bc3), color),
+        F::Bc4RUnorm => (ldr(|b| bc4_block(b, false, 1)), F::Rgba8Unorm),
+        F::Bc4RSnorm => (ldr(|b| bc4_block(b, true, 1)), F::Rgba8Snorm),
+        F::Bc5RgUnorm => (ldr(|b| bc4_block(b, false, 2)), F::Rgba8Unorm),
+        F::Bc5RgSnorm => (ldr(|b| bc4_block(b, true, 2)), F::Rgba8Snorm),
+        F::Bc6hRgbUfloat | F::Bc6hRgbFloat => {
+            let signed = format == F::Bc6hRgbFloat;
+            let decode_block: BlockDecoder = Box::new(move |block, out| {
+                out.copy_from_slice(bytemuck::cast_slice(&bptc::bc6h(block, signed)[..]))
+            });
+            (decode_block, F::Rgba16Float)
+        }
+        F::Bc7RgbaUnorm | F::Bc7RgbaUnormSrgb => (ldr(bptc::bc7), color),
+        F::Etc2Rgb8Unorm | F::Etc2Rgb8UnormSrgb => (ldr(|b| etc2_rgb(b, false)), color),
+        F::Etc2Rgb8A1Unorm | F::Etc2Rgb8A1UnormSrgb => (ldr(|b| etc2_rgb(b, true)), color),
+        F::Etc2Rgba8Unorm | F::Etc2Rgba8UnormSrgb => (ldr(etc2_rgba), color),
+        F::EacR11Unorm => (ldr(|b| eac_block(b, false, 1)), F::Rgba8Unorm),
+        F::EacR11Snorm => (ldr(|b| eac_block(b, true, 1)), F::Rgba8Snorm),
+        F::EacRg11Unorm => (ldr(|b| eac_block(b, false, 2)), F::Rgba8Unorm),
+        F::EacRg11Snorm => (ldr(|b| eac_block(b, true, 2)), F::Rgba8Snorm),
+        F::Astc { channel, .. } => {
+            let (profile, decoded) = match channel {
+                wgpu::AstcChannel::Hdr => (Profile::Hdr, F::Rgba16Float),
+                wgpu::AstcChannel::UnormSrgb => (Profile::Srgb, color),
+                wgpu::AstcChannel::Unorm => (Profile::Ldr, color),
+            };
+            let (bw, bh) = format.block_dimensions();
+            let decode_block: BlockDecoder = Box::new(move |block, out| {
+                astc::decode(block, bw as usize, bh as usize, profile, out)
+            });
+            (decode_block, decoded)
+        }
+        _ => {
+            return Err(TextureError::Unsupported(format!(
+                "{:?} has no CPU decoder",
+                format
+            )))
+        }
+    };
+
+    let (bw, bh) = format.block_dimensions();
+    let block_size = format.block_copy_size(None).unwrap() as usize;
+    let blocks_x = width.div_ceil(bw) as usize;
+    let blocks_y = height.div_ceil(bh) as usize;
+    if data.len() < blocks_x * blocks_y * block_size {
+        return Err(TextureError::Container(format!(
+            "{:?} level {}x{} is truncated",
+            format, width, height
+        )));
+    }
+
+    let (bw, bh) = (bw as usize, bh as usize);
+    let texel_size = decoded.block_copy_size(None).unwrap() as usize;
+    let (width, height) = (width as usize, height as usize);
+    let mut texels = vec![0u8; width * height * texel_size];
+    let mut scratch = vec![0u8; bw * bh * texel_size];
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let offset = (by * blocks_x + bx) * block_size;
+            decode_block(&data[offset..offset + block_size], &mut scratch);
+            // 边缘的块只取图像内的部分
+            let (px, row_len) = (bx * bw, bw.min(width - bx * bw) * texel_size);
+            for y in 0..bh.min(height - by * bh) {
+                let dst = ((by * bh + y) * width + px) * texel_size;
+                let src = y * bw * texel_size;
+                texels[dst..dst + row_len].copy_from_slice(&scratch[src..src + row_len]);
+            }
+        }
+    }
+    Ok((texels, decoded))
+}
+
+fn rgb565(c: u16) -> [i32; 3] {
+    let r = ((c >> 11) & 31) as i32;
+    let g = ((c >> 5) & 63) as i32;
+    let b = (c & 31) as i32;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// BC2 / BC3 的颜色部分总是四色模式
+fn bc1(block: &[u8], force_four_color: bool) -> Block {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let mix = |wa: i32, wb: i32, d: i32| -> [u8; 4] {
+        [
+            ((a[0] * wa + b[0] * wb) / d) as u8,
+            ((a[1] * wa + b[1] * wb) / d) as u8,
+            ((a[2] * wa + b[2] * wb) / d) as u8,
+            255,
+        ]
+    };
+    let palette = if c0 > c1 || force_four_color {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0, 0, 0, 0]]
+    };
+    std::array::from_fn(|i| palette[((indices >> (i * 2)) & 3) as usize])
+}
+
+fn bc2(block: &[u8]) -> Block {
+    let alpha = u64::from_le_bytes(block[0..8].try_into().unwrap());
+    let mut texels = bc1(&block[8..16], true);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        texel[3] = ((alpha >> (i * 4)) & 15) as u8 * 17;
+    }
+    texels
+}
+
+fn bc3(block: &[u8]) -> Block {
+    let alpha = bc4(&block[0..8], false);
+    let mut texels = bc1(&block[8..16], true);
+    for (texel, a) in texels.iter_mut().zip(alpha) {
+        texel[3] = a;
+    }
+    texels
+}
+
+/// 单通道，snorm 的结果按 i8 的位模式存
+fn bc4(block: &[u8], snorm: bool) -> [u8; 16] {
+    let bits = u64::from_le_bytes(block[0..8].try_into().unwrap()) >> 16;
+    let (a0, a1, min, max) = if snorm {
+        let a0 = (block[0] as i8).max(-127) as i32;
+        let a1 = (block[1] as i8).max(-127) as i32;
+        (a0, a1, -127, 127)
+    } else {
+        (block[0] as i32, block[1] as i32, 0, 255)
+    };
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+    if a0 > a1 {
+        for i in 1..7 {
+            palette[i + 1] = (a0 * (7 - i as i32) + a1 * i as i32) / 7;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = (a0 * (5 - i as i32) + a1 * i as i32) / 5;
+        }
+        palette[6] = min;
+        palette[7] = max;
+    }
+    std::array::from_fn(|i| palette[((bits >> (i * 3)) & 7) as usize] as u8)
+}
+
+/// BC4 / BC5，后者是两个 BC4 块拼起来
+fn bc4_block(block: &[u8], snorm: bool, channels: usize) -> Block {
+    single_or_dual(snorm, channels, |c| bc4(&block[c * 8..c * 8 + 8], snorm))
+}
+
+fn single_or_dual(snorm: bool, channels: usize, channel: impl Fn(usize) -> [u8; 16]) -> Block {
+    let one = if snorm { 127 } else { 255 };
+    let r = channel(0);
+    let g = if channels == 2 { channel(1) } else { [0; 16] };
+    std::array::from_fn(|i| [r[i], g[i], 0, one])
+}
+
+const ETC1_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC2_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+fn extend4(v: u32) -> i32 {
+    (v * 17) as i32
+}
+
+fn extend5(v: u32) -> i32 {
+    ((v << 3) | (v >> 2)) as i32
+}
+
+fn extend6(v: u32) -> i32 {
+    ((v << 2) | (v >> 4)) as i32
+}
+
+fn extend7(v: u32) -> i32 {
+    ((v << 1) | (v >> 6)) as i32
+}
+
+fn sign3(v: u32) -> i32 {
+    ((v << 29) as i32) >> 29
+}
+
+fn rgba(c: [i32; 3], d: i32) -> [u8; 4] {
+    [
+        (c[0] + d).clamp(0, 255) as u8,
+        (c[1] + d).clamp(0, 255) as u8,
+        (c[2] + d).clamp(0, 255) as u8,
+        255,
+    ]
+}
+
+/// ETC2 RGB8，punchthrough 为 true 时是 RGB8A1
+/// ETC 的像素是按列编号的，pixel i = x * 4 + y
+fn etc2_rgb(block: &[u8], punchthrough: bool) -> Block {
+    let bits = u64::from_be_bytes(block[0..8].try_into().unwrap());
+    let hi = (bits >> 32) as u32;
+    let lo = bits as u32;
+    let index = |x: usize, y: usize| {
+        let i = x * 4 + y;
+        (((lo >> (16 + i)) & 1) << 1 | ((lo >> i) & 1)) as usize
+    };
+    let diff_bit = (hi >> 1) & 1 == 1;
+    // punchthrough 里这一位表示不透明，并且没有 individual 模式
+    let opaque = !punchthrough || diff_bit;
+    let differential = punchthrough || diff_bit;
+
+    let mut texels = [[0u8; 4]; 16];
+    if differential {
+        let r = (hi >> 27) & 31;
+        let g = (hi >> 19) & 31;
+        let b = (hi >> 11) & 31;
+        let r2 = r as i32 + sign3(hi >> 24);
+        let g2 = g as i32 + sign3(hi >> 16);
+        let b2 = b as i32 + sign3(hi >> 8);
+        if !(0..32).contains(&r2) {
+            return etc2_t(hi, opaque, index);
+        }
+        if !(0..32).contains(&g2) {
+            return etc2_h(hi, opaque, index);
+        }
+        if !(0..32).contains(&b2) {
+            return etc2_planar(hi, lo);
+        }
+        let base = [
+            [extend5(r), extend5(g), extend5(b)],
+            [extend5(r2 as u32), extend5(g2 as u32), extend5(b2 as u32)],
+        ];
+        etc1_subblocks(hi, base, opaque, index, &mut texels);
+    } else {
+        let base = [
+            [
+                extend4((hi >> 28) & 15),
+                extend4((hi >> 20) & 15),
+                extend4((hi >> 12) & 15),
+            ],
+            [
+                extend4((hi >> 24) & 15),
+                extend4((hi >> 16) & 15),
+                extend4((hi >> 8) & 15),
+            ],
+        ];
+        etc1_subblocks(hi, base, true, index, &mut texels);
+    }
+    texels
+}
+
+fn etc1_subblocks(
+    hi: u32,
+    base: [[i32; 3]; 2],
+    opaque: bool,
+    index: impl Fn(usize, usize) -> usize,
+    texels: &mut Block,
+) {
+    let tables = [(hi >> 5) & 7, (hi >> 2) & 7];
+    let flip = hi & 1 == 1;
+    for y in 0..4 {
+        for x in 0..4 {
+            let sub = if flip { y / 2 } else { x / 2 };
+            let [a, b] = ETC1_MODIFIERS[tables[sub] as usize];
+            texels[y * 4 + x] = match (index(x, y), opaque) {
+                (0, true) => rgba(base[sub], a),
+                (1, _) => rgba(base[sub], b),
+                (2, true) => rgba(base[sub], -a),
+                (3, _) => rgba(base[sub], -b),
+                (0, false) => rgba(base[sub], 0),
+                _ => [0, 0, 0, 0],
+            };
+        }
+    }
+}
+
+fn etc2_paint(paint: [[u8; 4]; 4], opaque: bool, index: impl Fn(usize, usize) -> usize) -> Block {
+    let mut texels = [[0u8; 4]; 16];
+    for y in 0..4 {
+        for x in 0..4 {
+            let i = index(x, y);
+            texels[y * 4 + x] = if !opaque && i == 2 {
+                [0, 0, 0, 0]
+            } else {
+                paint[i]
+            };
+        }
+    }
+    texels
+}
+
+fn etc2_t(hi: u32, opaque: bool, index: impl Fn(usize, usize) -> usize) -> Block {
+    let c1 = [
+        extend4(((hi >> 27) & 3) << 2 | ((hi >> 24) & 3)),
+        extend4((hi >> 20) & 15),
+        extend4((hi >> 16) & 15),
+    ];
+    let c2 = [
+        extend4((hi >> 12) & 15),
+        extend4((hi >> 8) & 15),
+        extend4((hi >> 4) & 15),
+    ];
+    let d = ETC2_DISTANCES[(((hi >> 2) & 3) << 1 | (hi & 1)) as usize];
+    let paint = [rgba(c1, 0), rgba(c2, d), rgba(c2, 0), rgba(c2, -d)];
+    etc2_paint(paint, opaque, index)
+}
+
+fn etc2_h(hi: u32, opaque: bool, index: impl Fn(usize, usize) -> usize) -> Block {
+    let r1 = (hi >> 27) & 15;
+    let g1 = ((hi >> 24) & 7) << 1 | ((hi >> 20) & 1);
+    let b1 = ((hi >> 19) & 1) << 3 | ((hi >> 15) & 7);
+    let r2 = (hi >> 11) & 15;
+    let g2 = (hi >> 7) & 15;
+    let b2 = (hi >> 3) & 15;
+    let v1 = (r1 << 8) | (g1 << 4) | b1;
+    let v2 = (r2 << 8) | (g2 << 4) | b2;
+    let d = ((hi >> 2) & 1) << 2 | (hi & 1) << 1 | (v1 >= v2) as u32;
+    let d = ETC2_DISTANCES[d as usize];
+    let c1 = [extend4(r1), extend4(g1), extend4(b1)];
+    let c2 = [extend4(r2), extend4(g2), extend4(b2)];
+    let paint = [rgba(c1, d), rgba(c1, -d), rgba(c2, d), rgba(c2, -d)];
+    etc2_paint(paint, opaque, index)
+}
+
+fn etc2_planar(hi: u32, lo: u32) -> Block {
+    let o = [
+        extend6((hi >> 25) & 63),
+        extend7(((hi >> 24) & 1) << 6 | ((hi >> 17) & 63)),
+        extend6(((hi >> 16) & 1) << 5 | ((hi >> 11) & 3) << 3 | ((hi >> 7) & 7)),
+    ];
+    let h = [
+        extend6(((hi >> 2) & 31) << 1 | (hi & 1)),
+        extend7((lo >> 25) & 127),
+        extend6((lo >> 19) & 63),
+    ];
+    let v = [
+        extend6((lo >> 13) & 63),
+        extend7((lo >> 6) & 127),
+        extend6(lo & 63),
+    ];
+    std::array::from_fn(|i| {
+        let (x, y) = ((i % 4) as i32, (i / 4) as i32);
+        let c = |k: usize| {
+            ((x * (h[k] - o[k]) + y * (v[k] - o[k]) + 4 * o[k] + 2) >> 2).clamp(0, 255) as u8
+        };
+        [c(0), c(1), c(2), 255]
+    })
+}
+
+#[rustfmt::skip]
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// EAC 的 8 字节块，f 把 (base, modifier, multiplier) 算成最终的值
+fn eac(block: &[u8], f: impl Fn(i32, i32, i32) -> u8) -> [u8; 16] {
+    let bits = u64::from_be_bytes(block[0..8].try_into().unwrap());
+    let base = (bits >> 56) as u8;
+    let multiplier = ((bits >> 52) & 15) as i32;
+    let table = &EAC_MODIFIERS[((bits >> 48) & 15) as usize];
+    let mut values = [0u8; 16];
+    for i in 0..16 {
+        let modifier = table[((bits >> (45 - 3 * i)) & 7) as usize];
+        let (x, y) = (i / 4, i % 4);
+        values[y * 4 + x] = f(base as i32, modifier, multiplier);
+    }
+    values
+}
+
+/// R11 / RG11，精度截到 8 位
+fn eac_block(block: &[u8], snorm: bool, channels: usize) -> Block {
+    single_or_dual(snorm, channels, |c| {
+        let block = &block[c * 8..c * 8 + 8];
+        if snorm {
+            eac(block, |base, modifier, multiplier| {
+                let base = base as u8 as i8 as i32;
+                let step = if multiplier == 0 {
+                    modifier
+                } else {
+                    modifier * multiplier * 8
+                };
+                let v = (base * 8 + step).clamp(-1023, 1023);
+                (v >> 3).max(-127) as i8 as u8
+            })
+        } else {
+            eac(block, |base, modifier, multiplier| {
+                let step = if multiplier == 0 {
+                    modifier
+                } else {
+                    modifier * multiplier * 8
+                };
+                ((base * 8 + 4 + step).clamp(0, 2047) >> 3) as u8
+            })
+        }
+    })
+}
+
+fn etc2_rgba(block: &[u8]) -> Block {
+    let alpha = eac(&block[0..8], |base, modifier, multiplier| {
+        (base + modifier * multiplier).clamp(0, 255) as u8
+    });
+    let mut texels = etc2_rgb(&block[8..16], false);
+    for (texel, a) in texels.iter_mut().zip(alpha) {
+        texel[3] = a;
+    }
+    texels
+}
+
+/// 按 (值, 位数) 从低位开始拼出一个 128 位的块
+#[cfg(test)]
+pub(super) fn pack(fields: &[(u32, u32)]) -> [u8; 16] {
+    let mut bits = 0u128;
+    let mut pos = 0;
+    for &(value, count) in fields {
+        bits |= (value as u128) << pos;
+        pos += count;
+    }
+    assert!(pos <= 128);
+    bits.to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::TextureFormat as F;
+
+    fn bc1_block(c0: u16, c1: u16, indices: u32) -> [u8; 8] {
+        let mut block = [0u8; 8];
+        block[0..2].copy_from_slice(&c0.to_le_bytes());
+        block[2..4].copy_from_slice(&c1.to_le_bytes());
+        block[4..8].copy_from_slice(&indices.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn bc1_four_color_palette() {
+        let texels = bc1(&bc1_block(0xF800, 0x001F, 0b11_10_01_00), false);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 255]);
+        assert_eq!(texels[2], [170, 0, 85, 255]);
+        assert_eq!(texels[3], [85, 0, 170, 255]);
+    }
+
+    #[test]
+    fn bc1_three_color_mode_has_transparent_index() {
+        let texels = bc1(&bc1_block(0x001F, 0xF800, 0b11_10_00), false);
+        assert_eq!(texels[0], [0, 0, 255, 255]);
+        assert_eq!(texels[1], [127, 0, 127, 255]);
+        assert_eq!(texels[2], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bc4_eight_value_palette() {
+        let block = [255, 0, 0b10_001_000, 0, 0, 0, 0, 0];
+        let texels = bc4_block(&block, false, 1);
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 0, 255]);
+        assert_eq!(texels[2], [218, 0, 0, 255]);
+    }
+
+    #[test]
+    fn etc2_individual_mode() {
+        let hi: u32 = 8 << 28 | 8 << 24;
+        // 像素 (0, 1) 的索引是 1，其他是 0
+        let lo: u32 = 1 << 1;
+        let block = ((hi as u64) << 32 | lo as u64).to_be_bytes();
+        let texels = etc2_rgb(&block, false);
+        assert_eq!(texels[0], [138, 2, 2, 255]);
+        assert_eq!(texels[4], [144, 8, 8, 255]);
+    }
+
+    #[test]
+    fn partial_blocks_are_clipped() {
+        let mut data = bc1_block(0xF800, 0xF800, 0).to_vec();
+        data.extend(bc1_block(0x001F, 0x001F, 0));
+        let (texels, format) = decode(F::Bc1RgbaUnorm, 5, 3, &data).unwrap();
+        assert_eq!(format, F::Rgba8Unorm);
+        assert_eq!(texels.len(), 5 * 3 * 4);
+        assert_eq!(texels[12..16], [255, 0, 0, 255]);
+        assert_eq!(texels[16..20], [0, 0, 255, 255]);
+        assert_eq!(texels[(2 * 5 + 4) * 4..], [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn astc_6x6_void_extent() {
+        let mut bits: u128 = 0x1FC | ((1u128 << 52) - 1) << 12;
+        bits |= (0xFFFF_u128 | 0x8000 << 16 | 0xFFFF << 48) << 64;
+        let format = F::Astc {
+            block: wgpu::AstcBlock::B6x6,
+            channel: wgpu::AstcChannel::Unorm,
+        };
+        let (texels, decoded) = decode(format, 6, 6, &bits.to_le_bytes()).unwrap();
+        assert_eq!(decoded, F::Rgba8Unorm);
+        assert_eq!(texels.len(), 6 * 6 * 4);
+        assert!(texels.chunks(4).all(|t| t == [255, 128, 0, 255]));
+    }
+
+    #[test]
+    fn bc6h_decodes_to_half_float() {
+        let (texels, decoded) = decode(F::Bc6hRgbUfloat, 4, 4, &[0; 16]).unwrap();
+        assert_eq!(decoded, F::Rgba16Float);
+        assert_eq!(texels.len(), 16 * 8);
+        assert_eq!(texels[6..8], 0x3C00u16.to_le_bytes());
+    }
+}
@@ -0,0 +1,591 @@
+// BC6H / BC7 (BPTC) 的 CPU 解码，block_decode.rs 在设备没有 TEXTURE_COMPRESSION_BC 时调用
+// 两种格式共用分区表、锚点表和插值权重
+
+/// 从低位开始按顺序读 128 位的块
+struct BitReader {
+    bits: u128,
+    pos: u32,
+}
+
+impl BitReader {
+    fn new(block: &[u8]) -> Self {
+        Self {
+            bits: u128::from_le_bytes(block[0..16].try_into().unwrap()),
+            pos: 0,
+        }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let value = ((self.bits >> self.pos) & ((1u128 << count) - 1)) as u32;
+        self.pos += count;
+        value
+    }
+}
+
+const WEIGHTS2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn weight(index: u32, bits: u32) -> u32 {
+    match bits {
+        2 => WEIGHTS2[index as usize],
+        3 => WEIGHTS3[index as usize],
+        _ => WEIGHTS4[index as usize],
+    }
+}
+
+/// 两个分区时每个像素属于哪个分区，第 i 位是像素 i
+#[rustfmt::skip]
+const PARTITIONS2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+    0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A,
+    0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+    0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// 三个分区，每个像素两位，第 2i 位开始是像素 i
+const PARTITIONS3: [u32; 64] = {
+    #[rustfmt::skip]
+    const TABLE: [[u8; 16]; 64] = [
+        [0,0,1,1,0,0,1,1,0,2,2,1,2,2,2,2], [0,0,0,1,0,0,1,1,2,2,1,1,2,2,2,1],
+        [0,0,0,0,2,0,0,1,2,2,1,1,2,2,1,1], [0,2,2,2,0,0,2,2,0,0,1,1,0,1,1,1],
+        [0,0,0,0,0,0,0,0,1,1,2,2,1,1,2,2], [0,0,1,1,0,0,1,1,0,0,2,2,0,0,2,2],
+        [0,0,2,2,0,0,2,2,1,1,1,1,1,1,1,1], [0,0,1,1,0,0,1,1,2,2,1,1,2,2,1,1],
+        [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,1,1,1,1,2,2,2,2],
+        [0,0,0,0,1,1,1,1,2,2,2,2,2,2,2,2], [0,0,1,2,0,0,1,2,0,0,1,2,0,0,1,2],
+        [0,1,1,2,0,1,1,2,0,1,1,2,0,1,1,2], [0,1,2,2,0,1,2,2,0,1,2,2,0,1,2,2],
+        [0,0,1,1,0,1,1,2,1,1,2,2,1,2,2,2], [0,0,1,1,2,0,0,1,2,2,0,0,2,2,2,0],
+        [0,0,0,1,0,0,1,1,0,1,1,2,1,1,2,2], [0,1,1,1,0,0,1,1,2,0,0,1,2,2,0,0],
+        [0,0,0,0,1,1,2,2,1,1,2,2,1,1,2,2], [0,0,2,2,0,0,2,2,0,0,2,2,1,1,1,1],
+        [0,1,1,1,0,1,1,1,0,2,2,2,0,2,2,2], [0,0,0,1,0,0,0,1,2,2,2,1,2,2,2,1],
+        [0,0,0,0,0,0,1,1,0,1,2,2,0,1,2,2], [0,0,0,0,1,1,0,0,2,2,1,0,2,2,1,0],
+        [0,1,2,2,0,1,2,2,0,0,1,1,0,0,0,0], [0,0,1,2,0,0,1,2,1,1,2,2,2,2,2,2],
+        [0,1,1,0,1,2,2,1,1,2,2,1,0,1,1,0], [0,0,0,0,0,1,1,0,1,2,2,1,1,2,2,1],
+        [0,0,2,2,1,1,0,2,1,1,0,2,0,0,2,2], [0,1,1,0,0,1,1,0,2,0,0,2,2,2,2,2],
+        [0,0,1,1,0,1,2,2,0,1,2,2,0,0,1,1], [0,0,0,0,2,0,0,0,2,2,1,1,2,2,2,1],
+        [0,0,0,0,0,0,0,2,1,1,2,2,1,2,2,2], [0,2,2,2,0,0,2,2,0,0,1,2,0,0,1,1],
+        [0,0,1,1,0,0,1,2,0,0,2,2,0,2,2,2], [0,1,2,0,0,1,2,0,0,1,2,0,0,1,2,0],
+        [0,0,0,0,1,1,1,1,2,2,2,2,0,0,0,0], [0,1,2,0,1,2,0,1,2,0,1,2,0,1,2,0],
+        [0,1,2,0,2,0,1,2,1,2,0,1,0,1,2,0], [0,0,1,1,2,2,0,0,1,1,2,2,0,0,1,1],
+        [0,0,1,1,1,1,2,2,2,2,0,0,0,0,1,1], [0,1,0,1,0,1,0,1,2,2,2,2,2,2,2,2],
+        [0,0,0,0,0,0,0,0,2,1,2,1,2,1,2,1], [0,0,2,2,1,1,2,2,0,0,2,2,1,1,2,2],
+        [0,0,2,2,0,0,1,1,0,0,2,2,0,0,1,1], [0,2,2,0,1,2,2,1,0,2,2,0,1,2,2,1],
+        [0,1,0,1,2,2,2,2,2,2,2,2,0,1,0,1], [0,0,0,0,2,1,2,1,2,1,2,1,2,1,2,1],
+        [0,1,0,1,0,1,0,1,0,1,0,1,2,2,2,2], [0,2,2,2,0,1,1,1,0,2,2,2,0,1,1,1],
+        [0,0,0,2,1,1,1,2,0,0,0,2,1,1,1,2], [0,0,0,0,2,1,1,2,2,1,1,2,2,1,1,2],
+        [0,2,2,2,0,1,1,1,0,1,1,1,0,2,2,2], [0,0,0,2,1,1,1,2,1,1,1,2,0,0,0,2],
+        [0,1,1,0,0,1,1,0,0,1,1,0,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,1,2,2,1,1,2],
+        [0,1,1,0,0,1,1,0,2,2,2,2,2,2,2,2], [0,0,2,2,0,0,1,1,0,0,1,1,0,0,2,2],
+        [0,0,2,2,1,1,2,2,1,1,2,2,0,0,2,2], [0,0,0,0,0,0,0,0,0,0,0,0,2,1,1,2],
+        [0,0,0,2,0,0,0,1,0,0,0,2,0,0,0,1], [0,2,2,2,1,2,2,2,0,2,2,2,1,2,2,2],
+        [0,1,0,1,2,2,2,2,2,2,2,2,2,2,2,2], [0,1,1,1,2,0,1,1,2,2,0,1,2,2,2,0],
+    ];
+    let mut packed = [0u32; 64];
+    let mut p = 0;
+    while p < 64 {
+        let mut i = 0;
+        while i < 16 {
+            packed[p] |= (TABLE[p][i] as u32) << (i * 2);
+            i += 1;
+        }
+        p += 1;
+    }
+    packed
+};
+
+/// 两个分区时第二个分区的锚点像素
+#[rustfmt::skip]
+const ANCHORS2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// 三个分区时第二、第三个分区的锚点像素
+#[rustfmt::skip]
+const ANCHORS3: [[u8; 64]; 2] = [
+    [
+        3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3,
+        3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5, 15, 15,
+        8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15,
+        3, 15, 5, 5, 5, 8, 5, 10, 5, 10, 8, 13, 15, 12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8,
+        15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6, 10, 15, 15, 10, 8,
+        15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8,
+        15, 3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+    ],
+];
+
+/// 像素 pixel 在第几个分区
+fn subset(subsets: usize, partition: usize, pixel: usize) -> usize {
+    match subsets {
+        1 => 0,
+        2 => ((PARTITIONS2[partition] >> pixel) & 1) as usize,
+        _ => ((PARTITIONS3[partition] >> (pixel * 2)) & 3) as usize,
+    }
+}
+
+/// 锚点像素的索引省掉最高位
+fn is_anchor(subsets: usize, partition: usize, pixel: usize) -> bool {
+    pixel == 0
+        || match subsets {
+            2 => pixel == ANCHORS2[partition] as usize,
+            3 => {
+                pixel == ANCHORS3[0][partition] as usize || pixel == ANCHORS3[1][partition] as usize
+            }
+            _ => false,
+        }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// 每个端点一个 P 位
+    endpoint_pbits: bool,
+    /// 每个分区共用一个 P 位
+    shared_pbits: bool,
+    index_bits: u32,
+    /// 只有模式 4、5 有第二组索引
+    index2_bits: u32,
+}
+
+const fn bc7_mode(
+    subsets: usize,
+    [partition_bits, rotation_bits, index_selection_bits, color_bits, alpha_bits]: [u32; 5],
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    [index_bits, index2_bits]: [u32; 2],
+) -> Bc7Mode {
+    Bc7Mode {
+        subsets,
+        partition_bits,
+        rotation_bits,
+        index_selection_bits,
+        color_bits,
+        alpha_bits,
+        endpoint_pbits,
+        shared_pbits,
+        index_bits,
+        index2_bits,
+    }
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    bc7_mode(3, [4, 0, 0, 4, 0], true, false, [3, 0]),
+    bc7_mode(2, [6, 0, 0, 6, 0], false, true, [3, 0]),
+    bc7_mode(3, [6, 0, 0, 5, 0], false, false, [2, 0]),
+    bc7_mode(2, [6, 0, 0, 7, 0], true, false, [2, 0]),
+    bc7_mode(1, [0, 2, 1, 5, 6], false, false, [2, 3]),
+    bc7_mode(1, [0, 2, 0, 7, 8], false, false, [2, 2]),
+    bc7_mode(1, [0, 0, 0, 7, 7], true, false, [4, 0]),
+    bc7_mode(2, [6, 0, 0, 5, 5], true, false, [2, 0]),
+];
+
+/// 把 bits 位的端点扩展到 8 位，高位复制到低位
+fn expand8(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    value | (value >> bits)
+}
+
+fn interpolate(e0: u32, e1: u32, index: u32, bits: u32) -> u8 {
+    let w = weight(index, bits);
+    (((64 - w) * e0 + w * e1 + 32) >> 6) as u8
+}
+
+pub(super) fn bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = BitReader::new(block);
+    // 模式是开头连续 0 的个数，全 0 的块是保留的
+    let Some(mode) = (0..8).position(|_| bits.read(1) == 1) else {
+        return [[0; 4]; 16];
+    };
+    let m = &BC7_MODES[mode];
+    let partition = bits.read(m.partition_bits) as usize;
+    let rotation = bits.read(m.rotation_bits);
+    let index_selection = bits.read(m.index_selection_bits);
+
+    let count = m.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for c in 0..3 {
+        for endpoint in endpoints.iter_mut().take(count) {
+            endpoint[c] = bits.read(m.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(count) {
+        endpoint[3] = bits.read(m.alpha_bits);
+    }
+    let (mut color_bits, mut alpha_bits) = (m.color_bits, m.alpha_bits);
+    if m.endpoint_pbits || m.shared_pbits {
+        let pbits: Vec<u32> = if m.endpoint_pbits {
+            (0..count).map(|_| bits.read(1)).collect()
+        } else {
+            (0..m.subsets)
+                .flat_map(|_| {
+                    let p = bits.read(1);
+                    [p, p]
+                })
+                .collect()
+        };
+        for (endpoint, p) in endpoints.iter_mut().zip(pbits) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | p;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(count) {
+        for channel in endpoint.iter_mut().take(3) {
+            *channel = expand8(*channel, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            expand8(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let indices: [u32; 16] = std::array::from_fn(|i| {
+        bits.read(m.index_bits - is_anchor(m.subsets, partition, i) as u32)
+    });
+    let indices2: [u32; 16] =
+        std::array::from_fn(|i| bits.read(m.index2_bits.saturating_sub((i == 0) as u32)));
+
+    std::array::from_fn(|i| {
+        let s = subset(m.subsets, partition, i);
+        let (e0, e1) = (endpoints[s * 2], endpoints[s * 2 + 1]);
+        // 模式 4 的 index_selection 交换颜色和 alpha 用的两组索引
+        let (color, alpha) = if m.index2_bits == 0 {
+            ((indices[i], m.index_bits), (indices[i], m.index_bits))
+        } else if index_selection == 0 {
+            ((indices[i], m.index_bits), (indices2[i], m.index2_bits))
+        } else {
+            ((indices2[i], m.index2_bits), (indices[i], m.index_bits))
+        };
+        let mut texel = [0u8; 4];
+        for c in 0..3 {
+            texel[c] = interpolate(e0[c], e1[c], color.0, color.1);
+        }
+        texel[3] = interpolate(e0[3], e1[3], alpha.0, alpha.1);
+        match rotation {
+            1 => texel.swap(0, 3),
+            2 => texel.swap(1, 3),
+            3 => texel.swap(2, 3),
+            _ => {}
+        }
+        texel
+    })
+}
+
+// BC6H 端点字段：R/G/B 的 w x y z，w x 是第一个分区的两个端点，y z 是第二个
+const RW: u8 = 0;
+const RX: u8 = 1;
+const RY: u8 = 2;
+const RZ: u8 = 3;
+const GW: u8 = 4;
+const GX: u8 = 5;
+const GY: u8 = 6;
+const GZ: u8 = 7;
+const BW: u8 = 8;
+const BX: u8 = 9;
+const BY: u8 = 10;
+const BZ: u8 = 11;
+/// 分区号
+const D: u8 = 12;
+
+/// 模式位之后的字段顺序，(字段, 起始位, 位数)，低位在前
+type Layout = &'static [(u8, u8, u8)];
+
+struct Bc6hMode {
+    /// 模式位的值，两位或五位
+    code: u32,
+    transformed: bool,
+    endpoint_bits: u32,
+    delta_bits: [u32; 3],
+    layout: Layout,
+}
+
+#[rustfmt::skip]
+const BC6H_MODES: [Bc6hMode; 14] = [
+    Bc6hMode { code: 0x00, transformed: true, endpoint_bits: 10, delta_bits: [5, 5, 5], layout: &[
+        (GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5),
+        (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+        (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x01, transformed: true, endpoint_bits: 7, delta_bits: [6, 6, 6], layout: &[
+        (GY, 5, 1), (GZ, 4, 1), (GZ, 5, 1), (RW, 0, 7), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1),
+        (GW, 0, 7), (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1),
+        (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4),
+        (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x02, transformed: true, endpoint_bits: 11, delta_bits: [5, 4, 4], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4), (GX, 0, 4),
+        (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4),
+        (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x06, transformed: true, endpoint_bits: 11, delta_bits: [4, 5, 4], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1), (GY, 0, 4),
+        (GX, 0, 5), (GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1), (BY, 0, 4),
+        (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1), (RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x0A, transformed: true, endpoint_bits: 11, delta_bits: [4, 4, 5], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1), (GY, 0, 4),
+        (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1), (BY, 0, 4),
+        (RY, 0, 4), (BZ, 1, 1), (BZ, 2, 1), (RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x0E, transformed: true, endpoint_bits: 9, delta_bits: [5, 5, 5], layout: &[
+        (RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5),
+        (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+        (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x12, transformed: true, endpoint_bits: 8, delta_bits: [6, 5, 5], layout: &[
+        (RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8),
+        (BZ, 3, 1), (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4),
+        (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x16, transformed: true, endpoint_bits: 8, delta_bits: [5, 6, 5], layout: &[
+        (RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8),
+        (GZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4),
+        (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x1A, transformed: true, endpoint_bits: 8, delta_bits: [5, 5, 6], layout: &[
+        (RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8),
+        (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1),
+        (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x1E, transformed: false, endpoint_bits: 6, delta_bits: [6, 6, 6], layout: &[
+        (RW, 0, 6), (GZ, 4, 1), (BZ, 0, 1), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1),
+        (BY, 5, 1), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1),
+        (BZ, 4, 1), (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4),
+        (RY, 0, 6), (RZ, 0, 6), (D, 0, 5),
+    ] },
+    Bc6hMode { code: 0x03, transformed: false, endpoint_bits: 10, delta_bits: [10, 10, 10], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10),
+    ] },
+    Bc6hMode { code: 0x07, transformed: true, endpoint_bits: 11, delta_bits: [9, 9, 9], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9), (GW, 10, 1),
+        (BX, 0, 9), (BW, 10, 1),
+    ] },
+    // 高位是倒着存的，一位一位列出来
+    Bc6hMode { code: 0x0B, transformed: true, endpoint_bits: 12, delta_bits: [8, 8, 8], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 11, 1), (RW, 10, 1), (GX, 0, 8),
+        (GW, 11, 1), (GW, 10, 1), (BX, 0, 8), (BW, 11, 1), (BW, 10, 1),
+    ] },
+    Bc6hMode { code: 0x0F, transformed: true, endpoint_bits: 16, delta_bits: [4, 4, 4], layout: &[
+        (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 15, 1), (RW, 14, 1), (RW, 13, 1),
+        (RW, 12, 1), (RW, 11, 1), (RW, 10, 1), (GX, 0, 4), (GW, 15, 1), (GW, 14, 1), (GW, 13, 1),
+        (GW, 12, 1), (GW, 11, 1), (GW, 10, 1), (BX, 0, 4), (BW, 15, 1), (BW, 14, 1), (BW, 13, 1),
+        (BW, 12, 1), (BW, 11, 1), (BW, 10, 1),
+    ] },
+];
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+/// 端点反量化到 16 位
+fn unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if !signed {
+        if bits >= 15 || value == 0 {
+            value
+        } else if value == (1 << bits) - 1 {
+            0xFFFF
+        } else {
+            ((value << 16) + 0x8000) >> bits
+        }
+    } else if bits >= 16 {
+        value
+    } else {
+        let magnitude = value.abs();
+        let unq = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+        if value < 0 {
+            -unq
+        } else {
+            unq
+        }
+    }
+}
+
+/// 插值结果缩放到 half 的位模式
+fn finish_unquantize(value: i32, signed: bool) -> u16 {
+    if !signed {
+        ((value * 31) >> 6) as u16
+    } else if value < 0 {
+        0x8000 | (((-value) * 31) >> 5) as u16
+    } else {
+        ((value * 31) >> 5) as u16
+    }
+}
+
+/// 解成 half float 的 RGBA，alpha 固定是 1.0
+pub(super) fn bc6h(block: &[u8], signed: bool) -> [[u16; 4]; 16] {
+    let mut bits = BitReader::new(block);
+    let low = bits.read(2);
+    let code = if low < 2 {
+        low
+    } else {
+        low | bits.read(3) << 2
+    };
+    // 保留的模式解成黑色
+    let Some(mode) = BC6H_MODES.iter().find(|mode| mode.code == code) else {
+        return [[0, 0, 0, 0x3C00]; 16];
+    };
+
+    let mut fields = [0i32; 13];
+    for &(field, first, count) in mode.layout {
+        fields[field as usize] |= (bits.read(count as u32) as i32) << first;
+    }
+    let two_regions = mode.code < 0x03 || mode.code & 3 == 2;
+    let regions = if two_regions { 2 } else { 1 };
+    let partition = fields[D as usize] as usize;
+    let endpoint_bits = mode.endpoint_bits;
+
+    // endpoints[c][i]：通道 c 的第 i 个端点 (w x y z)
+    let mut endpoints = [[0i32; 4]; 3];
+    for (c, channel) in endpoints.iter_mut().enumerate() {
+        channel.copy_from_slice(&fields[c * 4..c * 4 + 4]);
+        if signed {
+            channel[0] = sign_extend(channel[0], endpoint_bits);
+        }
+        let base = channel[0];
+        for value in channel.iter_mut().take(regions * 2).skip(1) {
+            if mode.transformed {
+                // 其余端点存的是相对 w 的差值
+                let delta = sign_extend(*value, mode.delta_bits[c]);
+                *value = (base + delta) & ((1 << endpoint_bits) - 1);
+                if signed {
+                    *value = sign_extend(*value, endpoint_bits);
+                }
+            } else if signed {
+                *value = sign_extend(*value, endpoint_bits);
+            }
+        }
+        for value in channel.iter_mut() {
+            *value = unquantize(*value, endpoint_bits, signed);
+        }
+    }
+
+    let index_bits = if two_regions { 3 } else { 4 };
+    let subsets = regions;
+    let indices: [u32; 16] =
+        std::array::from_fn(|i| bits.read(index_bits - is_anchor(subsets, partition, i) as u32));
+    std::array::from_fn(|i| {
+        let s = subset(subsets, partition, i);
+        let w = weight(indices[i], index_bits) as i32;
+        let mut texel = [0, 0, 0, 0x3C00];
+        for (c, channel) in endpoints.iter().enumerate() {
+            let (e0, e1) = (channel[s * 2], channel[s * 2 + 1]);
+            texel[c] = finish_unquantize(((64 - w) * e0 + w * e1 + 32) >> 6, signed);
+        }
+        texel
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::block_decode::pack;
+    use super::*;
+
+    #[test]
+    fn anchors_belong_to_their_subset() {
+        for p in 0..64 {
+            assert_eq!(subset(2, p, ANCHORS2[p] as usize), 1, "partition {p}");
+            assert_eq!(subset(3, p, ANCHORS3[0][p] as usize), 1, "partition {p}");
+            assert_eq!(subset(3, p, ANCHORS3[1][p] as usize), 2, "partition {p}");
+        }
+    }
+
+    #[test]
+    fn bc6h_layouts_fill_the_header() {
+        for mode in &BC6H_MODES {
+            let mode_bits = if mode.code < 2 { 2 } else { 5 };
+            let total: u32 = mode_bits + mode.layout.iter().map(|f| f.2 as u32).sum::<u32>();
+            let two_regions = mode.code < 0x03 || mode.code & 3 == 2;
+            assert_eq!(
+                total,
+                if two_regions { 82 } else { 65 },
+                "mode {:#x}",
+                mode.code
+            );
+        }
+    }
+
+    #[test]
+    fn bc7_mode6_interpolates_between_endpoints() {
+        let mut fields = vec![(1 << 6, 7)];
+        // R G B A 各两个端点
+        for _ in 0..4 {
+            fields.extend([(0, 7), (127, 7)]);
+        }
+        fields.extend([(0, 1), (1, 1), (0, 3), (15, 4), (8, 4)]);
+        let block = pack(&fields);
+        assert_eq!(block[0], 0x40);
+        let texels = bc7(&block);
+        assert_eq!(texels[0], [0, 0, 0, 0]);
+        assert_eq!(texels[1], [255; 4]);
+        assert_eq!(texels[2], [135; 4]);
+    }
+
+    #[test]
+    fn bc7_mode1_uses_partition_mask() {
+        let mut fields = vec![(0b10, 2), (0, 6)];
+        // R、G、B 各四个端点：分区 0 红色，分区 1 蓝色
+        fields.extend([(63, 6), (63, 6), (0, 6), (0, 6)]);
+        fields.extend([(0, 6); 4]);
+        fields.extend([(0, 6), (0, 6), (63, 6), (63, 6)]);
+        fields.extend([(1, 1), (1, 1)]);
+        let texels = bc7(&pack(&fields));
+        for (i, texel) in texels.iter().enumerate() {
+            if i % 4 < 2 {
+                assert_eq!(*texel, [255, 2, 2, 255], "pixel {i}");
+            } else {
+                assert_eq!(*texel, [2, 2, 255, 255], "pixel {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn bc6h_mode10_unsigned_endpoints() {
+        let mut fields = vec![(0x03, 5), (0, 10), (0, 10), (0, 10)];
+        fields.extend([(1023, 10); 3]);
+        fields.extend([(0, 3), (15, 4)]);
+        let texels = bc6h(&pack(&fields), false);
+        assert_eq!(texels[0], [0, 0, 0, 0x3C00]);
+        assert_eq!(texels[1], [0x7BFF, 0x7BFF, 0x7BFF, 0x3C00]);
+    }
+
+    #[test]
+    fn bc6h_mode10_signed_endpoint() {
+        let texels = bc6h(&pack(&[(0x03, 5), (0x3FF, 10)]), true);
+        assert_eq!(texels[0], [0x805D, 0, 0, 0x3C00]);
+    }
+
+    #[test]
+    fn bc7_reserved_mode_is_transparent_black() {
+        assert_eq!(bc7(&[0; 16]), [[0; 4]; 16]);
+    }
+}
@@ -0,0 +1,407 @@
+use std::io::Read;
+
+use crate::gfx;
+
+use super::{
+    block_decode, f32_to_f16, generate_mipmaps, mip_level_count, MipGeneration, TextureBundle,
+    TextureError, TextureOptions,
+};
+
+// KTX2 / DDS 容器
+// 块压缩格式 (BC / ETC2 / ASTC) 和预先烘好的 mip 原样上传，
+// 设备不支持对应的压缩 feature 时在 CPU 上解成 RGBA8（BC6H / HDR ASTC 解成 Rgba16Float）
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+
+/// 从容器里解析出来、还没上传的纹理
+pub(crate) struct CompressedImage {
+    pub(crate) format: wgpu::TextureFormat,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// 每一级的数据，从 level 0 开始
+    pub(crate) levels: Vec<Vec<u8>>,
+}
+
+pub(crate) fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(&KTX2_MAGIC) || bytes.starts_with(DDS_MAGIC)
+}
+
+impl CompressedImage {
+    /// srgb 只在容器本身不带颜色空间信息时使用 (老的 DDS)
+    pub(crate) fn parse(bytes: &[u8], srgb: bool) -> Result<Self, TextureError> {
+        if bytes.starts_with(&KTX2_MAGIC) {
+            parse_ktx2(bytes)
+        } else if bytes.starts_with(DDS_MAGIC) {
+            parse_dds(bytes, srgb)
+        } else {
+            Err(TextureError::Container(
+                "unknown container, expected KTX2 or DDS".to_string(),
+            ))
+        }
+    }
+
+    pub(crate) fn byte_size(&self) -> u64 {
+        self.levels.iter().map(|level| level.len() as u64).sum()
+    }
+
+    /// 逐级在 CPU 上解压
+    pub(crate) fn decompress(self) -> Result<Self, TextureError> {
+        let mut format = self.format;
+        let mut levels = Vec::with_capacity(self.levels.len());
+        for (level, data) in self.levels.iter().enumerate() {
+            let width = (self.width >> level).max(1);
+            let height = (self.height >> level).max(1);
+            let (texels, decoded_format) = block_decode::decode(self.format, width, height, data)?;
+            format = decoded_format;
+            levels.push(texels);
+        }
+        Ok(Self {
+            format,
+            width: self.width,
+            height: self.height,
+            levels,
+        })
+    }
+
+    /// Rgba32Float 转成 Rgba16Float，给不支持 32 位浮点过滤的设备用
+    fn to_half_float(self) -> Self {
+        let levels = self
+            .levels
+            .iter()
+            .map(|data| {
+                data.chunks_exact(4)
+                    .flat_map(|c| {
+                        f32_to_f16(f32::from_le_bytes(c.try_into().unwrap())).to_le_bytes()
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            format: wgpu::TextureFormat::Rgba16Float,
+            levels,
+            ..self
+        }
+    }
+}
+
+fn parse_ktx2(bytes: &[u8]) -> Result<CompressedImage, TextureError> {
+    let reader =
+        ktx2::Reader::new(bytes).map_err(|e| TextureError::Container(format!("ktx2: {}", e)))?;
+    let header = reader.header();
+    if header.face_count != 1 || header.layer_count > 1 || header.pixel_depth > 1 {
+        return Err(TextureError::Unsupported(
+            "only single 2D KTX2 textures are supported".to_string(),
+        ));
+    }
+    // vkFormat 为 0 的是 Basis Universal，需要转码器
+    let ktx_format = header.format.ok_or_else(|| {
+        TextureError::Unsupported("KTX2 without vkFormat (Basis Universal)".to_string())
+    })?;
+    let format = KTX2_FORMATS
+        .iter()
+        .find(|(k, _)| *k == ktx_format)
+        .map(|(_, w)| *w)
+        .ok_or_else(|| TextureError::Unsupported(format!("KTX2 format {:?}", ktx_format)))?;
+
+    let levels = reader
+        .levels()
+        .map(|level| match header.supercompression_scheme {
+            None => Ok(level.to_vec()),
+            Some(ktx2::SupercompressionScheme::Zstandard) => {
+                let mut decoder = ruzstd::StreamingDecoder::new(level)
+                    .map_err(|e| TextureError::Container(format!("zstd: {:?}", e)))?;
+                let mut data = Vec::new();
+                decoder.read_to_end(&mut data)?;
+                Ok(data)
+            }
+            Some(scheme) => Err(TextureError::Unsupported(format!(
+                "KTX2 supercompression {:?}",
+                scheme
+            ))),
+        })
+        .collect::<Result<Vec<_>, TextureError>>()?;
+
+    Ok(CompressedImage {
+        format,
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        levels,
+    })
+}
+
+fn parse_dds(bytes: &[u8], srgb: bool) -> Result<CompressedImage, TextureError> {
+    let dds =
+        ddsfile::Dds::read(bytes).map_err(|e| TextureError::Container(format!("dds: {}", e)))?;
+    if dds.get_depth() > 1 || dds.get_num_array_layers() > 1 {
+        return Err(TextureError::Unsupported(
+            "only single 2D DDS textures are supported".to_string(),
+        ));
+    }
+    let format = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+        (Some(dxgi), _) => dxgi_format(dxgi, srgb),
+        (None, Some(d3d)) => d3d_format(d3d, srgb),
+        (None, None) => None,
+    }
+    .ok_or_else(|| TextureError::Unsupported("DDS pixel format".to_string()))?;
+
+    let (width, height) = (dds.get_width(), dds.get_height());
+    let data = dds
+        .get_data(0)
+        .map_err(|e| TextureError::Container(format!("dds: {}", e)))?;
+    // DDS 的 mip 链是连续排在一起的
+    let mut levels = Vec::new();
+    let mut offset = 0;
+    for level in 0..dds.get_num_mipmap_levels().max(1) {
+        let size = level_byte_size(format, width, height, level);
+        let Some(level_data) = data.get(offset..offset + size) else {
+            break;
+        };
+        levels.push(level_data.to_vec());
+        offset += size;
+    }
+    if levels.is_empty() {
+        return Err(TextureError::Container("dds: truncated data".to_string()));
+    }
+
+    Ok(CompressedImage {
+        format,
+        width,
+        height,
+        levels,
+    })
+}
+
+fn level_byte_size(format: wgpu::TextureFormat, width: u32, height: u32, level: u32) -> usize {
+    let (block_width, block_height) = format.block_dimensions();
+    let width = (width >> level).max(1);
+    let height = (height >> level).max(1);
+    let blocks = width.div_ceil(block_width) * height.div_ceil(block_height);
+    (blocks * format.block_copy_size(None).unwrap_or(4)) as usize
+}
+
+fn d3d_format(format: ddsfile::D3DFormat, srgb: bool) -> Option<wgpu::TextureFormat> {
+    use ddsfile::D3DFormat;
+    use wgpu::TextureFormat as F;
+    let pick = |linear: F, gamma: F| if srgb { gamma } else { linear };
+    Some(match format {
+        D3DFormat::DXT1 => pick(F::Bc1RgbaUnorm, F::Bc1RgbaUnormSrgb),
+        D3DFormat::DXT2 | D3DFormat::DXT3 => pick(F::Bc2RgbaUnorm, F::Bc2RgbaUnormSrgb),
+        D3DFormat::DXT4 | D3DFormat::DXT5 => pick(F::Bc3RgbaUnorm, F::Bc3RgbaUnormSrgb),
+        D3DFormat::A8B8G8R8 => pick(F::Rgba8Unorm, F::Rgba8UnormSrgb),
+        D3DFormat::A8R8G8B8 => pick(F::Bgra8Unorm, F::Bgra8UnormSrgb),
+        D3DFormat::A16B16G16R16F => F::Rgba16Float,
+        D3DFormat::A32B32G32R32F => F::Rgba32Float,
+        _ => return None,
+    })
+}
+
+fn dxgi_format(format: ddsfile::DxgiFormat, srgb: bool) -> Option<wgpu::TextureFormat> {
+    use ddsfile::DxgiFormat;
+    use wgpu::TextureFormat as F;
+    // Typeless 的按调用方给的颜色空间来
+    let pick = |linear: F, gamma: F| if srgb { gamma } else { linear };
+    Some(match format {
+        DxgiFormat::R8G8B8A8_UNorm => F::Rgba8Unorm,
+        DxgiFormat::R8G8B8A8_UNorm_sRGB => F::Rgba8UnormSrgb,
+        DxgiFormat::R8G8B8A8_Typeless => pick(F::Rgba8Unorm, F::Rgba8UnormSrgb),
+        DxgiFormat::B8G8R8A8_UNorm => F::Bgra8Unorm,
+        DxgiFormat::B8G8R8A8_UNorm_sRGB => F::Bgra8UnormSrgb,
+        DxgiFormat::B8G8R8A8_Typeless => pick(F::Bgra8Unorm, F::Bgra8UnormSrgb),
+        DxgiFormat::R16G16B16A16_Float => F::Rgba16Float,
+        DxgiFormat::R32G32B32A32_Float => F::Rgba32Float,
+        DxgiFormat::BC1_UNorm => F::Bc1RgbaUnorm,
+        DxgiFormat::BC1_UNorm_sRGB => F::Bc1RgbaUnormSrgb,
+        DxgiFormat::BC1_Typeless => pick(F::Bc1RgbaUnorm, F::Bc1RgbaUnormSrgb),
+        DxgiFormat::BC2_UNorm => F::Bc2RgbaUnorm,
+        DxgiFormat::BC2_UNorm_sRGB => F::Bc2RgbaUnormSrgb,
+        DxgiFormat::BC2_Typeless => pick(F::Bc2RgbaUnorm, F::Bc2RgbaUnormSrgb),
+        DxgiFormat::BC3_UNorm => F::Bc3RgbaUnorm,
+        DxgiFormat::BC3_UNorm_sRGB => F::Bc3RgbaUnormSrgb,
+        DxgiFormat::BC3_Typeless => pick(F::Bc3RgbaUnorm, F::Bc3RgbaUnormSrgb),
+        DxgiFormat::BC4_UNorm | DxgiFormat::BC4_Typeless => F::Bc4RUnorm,
+        DxgiFormat::BC4_SNorm => F::Bc4RSnorm,
+        DxgiFormat::BC5_UNorm | DxgiFormat::BC5_Typeless => F::Bc5RgUnorm,
+        DxgiFormat::BC5_SNorm => F::Bc5RgSnorm,
+        DxgiFormat::BC6H_UF16 | DxgiFormat::BC6H_Typeless => F::Bc6hRgbUfloat,
+        DxgiFormat::BC6H_SF16 => F::Bc6hRgbFloat,
+        DxgiFormat::BC7_UNorm => F::Bc7RgbaUnorm,
+        DxgiFormat::BC7_UNorm_sRGB => F::Bc7RgbaUnormSrgb,
+        DxgiFormat::BC7_Typeless => pick(F::Bc7RgbaUnorm, F::Bc7RgbaUnormSrgb),
+        _ => return None,
+    })
+}
+
+const fn astc(block: wgpu::AstcBlock, channel: wgpu::AstcChannel) -> wgpu::TextureFormat {
+    wgpu::TextureFormat::Astc { block, channel }
+}
+
+#[rustfmt::skip]
+const KTX2_FORMATS: &[(ktx2::Format, wgpu::TextureFormat)] = {
+    use ktx2::Format as K;
+    use wgpu::AstcBlock as B;
+    use wgpu::AstcChannel::{Unorm, UnormSrgb};
+    use wgpu::TextureFormat as F;
+    &[
+        (K::R8G8B8A8_UNORM, F::Rgba8Unorm),            (K::R8G8B8A8_SRGB, F::Rgba8UnormSrgb),
+        (K::B8G8R8A8_UNORM, F::Bgra8Unorm),            (K::B8G8R8A8_SRGB, F::Bgra8UnormSrgb),
+        (K::R16G16B16A16_SFLOAT, F::Rgba16Float),      (K::R32G32B32A32_SFLOAT, F::Rgba32Float),
+        (K::BC1_RGB_UNORM_BLOCK, F::Bc1RgbaUnorm),     (K::BC1_RGB_SRGB_BLOCK, F::Bc1RgbaUnormSrgb),
+        (K::BC1_RGBA_UNORM_BLOCK, F::Bc1RgbaUnorm),    (K::BC1_RGBA_SRGB_BLOCK, F::Bc1RgbaUnormSrgb),
+        (K::BC2_UNORM_BLOCK, F::Bc2RgbaUnorm),         (K::BC2_SRGB_BLOCK, F::Bc2RgbaUnormSrgb),
+        (K::BC3_UNORM_BLOCK, F::Bc3RgbaUnorm),         (K::BC3_SRGB_BLOCK, F::Bc3RgbaUnormSrgb),
+        (K::BC4_UNORM_BLOCK, F::Bc4RUnorm),            (K::BC4_SNORM_BLOCK, F::Bc4RSnorm),
+        (K::BC5_UNORM_BLOCK, F::Bc5RgUnorm),           (K::BC5_SNORM_BLOCK, F::Bc5RgSnorm),
+        (K::BC6H_UFLOAT_BLOCK, F::Bc6hRgbUfloat),      (K::BC6H_SFLOAT_BLOCK, F::Bc6hRgbFloat),
+        (K::BC7_UNORM_BLOCK, F::Bc7RgbaUnorm),         (K::BC7_SRGB_BLOCK, F::Bc7RgbaUnormSrgb),
+        (K::ETC2_R8G8B8_UNORM_BLOCK, F::Etc2Rgb8Unorm),     (K::ETC2_R8G8B8_SRGB_BLOCK, F::Etc2Rgb8UnormSrgb),
+        (K::ETC2_R8G8B8A1_UNORM_BLOCK, F::Etc2Rgb8A1Unorm), (K::ETC2_R8G8B8A1_SRGB_BLOCK, F::Etc2Rgb8A1UnormSrgb),
+        (K::ETC2_R8G8B8A8_UNORM_BLOCK, F::Etc2Rgba8Unorm),  (K::ETC2_R8G8B8A8_SRGB_BLOCK, F::Etc2Rgba8UnormSrgb),
+        (K::EAC_R11_UNORM_BLOCK, F::EacR11Unorm),      (K::EAC_R11_SNORM_BLOCK, F::EacR11Snorm),
+        (K::EAC_R11G11_UNORM_BLOCK, F::EacRg11Unorm),  (K::EAC_R11G11_SNORM_BLOCK, F::EacRg11Snorm),
+        (K::ASTC_4x4_UNORM_BLOCK, astc(B::B4x4, Unorm)),     (K::ASTC_4x4_SRGB_BLOCK, astc(B::B4x4, UnormSrgb)),
+        (K::ASTC_5x4_UNORM_BLOCK, astc(B::B5x4, Unorm)),     (K::ASTC_5x4_SRGB_BLOCK, astc(B::B5x4, UnormSrgb)),
+        (K::ASTC_5x5_UNORM_BLOCK, astc(B::B5x5, Unorm)),     (K::ASTC_5x5_SRGB_BLOCK, astc(B::B5x5, UnormSrgb)),
+        (K::ASTC_6x5_UNORM_BLOCK, astc(B::B6x5, Unorm)),     (K::ASTC_6x5_SRGB_BLOCK, astc(B::B6x5, UnormSrgb)),
+        (K::ASTC_6x6_UNORM_BLOCK, astc(B::B6x6, Unorm)),     (K::ASTC_6x6_SRGB_BLOCK, astc(B::B6x6, UnormSrgb)),
+        (K::ASTC_8x5_UNORM_BLOCK, astc(B::B8x5, Unorm)),     (K::ASTC_8x5_SRGB_BLOCK, astc(B::B8x5, UnormSrgb)),
+        (K::ASTC_8x6_UNORM_BLOCK, astc(B::B8x6, Unorm)),     (K::ASTC_8x6_SRGB_BLOCK, astc(B::B8x6, UnormSrgb)),
+        (K::ASTC_8x8_UNORM_BLOCK, astc(B::B8x8, Unorm)),     (K::ASTC_8x8_SRGB_BLOCK, astc(B::B8x8, UnormSrgb)),
+        (K::ASTC_10x5_UNORM_BLOCK, astc(B::B10x5, Unorm)),   (K::ASTC_10x5_SRGB_BLOCK, astc(B::B10x5, UnormSrgb)),
+        (K::ASTC_10x6_UNORM_BLOCK, astc(B::B10x6, Unorm)),   (K::ASTC_10x6_SRGB_BLOCK, astc(B::B10x6, UnormSrgb)),
+        (K::ASTC_10x8_UNORM_BLOCK, astc(B::B10x8, Unorm)),   (K::ASTC_10x8_SRGB_BLOCK, astc(B::B10x8, UnormSrgb)),
+        (K::ASTC_10x10_UNORM_BLOCK, astc(B::B10x10, Unorm)), (K::ASTC_10x10_SRGB_BLOCK, astc(B::B10x10, UnormSrgb)),
+        (K::ASTC_12x10_UNORM_BLOCK, astc(B::B12x10, Unorm)), (K::ASTC_12x10_SRGB_BLOCK, astc(B::B12x10, UnormSrgb)),
+        (K::ASTC_12x12_UNORM_BLOCK, astc(B::B12x12, Unorm)), (K::ASTC_12x12_SRGB_BLOCK, astc(B::B12x12, UnormSrgb)),
+    ]
+};
+
+/// 能被 blit pass 当作渲染目标的格式，只有这些在单级时补 mip
+fn mip_renderable(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Rgba8Unorm
+            | wgpu::TextureFormat::Rgba8UnormSrgb
+            | wgpu::TextureFormat::Bgra8Unorm
+            | wgpu::TextureFormat::Bgra8UnormSrgb
+            | wgpu::TextureFormat::Rgba16Float
+    )
+}
+
+impl TextureBundle {
+    pub(crate) fn from_compressed_image(
+        context: &gfx::GfxContext,
+        image: CompressedImage,
+        options: TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let (block_width, block_height) = image.format.block_dimensions();
+        // 块压缩纹理的 level 0 必须是块大小的整数倍
+        let native = context
+            .device
+            .features()
+            .contains(image.format.required_features())
+            && image.width % block_width == 0
+            && image.height % block_height == 0;
+        let image = if native {
+            image
+        } else {
+            println!(
+                "{:?} not supported by the device, decoding on the CPU",
+                image.format
+            );
+            image.decompress()?
+        };
+        // 纹理统一绑定成可过滤的浮点纹理加线性采样器，Rgba32Float 要有 FLOAT32_FILTERABLE
+        let image = if image.format == wgpu::TextureFormat::Rgba32Float
+            && !context
+                .device
+                .features()
+                .contains(wgpu::Features::FLOAT32_FILTERABLE)
+        {
+            image.to_half_float()
+        } else {
+            image
+        };
+
+        let format = image.format;
+        let size = wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth_or_array_layers: 1,
+        };
+        // 只有一级并且要求 mip 时用 blit pass 补上
+        let generate = image.levels.len() == 1
+            && options.mips != MipGeneration::None
+            && mip_renderable(format);
+        let level_count = if generate {
+            mip_level_count(size.width, size.height)
+        } else {
+            image.levels.len() as u32
+        };
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if generate {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+            label: options.label,
+            size,
+            mip_level_count: level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+
+        let block_size = format.block_copy_size(None).unwrap_or(4);
+        for (level, data) in image.levels.iter().enumerate() {
+            let level = level as u32;
+            let physical = size
+                .mip_level_size(level, wgpu::TextureDimension::D2)
+                .physical_size(format);
+            context.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(physical.width / block_width * block_size),
+                    rows_per_image: None,
+                },
+                physical,
+            );
+        }
+        if generate {
+            let mut encoder =
+                context
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("mipmap encoder"),
+                    });
+            generate_mipmaps(context, &mut encoder, &texture, format, level_count);
+            context.queue.submit(Some(encoder.finish()));
+        }
+
+        let byte_size = if generate {
+            super::mip_chain_byte_size(format, size, level_count)
+        } else {
+            image.byte_size()
+        };
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = options.sampler.create_sampler(context, options.label);
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            size,
+            format,
+            byte_size,
+        })
+    }
+}
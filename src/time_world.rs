@@ -19,6 +19,10 @@ pub struct FrameCounter {
     pub frame_count: u32,
     pub fps: Rc<RefCell<f32>>,
     pub frame_time: f32,
+    pub last_frame_instant: time::Instant,
+    /// 打开 record_frame_times 后每帧的耗时 (ms)，跑分模式用来算百分位
+    pub record_frame_times: bool,
+    pub frame_times: Vec<f32>,
 }
 impl FrameCounter {
     pub fn new() -> Self {
@@ -27,6 +31,9 @@ impl FrameCounter {
             frame_count: 0,
             fps: Rc::new(RefCell::new(0.)),
             frame_time: 0.,
+            last_frame_instant: time::Instant::now(),
+            record_frame_times: false,
+            frame_times: Vec::new(),
         }
     }
 
@@ -34,6 +41,11 @@ impl FrameCounter {
         println!("FrameCounter::update");
         self.frame_count += 1;
         let new_instant = game.last_update;
+        if self.record_frame_times {
+            let frame_ms = (new_instant - self.last_frame_instant).as_secs_f32() * 1000.0;
+            self.frame_times.push(frame_ms);
+        }
+        self.last_frame_instant = new_instant;
         let elapsed_secs = (new_instant - self.last_printed_instant).as_secs_f32();
         if elapsed_secs > 1.0 {
             let elapsed_ms = elapsed_secs * 1000.0;
@@ -1,4 +1,11 @@
+pub mod bvh;
+pub mod camera;
+pub mod debug_draw;
+pub mod globals;
 pub mod models;
+pub mod picking;
+pub mod scene_graph;
+pub mod view_modes;
 
 use bytemuck::{Pod, Zeroable};
 
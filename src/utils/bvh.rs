@@ -0,0 +1,597 @@
+// 包围盒层次（BVH），按 SAH 分桶构建
+// CPU 上做射线和包围盒查询；节点是 Pod，可以直接传给 shader，遍历见 bvh.wgsl
+// GPU 上要先把图元按 Bvh::indices 的顺序排好，节点里的 first 指的是排好后的位置
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+
+use super::models::ModelType;
+
+pub(crate) const BVH_WGSL: &str = include_str!("bvh.wgsl");
+
+/// 拼在用到 BVH 的 shader 前面
+pub(crate) fn with_bvh(source: &str) -> String {
+    format!("{}\n{}", BVH_WGSL, source)
+}
+
+/// 每个轴分多少个桶
+const BINS: usize = 12;
+/// 图元不超过这么多，且切开不划算时就做成叶子
+const MAX_LEAF_SIZE: usize = 4;
+/// 树的最大深度，bvh.wgsl 里的遍历栈也是这么大
+pub(crate) const MAX_DEPTH: usize = 32;
+/// 射线起点附近的命中不算，免得打到自己
+const T_MIN: f32 = 1e-4;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Aabb {
+    pub(crate) min: Vec3,
+    pub(crate) max: Vec3,
+}
+
+impl Aabb {
+    pub(crate) const EMPTY: Aabb = Aabb {
+        min: Vec3::splat(f32::INFINITY),
+        max: Vec3::splat(f32::NEG_INFINITY),
+    };
+
+    pub(crate) fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub(crate) fn from_points(points: &[Vec3]) -> Self {
+        points.iter().fold(Aabb::EMPTY, |aabb, &p| aabb.grow(p))
+    }
+
+    pub(crate) fn grow(self, p: Vec3) -> Self {
+        Aabb {
+            min: self.min.min(p),
+            max: self.max.max(p),
+        }
+    }
+
+    pub(crate) fn union(self, other: Aabb) -> Self {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub(crate) fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub(crate) fn surface_area(&self) -> f32 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let d = self.max - self.min;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub(crate) fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// slab 求交，返回射线进盒子的 t（起点在盒子里就是 0），inv_dir 是 1 / direction
+    pub(crate) fn ray(&self, origin: Vec3, inv_dir: Vec3, t_max: f32) -> Option<f32> {
+        let t1 = (self.min - origin) * inv_dir;
+        let t2 = (self.max - origin) * inv_dir;
+        let near = t1.min(t2).max_element().max(0.0);
+        let far = t1.max(t2).min_element().min(t_max);
+        (near <= far).then_some(near)
+    }
+}
+
+/// 和 bvh.wgsl 里的 BvhNode 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Debug)]
+pub(crate) struct BvhNode {
+    pub(crate) min: [f32; 3],
+    /// 叶子是第一个图元在 indices 里的位置；内部节点是右孩子，左孩子紧跟在自己后面
+    pub(crate) first: u32,
+    pub(crate) max: [f32; 3],
+    /// 叶子里的图元个数，0 表示内部节点
+    pub(crate) count: u32,
+}
+
+impl BvhNode {
+    pub(crate) fn bounds(&self) -> Aabb {
+        Aabb::new(Vec3::from(self.min), Vec3::from(self.max))
+    }
+}
+
+/// 一次分桶切分的结果，partition 时用同样的 lo / scale 算桶，免得浮点比较对不上
+struct Split {
+    axis: usize,
+    lo: f32,
+    scale: f32,
+    /// 桶号不大于它的去左边
+    bin: usize,
+    cost: f32,
+}
+
+impl Split {
+    fn bin_of(&self, center: Vec3) -> usize {
+        (((center[self.axis] - self.lo) * self.scale) as usize).min(BINS - 1)
+    }
+}
+
+pub(crate) struct Bvh {
+    /// 深度优先排列，0 是根
+    pub(crate) nodes: Vec<BvhNode>,
+    /// 按叶子顺序排好的图元编号
+    pub(crate) indices: Vec<u32>,
+}
+
+impl Bvh {
+    /// bounds 是每个图元的包围盒，图元编号就是它在 bounds 里的下标
+    pub(crate) fn build(bounds: &[Aabb]) -> Self {
+        let centers: Vec<Vec3> = bounds.iter().map(Aabb::center).collect();
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(bounds.len() * 2),
+            indices: (0..bounds.len() as u32).collect(),
+        };
+        if !bounds.is_empty() {
+            bvh.split(bounds, &centers, 0, bounds.len(), 0);
+        }
+        bvh
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// 给 indices[start..end] 建节点，返回节点下标
+    fn split(
+        &mut self,
+        bounds: &[Aabb],
+        centers: &[Vec3],
+        start: usize,
+        end: usize,
+        depth: usize,
+    ) -> usize {
+        let node = self.nodes.len();
+        let items = &self.indices[start..end];
+        let aabb = items
+            .iter()
+            .fold(Aabb::EMPTY, |aabb, &i| aabb.union(bounds[i as usize]));
+        let count = end - start;
+        self.nodes.push(BvhNode {
+            min: aabb.min.to_array(),
+            first: start as u32,
+            max: aabb.max.to_array(),
+            count: count as u32,
+        });
+        if count == 1 || depth + 1 >= MAX_DEPTH {
+            return node;
+        }
+
+        let split = self.best_split(bounds, centers, start, end);
+        // 遍历一个节点算 1，测一个图元也算 1
+        let leaf_cost = count as f32 * aabb.surface_area();
+        let mid = match split {
+            Some(split)
+                if count > MAX_LEAF_SIZE || aabb.surface_area() + split.cost < leaf_cost =>
+            {
+                let (left, right): (Vec<u32>, Vec<u32>) = self.indices[start..end]
+                    .iter()
+                    .partition(|&&i| split.bin_of(centers[i as usize]) <= split.bin);
+                let mid = start + left.len();
+                self.indices[start..mid].copy_from_slice(&left);
+                self.indices[mid..end].copy_from_slice(&right);
+                mid
+            }
+            // 中心全挤在一起分不开，只能对半切
+            None if count > MAX_LEAF_SIZE => start + count / 2,
+            _ => return node,
+        };
+
+        self.nodes[node].count = 0;
+        self.split(bounds, centers, start, mid, depth + 1);
+        let right = self.split(bounds, centers, mid, end, depth + 1);
+        self.nodes[node].first = right as u32;
+        node
+    }
+
+    /// 三个轴上各分 BINS 个桶，找 SAH 代价最小的切法
+    fn best_split(
+        &self,
+        bounds: &[Aabb],
+        centers: &[Vec3],
+        start: usize,
+        end: usize,
+    ) -> Option<Split> {
+        let items = &self.indices[start..end];
+        let centroid_bounds = items
+            .iter()
+            .fold(Aabb::EMPTY, |aabb, &i| aabb.grow(centers[i as usize]));
+        let mut best: Option<Split> = None;
+        for axis in 0..3 {
+            let (lo, hi) = (centroid_bounds.min[axis], centroid_bounds.max[axis]);
+            if hi - lo <= f32::EPSILON * hi.abs().max(1.0) {
+                continue;
+            }
+            let mut split = Split {
+                axis,
+                lo,
+                scale: BINS as f32 / (hi - lo),
+                bin: 0,
+                cost: f32::INFINITY,
+            };
+            let mut bins = [(Aabb::EMPTY, 0usize); BINS];
+            for &i in items {
+                let bin = &mut bins[split.bin_of(centers[i as usize])];
+                bin.0 = bin.0.union(bounds[i as usize]);
+                bin.1 += 1;
+            }
+            // 从右往左累计一遍，再从左往右扫一遍算代价
+            let mut right_cost = [0.0; BINS];
+            let (mut aabb, mut count) = (Aabb::EMPTY, 0);
+            for k in (1..BINS).rev() {
+                aabb = aabb.union(bins[k].0);
+                count += bins[k].1;
+                right_cost[k - 1] = aabb.surface_area() * count as f32;
+            }
+            let (mut aabb, mut count) = (Aabb::EMPTY, 0);
+            for k in 0..BINS - 1 {
+                aabb = aabb.union(bins[k].0);
+                count += bins[k].1;
+                if count == 0 || count == items.len() {
+                    continue;
+                }
+                let cost = aabb.surface_area() * count as f32 + right_cost[k];
+                if cost < split.cost {
+                    split.cost = cost;
+                    split.bin = k;
+                }
+            }
+            if split.cost < best.as_ref().map_or(f32::INFINITY, |b| b.cost) {
+                best = Some(split);
+            }
+        }
+        best
+    }
+
+    /// 沿射线大致由近到远访问叶子里的图元
+    /// visit 拿到图元编号和当前的 t_max，返回新的 t_max：打到了就缩小它，更远的盒子就跳过了
+    pub(crate) fn traverse_ray(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        mut t_max: f32,
+        mut visit: impl FnMut(u32, f32) -> f32,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+        let inv_dir = direction.recip();
+        let Some(t) = self.nodes[0].bounds().ray(origin, inv_dir, t_max) else {
+            return;
+        };
+        let mut stack = Vec::with_capacity(MAX_DEPTH);
+        stack.push((0u32, t));
+        while let Some((index, t_near)) = stack.pop() {
+            if t_near > t_max {
+                continue;
+            }
+            let node = self.nodes[index as usize];
+            if node.count > 0 {
+                let first = node.first as usize;
+                for &i in &self.indices[first..first + node.count as usize] {
+                    t_max = visit(i, t_max);
+                }
+                continue;
+            }
+            let [near, far] = {
+                let children = [index + 1, node.first].map(|child| {
+                    let t = self.nodes[child as usize]
+                        .bounds()
+                        .ray(origin, inv_dir, t_max);
+                    (child, t)
+                });
+                let key = |t: Option<f32>| t.unwrap_or(f32::INFINITY);
+                if key(children[0].1) <= key(children[1].1) {
+                    children
+                } else {
+                    [children[1], children[0]]
+                }
+            };
+            // 近的后压栈，先出来
+            for (child, t) in [far, near] {
+                if let Some(t) = t {
+                    stack.push((child, t));
+                }
+            }
+        }
+    }
+
+    /// 访问包围盒和 aabb 相交的叶子里的所有图元，图元本身要不要再细测由调用方决定
+    pub(crate) fn query_aabb(&self, aabb: &Aabb, mut visit: impl FnMut(u32)) {
+        if self.is_empty() {
+            return;
+        }
+        let mut stack = Vec::with_capacity(MAX_DEPTH);
+        stack.push(0u32);
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index as usize];
+            if !node.bounds().overlaps(aabb) {
+                continue;
+            }
+            if node.count > 0 {
+                let first = node.first as usize;
+                for &i in &self.indices[first..first + node.count as usize] {
+                    visit(i);
+                }
+            } else {
+                stack.push(node.first);
+                stack.push(index + 1);
+            }
+        }
+    }
+}
+
+/// Möller–Trumbore，返回 (t, u, v)
+pub(crate) fn intersect_triangle(
+    [v0, v1, v2]: &[Vec3; 3],
+    origin: Vec3,
+    direction: Vec3,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let e1 = *v1 - *v0;
+    let e2 = *v2 - *v0;
+    let p = direction.cross(e2);
+    let det = e1.dot(p);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let inv = 1.0 / det;
+    let s = origin - *v0;
+    let u = s.dot(p) * inv;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(e1);
+    let v = direction.dot(q) * inv;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(q) * inv;
+    (T_MIN..=t_max).contains(&t).then_some((t, u, v))
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TriangleHit {
+    pub(crate) t: f32,
+    /// 在 TriangleMesh::triangles 里的下标
+    pub(crate) triangle: u32,
+    /// 重心坐标，交点 = v0 + u * (v1 - v0) + v * (v2 - v0)
+    pub(crate) u: f32,
+    pub(crate) v: f32,
+}
+
+/// 一堆世界空间的三角形和它们的 BVH
+pub(crate) struct TriangleMesh {
+    pub(crate) triangles: Vec<[Vec3; 3]>,
+    pub(crate) bvh: Bvh,
+}
+
+impl TriangleMesh {
+    pub(crate) fn new(triangles: Vec<[Vec3; 3]>) -> Self {
+        let bounds: Vec<Aabb> = triangles.iter().map(|t| Aabb::from_points(t)).collect();
+        let bvh = Bvh::build(&bounds);
+        TriangleMesh { triangles, bvh }
+    }
+
+    pub(crate) fn from_model(model: ModelType, transform: Mat4) -> Self {
+        Self::new(model.triangles(transform))
+    }
+
+    /// 最近的交点，direction 不用归一化，t 按 direction 的长度算
+    pub(crate) fn intersect(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        t_max: f32,
+    ) -> Option<TriangleHit> {
+        let mut best = None;
+        self.bvh.traverse_ray(
+            origin,
+            direction,
+            t_max,
+            |i, t_max| match intersect_triangle(
+                &self.triangles[i as usize],
+                origin,
+                direction,
+                t_max,
+            ) {
+                Some((t, u, v)) => {
+                    best = Some(TriangleHit {
+                        t,
+                        triangle: i,
+                        u,
+                        v,
+                    });
+                    t
+                }
+                None => t_max,
+            },
+        );
+        best
+    }
+
+    /// 包围盒和 aabb 相交的三角形
+    pub(crate) fn query_aabb(&self, aabb: &Aabb) -> Vec<u32> {
+        let mut found = Vec::new();
+        self.bvh.query_aabb(aabb, |i| {
+            if Aabb::from_points(&self.triangles[i as usize]).overlaps(aabb) {
+                found.push(i);
+            }
+        });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nanorand::{Rng, WyRand};
+
+    fn point(rng: &mut WyRand, extent: f32) -> Vec3 {
+        Vec3::new(rng.generate(), rng.generate(), rng.generate()) * 2.0 * extent - extent
+    }
+
+    /// 随机的三角形汤，大小不一，互相穿插
+    fn soup(rng: &mut WyRand, count: usize) -> Vec<[Vec3; 3]> {
+        (0..count)
+            .map(|_| {
+                let center = point(rng, 10.0);
+                let size = 0.2 + rng.generate::<f32>() * 2.0;
+                [0; 3].map(|_| center + point(rng, size))
+            })
+            .collect()
+    }
+
+    fn brute_intersect(
+        triangles: &[[Vec3; 3]],
+        origin: Vec3,
+        direction: Vec3,
+        t_max: f32,
+    ) -> Option<(f32, u32)> {
+        let mut best: Option<(f32, u32)> = None;
+        for (i, triangle) in triangles.iter().enumerate() {
+            if let Some((t, _, _)) = intersect_triangle(triangle, origin, direction, t_max) {
+                if best.map_or(true, |(best_t, _)| t < best_t) {
+                    best = Some((t, i as u32));
+                }
+            }
+        }
+        best
+    }
+
+    fn brute_query(triangles: &[[Vec3; 3]], aabb: &Aabb) -> Vec<u32> {
+        (0..triangles.len() as u32)
+            .filter(|&i| Aabb::from_points(&triangles[i as usize]).overlaps(aabb))
+            .collect()
+    }
+
+    fn check_rays(mesh: &TriangleMesh, rays: impl Iterator<Item = (Vec3, Vec3)>) -> usize {
+        let mut hits = 0;
+        for (origin, direction) in rays {
+            let expected = brute_intersect(&mesh.triangles, origin, direction, f32::MAX);
+            let found = mesh
+                .intersect(origin, direction, f32::MAX)
+                .map(|hit| (hit.t, hit.triangle));
+            assert_eq!(found, expected, "ray {origin} {direction}");
+            hits += found.is_some() as usize;
+        }
+        hits
+    }
+
+    fn check_queries(mesh: &TriangleMesh, rng: &mut WyRand, count: usize) {
+        for _ in 0..count {
+            let center = point(rng, 12.0);
+            let half = point(rng, 3.0).abs();
+            let aabb = Aabb::new(center - half, center + half);
+            let mut found = mesh.query_aabb(&aabb);
+            found.sort();
+            assert_eq!(found, brute_query(&mesh.triangles, &aabb));
+        }
+    }
+
+    #[test]
+    fn random_soup_matches_brute_force() {
+        let mut rng = WyRand::new_seed(7);
+        for count in [2, 5, 17, 100, 1000] {
+            let mesh = TriangleMesh::new(soup(&mut rng, count));
+            let rays: Vec<_> = (0..300)
+                .map(|_| {
+                    let origin = point(&mut rng, 15.0);
+                    // 朝某个三角形的重心附近射，保证有一部分能打中
+                    let [a, b, c] = mesh.triangles[rng.generate_range(0..count)];
+                    let target = (a + b + c) / 3.0 + point(&mut rng, 0.5);
+                    (origin, target - origin)
+                })
+                .collect();
+            let hits = check_rays(&mesh, rays.into_iter());
+            assert!(hits > 0, "{count} triangles: no ray hit anything");
+            check_queries(&mesh, &mut rng, 100);
+        }
+    }
+
+    #[test]
+    fn empty_bvh() {
+        let mesh = TriangleMesh::new(Vec::new());
+        assert!(mesh.bvh.is_empty());
+        assert!(mesh.intersect(Vec3::ZERO, Vec3::X, f32::MAX).is_none());
+        let aabb = Aabb::new(Vec3::splat(-100.0), Vec3::splat(100.0));
+        assert!(mesh.query_aabb(&aabb).is_empty());
+    }
+
+    #[test]
+    fn single_primitive() {
+        let triangle = [
+            Vec3::new(-1.0, -1.0, 0.0),
+            Vec3::new(1.0, -1.0, 0.0),
+            Vec3::Y,
+        ];
+        let mesh = TriangleMesh::new(vec![triangle]);
+        assert_eq!(mesh.bvh.nodes.len(), 1);
+        let hit = mesh.intersect(Vec3::new(0.0, 0.0, -5.0), Vec3::Z, f32::MAX);
+        assert_eq!(hit.map(|hit| (hit.t, hit.triangle)), Some((5.0, 0)));
+        assert!(mesh
+            .intersect(Vec3::new(5.0, 0.0, -5.0), Vec3::Z, f32::MAX)
+            .is_none());
+        let aabb = Aabb::new(Vec3::splat(-0.5), Vec3::splat(0.5));
+        assert_eq!(mesh.query_aabb(&aabb), vec![0]);
+        let far = Aabb::new(Vec3::splat(2.0), Vec3::splat(3.0));
+        assert!(mesh.query_aabb(&far).is_empty());
+    }
+
+    #[test]
+    fn coincident_centroids_split_in_half() {
+        // 每个三角形都连着同一个盒子的两个对角，包围盒完全一样，中心分桶分不开，只能走对半切
+        let mut rng = WyRand::new_seed(11);
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        let triangles: Vec<[Vec3; 3]> = (0..40)
+            .map(|_| [center - 2.0, center + 2.0, center + point(&mut rng, 2.0)])
+            .collect();
+        let mesh = TriangleMesh::new(triangles);
+        assert!(mesh.bvh.nodes.len() > 1);
+        assert!(mesh
+            .bvh
+            .nodes
+            .iter()
+            .all(|node| node.count as usize <= MAX_LEAF_SIZE));
+        let rays = (0..200).map(|_| {
+            let origin = center + point(&mut rng, 6.0);
+            (origin, center + point(&mut rng, 1.0) - origin)
+        });
+        assert!(check_rays(&mesh, rays) > 0);
+        check_queries(&mesh, &mut rng, 100);
+    }
+
+    #[test]
+    fn rays_with_zero_direction_components() {
+        let mut rng = WyRand::new_seed(3);
+        let mesh = TriangleMesh::new(soup(&mut rng, 300));
+        let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        let rays: Vec<_> = (0..600)
+            .map(|i| {
+                let origin = point(&mut rng, 12.0);
+                let target = point(&mut rng, 8.0);
+                let mut direction = target - origin;
+                // 一个或两个分量置 0，inv_dir 在这些轴上是无穷大
+                direction *= Vec3::ONE - axes[i % 3];
+                if i % 2 == 0 {
+                    direction *= Vec3::ONE - axes[(i + 1) % 3];
+                }
+                (origin, direction)
+            })
+            .collect();
+        assert!(check_rays(&mesh, rays.into_iter()) > 0);
+    }
+}
@@ -0,0 +1,82 @@
+// BVH 遍历，节点布局和 utils/bvh.rs 里的 BvhNode 一样
+// 用到它的 shader 自己声明
+//   var<storage, read> bvh_nodes: array<BvhNode>;
+//   fn bvh_hit(index: u32, ro: vec3<f32>, rd: vec3<f32>, t_max: f32) -> f32
+// bvh_hit 测第 index 个图元（按 Bvh::indices 排好之后的位置），没打到返回不小于 t_max 的值
+// 节点数组不能是空的，没有图元时调用方自己跳过
+
+const BVH_STACK_SIZE: u32 = 32u;
+const BVH_MISS: f32 = 3.4e38;
+const BVH_NONE: u32 = 0xffffffffu;
+
+struct BvhNode {
+    min: vec3<f32>,
+    // 叶子是第一个图元，内部节点是右孩子，左孩子就是下一个节点
+    first: u32,
+    max: vec3<f32>,
+    // 0 表示内部节点
+    count: u32,
+};
+
+struct BvhHit {
+    t: f32,
+    // 没打到是 BVH_NONE
+    index: u32,
+};
+
+fn bvh_slab(node: BvhNode, ro: vec3<f32>, inv_rd: vec3<f32>, t_max: f32) -> f32 {
+    let t1 = (node.min - ro) * inv_rd;
+    let t2 = (node.max - ro) * inv_rd;
+    let t_near = min(t1, t2);
+    let t_far = max(t1, t2);
+    let near = max(max(t_near.x, t_near.y), max(t_near.z, 0.0));
+    let far = min(min(t_far.x, t_far.y), min(t_far.z, t_max));
+    return select(BVH_MISS, near, near <= far);
+}
+
+fn bvh_traverse(ro: vec3<f32>, rd: vec3<f32>, t_max: f32) -> BvhHit {
+    var hit = BvhHit(t_max, BVH_NONE);
+    let inv_rd = 1.0 / rd;
+    if bvh_slab(bvh_nodes[0], ro, inv_rd, hit.t) >= BVH_MISS {
+        return hit;
+    }
+    var stack: array<u32, BVH_STACK_SIZE>;
+    stack[0] = 0u;
+    var top = 1u;
+    while top > 0u {
+        top -= 1u;
+        let index = stack[top];
+        let node = bvh_nodes[index];
+        if node.count > 0u {
+            for (var i = node.first; i < node.first + node.count; i++) {
+                let t = bvh_hit(i, ro, rd, hit.t);
+                if t < hit.t {
+                    hit = BvhHit(t, i);
+                }
+            }
+            continue;
+        }
+        var near = index + 1u;
+        var far = node.first;
+        var t_near = bvh_slab(bvh_nodes[near], ro, inv_rd, hit.t);
+        var t_far = bvh_slab(bvh_nodes[far], ro, inv_rd, hit.t);
+        if t_far < t_near {
+            let swap_index = near;
+            near = far;
+            far = swap_index;
+            let swap_t = t_near;
+            t_near = t_far;
+            t_far = swap_t;
+        }
+        // 近的后压栈，先出来
+        if t_far < BVH_MISS && top < BVH_STACK_SIZE {
+            stack[top] = far;
+            top += 1u;
+        }
+        if t_near < BVH_MISS && top < BVH_STACK_SIZE {
+            stack[top] = near;
+            top += 1u;
+        }
+    }
+    return hit;
+}
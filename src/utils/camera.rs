@@ -0,0 +1,87 @@
+use glam::{Mat4, Vec3};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+/// 绕着 target 转的相机，左键拖动旋转，滚轮缩放
+pub(crate) struct OrbitCamera {
+    pub(crate) target: Vec3,
+    /// 绕 y 轴，弧度
+    pub(crate) yaw: f32,
+    /// 仰角，弧度
+    pub(crate) pitch: f32,
+    pub(crate) distance: f32,
+    pub(crate) fov_y: f32,
+    pub(crate) near: f32,
+    pub(crate) far: f32,
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+}
+
+impl OrbitCamera {
+    pub(crate) fn new(target: Vec3, distance: f32) -> Self {
+        OrbitCamera {
+            target,
+            yaw: 0.6,
+            pitch: 0.4,
+            distance,
+            fov_y: 1.0,
+            near: 0.1,
+            far: 100.0,
+            dragging: false,
+            cursor: None,
+        }
+    }
+
+    pub(crate) fn position(&self) -> Vec3 {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        self.target + Vec3::new(sy * cp, sp, cy * cp) * self.distance
+    }
+
+    pub(crate) fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position(), self.target, Vec3::Y)
+    }
+
+    pub(crate) fn proj(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective_rh(self.fov_y, aspect, self.near, self.far)
+    }
+
+    pub(crate) fn view_proj(&self, aspect: f32) -> Mat4 {
+        self.proj(aspect) * self.view()
+    }
+
+    /// 相机动了返回 true，点在 egui 上的事件由场景自己先过滤掉
+    pub(crate) fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let last = self.cursor.replace((position.x, position.y));
+                match last {
+                    Some((x, y)) if self.dragging => {
+                        self.yaw -= (position.x - x) as f32 * 0.005;
+                        self.pitch =
+                            (self.pitch + (position.y - y) as f32 * 0.005).clamp(-1.5, 1.5);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 50.0,
+                };
+                self.distance =
+                    (self.distance * 0.9f32.powf(lines)).clamp(self.near * 2.0, self.far * 0.5);
+                true
+            }
+            _ => false,
+        }
+    }
+}
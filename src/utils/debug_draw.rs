@@ -0,0 +1,385 @@
+// 即时模式的调试线框
+// 场景在每帧更新的时候随手调 line / aabb / sphere / axes / grid / frustum，线段都攒在 CPU 上
+// prepare 一次写进动态的顶点 buffer，render 在场景的 pass 之后接着画，然后清空，下一帧重新攒
+// depth_test 管之后加的线：开着会被场景挡住，关了总在最上面，一帧里两种可以混着画
+// 颜色和 sprite 一样是 0xAABBGGRR
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use wgpu::util::DeviceExt;
+
+use super::bvh::Aabb;
+use crate::gfx;
+
+pub(crate) const RED: u32 = 0xff4040ff;
+pub(crate) const GREEN: u32 = 0xff40ff40;
+pub(crate) const BLUE: u32 = 0xffff6040;
+pub(crate) const YELLOW: u32 = 0xff40e0ff;
+pub(crate) const WHITE: u32 = 0xffffffff;
+pub(crate) const GRAY: u32 = 0xff808080;
+
+/// 圆和球上的折线段数
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// 把 color 的 alpha 换掉
+pub(crate) fn with_alpha(color: u32, alpha: f32) -> u32 {
+    let alpha = (alpha.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (color & 0x00ffffff) | (alpha << 24)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct LineVertex {
+    position: [f32; 3],
+    color: u32,
+}
+
+pub(crate) struct DebugDraw {
+    /// 之后加的线要不要被场景挡住
+    pub(crate) depth_test: bool,
+    /// [0] 做深度测试的，[1] 不做的，两个顶点一段
+    lines: [Vec<LineVertex>; 2],
+    /// 最近一次 prepare 写进 buffer 的两批顶点数
+    counts: [u32; 2],
+    /// 没有深度贴图时是 None，两批都用 overlay 画
+    tested: Option<wgpu::RenderPipeline>,
+    overlay: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    /// 顶点数，放不下时翻倍
+    capacity: usize,
+}
+
+impl DebugDraw {
+    /// depth_format 要和 render 时传的深度贴图一致，场景没有深度贴图就传 None
+    pub(crate) fn new(
+        context: &gfx::GfxContext,
+        format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let uniform_buffer = context
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("debug draw"),
+                contents: bytemuck::bytes_of(&Mat4::IDENTITY.to_cols_array_2d()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+        let bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("debug draw"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("debug draw"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+            });
+        let pipeline_layout =
+            context
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("debug draw"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+        let shader = context
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("debug draw"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("debug_draw.wgsl"))),
+            });
+        // 线只读深度不写，不会挡住后面别的线
+        let pipeline = |compare| {
+            context
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("debug draw"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Uint32],
+                        }],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::LineList,
+                        ..Default::default()
+                    },
+                    depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                        format,
+                        depth_write_enabled: false,
+                        depth_compare: compare,
+                        stencil: Default::default(),
+                        bias: Default::default(),
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                    cache: None,
+                })
+        };
+        let capacity = 1024;
+        DebugDraw {
+            depth_test: true,
+            lines: [Vec::new(), Vec::new()],
+            counts: [0; 2],
+            tested: depth_format.map(|_| pipeline(wgpu::CompareFunction::LessEqual)),
+            overlay: pipeline(wgpu::CompareFunction::Always),
+            uniform_buffer,
+            bind_group,
+            vertex_buffer: Self::create_vertex_buffer(context, capacity),
+            capacity,
+        }
+    }
+
+    fn create_vertex_buffer(context: &gfx::GfxContext, capacity: usize) -> wgpu::Buffer {
+        context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("debug draw lines"),
+            size: (capacity * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub(crate) fn line(&mut self, a: Vec3, b: Vec3, color: u32) {
+        let batch = &mut self.lines[!self.depth_test as usize];
+        batch.push(LineVertex {
+            position: a.to_array(),
+            color,
+        });
+        batch.push(LineVertex {
+            position: b.to_array(),
+            color,
+        });
+    }
+
+    /// 依次连起来，closed 时首尾也连上
+    fn polyline(&mut self, points: &[Vec3], closed: bool, color: u32) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+        if closed && points.len() > 2 {
+            self.line(points[points.len() - 1], points[0], color);
+        }
+    }
+
+    /// 八个角按 xyz 三位编号，先画底下四条，再画顶上四条和竖着的四条
+    fn box_corners(&mut self, corners: [Vec3; 8], color: u32) {
+        for [a, b] in [
+            [0, 1],
+            [1, 3],
+            [3, 2],
+            [2, 0],
+            [4, 5],
+            [5, 7],
+            [7, 6],
+            [6, 4],
+            [0, 4],
+            [1, 5],
+            [2, 6],
+            [3, 7],
+        ] {
+            self.line(corners[a], corners[b], color);
+        }
+    }
+
+    pub(crate) fn aabb(&mut self, aabb: &Aabb, color: u32) {
+        let corners = std::array::from_fn(|i| {
+            Vec3::select(
+                glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                aabb.max,
+                aabb.min,
+            )
+        });
+        self.box_corners(corners, color);
+    }
+
+    /// normal 是圆所在平面的法线
+    pub(crate) fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: u32) {
+        let (u, v) = normal.normalize().any_orthonormal_pair();
+        let points: Vec<Vec3> = (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let (s, c) = (i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU).sin_cos();
+                center + (u * c + v * s) * radius
+            })
+            .collect();
+        self.polyline(&points, true, color);
+    }
+
+    /// 三个轴上各一个大圆
+    pub(crate) fn sphere(&mut self, center: Vec3, radius: f32, color: u32) {
+        for normal in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.circle(center, normal, radius, color);
+        }
+    }
+
+    /// 标一个点，三条轴向的短线
+    pub(crate) fn cross(&mut self, center: Vec3, size: f32, color: u32) {
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.line(center - axis * size, center + axis * size, color);
+        }
+    }
+
+    /// 末端带四根倒刺
+    pub(crate) fn arrow(&mut self, from: Vec3, to: Vec3, color: u32) {
+        self.line(from, to, color);
+        let direction = to - from;
+        let length = direction.length();
+        if length < 1e-6 {
+            return;
+        }
+        let (u, v) = (direction / length).any_orthonormal_pair();
+        let head = length * 0.15;
+        let base = to - direction / length * head;
+        for side in [u, -u, v, -v] {
+            self.line(to, base + side * head * 0.4, color);
+        }
+    }
+
+    /// transform 的 xyz 轴画成红绿蓝，长度 size，跟着 transform 一起缩放
+    pub(crate) fn axes(&mut self, transform: Mat4, size: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for (axis, color) in [(Vec3::X, RED), (Vec3::Y, GREEN), (Vec3::Z, BLUE)] {
+            self.line(origin, transform.transform_point3(axis * size), color);
+        }
+    }
+
+    /// xz 平面上以 center 为中心的网格，每边 cells 格
+    pub(crate) fn grid(&mut self, center: Vec3, cells: u32, spacing: f32, color: u32) {
+        let half = cells as f32 * spacing * 0.5;
+        for i in 0..=cells {
+            let offset = i as f32 * spacing - half;
+            self.line(
+                center + Vec3::new(offset, 0.0, -half),
+                center + Vec3::new(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vec3::new(-half, 0.0, offset),
+                center + Vec3::new(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// 把 NDC 的八个角用 view_proj 的逆变回世界，远平面要是有限的
+    pub(crate) fn frustum(&mut self, view_proj: Mat4, color: u32) {
+        let inverse = view_proj.inverse();
+        let corners = std::array::from_fn(|i| {
+            let ndc = Vec3::new(
+                if i & 1 != 0 { 1.0 } else { -1.0 },
+                if i & 2 != 0 { 1.0 } else { -1.0 },
+                if i & 4 != 0 { 1.0 } else { 0.0 },
+            );
+            inverse.project_point3(ndc)
+        });
+        self.box_corners(corners, color);
+    }
+
+    /// 这一帧攒了多少段
+    pub(crate) fn line_count(&self) -> usize {
+        self.counts.iter().sum::<u32>() as usize / 2
+    }
+
+    /// 在 render 之前调，把攒的线写进 buffer，然后清空
+    pub(crate) fn prepare(&mut self, context: &gfx::GfxContext, view_proj: Mat4) {
+        context.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&view_proj.to_cols_array_2d()),
+        );
+        let total = self.lines[0].len() + self.lines[1].len();
+        if total > self.capacity {
+            self.capacity = total.next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(context, self.capacity);
+        }
+        let mut offset = 0;
+        for (batch, count) in self.lines.iter_mut().zip(&mut self.counts) {
+            context.queue.write_buffer(
+                &self.vertex_buffer,
+                offset,
+                bytemuck::cast_slice(batch.as_slice()),
+            );
+            offset += std::mem::size_of_val(batch.as_slice()) as wgpu::BufferAddress;
+            *count = batch.len() as u32;
+            batch.clear();
+        }
+    }
+
+    /// 在场景的 pass 后面接一个 pass，颜色和深度都保留
+    /// depth 是场景用的深度贴图，要和 new 时的 depth_format 对上，而且场景的 pass 得把它 Store 下来
+    pub(crate) fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        depth: Option<&wgpu::TextureView>,
+    ) {
+        assert_eq!(depth.is_some(), self.tested.is_some());
+        if self.counts == [0; 2] {
+            return;
+        }
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("debug draw"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: depth.map(|view| wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        let [tested, overlay] = self.counts;
+        if tested > 0 {
+            rpass.set_pipeline(self.tested.as_ref().unwrap_or(&self.overlay));
+            rpass.draw(0..tested, 0..1);
+        }
+        if overlay > 0 {
+            rpass.set_pipeline(&self.overlay);
+            rpass.draw(tested..tested + overlay, 0..1);
+        }
+    }
+}
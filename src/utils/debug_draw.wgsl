@@ -0,0 +1,22 @@
+// 调试线段，和 utils/debug_draw.rs 里的 LineVertex 对应
+// 颜色是 0xAABBGGRR
+
+@group(0)
+@binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>, @location(1) color: u32) -> VertexOutput {
+    let rgba = vec4<f32>((vec4<u32>(color) >> vec4<u32>(0u, 8u, 16u, 24u)) & vec4<u32>(255u)) / 255.0;
+    return VertexOutput(view_proj * vec4<f32>(position, 1.0), rgba);
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return vertex.color;
+}
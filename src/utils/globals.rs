@@ -0,0 +1,112 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::camera::OrbitCamera;
+use crate::painter::Sandy;
+
+/// 拼在场景 shader 前面，声明 Globals 和 group(0) 的绑定
+pub(crate) const GLOBALS_WGSL: &str = include_str!("globals.wgsl");
+
+pub(crate) fn with_globals(source: &str) -> String {
+    format!("{}\n{}", GLOBALS_WGSL, source)
+}
+
+/// 和 globals.wgsl 里的 Globals 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub(crate) struct GlobalsUniform {
+    pub(crate) view_proj: [[f32; 4]; 4],
+    pub(crate) inv_view_proj: [[f32; 4]; 4],
+    pub(crate) camera_position: [f32; 3],
+    pub(crate) time: f32,
+    pub(crate) resolution: [f32; 2],
+    pub(crate) delta_time: f32,
+    pub(crate) frame: u32,
+}
+
+/// 每帧共用的时间和相机 uniform
+pub(crate) struct Globals {
+    pub(crate) uniform: GlobalsUniform,
+    pub(crate) buffer: wgpu::Buffer,
+    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+impl Sandy for Globals {
+    type Extra = ();
+
+    fn ready(context: &crate::gfx::GfxContext, _extra: Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("globals"),
+            size: std::mem::size_of::<GlobalsUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout =
+            context
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("globals"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX
+                            | wgpu::ShaderStages::FRAGMENT
+                            | wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                                GlobalsUniform,
+                            >()
+                                as u64),
+                        },
+                        count: None,
+                    }],
+                });
+        let bind_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("globals"),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+        Globals {
+            uniform: GlobalsUniform::zeroed(),
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+}
+
+impl Globals {
+    /// 每帧开头调一次，frame 自己加一
+    pub(crate) fn update(
+        &mut self,
+        context: &crate::gfx::GfxContext,
+        camera: &OrbitCamera,
+        dt: f32,
+        time: f32,
+    ) {
+        let config = context.surface_config.as_ref().unwrap();
+        let (width, height) = (config.width as f32, config.height.max(1) as f32);
+        let view_proj = camera.view_proj(width / height);
+        self.uniform = GlobalsUniform {
+            view_proj: view_proj.to_cols_array_2d(),
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            camera_position: camera.position().to_array(),
+            time,
+            resolution: [width, height],
+            delta_time: dt,
+            frame: self.uniform.frame.wrapping_add(1),
+        };
+        context
+            .queue
+            .write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+}
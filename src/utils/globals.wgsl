@@ -0,0 +1,25 @@
+// 每帧共用的时间和相机，和 utils/globals.rs 里的 GlobalsUniform 对应
+// 固定放在 group(0) binding(0)
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    time: f32,
+    // 像素
+    resolution: vec2<f32>,
+    delta_time: f32,
+    // 从场景开始数的帧数
+    frame: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> globals: Globals;
+
+// 屏幕 uv（左上角 0）-> 世界空间的射线方向
+fn camera_ray(uv: vec2<f32>) -> vec3<f32> {
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let far = globals.inv_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+    return normalize(far.xyz / far.w - globals.camera_position);
+}
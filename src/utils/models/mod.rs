@@ -5,6 +5,8 @@ use wgpu::util::DeviceExt;
 
 use super::{vertex, Vertex};
 
+pub mod obj;
+
 pub fn gen_plane() -> ([f32; 36], [u16; 6]) {
     #[rustfmt::skip]
     let vs: [f32; 36] = [
@@ -125,28 +127,6 @@ pub trait Model {
     type IndexType: Sized + Copy + 'static + Pod;
 
     fn gen() -> (Self::V, Self::I);
-    fn to_buffer<'a>(
-        v: Self::V,
-        i: Self::I,
-        context: &crate::gfx::GfxContext,
-    ) -> (wgpu::Buffer, wgpu::Buffer) {
-        let vertexes_buffer =
-            context
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: None,
-                    contents: bytemuck::cast_slice(v.as_ref()),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
-        let indexes_buffer = context
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: None,
-                contents: bytemuck::cast_slice(i.as_ref()),
-                usage: wgpu::BufferUsages::INDEX,
-            });
-        (vertexes_buffer, indexes_buffer)
-    }
 }
 
 pub struct Plane {
@@ -198,7 +178,7 @@ impl Model for Cube {
     type IndexType = u16;
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum ModelType {
     Plane,
     Sphere,
@@ -211,30 +191,118 @@ pub struct ModelBuffers {
     pub index_count: u32,
 }
 
+impl ModelBuffers {
+    /// 索引是 u16 还是 u32 由画的地方决定
+    /// 两个 buffer 都带 STORAGE，调试视图（utils::view_modes）要直接读
+    pub fn new<V: Pod, I: Pod>(
+        context: &crate::gfx::GfxContext,
+        label: &str,
+        vertices: &[V],
+        indices: &[I],
+    ) -> Self {
+        ModelBuffers {
+            vertex_buffer: context
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(vertices),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+                }),
+            index_buffer: context
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(indices),
+                    usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE,
+                }),
+            index_count: indices.len() as u32,
+        }
+    }
+}
+
 impl ModelType {
     pub fn iterator() -> impl Iterator<Item = ModelType> {
         [Self::Plane, Self::Sphere, Self::Cube, Self::Plane]
             .iter()
             .copied()
     }
-    pub fn create_all_buffers(context: &crate::gfx::GfxContext) -> Vec<ModelBuffers> {
-        Self::iterator()
-            .map(|model_type| {
-                let (vertices, indices) = match model_type {
-                    ModelType::Plane => Plane::gen(),
-                    ModelType::Sphere => Sphere::gen(),
-                    ModelType::Cube => Cube::gen(),
-                };
-                let (vertex_buffer, index_buffer) = match model_type {
-                    ModelType::Plane => Plane::to_buffer(vertices, indices.clone(), context),
-                    ModelType::Sphere => Sphere::to_buffer(vertices, indices.clone(), context),
-                    ModelType::Cube => Cube::to_buffer(vertices, indices.clone(), context),
+    pub fn name(self) -> &'static str {
+        match self {
+            ModelType::Plane => "plane",
+            ModelType::Sphere => "sphere",
+            ModelType::Cube => "cube",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Plane, Self::Sphere, Self::Cube]
+            .into_iter()
+            .find(|m| m.name() == name)
+    }
+
+    /// 位置 + 法线，每个顶点 6 个 f32，光照用
+    /// Cube 原来的数据里没有法线，Plane 的法线不对，这两个按三角形重新算
+    pub fn gen_lit(self) -> (Vec<[f32; 6]>, Vec<u16>) {
+        let (raw, indices) = match self {
+            ModelType::Plane => Plane::gen(),
+            ModelType::Sphere => {
+                let (v, i) = Sphere::gen();
+                let lit = v
+                    .chunks(8)
+                    .map(|c| [c[0], c[1], c[2], c[3], c[4], c[5]])
+                    .collect();
+                return (lit, i);
+            }
+            ModelType::Cube => Cube::gen(),
+        };
+        let mut lit: Vec<[f32; 6]> = raw
+            .chunks(6)
+            .map(|c| [c[0], c[1], c[2], 0.0, 0.0, 0.0])
+            .collect();
+        for tri in indices.chunks(3) {
+            let [a, b, c] =
+                [tri[0], tri[1], tri[2]].map(|i| glam::Vec3::from_slice(&lit[i as usize][..3]));
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            for &i in tri {
+                lit[i as usize][3..].copy_from_slice(&normal.to_array());
+            }
+        }
+        (lit, indices)
+    }
+
+    /// 位置 + 法线 + uv，每个顶点 8 个 f32，要贴图的时候用
+    /// 法线和 gen_lit 一样，Plane 原来没有 uv，按 xy 平铺
+    pub fn gen_textured(self) -> (Vec<[f32; 8]>, Vec<u16>) {
+        let (raw, _) = match self {
+            ModelType::Plane => Plane::gen(),
+            ModelType::Sphere => Sphere::gen(),
+            ModelType::Cube => Cube::gen(),
+        };
+        let (lit, indices) = self.gen_lit();
+        let textured = lit
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let uv = match self {
+                    ModelType::Plane => [v[0] / 0.6 + 0.5, 0.5 - v[1] / 0.6],
+                    ModelType::Sphere => [raw[i * 8 + 6], raw[i * 8 + 7]],
+                    ModelType::Cube => [raw[i * 6 + 3], raw[i * 6 + 4]],
                 };
-                ModelBuffers {
-                    vertex_buffer,
-                    index_buffer,
-                    index_count: indices.len() as u32,
-                }
+                [v[0], v[1], v[2], v[3], v[4], v[5], uv[0], uv[1]]
+            })
+            .collect();
+        (textured, indices)
+    }
+
+    /// 变换到世界空间的三角形，给 BVH 和 CPU 射线查询用
+    pub fn triangles(self, transform: glam::Mat4) -> Vec<[glam::Vec3; 3]> {
+        let (vertices, indices) = self.gen_lit();
+        indices
+            .chunks(3)
+            .map(|tri| {
+                [tri[0], tri[1], tri[2]].map(|i| {
+                    transform.transform_point3(glam::Vec3::from_slice(&vertices[i as usize][..3]))
+                })
             })
             .collect()
     }
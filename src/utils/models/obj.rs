@@ -0,0 +1,143 @@
+// 很小的 Wavefront OBJ 读取，只认 v / vt / vn / f
+// 多边形按扇形拆成三角形，没有法线的按面法线平均出来
+// 输出和 ModelType::gen_textured 一样的顶点：位置 + 法线 + uv
+use std::{collections::HashMap, path::Path};
+
+use glam::{Vec2, Vec3};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    /// 行号从 1 开始
+    Parse {
+        line: usize,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjError::Io(e) => write!(f, "obj io error: {}", e),
+            ObjError::Parse { line, message } => write!(f, "obj line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<std::io::Error> for ObjError {
+    fn from(e: std::io::Error) -> Self {
+        ObjError::Io(e)
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<(Vec<[f32; 8]>, Vec<u32>), ObjError> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse(source: &str) -> Result<(Vec<[f32; 8]>, Vec<u32>), ObjError> {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    // (位置, uv, 法线) 的组合 -> 输出的顶点编号
+    let mut corners: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut vertices: Vec<[f32; 8]> = Vec::new();
+    let mut indices = Vec::new();
+    let mut has_normals = true;
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| ObjError::Parse {
+            line: i + 1,
+            message,
+        };
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let mut floats = |count: usize| -> Result<Vec<f32>, ObjError> {
+            let values = words
+                .by_ref()
+                .take(count)
+                .map(|w| w.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| error(e.to_string()))?;
+            if values.len() < count {
+                return Err(error(format!("`{}` needs {} numbers", keyword, count)));
+            }
+            Ok(values)
+        };
+        match keyword {
+            "v" => positions.push(Vec3::from_slice(&floats(3)?)),
+            "vt" => uvs.push(Vec2::from_slice(&floats(2)?)),
+            "vn" => normals.push(Vec3::from_slice(&floats(3)?)),
+            "f" => {
+                let mut face = Vec::new();
+                for corner in words {
+                    // 编号从 1 开始，负数从末尾倒着数
+                    let index = |s: Option<&str>, len: usize| -> Result<Option<usize>, ObjError> {
+                        let Some(s) = s.filter(|s| !s.is_empty()) else {
+                            return Ok(None);
+                        };
+                        let n: i64 = s.parse().map_err(|_| error(format!("bad index `{}`", s)))?;
+                        let n = if n < 0 { len as i64 + n } else { n - 1 };
+                        if n < 0 || n >= len as i64 {
+                            return Err(error(format!("index `{}` out of range", s)));
+                        }
+                        Ok(Some(n as usize))
+                    };
+                    let mut parts = corner.split('/');
+                    let p = index(parts.next(), positions.len())?.unwrap();
+                    let t = index(parts.next(), uvs.len())?;
+                    let n = index(parts.next(), normals.len())?;
+                    has_normals &= n.is_some();
+                    let id = *corners.entry((p, t, n)).or_insert_with(|| {
+                        let position = positions[p];
+                        let normal = n.map_or(Vec3::ZERO, |n| normals[n]);
+                        // obj 的 v 朝上，贴图的 v 朝下
+                        let uv = t.map_or(Vec2::ZERO, |t| Vec2::new(uvs[t].x, 1.0 - uvs[t].y));
+                        vertices.push([
+                            position.x, position.y, position.z, normal.x, normal.y, normal.z, uv.x,
+                            uv.y,
+                        ]);
+                        vertices.len() as u32 - 1
+                    });
+                    face.push(id);
+                }
+                if face.len() < 3 {
+                    return Err(error("face needs at least 3 vertices".into()));
+                }
+                for k in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[k], face[k + 1]]);
+                }
+            }
+            // 材质、分组之类的都不管
+            _ => {}
+        }
+    }
+
+    if !has_normals {
+        smooth_normals(&mut vertices, &indices);
+    }
+    Ok((vertices, indices))
+}
+
+/// 同一个位置的顶点共享法线，按面积加权
+fn smooth_normals(vertices: &mut [[f32; 8]], indices: &[u32]) {
+    let position = |v: &[f32; 8]| Vec3::from_slice(&v[..3]);
+    let mut sums: HashMap<[u32; 3], Vec3> = HashMap::new();
+    let key = |v: &[f32; 8]| [v[0].to_bits(), v[1].to_bits(), v[2].to_bits()];
+    for tri in indices.chunks(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| vertices[i as usize]);
+        let normal = (position(&b) - position(&a)).cross(position(&c) - position(&a));
+        for v in [a, b, c] {
+            *sums.entry(key(&v)).or_default() += normal;
+        }
+    }
+    for v in vertices.iter_mut() {
+        let normal = sums
+            .get(&key(v))
+            .copied()
+            .unwrap_or(Vec3::Y)
+            .normalize_or_zero();
+        v[3..6].copy_from_slice(&normal.to_array());
+    }
+}
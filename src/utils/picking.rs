@@ -0,0 +1,295 @@
+// 鼠标拾取，两种办法：
+// ID 缓冲：点击的那一帧把每个物体的编号画到一张 R32Uint 贴图上，再把光标下的像素读回来
+// CPU 射线：场景自己用 ndc_ray 反投影出射线，和 bvh::TriangleMesh 或者解析形状求交
+// 贴图里存的是编号 + 1，0 表示什么都没点到；对外给出的都是从 0 开始的编号
+use glam::{Mat4, Vec2, Vec3};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+use crate::painter::Sandy;
+
+pub(crate) const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// 只读一个像素，但拷贝的行宽要按 256 字节对齐
+const READBACK_SIZE: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+/// 像素坐标（y 朝下）转成 NDC（y 朝上），size 是视口大小
+pub(crate) fn pixel_to_ndc(pixel: Vec2, size: Vec2) -> Vec2 {
+    let uv = pixel / size;
+    Vec2::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0)
+}
+
+/// 把 NDC 上的一点反投影成世界空间的射线，返回 (起点, 方向)
+/// 起点在近平面上，方向没归一化，t = 1 正好到远平面
+pub(crate) fn ndc_ray(inv_view_proj: Mat4, ndc: Vec2) -> (Vec3, Vec3) {
+    let near = inv_view_proj.project_point3(ndc.extend(0.0));
+    let far = inv_view_proj.project_point3(ndc.extend(1.0));
+    (near, far - near)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum PickMode {
+    IdBuffer,
+    CpuRay,
+}
+
+/// 一张和窗口一样大的 ID 贴图，带自己的深度
+pub(crate) struct IdBuffer {
+    size: (u32, u32),
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    readback: wgpu::Buffer,
+    /// 这一帧要读的像素
+    request: Option<(u32, u32)>,
+}
+
+fn id_targets(
+    context: &crate::gfx::GfxContext,
+    (width, height): (u32, u32),
+) -> (wgpu::Texture, wgpu::TextureView, wgpu::TextureView) {
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = context.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("picking ids"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ID_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let depth = context.device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("picking depth"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view, depth_view)
+}
+
+impl Sandy for IdBuffer {
+    type Extra = ();
+
+    fn ready(context: &crate::gfx::GfxContext, _extra: Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        let config = context.surface_config.as_ref().unwrap();
+        let size = (config.width, config.height);
+        let (texture, view, depth_view) = id_targets(context, size);
+        let readback = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("picking readback"),
+            size: READBACK_SIZE as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        IdBuffer {
+            size,
+            texture,
+            view,
+            depth_view,
+            readback,
+            request: None,
+        }
+    }
+}
+
+impl IdBuffer {
+    /// ID pipeline 的颜色目标，整数贴图不能混合
+    pub(crate) fn target() -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format: ID_FORMAT,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+
+    /// ID pipeline 的深度，相等也算过，同一深度上后画的盖住先画的，和颜色 pass 一致
+    pub(crate) fn depth_stencil() -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+
+    /// 记下要读的像素，本帧画 ID pass 的时候拷出来
+    pub(crate) fn request(&mut self, x: u32, y: u32) {
+        self.request = Some((x, y));
+    }
+
+    /// 有请求才需要画 ID pass
+    pub(crate) fn requested(&self) -> bool {
+        self.request.is_some()
+    }
+
+    /// 开一个清成 0 的 pass，调用方在里面按编号把物体画一遍
+    /// 窗口大小变了先重建贴图
+    pub(crate) fn begin<'a>(
+        &mut self,
+        context: &crate::gfx::GfxContext,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        let config = context.surface_config.as_ref().unwrap();
+        let size = (config.width, config.height);
+        if size != self.size {
+            (self.texture, self.view, self.depth_view) = id_targets(context, size);
+            self.size = size;
+        }
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("picking ids"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: context.render_timestamps("picking ids"),
+            occlusion_query_set: None,
+        })
+    }
+
+    /// ID pass 结束后调，把请求的像素拷到读回 buffer
+    pub(crate) fn copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some((x, y)) = self.request else {
+            return;
+        };
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: x.min(self.size.0 - 1),
+                    y: y.min(self.size.1 - 1),
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(READBACK_SIZE),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// submit 之后调，等 GPU 拷完把编号读回来
+    /// 这一帧没有请求返回 None，点到空白处返回 Some(None)
+    pub(crate) fn read(&mut self, context: &crate::gfx::GfxContext) -> Option<Option<u32>> {
+        self.request.take()?;
+        let slice = self.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        // 只在点击的那一帧等一下，和 bench 里读时间戳一样
+        context.device.poll(wgpu::Maintain::Wait);
+        let id: u32 = {
+            let data = slice.get_mapped_range();
+            bytemuck::pod_read_unaligned(&data[..4])
+        };
+        self.readback.unmap();
+        Some(id.checked_sub(1))
+    }
+}
+
+/// 场景里拾取要用的状态：光标、模式、选中的物体
+pub(crate) struct Picker {
+    pub(crate) mode: PickMode,
+    pub(crate) selected: Option<u32>,
+    pub(crate) ids: IdBuffer,
+    cursor: Vec2,
+}
+
+impl Sandy for Picker {
+    type Extra = ();
+
+    fn ready(context: &crate::gfx::GfxContext, _extra: Self::Extra) -> Self
+    where
+        Self: Sized,
+    {
+        Picker {
+            mode: PickMode::IdBuffer,
+            selected: None,
+            ids: IdBuffer::ready(context, ()),
+            cursor: Vec2::ZERO,
+        }
+    }
+}
+
+impl Picker {
+    /// 跟踪光标，左键点下（且不在 egui 上）时：
+    /// ID 模式记下请求，等本帧的 ID pass；CPU 模式返回光标的像素坐标，由场景自己求交
+    pub(crate) fn input(&mut self, event: &WindowEvent, over_ui: bool) -> Option<Vec2> {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Vec2::new(position.x as f32, position.y as f32);
+                None
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if !over_ui => match self.mode {
+                PickMode::IdBuffer => {
+                    let pixel = self.cursor.max(Vec2::ZERO);
+                    self.ids.request(pixel.x as u32, pixel.y as u32);
+                    None
+                }
+                PickMode::CpuRay => Some(self.cursor),
+            },
+            _ => None,
+        }
+    }
+
+    /// submit 之后调，ID 模式的结果这时才拿得到
+    pub(crate) fn finish(&mut self, context: &crate::gfx::GfxContext) {
+        if let Some(id) = self.ids.read(context) {
+            self.selected = id;
+        }
+    }
+
+    /// 检查器的公共部分，name 把编号转成显示的名字
+    pub(crate) fn ui(&mut self, ui: &mut egui::Ui, name: impl Fn(u32) -> String) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.mode, PickMode::IdBuffer, "ID buffer");
+            ui.radio_value(&mut self.mode, PickMode::CpuRay, "CPU ray");
+        });
+        ui.horizontal(|ui| {
+            match self.selected {
+                Some(id) => ui.label(format!("selected: #{} {}", id, name(id))),
+                None => ui.label("selected: none"),
+            };
+            if ui.button("clear").clicked() {
+                self.selected = None;
+            }
+        });
+    }
+}
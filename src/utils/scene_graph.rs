@@ -0,0 +1,423 @@
+// 场景图：节点带平移 / 旋转 / 缩放，可以挂到别的节点下面
+// 改局部变换只给节点标脏，update 的时候从根往下把脏的子树重新乘一遍世界矩阵
+// 节点上可以挂一个 Renderable，指向要画的 ModelBuffers
+use std::sync::Arc;
+
+use glam::{EulerRot, Mat4, Quat, Vec3};
+
+use super::models::ModelBuffers;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Transform {
+    pub(crate) translation: Vec3,
+    pub(crate) rotation: Quat,
+    pub(crate) scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Transform {
+    pub(crate) const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub(crate) fn from_translation(translation: Vec3) -> Self {
+        Transform {
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub(crate) fn with_rotation(self, rotation: Quat) -> Self {
+        Transform { rotation, ..self }
+    }
+
+    pub(crate) fn with_scale(self, scale: Vec3) -> Self {
+        Transform { scale, ..self }
+    }
+
+    /// 先缩放，再旋转，最后平移
+    pub(crate) fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+/// 在 egui 里编辑一个变换，旋转按 YXZ 欧拉角用度数显示，改了返回 true
+pub(crate) fn transform_ui(ui: &mut egui::Ui, transform: &mut Transform) -> bool {
+    let mut changed = false;
+    let mut row = |ui: &mut egui::Ui, label: &str, v: &mut Vec3, speed: f64| {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for (value, prefix) in [(&mut v.x, "x "), (&mut v.y, "y "), (&mut v.z, "z ")] {
+                changed |= ui
+                    .add(egui::DragValue::new(value).speed(speed).prefix(prefix))
+                    .changed();
+            }
+        });
+    };
+    row(ui, "translate", &mut transform.translation, 0.01);
+    let (y, x, z) = transform.rotation.to_euler(EulerRot::YXZ);
+    let before = Vec3::new(x, y, z) * 180.0 / std::f32::consts::PI;
+    let mut degrees = before;
+    row(ui, "rotate", &mut degrees, 0.5);
+    // 没动的时候不要来回转换，免得误差慢慢累积
+    if degrees != before {
+        let r = degrees * std::f32::consts::PI / 180.0;
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, r.y, r.x, r.z);
+    }
+    row(ui, "scale", &mut transform.scale, 0.01);
+    changed
+}
+
+/// 挂在节点上的可画物体
+#[derive(Clone)]
+pub(crate) struct Renderable {
+    pub(crate) model: Arc<ModelBuffers>,
+}
+
+/// 槽位编号加代数，槽位复用后旧的 NodeId 代数对不上，不会指到新节点
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+impl NodeId {
+    /// 槽位编号，删掉的节点的槽位会被复用，要唯一标识用 NodeId 本身
+    pub(crate) fn index(self) -> usize {
+        self.index
+    }
+}
+
+struct Node {
+    name: String,
+    local: Transform,
+    world: Mat4,
+    /// 局部变换或者父节点变了，world 还没更新
+    dirty: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    renderable: Option<Renderable>,
+}
+
+struct Slot {
+    /// 每删一次加一
+    generation: u32,
+    node: Option<Node>,
+}
+
+/// 删掉的节点留个空位给后面的节点复用，靠 NodeId 里的代数区分新旧
+#[derive(Default)]
+pub(crate) struct SceneGraph {
+    nodes: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// 节点还在并且不是被删后复用了槽位
+    pub(crate) fn contains(&self, id: NodeId) -> bool {
+        self.nodes
+            .get(id.index)
+            .is_some_and(|slot| slot.generation == id.generation && slot.node.is_some())
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        let slot = &self.nodes[id.index];
+        assert_eq!(slot.generation, id.generation, "stale {:?}", id);
+        slot.node.as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        let slot = &mut self.nodes[id.index];
+        assert_eq!(slot.generation, id.generation, "stale {:?}", id);
+        slot.node.as_mut().unwrap()
+    }
+
+    fn id(&self, index: usize) -> NodeId {
+        NodeId {
+            index,
+            generation: self.nodes[index].generation,
+        }
+    }
+
+    /// 加一个节点，parent 为 None 时是根节点
+    pub(crate) fn add(
+        &mut self,
+        name: impl Into<String>,
+        local: Transform,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let node = Node {
+            name: name.into(),
+            local,
+            world: Mat4::IDENTITY,
+            dirty: true,
+            parent,
+            children: Vec::new(),
+            renderable: None,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index].node = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                self.nodes.len() - 1
+            }
+        };
+        let id = self.id(index);
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// 连同子树一起删掉
+    pub(crate) fn remove(&mut self, id: NodeId) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            stack.extend(node.children.iter().copied());
+            let slot = &mut self.nodes[id.index];
+            slot.node = None;
+            slot.generation += 1;
+            self.free.push(id.index);
+        }
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        match self.node(id).parent {
+            Some(parent) => self.node_mut(parent).children.retain(|&c| c != id),
+            None => self.roots.retain(|&c| c != id),
+        }
+    }
+
+    /// 换父节点，世界矩阵跟着新父节点走，不保持原来的位置
+    /// 不能挂到自己的子树下面，那样会成环，返回 false
+    pub(crate) fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == id {
+                return false;
+            }
+            ancestor = self.node(a).parent;
+        }
+        self.detach(id);
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        let node = self.node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+        true
+    }
+
+    pub(crate) fn set_renderable(&mut self, id: NodeId, renderable: Option<Renderable>) {
+        self.node_mut(id).renderable = renderable;
+    }
+
+    pub(crate) fn renderable(&self, id: NodeId) -> Option<&Renderable> {
+        self.node(id).renderable.as_ref()
+    }
+
+    pub(crate) fn name(&self, id: NodeId) -> &str {
+        &self.node(id).name
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .find(|(_, slot)| slot.node.as_ref().is_some_and(|node| node.name == name))
+            .map(|(i, _)| self.id(i))
+    }
+
+    pub(crate) fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub(crate) fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    pub(crate) fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub(crate) fn local(&self, id: NodeId) -> &Transform {
+        &self.node(id).local
+    }
+
+    /// 拿可变的局部变换，顺手标脏
+    pub(crate) fn local_mut(&mut self, id: NodeId) -> &mut Transform {
+        let node = self.node_mut(id);
+        node.dirty = true;
+        &mut node.local
+    }
+
+    pub(crate) fn set_local(&mut self, id: NodeId, local: Transform) {
+        *self.local_mut(id) = local;
+    }
+
+    /// 上一次 update 算出来的世界矩阵
+    pub(crate) fn world(&self, id: NodeId) -> Mat4 {
+        self.node(id).world
+    }
+
+    /// 从根往下传，只重算脏节点和它们的子树，返回重算了多少个
+    pub(crate) fn update(&mut self) -> usize {
+        let mut updated = 0;
+        let mut stack: Vec<(NodeId, Mat4, bool)> = self
+            .roots
+            .iter()
+            .map(|&root| (root, Mat4::IDENTITY, false))
+            .collect();
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = self.node_mut(id);
+            let changed = node.dirty || parent_changed;
+            if changed {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+                updated += 1;
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().map(|&child| (child, world, changed)));
+        }
+        updated
+    }
+
+    /// 所有挂了 Renderable 的节点和它们的世界矩阵，先 update 再调
+    pub(crate) fn renderables(&self) -> impl Iterator<Item = (NodeId, Mat4, &Renderable)> {
+        self.nodes.iter().enumerate().filter_map(|(i, slot)| {
+            let node = slot.node.as_ref()?;
+            Some((self.id(i), node.world, node.renderable.as_ref()?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reused_slot_rejects_stale_id() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", Transform::IDENTITY, None);
+        let child = graph.add("child", Transform::IDENTITY, Some(root));
+        graph.remove(root);
+        assert!(!graph.contains(root));
+        assert!(!graph.contains(child));
+
+        let reused = graph.add("reused", Transform::IDENTITY, None);
+        assert!(reused.index() == root.index() || reused.index() == child.index());
+        assert_ne!(reused, root);
+        assert_ne!(reused, child);
+        assert!(graph.contains(reused));
+        assert_eq!(graph.find("reused"), Some(reused));
+        assert_eq!(graph.roots(), &[reused]);
+    }
+
+    /// root -> arm -> (hand, tool)
+    fn arm() -> (SceneGraph, [NodeId; 4]) {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", Transform::from_translation(Vec3::X), None);
+        let arm = graph.add(
+            "arm",
+            Transform::from_translation(Vec3::Y).with_rotation(Quat::from_rotation_z(0.5)),
+            Some(root),
+        );
+        let hand = graph.add(
+            "hand",
+            Transform::from_translation(Vec3::Z).with_scale(Vec3::splat(2.0)),
+            Some(arm),
+        );
+        let tool = graph.add("tool", Transform::from_translation(Vec3::X), Some(arm));
+        (graph, [root, arm, hand, tool])
+    }
+
+    #[test]
+    fn update_counts_recomputed_nodes() {
+        let (mut graph, [_, arm, hand, _]) = arm();
+        assert_eq!(graph.update(), 4);
+        assert_eq!(graph.update(), 0);
+        // 只有 arm 和它的子树
+        graph.local_mut(arm).translation.y = 3.0;
+        assert_eq!(graph.update(), 3);
+        graph.local_mut(hand);
+        assert_eq!(graph.update(), 1);
+        assert_eq!(graph.update(), 0);
+    }
+
+    #[test]
+    fn children_follow_parent() {
+        let (mut graph, [root, arm, hand, tool]) = arm();
+        graph.update();
+        graph.local_mut(root).rotation = Quat::from_rotation_y(1.0);
+        graph.local_mut(arm).scale = Vec3::new(1.0, 2.0, 1.0);
+        graph.update();
+        for (parent, child) in [(root, arm), (arm, hand), (arm, tool)] {
+            let expected = graph.world(parent) * graph.local(child).matrix();
+            assert!(graph.world(child).abs_diff_eq(expected, 1e-5));
+        }
+    }
+
+    #[test]
+    fn set_parent_refuses_cycles() {
+        let (mut graph, [root, arm, hand, _]) = arm();
+        assert!(!graph.set_parent(root, Some(hand)));
+        assert!(!graph.set_parent(arm, Some(arm)));
+        assert_eq!(graph.parent(root), None);
+        assert_eq!(graph.parent(arm), Some(root));
+        // 往旁边挪是可以的
+        assert!(graph.set_parent(hand, Some(root)));
+        assert_eq!(graph.parent(hand), Some(root));
+        assert!(graph.children(root).contains(&hand));
+        assert!(!graph.children(arm).contains(&hand));
+    }
+
+    #[test]
+    fn remove_frees_subtree() {
+        let (mut graph, [root, arm, hand, tool]) = arm();
+        graph.remove(arm);
+        assert!(graph.contains(root));
+        for id in [arm, hand, tool] {
+            assert!(!graph.contains(id));
+        }
+        assert!(graph.children(root).is_empty());
+        assert_eq!(graph.find("hand"), None);
+        assert_eq!(graph.find("tool"), None);
+        // 三个槽位都空出来了，再加三个不会变长
+        for name in ["a", "b", "c"] {
+            graph.add(name, Transform::IDENTITY, None);
+        }
+        assert_eq!(graph.nodes.len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "stale")]
+    fn stale_id_panics() {
+        let mut graph = SceneGraph::new();
+        let old = graph.add("old", Transform::IDENTITY, None);
+        graph.remove(old);
+        graph.add("new", Transform::IDENTITY, None);
+        graph.name(old);
+    }
+}
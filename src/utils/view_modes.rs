@@ -0,0 +1,698 @@
+// 所有 3D 场景共用的调试视图：线框、法线、uv 棋盘格、深度、overdraw 热力图
+// 当前模式放在 GfxContext::view_mode 上，按 V 轮换，换了场景也还在；接入的场景在 egui 里也能选（ViewModes::ui）
+// 场景接入：
+//   1. ready 里按自己的顶点格式 ViewModes::new，网格的 vertex / index buffer 要带 STORAGE
+//   2. paint 开头 sync 拿到当前模式，replaces_scene 的时候自己的 pass 不用画
+//   3. 场景画完以后 render，把网格和相机交过来
+// 不碰场景的 shader：view_modes.wgsl 自己从 buffer 里取顶点，所以什么顶点格式都能画
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Mat4;
+
+use crate::gfx;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// overdraw 计数用，要能做加法混合
+const COUNT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+const OBJECT_SIZE: wgpu::BufferAddress =
+    std::mem::size_of::<ObjectUniform>() as wgpu::BufferAddress;
+/// 替换掉场景的几种模式用的背景色
+const BACKGROUND: wgpu::Color = wgpu::Color {
+    r: 0.05,
+    g: 0.05,
+    b: 0.07,
+    a: 1.0,
+};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ViewMode {
+    /// 场景自己画
+    #[default]
+    Shaded,
+    /// 叠在场景上面
+    Wireframe,
+    Normals,
+    UvChecker,
+    Depth,
+    Overdraw,
+}
+
+impl ViewMode {
+    pub(crate) const ALL: [ViewMode; 6] = [
+        ViewMode::Shaded,
+        ViewMode::Wireframe,
+        ViewMode::Normals,
+        ViewMode::UvChecker,
+        ViewMode::Depth,
+        ViewMode::Overdraw,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ViewMode::Shaded => "shaded",
+            ViewMode::Wireframe => "wireframe",
+            ViewMode::Normals => "normals",
+            ViewMode::UvChecker => "uv checker",
+            ViewMode::Depth => "depth",
+            ViewMode::Overdraw => "overdraw",
+        }
+    }
+
+    pub(crate) fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// 整个画面由 ViewModes 画，场景自己的 pass 可以跳过
+    /// 接入的场景照样开 pass 清屏、清深度，只是不画东西；阴影这种只给着色用的 pass 也不用画
+    pub(crate) fn replaces_scene(self) -> bool {
+        !matches!(self, ViewMode::Shaded | ViewMode::Wireframe)
+    }
+}
+
+/// 网格在 vertex buffer 里的样子，都按 f32 的个数算，位置总在最前面
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct MeshLayout {
+    pub(crate) stride: u32,
+    pub(crate) normal: Option<u32>,
+    pub(crate) uv: Option<u32>,
+    pub(crate) index_format: wgpu::IndexFormat,
+}
+
+impl MeshLayout {
+    /// 拼在 view_modes.wgsl 前面
+    fn constants(&self) -> String {
+        format!(
+            "const STRIDE: u32 = {}u;\n\
+             const HAS_NORMAL: bool = {};\n\
+             const NORMAL_OFFSET: u32 = {}u;\n\
+             const HAS_UV: bool = {};\n\
+             const UV_OFFSET: u32 = {}u;\n\
+             const INDEX_U16: bool = {};\n",
+            self.stride,
+            self.normal.is_some(),
+            self.normal.unwrap_or(0),
+            self.uv.is_some(),
+            self.uv.unwrap_or(0),
+            self.index_format == wgpu::IndexFormat::Uint16,
+        )
+    }
+}
+
+/// 要画的一个网格，index_count 个索引从头开始画
+pub(crate) struct ViewMesh<'a> {
+    pub(crate) vertices: &'a wgpu::Buffer,
+    pub(crate) indices: &'a wgpu::Buffer,
+    pub(crate) index_count: u32,
+    pub(crate) model: Mat4,
+    /// 分屏的场景给每个网格自己的视口 (x, y, w, h) 和 view_proj；None 就是整个画面，用 ViewCamera 的
+    pub(crate) viewport: Option<([f32; 4], Mat4)>,
+}
+
+/// near / far 给深度视图做线性化
+pub(crate) struct ViewCamera {
+    pub(crate) view_proj: Mat4,
+    pub(crate) near: f32,
+    pub(crate) far: f32,
+}
+
+/// 和 view_modes.wgsl 里的 Frame 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct FrameUniform {
+    near: f32,
+    far: f32,
+    _pad: [f32; 2],
+}
+
+/// 和 view_modes.wgsl 里的 Object 对应
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct ObjectUniform {
+    model: [[f32; 4]; 4],
+    view_proj: [[f32; 4]; 4],
+}
+
+/// 跟着窗口大小走的两张贴图
+struct Targets {
+    size: (u32, u32),
+    depth: wgpu::TextureView,
+    count: wgpu::TextureView,
+    heatmap_group: wgpu::BindGroup,
+}
+
+pub(crate) struct ViewModes {
+    mode: ViewMode,
+    /// egui 里改过，下一次 sync 写回 GfxContext
+    changed: bool,
+    /// 支持 PolygonMode::Line 的时候也可以手动换成重心坐标的画法对比
+    pub(crate) barycentric: bool,
+    frame_buffer: wgpu::Buffer,
+    /// 每个网格一个 model 矩阵，按 object_stride 对齐，用动态偏移取
+    object_buffer: wgpu::Buffer,
+    object_capacity: usize,
+    object_stride: wgpu::BufferAddress,
+    frame_layout: wgpu::BindGroupLayout,
+    frame_group: wgpu::BindGroup,
+    mesh_layout: wgpu::BindGroupLayout,
+    heatmap_layout: wgpu::BindGroupLayout,
+    prepass: wgpu::RenderPipeline,
+    /// 设备不支持 PolygonMode::Line 时是 None
+    wire_line: Option<wgpu::RenderPipeline>,
+    wire_barycentric: wgpu::RenderPipeline,
+    normals: wgpu::RenderPipeline,
+    uv_checker: wgpu::RenderPipeline,
+    depth: wgpu::RenderPipeline,
+    overdraw: wgpu::RenderPipeline,
+    heatmap: wgpu::RenderPipeline,
+    targets: Option<Targets>,
+}
+
+impl ViewModes {
+    pub(crate) fn new(
+        context: &gfx::GfxContext,
+        format: wgpu::TextureFormat,
+        layout: MeshLayout,
+    ) -> Self {
+        let device = &context.device;
+        let uniform = |binding, dynamic| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: dynamic,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let frame_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("view modes frame"),
+            entries: &[uniform(0, false), uniform(1, true)],
+        });
+        let storage = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let mesh_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("view modes mesh"),
+            entries: &[storage(0), storage(1)],
+        });
+        let heatmap_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("view modes heatmap"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let frame_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("view modes frame"),
+            size: std::mem::size_of::<FrameUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let object_stride = wgpu::util::align_to(OBJECT_SIZE, alignment);
+        let object_capacity = 16;
+        let object_buffer = Self::create_object_buffer(context, object_stride, object_capacity);
+        let frame_group =
+            Self::create_frame_group(context, &frame_layout, &frame_buffer, &object_buffer);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("view modes"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!(
+                "{}\n{}",
+                layout.constants(),
+                include_str!("view_modes.wgsl")
+            ))),
+        });
+        let mesh_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("view modes"),
+            bind_group_layouts: &[&frame_layout, &mesh_layout],
+            push_constant_ranges: &[],
+        });
+        // 网格的绕序各个场景不一样，都不剔除
+        let pipeline = |entry_point,
+                        target: wgpu::ColorTargetState,
+                        depth_stencil: Option<wgpu::DepthStencilState>,
+                        polygon_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&mesh_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(target)],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    polygon_mode,
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let depth_state = |write, compare, bias| {
+            Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: write,
+                depth_compare: compare,
+                stencil: Default::default(),
+                bias,
+            })
+        };
+        let blended = wgpu::ColorTargetState {
+            format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        };
+        // 线框先只写深度，往后推一点，线画在同一个面上不会闪；背面的线被前面的面挡住
+        let prepass = pipeline(
+            "fs_wire",
+            wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::empty(),
+            },
+            depth_state(
+                true,
+                wgpu::CompareFunction::Less,
+                wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 1.0,
+                    clamp: 0.0,
+                },
+            ),
+            wgpu::PolygonMode::Fill,
+        );
+        let wire_depth =
+            || depth_state(false, wgpu::CompareFunction::LessEqual, Default::default());
+        let wire_line = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| {
+                pipeline(
+                    "fs_wire",
+                    blended.clone(),
+                    wire_depth(),
+                    wgpu::PolygonMode::Line,
+                )
+            });
+        let wire_barycentric = pipeline(
+            "fs_wire_barycentric",
+            blended,
+            wire_depth(),
+            wgpu::PolygonMode::Fill,
+        );
+        let opaque = || {
+            (
+                format.into(),
+                depth_state(true, wgpu::CompareFunction::Less, Default::default()),
+                wgpu::PolygonMode::Fill,
+            )
+        };
+        let (target, depth_stencil, polygon_mode) = opaque();
+        let normals = pipeline("fs_normals", target, depth_stencil, polygon_mode);
+        let (target, depth_stencil, polygon_mode) = opaque();
+        let uv_checker = pipeline("fs_uv", target, depth_stencil, polygon_mode);
+        let (target, depth_stencil, polygon_mode) = opaque();
+        let depth = pipeline("fs_depth", target, depth_stencil, polygon_mode);
+        // 不做深度测试，每一层都数上
+        let overdraw = pipeline(
+            "fs_overdraw",
+            wgpu::ColorTargetState {
+                format: COUNT_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            None,
+            wgpu::PolygonMode::Fill,
+        );
+        let heatmap_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("view modes heatmap"),
+                bind_group_layouts: &[&heatmap_layout],
+                push_constant_ranges: &[],
+            });
+        let heatmap = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("view modes heatmap"),
+            layout: Some(&heatmap_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_heatmap",
+                targets: &[Some(format.into())],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        ViewModes {
+            mode: context.view_mode.get(),
+            changed: false,
+            barycentric: wire_line.is_none(),
+            frame_buffer,
+            object_buffer,
+            object_capacity,
+            object_stride,
+            frame_layout,
+            frame_group,
+            mesh_layout,
+            heatmap_layout,
+            prepass,
+            wire_line,
+            wire_barycentric,
+            normals,
+            uv_checker,
+            depth,
+            overdraw,
+            heatmap,
+            targets: None,
+        }
+    }
+
+    fn create_object_buffer(
+        context: &gfx::GfxContext,
+        stride: wgpu::BufferAddress,
+        capacity: usize,
+    ) -> wgpu::Buffer {
+        context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("view modes objects"),
+            size: stride * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_frame_group(
+        context: &gfx::GfxContext,
+        layout: &wgpu::BindGroupLayout,
+        frame_buffer: &wgpu::Buffer,
+        object_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("view modes frame"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: frame_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                            buffer: object_buffer,
+                            offset: 0,
+                            size: wgpu::BufferSize::new(OBJECT_SIZE),
+                        }),
+                    },
+                ],
+            })
+    }
+
+    /// 窗口大小变了重建深度和计数贴图
+    fn update_targets(&mut self, context: &gfx::GfxContext) {
+        let config = context.surface_config.as_ref().unwrap();
+        let size = (config.width, config.height);
+        if self.targets.as_ref().map(|t| t.size) == Some(size) {
+            return;
+        }
+        let texture = |label, format, usage| {
+            context
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.0,
+                        height: size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | usage,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let depth = texture(
+            "view modes depth",
+            DEPTH_FORMAT,
+            wgpu::TextureUsages::empty(),
+        );
+        let count = texture(
+            "view modes overdraw",
+            COUNT_FORMAT,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let heatmap_group = context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("view modes heatmap"),
+                layout: &self.heatmap_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&count),
+                }],
+            });
+        self.targets = Some(Targets {
+            size,
+            depth,
+            count,
+            heatmap_group,
+        });
+    }
+
+    /// paint 开头调，拿到这一帧的模式；egui 里改过的先写回 GfxContext
+    pub(crate) fn sync(&mut self, context: &gfx::GfxContext) -> ViewMode {
+        if std::mem::take(&mut self.changed) {
+            context.view_mode.set(self.mode);
+        } else {
+            self.mode = context.view_mode.get();
+        }
+        self.mode
+    }
+
+    pub(crate) fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            for mode in ViewMode::ALL {
+                self.changed |= ui.radio_value(&mut self.mode, mode, mode.name()).changed();
+            }
+        });
+        if self.mode == ViewMode::Wireframe {
+            // 不支持线框模式的设备只能用重心坐标
+            ui.add_enabled(
+                self.wire_line.is_some(),
+                egui::Checkbox::new(&mut self.barycentric, "barycentric wireframe"),
+            );
+        }
+    }
+
+    fn draw_meshes(
+        &self,
+        rpass: &mut wgpu::RenderPass,
+        groups: &[wgpu::BindGroup],
+        meshes: &[ViewMesh],
+    ) {
+        let (width, height) = self.targets.as_ref().unwrap().size;
+        for (i, (group, mesh)) in groups.iter().zip(meshes).enumerate() {
+            let [x, y, w, h] = mesh
+                .viewport
+                .map_or([0.0, 0.0, width as f32, height as f32], |(viewport, _)| {
+                    viewport
+                });
+            rpass.set_viewport(x, y, w, h, 0.0, 1.0);
+            let offset = (i as wgpu::BufferAddress * self.object_stride) as wgpu::DynamicOffset;
+            rpass.set_bind_group(0, &self.frame_group, &[offset]);
+            rpass.set_bind_group(1, group, &[]);
+            rpass.draw(0..mesh.index_count, 0..1);
+        }
+    }
+
+    /// 场景画完以后调；Shaded 什么都不做，Wireframe 叠在 view 上，别的模式整个重画
+    pub(crate) fn render(
+        &mut self,
+        context: &gfx::GfxContext,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        camera: &ViewCamera,
+        meshes: &[ViewMesh],
+    ) {
+        if self.mode == ViewMode::Shaded {
+            return;
+        }
+        self.update_targets(context);
+        let frame = FrameUniform {
+            near: camera.near,
+            far: camera.far,
+            _pad: [0.0; 2],
+        };
+        context
+            .queue
+            .write_buffer(&self.frame_buffer, 0, bytemuck::bytes_of(&frame));
+        if meshes.len() > self.object_capacity {
+            self.object_capacity = meshes.len().next_power_of_two();
+            self.object_buffer =
+                Self::create_object_buffer(context, self.object_stride, self.object_capacity);
+            self.frame_group = Self::create_frame_group(
+                context,
+                &self.frame_layout,
+                &self.frame_buffer,
+                &self.object_buffer,
+            );
+        }
+        let mut objects = vec![0u8; self.object_stride as usize * meshes.len()];
+        for (i, mesh) in meshes.iter().enumerate() {
+            let offset = i * self.object_stride as usize;
+            let object = ObjectUniform {
+                model: mesh.model.to_cols_array_2d(),
+                view_proj: mesh
+                    .viewport
+                    .map_or(camera.view_proj, |(_, view_proj)| view_proj)
+                    .to_cols_array_2d(),
+            };
+            objects[offset..offset + OBJECT_SIZE as usize]
+                .copy_from_slice(bytemuck::bytes_of(&object));
+        }
+        context.queue.write_buffer(&self.object_buffer, 0, &objects);
+        let groups: Vec<wgpu::BindGroup> = meshes
+            .iter()
+            .map(|mesh| {
+                context
+                    .device
+                    .create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("view modes mesh"),
+                        layout: &self.mesh_layout,
+                        entries: &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: mesh.indices.as_entire_binding(),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: mesh.vertices.as_entire_binding(),
+                            },
+                        ],
+                    })
+            })
+            .collect();
+
+        let targets = self.targets.as_ref().unwrap();
+        let color = |load| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+        let depth = Some(wgpu::RenderPassDepthStencilAttachment {
+            view: &targets.depth,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Discard,
+            }),
+            stencil_ops: None,
+        });
+        if self.mode == ViewMode::Overdraw {
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("view modes overdraw"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &targets.count,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: context.render_timestamps("view modes overdraw"),
+                    occlusion_query_set: None,
+                });
+                rpass.set_pipeline(&self.overdraw);
+                self.draw_meshes(&mut rpass, &groups, meshes);
+            }
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("view modes heatmap"),
+                color_attachments: &[color(wgpu::LoadOp::Clear(BACKGROUND))],
+                depth_stencil_attachment: None,
+                timestamp_writes: context.render_timestamps("view modes heatmap"),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.heatmap);
+            rpass.set_bind_group(0, &targets.heatmap_group, &[]);
+            rpass.draw(0..3, 0..1);
+            return;
+        }
+
+        let load = match self.mode {
+            ViewMode::Wireframe => wgpu::LoadOp::Load,
+            _ => wgpu::LoadOp::Clear(BACKGROUND),
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("view modes"),
+            color_attachments: &[color(load)],
+            depth_stencil_attachment: depth,
+            timestamp_writes: context.render_timestamps("view modes"),
+            occlusion_query_set: None,
+        });
+        let pipeline = match self.mode {
+            ViewMode::Wireframe => {
+                rpass.set_pipeline(&self.prepass);
+                self.draw_meshes(&mut rpass, &groups, meshes);
+                match &self.wire_line {
+                    Some(line) if !self.barycentric => line,
+                    _ => &self.wire_barycentric,
+                }
+            }
+            ViewMode::Normals => &self.normals,
+            ViewMode::UvChecker => &self.uv_checker,
+            _ => &self.depth,
+        };
+        rpass.set_pipeline(pipeline);
+        self.draw_meshes(&mut rpass, &groups, meshes);
+    }
+}
@@ -0,0 +1,146 @@
+// 调试视图，和 utils/view_modes.rs 对应
+// 不用场景自己的顶点格式和 shader：按绘制编号从 storage buffer 里取索引再取顶点（vertex pulling）
+// 网格画成不带索引的三角形列表，每个角顺便带上重心坐标，给线框的备用画法用
+// 前面由 view_modes.rs 拼上网格布局的常量：STRIDE、NORMAL_OFFSET、UV_OFFSET 都按 f32 的个数算
+
+struct Frame {
+    near: f32,
+    far: f32,
+    _pad: vec2<f32>,
+};
+
+// 分屏的场景每个网格的 view_proj 不一样，所以放在这里
+struct Object {
+    model: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> frame: Frame;
+@group(0)
+@binding(1)
+var<uniform> object: Object;
+
+@group(1)
+@binding(0)
+var<storage, read> indices: array<u32>;
+@group(1)
+@binding(1)
+var<storage, read> vertices: array<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) barycentric: vec3<f32>,
+    // 透视投影下 w 就是到相机的距离
+    @location(4) view_depth: f32,
+};
+
+fn read_index(i: u32) -> u32 {
+    if INDEX_U16 {
+        return (indices[i / 2u] >> (16u * (i % 2u))) & 0xffffu;
+    }
+    return indices[i];
+}
+
+fn read_vec3(at: u32) -> vec3<f32> {
+    return vec3<f32>(vertices[at], vertices[at + 1u], vertices[at + 2u]);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vi: u32) -> VertexOutput {
+    let base = read_index(vi) * STRIDE;
+    let world = (object.model * vec4<f32>(read_vec3(base), 1.0)).xyz;
+    var normal = vec3<f32>(0.0);
+    if HAS_NORMAL {
+        // 不等比缩放的时候不准，调试够用了
+        normal = (object.model * vec4<f32>(read_vec3(base + NORMAL_OFFSET), 0.0)).xyz;
+    }
+    var uv = world.xz;
+    if HAS_UV {
+        uv = vec2<f32>(vertices[base + UV_OFFSET], vertices[base + UV_OFFSET + 1u]);
+    }
+    let corner = vi % 3u;
+    let barycentric = vec3<f32>(f32(corner == 0u), f32(corner == 1u), f32(corner == 2u));
+    let position = object.view_proj * vec4<f32>(world, 1.0);
+    return VertexOutput(position, world, normal, uv, barycentric, position.w);
+}
+
+@fragment
+fn fs_normals(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    // 没有法线的网格用屏幕导数算面法线，朝着相机
+    let flat = normalize(cross(dpdy(vertex.world), dpdx(vertex.world)));
+    var normal = flat;
+    if HAS_NORMAL {
+        normal = normalize(vertex.normal);
+    }
+    return vec4<f32>(normal * 0.5 + 0.5, 1.0);
+}
+
+@fragment
+fn fs_uv(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    // 8x8 的格子，亮格染上 uv 本身的颜色，看得出方向和拉伸
+    let cell = floor(vertex.uv * 8.0);
+    let checker = abs(cell.x + cell.y) % 2.0;
+    let tint = vec3<f32>(fract(vertex.uv), 0.8);
+    return vec4<f32>(mix(vec3<f32>(0.12), tint, checker), 1.0);
+}
+
+@fragment
+fn fs_depth(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    // 线性深度，近白远黑
+    let t = clamp((vertex.view_depth - frame.near) / (frame.far - frame.near), 0.0, 1.0);
+    return vec4<f32>(vec3<f32>(1.0 - t), 1.0);
+}
+
+@fragment
+fn fs_overdraw() -> @location(0) vec4<f32> {
+    // 加法混合，每盖一层加一
+    return vec4<f32>(1.0, 0.0, 0.0, 0.0);
+}
+
+@fragment
+fn fs_wire() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 1.0, 0.4, 1.0);
+}
+
+// 没有 PolygonMode::Line 的时候：离三角形的边不到一个像素的地方涂上
+@fragment
+fn fs_wire_barycentric(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let pixels = vertex.barycentric / fwidth(vertex.barycentric);
+    let edge = min(min(pixels.x, pixels.y), pixels.z);
+    let alpha = 1.0 - smoothstep(0.5, 1.5, edge);
+    return vec4<f32>(0.1, 1.0, 0.4, alpha);
+}
+
+// 把 fs_overdraw 数出来的层数映射成热力图
+@group(0)
+@binding(0)
+var overdraw: texture_2d<f32>;
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vi: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vi << 1u) & 2u), f32(vi & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_heatmap(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let count = textureLoad(overdraw, vec2<i32>(position.xy), 0).r;
+    // 0 层黑，1 层蓝，然后青、绿、黄、红，6 层以上白
+    var ramp = array<vec3<f32>, 7>(
+        vec3<f32>(0.0, 0.0, 0.0),
+        vec3<f32>(0.0, 0.1, 0.6),
+        vec3<f32>(0.0, 0.6, 0.8),
+        vec3<f32>(0.1, 0.8, 0.2),
+        vec3<f32>(0.9, 0.9, 0.1),
+        vec3<f32>(1.0, 0.2, 0.1),
+        vec3<f32>(1.0, 1.0, 1.0),
+    );
+    let t = clamp(count, 0.0, 6.0);
+    let i = min(u32(t), 5u);
+    return vec4<f32>(mix(ramp[i], ramp[i + 1u], t - f32(i)), 1.0);
+}